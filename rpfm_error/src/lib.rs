@@ -206,6 +206,18 @@ pub enum ErrorKind {
     /// Error for when the PackFile size doesn't match what we expect. Contains both, the real size and the expected size.
     PackFileSizeIsNotWhatWeExpect(u64, u64),
 
+    /// Error for when the amount of PackedFiles in the index of a PackFile is not the one its header says. Contains both, the declared amount and the one we found.
+    PackFileFileCountMismatch { declared: u32, found: u32 },
+
+    /// Error for when the compressed data of one or more PackedFiles of a PackFile cannot be decompressed. Contains the path and the problem of each one.
+    PackFileCorruptedCompressedData(Vec<String>),
+
+    /// Error for when we try to compress the PackedFiles of a PackFile whose version doesn't support compression. Contains the version.
+    PackFileVersionDoesNotSupportCompression(String),
+
+    /// Error for when we try to encrypt the PackedFiles of a PackFile for a version that doesn't support encryption. Contains the version.
+    PackFileVersionDoesNotSupportEncryption(String),
+
     //-----------------------------------------------------//
     //                PackedFile Errors
     //-----------------------------------------------------//
@@ -222,12 +234,24 @@ pub enum ErrorKind {
     /// Error for when a load_data or get_data operation fails.
     PackedFileDataCouldNotBeLoaded,
 
+    /// Error for when the data of a PackedFile on disk is shorter than we expected. Contains the path of the PackedFile, the expected size and the available size.
+    PackedFileDataCouldNotBeRead(Vec<String>, u64, u64),
+
+    /// Error for when the index of a PackFile says the data of a PackedFile goes past the end of his file. Contains the path of the PackedFile, the position where his data ends and the size of the file.
+    PackedFileSizeExceedsFile(Vec<String>, u64, u64),
+
     /// Error for when the PackedFile size doesn't match what we expect. Contains the real size and the expected size.
     PackedFileSizeIsNotWhatWeExpect(usize, usize),
 
+    /// Error for when we finish decoding a PackedFile before reaching the end of his data. Contains the bytes decoded and the size of the data.
+    PackedFileHasExtraData(usize, usize),
+
     /// Error for when the compressed PackedFile is either incomplete (<9 bytes) or the decompression failed.
     PackedFileDataCouldNotBeDecompressed,
 
+    /// Error for when we try to decode a PackedFile without data.
+    PackedFileDataIsEmpty,
+
     /// Error for when we expect data to be in memory, but it isn't.
     PackedFileDataIsNotInMemory,
 
@@ -243,6 +267,12 @@ pub enum ErrorKind {
     /// Error for when we cannot open a PackedFile due to not being decodeable on the lib.
     PackedFileTypeUnknown,
 
+    /// Error for when we find more than one PackedFile with the same path in a PackFile. Contains the path of the PackedFile.
+    DuplicatedPackedFile(Vec<String>),
+
+    /// Error for when we try to encode a decoded PackedFile of a type the lib cannot save.
+    PackedFileTypeNotEncodable,
+
     /// Error for when we replace the binary data of a PackedFile with another data that's not decodeable in the same way as the old data.
     NewDataIsNotDecodeableTheSameWayAsOldDAta,
 
@@ -259,6 +289,47 @@ pub enum ErrorKind {
     /// Error for when a Table is empty and it doesn't have an `Definition`, so it's undecodeable.
     TableEmptyWithNoDefinition,
 
+    /// Error for when a row of a Table has not the amount of fields we expected. Contains the row, the amount we expected, and the amount we got.
+    TableInvalidRowFieldCount(u32, u32, u32),
+
+    /// Error for when a cell of a Table is not of the type we expected it to be. Contains the row, the column, the type we expected, and the type we got.
+    TableInvalidCellFieldType(u32, u32, String, String),
+
+    /// Error for when we fail to decode a row of a table. Contains the row that failed (starting at 1) and the error message.
+    TableRowDecodeError(u32, String),
+
+    /// Error for when we try to use a column that's not in the definition of a table. Contains the name of the column.
+    TableColumnNotFound(String),
+
+    /// Error for when we try to add a column with a name that's already in the definition of a table. Contains the name of the column.
+    TableColumnAlreadyExists(String),
+
+    /// Error for when a text cannot be parsed as the value of a cell of a table. Contains the text and the type we tried to parse it as.
+    TableCellParseError(String, String),
+
+    /// Error for when we try to use a row that's not in a table. Contains the index of the row, and the amount of rows in the table.
+    TableRowIndexOutOfBounds(usize, usize),
+
+    /// Error for when we try to use a column that's not in a row of a table. Contains the index of the column, and the amount of columns in the row.
+    TableColumnIndexOutOfBounds(usize, usize),
+
+    /// Error for when we try to paste rows into a table with different columns than the table they were copied from. Contains the columns of the table, and the ones of the rows.
+    TableRowsIncompatibleColumns(String, String),
+
+    /// Error for when we try to merge tables with different definitions.
+    TableMergeIncompatibleDefinitions,
+
+    /// Error for when a value in the JSON rows of a table is not valid for its column. Contains the row and column of the value, the value, and the type of the column.
+    TableJsonInvalidCell(u32, u32, String, String),
+
+    /// Error for when a cell of a table cannot be saved because its value doesn't fit in the range of its column. Contains the row and column of the cell,
+    /// and the value out of range.
+    TableCellValueOutOfRange {
+        row: u32,
+        column: u32,
+        value: String,
+    },
+
     //--------------------------------//
     // DB Table Errors
     //--------------------------------//
@@ -278,6 +349,15 @@ pub enum ErrorKind {
     /// Error for when we find missing references when checking a DB Table. Contains a list with the tables with missing references.
     DBMissingReferences(Vec<String>),
 
+    /// Error for when a DB Table we just encoded doesn't decode back to the same table.
+    DBTableSaveVerificationFailed,
+
+    /// Error for when none of the known versions of a DB Table can decode it. Contains the name of the table and the versions tried.
+    DBTableVersionNotDetected(String, Vec<i32>),
+
+    /// Error for when the columns of a DB Table in JSON are not the ones of its definition. Contains the columns of the definition, and the ones of the JSON.
+    DBTableJsonIncompatibleColumns(String, String),
+
     /// Error for when we found no newer version of a table than the one we have.
     NoDefinitionUpdateAvailable,
 
@@ -321,6 +401,9 @@ pub enum ErrorKind {
     /// Error for when the decal texture directory hasn't been found while examining a rigidmodel.
     RigidModelDecalTextureDirectoryNotFound,
 
+    /// Error for when a texture path is too long to fit in a RigidModel. Contains the path.
+    RigidModelTexturePathTooLong(String),
+
     //--------------------------------//
     // Text Errors
     //--------------------------------//
@@ -331,6 +414,23 @@ pub enum ErrorKind {
     /// Error for when a Text PackedFile fails to decode due to not being a plain text file or having an unsupported encoding.
     TextDecodeWrongEncodingOrNotATextFile,
 
+    /// Error for when a Text PackedFile has an invalid sequence for his encoding. Contains the position in bytes of the sequence,
+    /// and the line and column (in characters, both starting at 1) of it.
+    TextDecodeFailed {
+        offset: usize,
+        line: usize,
+        column: usize,
+    },
+
+    /// Error for when a Text PackedFile is bigger than the limit we're allowed to decode. Contains the size of the data and the limit, both in bytes.
+    TextTooLarge {
+        size: usize,
+        limit: usize,
+    },
+
+    /// Error for when we try to edit a line of a Text PackedFile that doesn't exist. Contains the line we tried to edit and the amount of lines.
+    TextLineOutOfBounds(usize, usize),
+
     /// Error for when we try to use Kailua without a types file.
     NoTypesFileFound,
 
@@ -350,6 +450,26 @@ pub enum ErrorKind {
     /// Error for when we try to decode a Loc PackedFile and fails for corruption.
     LocPackedFileCorrupted,
 
+    /// Error for when we fail to parse a PO file into a Loc PackedFile. Contains the line where it failed and the error message.
+    LocPOParse(usize, String),
+
+    /// Error for when we try to export a row of a Loc PackedFile that doesn't have the key, text and tooltip columns. Contains the number of the row, starting at 1.
+    LocInvalidRow(usize),
+
+    /// Error for when we fail to parse a CSV file in CA's format into a Loc PackedFile. Contains the line where it failed and the error message.
+    LocCaCsvParse(usize, String),
+
+    /// Error for when a Loc PackedFile ends before all the entries its header says it has. Contains the amount of entries in the header, and the ones found.
+    LocEntryCountMismatch {
+        declared: u32,
+        found: u32,
+    },
+
+    /// Error for when a Loc PackedFile has more than one entry with the same key. Contains the key.
+    LocDuplicateKey {
+        key: String,
+    },
+
     //--------------------------------//
     // Image Errors
     //--------------------------------//
@@ -357,6 +477,9 @@ pub enum ErrorKind {
     /// Error for when an Image fails to decode. Contains the error message.
     ImageDecode(String),
 
+    /// Error for when the data of an Image is clearly broken, like when it's truncated.
+    ImageDataCorrupted,
+
     //--------------------------------//
     // CA_VP8 Errors
     //--------------------------------//
@@ -364,6 +487,28 @@ pub enum ErrorKind {
     /// Error for when a CaVp8 PackedFile fails to decode. Contains the error message.
     CaVp8Decode(String),
 
+    //--------------------------------//
+    // ESF Errors
+    //--------------------------------//
+
+    /// Error for when we try to decode an ESF PackedFile with a signature we don't support. Contains the signature.
+    ESFUnsupportedSignature(u32),
+
+    /// Error for when we find a node of a type we don't support in an ESF PackedFile. Contains the type and the byte where we found it.
+    ESFUnsupportedDataType(u8, usize),
+
+    /// Error for when a node of an ESF PackedFile doesn't end where it says it ends. Contains the expected and the real end of the node.
+    ESFIncorrectNodeSize(usize, usize),
+
+    /// Error for when an ESF PackedFile references a record name that's not in his list of record names. Contains the index of the name.
+    ESFRecordNameNotFound(u16),
+
+    /// Error for when an ESF PackedFile references a string that's not in his list of strings. Contains the index of the string.
+    ESFStringNotFound(u32),
+
+    /// Error for when the nodes of an ESF PackedFile are nested deeper than we support. Contains the maximum depth.
+    ESFNodeTooDeep(u32),
+
     //--------------------------------//
     // PAK File Errors
     //--------------------------------//
@@ -626,7 +771,11 @@ impl Display for ErrorKind {
             <p><b>NOTE</b>: If you created this PackFile using the <i>'Load All CA PackedFiles'</i> feature, NEVER try to save it unless you have 64GB of ram or more. Otherwise it may hang your entire computer to dead.</p>"),
             ErrorKind::PackFileIsNotAPackFile => write!(f, "<p>This file is not a valid PackFile.</p>"),
             ErrorKind::PackFileIsNotAFile => write!(f, "<p>This PackFile doesn't exists as a file in the disk.</p>"),
+            ErrorKind::PackFileCorruptedCompressedData(errors) => write!(f, "<p>The compressed data of the following PackedFiles is corrupted:</p><ul>{}</ul>", errors.iter().map(|x| format!("<li>{}</li>", x)).collect::<String>()),
+            ErrorKind::PackFileVersionDoesNotSupportCompression(version) => write!(f, "<p>PackFiles of version <i><b>{}</b></i> don't support compression.</p>", version),
+            ErrorKind::PackFileVersionDoesNotSupportEncryption(version) => write!(f, "<p>PackFiles of version <i><b>{}</b></i> don't support encryption.</p>", version),
             ErrorKind::PackFileSizeIsNotWhatWeExpect(reported_size, expected_size) => write!(f, "<p>This PackFile's reported size is <i><b>{}</b></i> bytes, but we expected it to be <i><b>{}</b></i> bytes. This means that either the decoding logic in RPFM is broken for this PackFile, or this PackFile is corrupted.</p>", reported_size, expected_size),
+            ErrorKind::PackFileFileCountMismatch { declared, found } => write!(f, "<p>This PackFile's header says it contains <i><b>{}</b></i> PackedFiles, but its index contains <i><b>{}</b></i>. This means this PackFile is corrupted.</p>", declared, found),
             ErrorKind::NewDataIsNotDecodeableTheSameWayAsOldDAta => write!(f, "<p>The PackedFile you added is not the same type as the one you had before. So... the view showing it will get closed.</p>"),
 
             //-----------------------------------------------------//
@@ -635,14 +784,20 @@ impl Display for ErrorKind {
             ErrorKind::PackedFileNotFound => write!(f, "<p>This PackedFile no longer exists in the PackFile.</p>"),
             ErrorKind::PackedFileIsOpen => write!(f, "<p>That operation cannot be done while the PackedFile involved on it is open. Please, close it by selecting a Folder/PackFile in the TreeView and try again.</p>"),
             ErrorKind::PackedFileIsOpenInAnotherView => write!(f, "<p>That PackedFile is already open in another view. Opening the same PackedFile in multiple views is not supported.</p>"),
+            ErrorKind::PackedFileDataCouldNotBeRead(path, expected_size, available_size) => write!(f, "<p>The data of the PackedFile <i><b>{}</b></i> could not be read from disk. We expected <i><b>{}</b></i> bytes, but there are only <i><b>{}</b></i> bytes available. This means the PackFile has been changed or truncated on disk since it was opened.</p>", path.join("/"), expected_size, available_size),
             ErrorKind::PackedFileDataCouldNotBeLoaded => write!(f, "<p>This PackedFile's data could not be loaded. This means RPFM can no longer read the PackFile from the disk.</p>"),
+            ErrorKind::PackedFileHasExtraData(consumed, total) => write!(f, "<p>This PackedFile has been decoded up to the byte <i><b>{}</b></i>, but it has <i><b>{}</b></i> bytes. This means that either the decoding logic in RPFM is broken for this PackedFile, or this PackedFile is corrupted.</p>", consumed, total),
+            ErrorKind::PackedFileSizeExceedsFile(path, end, file_size) => write!(f, "<p>The data of the PackedFile <i><b>{}</b></i> ends at the byte <i><b>{}</b></i>, but its file only has <i><b>{}</b></i> bytes. This means the index of the PackFile is broken.</p>", path.join("/"), end, file_size),
             ErrorKind::PackedFileSizeIsNotWhatWeExpect(reported_size, expected_size) => write!(f, "<p>This PackedFile's reported size is <i><b>{}</b></i> bytes, but we expected it to be <i><b>{}</b></i> bytes. This means that either the decoding logic in RPFM is broken for this PackedFile, or this PackedFile is corrupted.</p>", reported_size, expected_size),
             ErrorKind::PackedFileDataCouldNotBeDecompressed => write!(f, "<p>This is a compressed file and the decompresion failed for some reason. This means this PackedFile cannot be opened in RPFM.</p>"),
+            ErrorKind::PackedFileDataIsEmpty => write!(f, "<p>This PackedFile is empty, so there is nothing to decode. This means this PackedFile cannot be opened in RPFM.</p>"),
             ErrorKind::PackedFileDataIsNotInMemory => write!(f, "<p>This PackedFile's data is not in memory. If you see this, report it, as it's a bug.</p>"),
             ErrorKind::PackedFileNotInFilter => write!(f, "<p>This PackedFile is not in the current TreeView filter. If you want to open it, remove the filter.</p>"),
             ErrorKind::PackedFileCouldNotBeImported(paths) => write!(f, "<p>The following failed to be imported:<ul>{}</ul></p>", paths.iter().map(|x| format!("<li>{}<li>", x)).collect::<String>()),
             ErrorKind::PackedFileSaveError(path) => write!(f, "<p>The following PackedFile failed to be saved: {}</p>", path.join("/")),
             ErrorKind::PackedFileTypeUnknown => write!(f, "<p>The PackedFile could not be opened.</p>"),
+            ErrorKind::DuplicatedPackedFile(path) => write!(f, "<p>There is more than one PackedFile with the following path:</p><p>{}</p>", path.join("/")),
+            ErrorKind::PackedFileTypeNotEncodable => write!(f, "<p>This type of PackedFile cannot be saved yet.</p>"),
 
             //--------------------------------//
             // Table Errors
@@ -650,12 +805,27 @@ impl Display for ErrorKind {
             ErrorKind::TableRowWrongFieldCount(expected, real) => write!(f, "<p>Error while trying to save a row from a table:</p><p>We expected a row with \"{}\" fields, but we got a row with \"{}\" fields instead.</p>", expected, real),
             ErrorKind::TableWrongFieldType(expected, real) => write!(f, "<p>Error while trying to save a row from a table:</p><p>We expected a field of type \"{}\", but we got a field of type \"{}\".</p>", expected, real),
             ErrorKind::TableEmptyWithNoDefinition => write!(f, "<p>This table is empty and there is not a Definition for it. That means is undecodeable.</p>"),
+            ErrorKind::TableInvalidRowFieldCount(row, expected, real) => write!(f, "<p>Error while validating a table:</p><p>We expected the row \"{}\" to have \"{}\" fields, but it has \"{}\" fields instead.</p>", row, expected, real),
+            ErrorKind::TableInvalidCellFieldType(row, column, expected, real) => write!(f, "<p>Error while validating a table:</p><p>We expected the cell at row \"{}\", column \"{}\" to be of type \"{}\", but it's of type \"{}\" instead.</p>", row, column, expected, real),
+            ErrorKind::TableRowDecodeError(row, cause) => write!(f, "<p>Error while trying to decode the row \"{}\" of the table:</p>{}", row, cause),
+            ErrorKind::TableColumnAlreadyExists(column) => write!(f, "<p>This table already has a column named \"{}\".</p>", column),
+            ErrorKind::TableCellParseError(text, field_type) => write!(f, "<p>The text \"{}\" is not a valid value for a cell of type \"{}\".</p>", text, field_type),
+            ErrorKind::TableRowIndexOutOfBounds(index, len) => write!(f, "<p>There is no row \"{}\" in this table, as it only has \"{}\" rows.</p>", index, len),
+            ErrorKind::TableColumnIndexOutOfBounds(index, len) => write!(f, "<p>There is no column \"{}\" in this table, as it only has \"{}\" columns.</p>", index, len),
+            ErrorKind::TableRowsIncompatibleColumns(expected, real) => write!(f, "<p>The rows you're trying to paste are not compatible with this table:</p><ul><li>Columns of the table: {}.</li><li>Columns of the rows: {}.</li></ul>", expected, real),
+            ErrorKind::TableMergeIncompatibleDefinitions => write!(f, "<p>The tables you're trying to merge don't have the same definition.</p>"),
+            ErrorKind::TableJsonInvalidCell(row, column, value, field_type) => write!(f, "<p>The value {} at row \"{}\", column \"{}\" is not a valid value for a cell of type \"{}\".</p>", value, row, column, field_type),
+            ErrorKind::TableCellValueOutOfRange { row, column, value } => write!(f, "<p>Error while trying to save a table:</p><p>The cell at row \"{}\", column \"{}\" is out of the range its column can hold: \"{}\".</p>", row, column, value),
+            ErrorKind::TableColumnNotFound(column) => write!(f, "<p>There is no column called \"{}\" in this table.</p>", column),
 
             //--------------------------------//
             // DB Table Errors
             //--------------------------------//
             ErrorKind::DBTableIsNotADBTable => write!(f, "<p>This is either not a DB Table, or it's a DB Table but it's corrupted.</p>"),
             ErrorKind::DBTableContainsListField => write!(f, "<p>This specific table version uses a currently unimplemented type (List), so is undecodeable, for now.</p>"),
+            ErrorKind::DBTableVersionNotDetected(name, versions) => write!(f, "<p>None of the known versions of the table \"{}\" can decode this data. Versions tried:</p><ul>{}</ul>", name, versions.iter().map(|x| format!("<li>{}</li>", x)).collect::<String>()),
+            ErrorKind::DBTableJsonIncompatibleColumns(expected, real) => write!(f, "<p>The columns of this JSON table are not the ones of its definition:</p><ul><li>Columns of the definition: {}.</li><li>Columns of the JSON: {}.</li></ul>", expected, real),
+            ErrorKind::DBTableSaveVerificationFailed => write!(f, "<p>This table has been encoded, but the encoded data doesn't decode back to the same table. This means the encoding logic in RPFM is broken for this table. Please, report it.</p>"),
             ErrorKind::DBTableReplaceInvalidData => write!(f, "<p>Error while trying to replace the data of a Cell.</p><p>This means you tried to replace a number cell with text, or used a too big, too low or invalid number. Don't do it. It wont end well.</p>"),
             ErrorKind::DBTableDecode(cause) => write!(f, "<p>Error while trying to decode the DB Table:</p><p>{}</p><p>Before anything else, please check your game selected is really the one this PackFile is for! If it isn't, change your game selected and try again.</p>", cause),
             ErrorKind::DBMissingReferences(references) => write!(f, "<p>The currently open PackFile has reference errors in the following tables:<ul>{}</ul></p>", references.iter().map(|x| format!("<li>{}<li>", x)).collect::<String>()),
@@ -676,12 +846,16 @@ impl Display for ErrorKind {
             ErrorKind::RigidModelUnknownMaskTypeFound => write!(f, "<p>Error while trying to decode the RigidModel file:</p><p><ul><li>Texture with unknown Mask Type found.</li></ul>"),
             ErrorKind::RigidModelTextureDirectoryNotFound => write!(f, "<p>Error while trying to decode the RigidModel file:</p><p><ul><li>Texture Directories not found.</li></ul>"),
             ErrorKind::RigidModelDecalTextureDirectoryNotFound => write!(f, "<p>Error while trying to decode the RigidModel file:</p><p><ul><li>Decal Texture Directory not found.</li></ul>"),
+            ErrorKind::RigidModelTexturePathTooLong(path) => write!(f, "<p>The texture path <i><b>{}</b></i> is too long. Texture paths in RigidModels can only have up to 255 bytes.</p>", path),
 
             //--------------------------------//
             // Text Errors
             //--------------------------------//
             ErrorKind::TextDecode(cause) => write!(f, "<p>Error while trying to decode the Text PackedFile:</p><p>{}</p>", cause),
             ErrorKind::TextDecodeWrongEncodingOrNotATextFile => write!(f, "<p>This is either not a Text PackedFile, or a Text PackedFile using an unsupported encoding</p>"),
+            ErrorKind::TextDecodeFailed { offset, line, column } => write!(f, "<p>Error while trying to decode the Text PackedFile:</p><p>There is an invalid character sequence at the byte <i><b>{}</b></i> (line <i><b>{}</b></i>, column <i><b>{}</b></i>).</p>", offset, line, column),
+            ErrorKind::TextTooLarge { size, limit } => write!(f, "<p>This Text PackedFile is too big to be opened as text. It has <i><b>{}</b></i> bytes, and the limit is <i><b>{}</b></i> bytes.</p>", size, limit),
+            ErrorKind::TextLineOutOfBounds(line, line_count) => write!(f, "<p>Error while trying to edit the Text PackedFile:</p><p>The line \"{}\" doesn't exist. This file only has \"{}\" lines.</p>", line, line_count),
             ErrorKind::NoTypesFileFound => write!(f, "<p>There is no Types file for the current Game Selected, so you can't use Kailua.</p>"),
             ErrorKind::KailuaNotFound => write!(f, "<p>Kailua executable not found. Install it and try again.</p>"),
 
@@ -691,17 +865,33 @@ impl Display for ErrorKind {
             ErrorKind::LocDecode(cause) => write!(f, "<p>Error while trying to decode the Loc PackedFile:</p><p>{}</p>", cause),
             ErrorKind::LocPackedFileIsNotALocPackedFile => write!(f, "<p>This is either not a Loc PackedFile, or it's a Loc PackedFile but it's corrupted.</p>"),
             ErrorKind::LocPackedFileCorrupted => write!(f, "<p>This Loc PackedFile seems to be corrupted.</p>"),
+            ErrorKind::LocPOParse(line, cause) => write!(f, "<p>Error while trying to import the PO file, on line \"{}\":</p><p>{}</p>", line, cause),
+            ErrorKind::LocInvalidRow(row) => write!(f, "<p>The row <i><b>{}</b></i> of this Loc PackedFile doesn't have the key, text and tooltip columns of a Loc PackedFile, so it cannot be exported.</p>", row),
+            ErrorKind::LocCaCsvParse(line, cause) => write!(f, "<p>Error while trying to import the CSV file, on line \"{}\":</p><p>{}</p>", line, cause),
+            ErrorKind::LocEntryCountMismatch { declared, found } => write!(f, "<p>This Loc PackedFile says it has <i><b>{}</b></i> entries, but it ends after <i><b>{}</b></i> entries. This means the file is truncated.</p>", declared, found),
+            ErrorKind::LocDuplicateKey { key } => write!(f, "<p>This Loc PackedFile has more than one entry with the key <i><b>{}</b></i>.</p>", key),
 
             //--------------------------------//
             // Image Errors
             //--------------------------------//
             ErrorKind::ImageDecode(cause) => write!(f, "<p>Error while trying to decode the Image PackedFile:</p><p>{}</p>", cause),
+            ErrorKind::ImageDataCorrupted => write!(f, "<p>The data of this Image PackedFile is corrupted. Maybe it's incomplete?</p>"),
 
             //--------------------------------//
             // CA_VP8 Errors
             //--------------------------------//
             ErrorKind::CaVp8Decode(cause) => write!(f, "<p>Error while trying to decode the CaVp8 PackedFile:</p><p>{}</p>", cause),
 
+            //--------------------------------//
+            // ESF Errors
+            //--------------------------------//
+            ErrorKind::ESFUnsupportedSignature(signature) => write!(f, "<p>This ESF PackedFile has the signature \"{:#X}\", which is not supported.</p>", signature),
+            ErrorKind::ESFUnsupportedDataType(data_type, position) => write!(f, "<p>This ESF PackedFile has a node of type \"{:#X}\" at the byte <i><b>{}</b></i>, which is not supported.</p>", data_type, position),
+            ErrorKind::ESFIncorrectNodeSize(expected, found) => write!(f, "<p>One of the nodes of this ESF PackedFile should end at the byte <i><b>{}</b></i>, but it ends at the byte <i><b>{}</b></i>. This means this PackedFile is corrupted.</p>", expected, found),
+            ErrorKind::ESFRecordNameNotFound(index) => write!(f, "<p>This ESF PackedFile uses the record name number <i><b>{}</b></i>, but there is no record name with that number in it. This means this PackedFile is corrupted.</p>", index),
+            ErrorKind::ESFStringNotFound(index) => write!(f, "<p>This ESF PackedFile uses the string number <i><b>{}</b></i>, but there is no string with that number in it. This means this PackedFile is corrupted.</p>", index),
+            ErrorKind::ESFNodeTooDeep(max_depth) => write!(f, "<p>This ESF PackedFile has nodes nested more than <i><b>{}</b></i> levels deep, which is not supported. This probably means this PackedFile is corrupted.</p>", max_depth),
+
             //--------------------------------//
             // PAK File Errors
             //--------------------------------//
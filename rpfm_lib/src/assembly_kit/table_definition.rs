@@ -35,6 +35,7 @@ use std::path::PathBuf;
 use rpfm_error::{Result, Error, ErrorKind};
 
 use crate::assembly_kit::get_raw_definition_paths;
+use crate::packedfile::PackedFileType;
 use super::*;
 
 //---------------------------------------------------------------------------//
@@ -118,7 +119,7 @@ impl RawDefinition {
                     .filter(|x| if skip_ingame_tables {
                             let base_name = x.file_stem().unwrap().to_str().unwrap().split_at(5).1;
                             let name_table = format!("{}_tables", base_name);
-                            dependency_db.iter().all(|x| x.get_path()[1] != name_table)
+                            dependency_db.iter().all(|x| PackedFileType::get_db_table_name(x.get_path()).map_or(true, |x| x != name_table))
                         } else { true }
                     )
                     .partition_map(|x|
@@ -66,6 +66,9 @@ pub trait Decoder {
     /// This function allows us to decode a f32 float from raw data.
     fn decode_float_f32(&self, offset: usize) -> Result<f32>;
 
+    /// This function allows us to decode a f64 float from raw data.
+    fn decode_float_f64(&self, offset: usize) -> Result<f64>;
+
     /// This function allows us to decode an UTF-8 String  from raw data.
     fn decode_string_u8(&self, offset: usize, size: usize) -> Result<String>;
 
@@ -117,6 +120,9 @@ pub trait Decoder {
     /// This function allows us to decode an f32 encoded float from raw data, moving the provided index to the byte where the next data starts.
     fn decode_packedfile_float_f32(&self, offset: usize, index: &mut usize) -> Result<f32>;
 
+    /// This function allows us to decode an f64 encoded float from raw data, moving the provided index to the byte where the next data starts.
+    fn decode_packedfile_float_f64(&self, offset: usize, index: &mut usize) -> Result<f64>;
+
     /// This function allows us to decode an UTF-8 encoded String from raw data, moving the provided index to the byte where the next data starts.
     fn decode_packedfile_string_u8(&self, offset: usize, index: &mut usize) -> Result<String>;
 
@@ -207,6 +213,11 @@ impl Decoder for [u8] {
         else { Err(ErrorKind::HelperDecodingEncodingError(format!("<p>Error trying to decode an f32 number:</p><ul><li>Required bytes: 4.</li><li>Provided bytes: {:?}.</li></ul>", offset.checked_sub(self.len()))).into()) }
     }
 
+    fn decode_float_f64(&self, offset: usize) -> Result<f64> {
+        if self.len() >= offset + 8 { Ok(LittleEndian::read_f64(&self[offset..])) }
+        else { Err(ErrorKind::HelperDecodingEncodingError(format!("<p>Error trying to decode an f64 number:</p><ul><li>Required bytes: 8.</li><li>Provided bytes: {:?}.</li></ul>", offset.checked_sub(self.len()))).into()) }
+    }
+
     fn decode_string_u8(&self, offset: usize, size: usize) -> Result<String> {
         if self.len() >= offset + size {
             String::from_utf8(self[offset..offset + size].to_vec()).map_err(|_| Error::from(ErrorKind::HelperDecodingEncodingError("<p>Error trying to decode an UTF-8 String.</p>".to_owned())))
@@ -310,6 +321,12 @@ impl Decoder for [u8] {
         result
     }
 
+    fn decode_packedfile_float_f64(&self, offset: usize, index: &mut usize) -> Result<f64> {
+        let result = self.decode_float_f64(offset);
+        if result.is_ok() { *index += 8; }
+        result
+    }
+
     fn decode_packedfile_string_u8(&self, offset: usize, mut index: &mut usize) -> Result<String> {
         if let Ok(size) = self.decode_packedfile_integer_u16(offset, &mut index) {
             let result = self.decode_string_u8(offset + 2, size as usize);
@@ -330,9 +347,9 @@ impl Decoder for [u8] {
     fn decode_packedfile_string_u16(&self, offset: usize, mut index: &mut usize) -> Result<String> {
         if let Ok(size) = self.decode_packedfile_integer_u16(offset, &mut index) {
 
-            // We wrap this to avoid overflow, as the limit of this is 65,535. We do this because u16 Strings
-            // counts pairs of bytes (u16), not single bytes.
-            let size = size.wrapping_mul(2) as usize;
+            // U16 Strings count pairs of bytes (u16), not single bytes. We need to do the math as usize,
+            // as the size of strings longer than 32,767 characters doesn't fit in an u16 once doubled.
+            let size = usize::from(size) * 2;
             let result = self.decode_string_u16(offset + 2, size);
             if result.is_err() { *index -= 2; } else { *index += size; }
             result
@@ -130,6 +130,17 @@ fn test_decode_float_f32() {
     assert_eq!(Decoder::decode_float_f32([0, 32, 65].as_ref(), 0).is_err(), true);
 }
 
+/// Test to make sure the f64 float decoder (`decode_float_f64()`) works and fails properly.
+#[test]
+fn test_decode_float_f64() {
+
+    // Check the decoding works for a proper value.
+    assert_eq!(Decoder::decode_float_f64([0, 0, 0, 0, 0, 0, 36, 64].as_ref(), 0).unwrap(), 10.0);
+
+    // Check the decoder returns an error for a slice who's length is smaller than 8.
+    assert!(Decoder::decode_float_f64([0, 0, 0, 0, 0, 36, 64].as_ref(), 0).is_err());
+}
+
 /// Test to make sure the u8 string decoder (`decode_string_u8()`) works and fails properly.
 #[test]
 fn test_decode_string_u8() {
@@ -405,6 +416,26 @@ fn test_decode_packedfile_float_f32() {
     }
 }
 
+/// Test to make sure the f64 float specific decoder (`decode_packedfile_float_f64()`) works
+/// and fails properly.
+#[test]
+fn test_decode_packedfile_float_f64() {
+
+    // Check the decoding works for a proper value.
+    {
+        let mut index = 0;
+        assert_eq!(Decoder::decode_packedfile_float_f64([102, 102, 102, 102, 102, 102, 36, 192].as_ref(), 0, &mut index).unwrap(), -10.2);
+        assert_eq!(index, 8);
+    }
+
+    // Check the decoder returns an error for a slice whose lenght is smaller than 8.
+    {
+        let mut index = 0;
+        assert!(Decoder::decode_packedfile_float_f64([51, 51, 35, 193].as_ref(), 0, &mut index).is_err());
+        assert_eq!(index, 0);
+    }
+}
+
 /// Test to make sure the u8 string specific decoder (`decode_packedfile_string_u8()`) works
 /// and fails properly.
 #[test]
@@ -58,6 +58,9 @@ pub trait Encoder {
     /// This function allows us to encode a f32 float into the provided `Vec<u8>`.
     fn encode_float_f32(&mut self, float: f32);
 
+    /// This function allows us to encode a f64 float into the provided `Vec<u8>`.
+    fn encode_float_f64(&mut self, float: f64);
+
     /// This function allows us to encode an UTF-8 String into the provided `Vec<u8>`.
     fn encode_string_u8(&mut self, string: &str);
 
@@ -71,6 +74,11 @@ pub trait Encoder {
     /// longer than the provided size, we throw an error.
     fn encode_string_u8_0padded(&mut self, string: &(String, usize)) -> Result<()>;
 
+    /// This function allows us to replace the u32 integer at the provided offset of the provided `Vec<u8>` with another one.
+    ///
+    /// Useful for sizes and offsets we don't know until we encode what comes after them. If there are not enough bytes at that offset, we throw an error.
+    fn replace_integer_u32(&mut self, offset: usize, integer: u32) -> Result<()>;
+
     /// This function allows us to encode an UTF-16 String into the provided `Vec<u8>`.
     fn encode_string_u16(&mut self, string: &str);
 
@@ -130,6 +138,10 @@ impl Encoder for Vec<u8> {
         self.write_f32::<LittleEndian>(float).unwrap();
     }
 
+    fn encode_float_f64(&mut self, float: f64) {
+        self.write_f64::<LittleEndian>(float).unwrap();
+    }
+
     fn encode_string_u8(&mut self, string: &str) {
         self.extend_from_slice(string.as_bytes());
     }
@@ -152,6 +164,16 @@ impl Encoder for Vec<u8> {
         string.encode_utf16().for_each(|character| self.encode_integer_u16(character));
     }
 
+    fn replace_integer_u32(&mut self, offset: usize, integer: u32) -> Result<()> {
+        match self.get_mut(offset..offset.saturating_add(4)) {
+            Some(bytes) => {
+                bytes.copy_from_slice(&integer.to_le_bytes());
+                Ok(())
+            }
+            None => Err(ErrorKind::HelperDecodingEncodingError(format!("Error trying to replace an u32: There are only {} bytes, but we need {} to replace it.", self.len(), offset.saturating_add(4))).into()),
+        }
+    }
+
     //---------------------------------------------------------------------------//
     //                          Indexed Encoders
     //---------------------------------------------------------------------------//
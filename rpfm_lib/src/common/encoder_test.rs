@@ -112,6 +112,16 @@ fn test_encode_float_f32() {
     assert_eq!(data, vec![51, 51, 35, 193]);
 }
 
+/// Test to make sure the f64 float encoder (`encode_float_f64()`) works properly.
+#[test]
+fn test_encode_float_f64() {
+
+    // Check the encoder works properly.
+    let mut data = vec![];
+    data.encode_float_f64(-10.2);
+    assert_eq!(data, vec![102, 102, 102, 102, 102, 102, 36, 192]);
+}
+
 /// Test to make sure the u8 string encoder (`encode_string_u8()`) works properly.
 #[test]
 fn test_encode_string_u8() {
@@ -147,6 +157,21 @@ fn test_encode_string_u16() {
     assert_eq!(data, vec![87, 0, 97, 0, 104, 0, 97, 0, 104, 0, 97, 0]);
 }
 
+/// Test to make sure the u32 integer replacer (`replace_integer_u32()`) works properly.
+#[test]
+fn test_replace_integer_u32() {
+
+    // Check the replacer works for an offset with enough bytes after it.
+    let mut data = vec![1, 0, 0, 0, 0, 0];
+    data.replace_integer_u32(1, 258).unwrap();
+    assert_eq!(data, vec![1, 2, 1, 0, 0, 0]);
+
+    // Check the replacer returns an error, and doesn't change anything, if there are not enough bytes.
+    assert!(data.replace_integer_u32(3, 258).is_err());
+    assert!(data.replace_integer_u32(usize::MAX, 258).is_err());
+    assert_eq!(data, vec![1, 2, 1, 0, 0, 0]);
+}
+
 //---------------------------------------------------------------------------//
 //                          Indexed Encoders
 //---------------------------------------------------------------------------//
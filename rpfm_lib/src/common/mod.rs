@@ -209,3 +209,64 @@ pub fn parse_str(string: &str) -> Result<bool> {
         Err(ErrorKind::NotABooleanValue.into())
     }
 }
+
+/// This function returns the canonical form of a path, split in folders.
+///
+/// Each item of the path is split again on `/` and `\`, and empty items are removed, so paths coming from different sources
+/// end up in the same form. If `lowercase` is true, the path is also turned to lowercase, for games that don't care about the case of their paths.
+pub fn normalize_path(path: &[String], lowercase: bool) -> Vec<String> {
+    path.iter()
+        .flat_map(|x| x.split(|x| x == '/' || x == '\\'))
+        .filter(|x| !x.is_empty())
+        .map(|x| if lowercase { x.to_lowercase() } else { x.to_owned() })
+        .collect()
+}
+
+/// This function checks if a path, split in folders, matches the provided glob pattern.
+///
+/// The pattern uses `/` (or `\`) to separate folders. A `*` matches any amount of characters within a single folder or file name,
+/// and a `**` folder matches any amount of folders, including none. As the games don't care about the case of their paths,
+/// the matching is case-insensitive.
+pub fn path_matches_glob(path: &[String], pattern: &str) -> bool {
+    let pattern = pattern.split(|x| x == '/' || x == '\\').filter(|x| !x.is_empty()).collect::<Vec<&str>>();
+    path_matches_glob_segments(path, &pattern)
+}
+
+/// This function checks if a path matches the provided glob pattern, already split in segments.
+fn path_matches_glob_segments(path: &[String], pattern: &[&str]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(&"**") => (0..=path.len()).any(|index| path_matches_glob_segments(&path[index..], &pattern[1..])),
+        Some(segment_pattern) => !path.is_empty() &&
+            segment_matches_glob(&path[0], segment_pattern) &&
+            path_matches_glob_segments(&path[1..], &pattern[1..]),
+    }
+}
+
+/// This function checks if a single folder or file name matches the provided glob pattern, ignoring the case.
+fn segment_matches_glob(segment: &str, pattern: &str) -> bool {
+    let segment = segment.to_lowercase().chars().collect::<Vec<char>>();
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<char>>();
+
+    // Backtracking matcher: on mismatch, we return to the last `*` found and make it eat one more character.
+    let (mut segment_index, mut pattern_index) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+    while segment_index < segment.len() {
+        if pattern_index < pattern.len() && pattern[pattern_index] == '*' {
+            last_star = Some((pattern_index, segment_index));
+            pattern_index += 1;
+        }
+        else if pattern_index < pattern.len() && pattern[pattern_index] == segment[segment_index] {
+            pattern_index += 1;
+            segment_index += 1;
+        }
+        else if let Some((star_pattern_index, star_segment_index)) = last_star {
+            pattern_index = star_pattern_index + 1;
+            segment_index = star_segment_index + 1;
+            last_star = Some((star_pattern_index, segment_index));
+        }
+        else { return false }
+    }
+
+    pattern[pattern_index..].iter().all(|x| *x == '*')
+}
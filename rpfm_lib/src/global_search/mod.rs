@@ -451,8 +451,10 @@ impl GlobalSearch {
                     }
                     DecodedData::StringU8(ref mut field) |
                     DecodedData::StringU16(ref mut field) |
-                    DecodedData::OptionalStringU8(ref mut field) |
-                    DecodedData::OptionalStringU16(ref mut field) => self.replace_match(field, matching_mode),
+                    DecodedData::OptionalStringU8(Some(ref mut field)) |
+                    DecodedData::OptionalStringU16(Some(ref mut field)) => self.replace_match(field, matching_mode),
+                    DecodedData::OptionalStringU8(None) |
+                    DecodedData::OptionalStringU16(None) => {},
                     DecodedData::Sequence(_) => return Err(ErrorKind::Generic.into()),
                 }
 
@@ -498,8 +500,10 @@ impl GlobalSearch {
 
                     DecodedData::StringU8(ref data) |
                     DecodedData::StringU16(ref data) |
-                    DecodedData::OptionalStringU8(ref data) |
-                    DecodedData::OptionalStringU16(ref data) => self.match_decoded_data(data, matching_mode, &mut matches.matches, table_data.get_ref_definition(), column_number as u32, row_number as i64),
+                    DecodedData::OptionalStringU8(Some(ref data)) |
+                    DecodedData::OptionalStringU16(Some(ref data)) => self.match_decoded_data(data, matching_mode, &mut matches.matches, table_data.get_ref_definition(), column_number as u32, row_number as i64),
+                    DecodedData::OptionalStringU8(None) |
+                    DecodedData::OptionalStringU16(None) |
                     DecodedData::Sequence(_) => continue,
                 }
             }
@@ -525,8 +529,10 @@ impl GlobalSearch {
 
                     DecodedData::StringU8(ref data) |
                     DecodedData::StringU16(ref data) |
-                    DecodedData::OptionalStringU8(ref data) |
-                    DecodedData::OptionalStringU16(ref data) => self.match_decoded_data(data, matching_mode, &mut matches.matches, table_data.get_ref_definition(), column_number as u32, row_number as i64),
+                    DecodedData::OptionalStringU8(Some(ref data)) |
+                    DecodedData::OptionalStringU16(Some(ref data)) => self.match_decoded_data(data, matching_mode, &mut matches.matches, table_data.get_ref_definition(), column_number as u32, row_number as i64),
+                    DecodedData::OptionalStringU8(None) |
+                    DecodedData::OptionalStringU16(None) |
                     DecodedData::Sequence(_) => continue,
                 }
             }
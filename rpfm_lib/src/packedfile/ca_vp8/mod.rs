@@ -124,6 +124,7 @@ impl CaVp8 {
     /// NOTE: this takes a whole vector, not a reference. The reason is this vector can by enormous and this way
     /// we can avoid duplicates.
     pub fn read(packed_file_data: Vec<u8>) -> Result<Self> {
+        if packed_file_data.is_empty() { return Err(ErrorKind::PackedFileDataIsEmpty.into()) }
         match &*packed_file_data.decode_string_u8(0, 4)? {
             SIGNATURE_IVF => Self::read_ivf(packed_file_data),
             SIGNATURE_CAMV => Self::read_camv(packed_file_data),
@@ -0,0 +1,217 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `ESF` module, just to make sure we don't break it... again...
+!*/
+
+use rpfm_error::ErrorKind;
+
+use super::{EsfFile, EsfNode, MAX_NODE_DEPTH, RecordBlockNode, RecordNode};
+
+/// This function returns the data of a small startpos ESF PackedFile, for testing purpouses.
+fn get_test_data() -> Vec<u8> {
+    let mut data = vec![];
+
+    // Header.
+    data.extend_from_slice(&[0xCE, 0xAB, 0, 0]);
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(&[0x10, 0x32, 0x54, 0x5F]);
+    data.extend_from_slice(&[93, 0, 0, 0]);
+
+    // Root record.
+    data.extend_from_slice(&[0x80, 0, 0, 1, 93, 0, 0, 0]);
+    data.extend_from_slice(&[0x01, 1]);
+    data.extend_from_slice(&[0x04, 0xFB, 0xFF, 0xFF, 0xFF]);
+    data.extend_from_slice(&[0x0E, 0, 0, 0, 0]);
+    data.extend_from_slice(&[0x0F, 3, 0, 0, 0]);
+    data.push(0x0D);
+    data.extend_from_slice(&1.0f32.to_le_bytes());
+    data.extend_from_slice(&(-2.5f32).to_le_bytes());
+    data.extend_from_slice(&0.0f32.to_le_bytes());
+
+    // Record block with two entries.
+    data.extend_from_slice(&[0x81, 1, 0, 0, 93, 0, 0, 0, 2, 0, 0, 0]);
+    data.extend_from_slice(&[75, 0, 0, 0]);
+    data.extend_from_slice(&[0x08, 7, 0, 0, 0]);
+    data.extend_from_slice(&[93, 0, 0, 0]);
+    data.extend_from_slice(&[0x0F, 3, 0, 0, 0]);
+    data.push(0x0B);
+    data.extend_from_slice(&1.5f64.to_le_bytes());
+
+    // Record names.
+    data.extend_from_slice(&[2, 0]);
+    data.extend_from_slice(&[17, 0]);
+    data.extend_from_slice(b"CAMPAIGN_STARTPOS");
+    data.extend_from_slice(&[7, 0]);
+    data.extend_from_slice(b"FACTION");
+
+    // UTF-16 strings.
+    data.extend_from_slice(&[1, 0, 0, 0]);
+    data.extend_from_slice(&[4, 0, b'K', 0, b'a', 0, b'r', 0, b'l', 0]);
+    data.extend_from_slice(&[0, 0, 0, 0]);
+
+    // ASCII strings.
+    data.extend_from_slice(&[1, 0, 0, 0]);
+    data.extend_from_slice(&[11, 0]);
+    data.extend_from_slice(b"wh_main_emp");
+    data.extend_from_slice(&[3, 0, 0, 0]);
+    data
+}
+
+/// Test to make sure ESF PackedFiles are decoded properly, and encoded back to the same data.
+#[test]
+fn test_read_save() {
+    let data = get_test_data();
+    let esf = EsfFile::read(&data).unwrap();
+    assert_eq!(esf.get_creation_date(), 0x5F54_3210);
+    assert_eq!(esf.get_ref_root_node(), &EsfNode::Record(RecordNode {
+        name: "CAMPAIGN_STARTPOS".to_owned(),
+        version: 1,
+        children: vec![
+            EsfNode::Bool(true),
+            EsfNode::I32(-5),
+            EsfNode::Utf16("Karl".to_owned()),
+            EsfNode::Ascii("wh_main_emp".to_owned()),
+            EsfNode::Coord3d(1.0, -2.5, 0.0),
+            EsfNode::RecordBlock(RecordBlockNode {
+                name: "FACTION".to_owned(),
+                version: 0,
+                entries: vec![
+                    vec![EsfNode::U32(7)],
+                    vec![EsfNode::Ascii("wh_main_emp".to_owned()), EsfNode::F64(1.5)],
+                ],
+            }),
+        ],
+    }));
+
+    assert_eq!(esf.save().unwrap(), data);
+}
+
+/// Test to make sure edited ESF PackedFiles keep their existing strings, and add the new ones at the end.
+#[test]
+fn test_save_edited() {
+    let mut esf = EsfFile::read(&get_test_data()).unwrap();
+    if let EsfNode::Record(record) = esf.get_ref_mut_root_node() {
+        record.children.push(EsfNode::Ascii("wh_main_dwf".to_owned()));
+        record.children.push(EsfNode::Record(RecordNode { name: "CHARACTER".to_owned(), version: 2, children: vec![EsfNode::Utf16("Karl".to_owned())] }));
+    }
+
+    let data = esf.save().unwrap();
+    let esf_new = EsfFile::read(&data).unwrap();
+    assert_eq!(esf_new.get_ref_root_node(), esf.get_ref_root_node());
+    assert_eq!(esf_new.record_names, vec!["CAMPAIGN_STARTPOS".to_owned(), "FACTION".to_owned(), "CHARACTER".to_owned()]);
+    assert_eq!(esf_new.strings_utf16, vec![("Karl".to_owned(), 0)]);
+    assert_eq!(esf_new.strings_ascii, vec![("wh_main_emp".to_owned(), 3), ("wh_main_dwf".to_owned(), 4)]);
+
+    // A new file should be readable too.
+    let esf = EsfFile::new(EsfNode::Record(RecordNode { name: "ROOT".to_owned(), version: 0, children: vec![EsfNode::Angle(90), EsfNode::Ascii("a".to_owned())] }));
+    assert_eq!(EsfFile::read(&esf.save().unwrap()).unwrap().get_ref_root_node(), esf.get_ref_root_node());
+}
+
+/// Test to make sure every type of node survives a save and read cycle, and that saving it again gives the same data.
+#[test]
+fn test_all_node_types() {
+    let root_node = EsfNode::Record(RecordNode {
+        name: "ROOT".to_owned(),
+        version: 3,
+        children: vec![
+            EsfNode::Bool(false),
+            EsfNode::I8(-8),
+            EsfNode::I16(-16),
+            EsfNode::I32(-32),
+            EsfNode::I64(-64),
+            EsfNode::U8(8),
+            EsfNode::U16(16),
+            EsfNode::U32(32),
+            EsfNode::U64(64),
+            EsfNode::F32(0.5),
+            EsfNode::F64(-0.25),
+            EsfNode::Coord2d(1.0, 2.0),
+            EsfNode::Coord3d(1.0, 2.0, 3.0),
+            EsfNode::Utf16("Ünicode".to_owned()),
+            EsfNode::Ascii("ascii".to_owned()),
+            EsfNode::Angle(180),
+            EsfNode::BoolArray(vec![true, false, true]),
+            EsfNode::I8Array(vec![-1, 0, 1]),
+            EsfNode::I16Array(vec![-300, 300]),
+            EsfNode::I32Array(vec![-70000]),
+            EsfNode::I64Array(vec![i64::MIN, i64::MAX]),
+            EsfNode::U8Array(vec![]),
+            EsfNode::U16Array(vec![1, 2, 3]),
+            EsfNode::U32Array(vec![u32::MAX]),
+            EsfNode::U64Array(vec![u64::MAX, 0]),
+            EsfNode::F32Array(vec![1.5, -1.5]),
+            EsfNode::F64Array(vec![2.5]),
+            EsfNode::Coord2dArray(vec![(0.0, 1.0), (2.0, 3.0)]),
+            EsfNode::Coord3dArray(vec![(0.0, 1.0, 2.0)]),
+            EsfNode::Utf16Array(vec!["Ünicode".to_owned(), "other".to_owned()]),
+            EsfNode::AsciiArray(vec!["ascii".to_owned(), "more_ascii".to_owned()]),
+            EsfNode::AngleArray(vec![0, 90, 270]),
+            EsfNode::Record(RecordNode { name: "EMPTY".to_owned(), version: 0, children: vec![] }),
+            EsfNode::RecordBlock(RecordBlockNode {
+                name: "BLOCK".to_owned(),
+                version: 1,
+                entries: vec![vec![], vec![EsfNode::Record(RecordNode { name: "ROOT".to_owned(), version: 0, children: vec![EsfNode::U8(1)] })]],
+            }),
+        ],
+    });
+
+    let data = EsfFile::new(root_node.clone()).save().unwrap();
+    let esf = EsfFile::read(&data).unwrap();
+    assert_eq!(esf.get_ref_root_node(), &root_node);
+    assert_eq!(esf.save().unwrap(), data);
+
+    // Arrays ending in the middle of an item are broken.
+    let mut data = EsfFile::new(EsfNode::U16Array(vec![1, 2])).save().unwrap();
+    data[17] = 24;
+    assert_eq!(EsfFile::read(&data).unwrap_err().kind(), &ErrorKind::ESFIncorrectNodeSize(24, 25));
+}
+
+/// Test to make sure broken or unsupported ESF PackedFiles are rejected.
+#[test]
+fn test_read_errors() {
+    let mut data = get_test_data();
+    data[0] = 0xCA;
+    assert_eq!(EsfFile::read(&data).unwrap_err().kind(), &ErrorKind::ESFUnsupportedSignature(0xABCA));
+
+    let mut data = get_test_data();
+    data[24] = 0x30;
+    assert_eq!(EsfFile::read(&data).unwrap_err().kind(), &ErrorKind::ESFUnsupportedDataType(0x30, 24));
+
+    // Root record ending in the middle of the record block.
+    let mut data = get_test_data();
+    data[20] = 70;
+    assert_eq!(EsfFile::read(&data).unwrap_err().kind(), &ErrorKind::ESFIncorrectNodeSize(70, 93));
+
+    let mut data = get_test_data();
+    data[37] = 9;
+    assert_eq!(EsfFile::read(&data).unwrap_err().kind(), &ErrorKind::ESFStringNotFound(9));
+
+    let mut data = get_test_data();
+    data.push(0);
+    assert_eq!(EsfFile::read(&data).unwrap_err().kind(), &ErrorKind::PackedFileHasExtraData(data.len() - 1, data.len()));
+}
+
+/// Test to make sure ESF PackedFiles with records nested too deep are rejected instead of overflowing the stack.
+#[test]
+fn test_read_max_depth() {
+    let nested = |depth: u32| {
+        let mut node = EsfNode::U8(1);
+        for _ in 0..depth {
+            node = EsfNode::Record(RecordNode { name: "NESTED".to_owned(), version: 0, children: vec![node] });
+        }
+        EsfFile::new(node).save().unwrap()
+    };
+
+    // The root node is at depth 0, so here the innermost node is exactly at the max depth.
+    assert!(EsfFile::read(&nested(MAX_NODE_DEPTH)).is_ok());
+    assert_eq!(EsfFile::read(&nested(MAX_NODE_DEPTH + 1)).unwrap_err().kind(), &ErrorKind::ESFNodeTooDeep(MAX_NODE_DEPTH));
+}
@@ -0,0 +1,753 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module with all the code to interact with ESF PackedFiles.
+
+ESF PackedFiles are binary files used by the games to store trees of data, like the campaign start positions (`startpos.esf`)
+or the CEO files (`.ccd`). We decode all of them into the same generic tree of nodes, keeping everything we need to encode them back
+to the same data, so specific decoders can be built on top of it.
+
+Only the `CEAB` signature is supported. Its structure is:
+
+- Header:
+    - Signature (`u32`).
+    - Unknown (`u32`).
+    - Creation date (`u32`).
+    - Offset of the record names and strings (`u32`).
+- Root node.
+- Record names:
+    - Amount of record names (`u16`).
+    - Each record name (`StringU8`).
+- UTF-16 strings:
+    - Amount of strings (`u32`).
+    - Each string (`StringU16`) and its index (`u32`).
+- ASCII strings:
+    - Amount of strings (`u32`).
+    - Each string (`StringU8`) and its index (`u32`).
+
+Each node starts with a byte with its type, followed by its data. Records contain the index of their name (`u16`),
+their version (`u8`) and the offset where they end (`u32`), followed by their children. Strings inside nodes are
+just the index (`u32`) of the string in the lists at the end of the file. Arrays contain the offset where they end (`u32`),
+followed by their items, encoded like the scalar nodes of their type but without the type byte.
+!*/
+
+use std::collections::HashMap;
+
+use rpfm_error::{ErrorKind, Result};
+
+use crate::common::{decoder::Decoder, encoder::Encoder};
+
+#[cfg(test)]
+mod esf_test;
+
+/// Name of the StarPos PackedFiles. Other ESF PackedFiles use the `.esf` extension too, so only this name counts.
+pub const NAME_STAR_POS: &str = "startpos.esf";
+
+/// Extension used by CEO PackedFiles.
+pub const EXTENSION_CEO: &str = ".ccd";
+
+/// Signature of the ESF PackedFiles we can decode.
+pub const SIGNATURE_CEAB: u32 = 0xABCE;
+
+/// Position of the offset of the string tables in the header of a `CEAB` ESF PackedFile.
+const STRINGS_OFFSET_POSITION: usize = 12;
+
+/// Maximum depth of nested records we decode, so broken files cannot make us run out of stack.
+pub const MAX_NODE_DEPTH: u32 = 256;
+
+// Types of the nodes we can decode.
+const BOOL: u8 = 0x01;
+const I8: u8 = 0x02;
+const I16: u8 = 0x03;
+const I32: u8 = 0x04;
+const I64: u8 = 0x05;
+const U8: u8 = 0x06;
+const U16: u8 = 0x07;
+const U32: u8 = 0x08;
+const U64: u8 = 0x09;
+const F32: u8 = 0x0A;
+const F64: u8 = 0x0B;
+const COORD_2D: u8 = 0x0C;
+const COORD_3D: u8 = 0x0D;
+const UTF16: u8 = 0x0E;
+const ASCII: u8 = 0x0F;
+const ANGLE: u8 = 0x10;
+const BOOL_ARRAY: u8 = 0x41;
+const I8_ARRAY: u8 = 0x42;
+const I16_ARRAY: u8 = 0x43;
+const I32_ARRAY: u8 = 0x44;
+const I64_ARRAY: u8 = 0x45;
+const U8_ARRAY: u8 = 0x46;
+const U16_ARRAY: u8 = 0x47;
+const U32_ARRAY: u8 = 0x48;
+const U64_ARRAY: u8 = 0x49;
+const F32_ARRAY: u8 = 0x4A;
+const F64_ARRAY: u8 = 0x4B;
+const COORD_2D_ARRAY: u8 = 0x4C;
+const COORD_3D_ARRAY: u8 = 0x4D;
+const UTF16_ARRAY: u8 = 0x4E;
+const ASCII_ARRAY: u8 = 0x4F;
+const ANGLE_ARRAY: u8 = 0x50;
+const RECORD: u8 = 0x80;
+const RECORD_BLOCK: u8 = 0x81;
+
+//---------------------------------------------------------------------------//
+//                              Enum & Structs
+//---------------------------------------------------------------------------//
+
+/// This holds an entire ESF PackedFile decoded in memory.
+#[derive(PartialEq, Clone, Debug)]
+pub struct EsfFile {
+
+    /// Signature of the file.
+    signature: u32,
+
+    /// Unknown value of the header.
+    unknown_1: u32,
+
+    /// Creation date of the file.
+    creation_date: u32,
+
+    /// The root node of the tree of data of the file.
+    root_node: EsfNode,
+
+    /// The list of record names of the file, in the order they're in the file.
+    record_names: Vec<String>,
+
+    /// The list of UTF-16 strings of the file, with their indexes, in the order they're in the file.
+    strings_utf16: Vec<(String, u32)>,
+
+    /// The list of ASCII strings of the file, with their indexes, in the order they're in the file.
+    strings_ascii: Vec<(String, u32)>,
+}
+
+/// This enum represents a node of the tree of data of an ESF PackedFile.
+#[derive(PartialEq, Clone, Debug)]
+pub enum EsfNode {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Coord2d(f32, f32),
+    Coord3d(f32, f32, f32),
+    Utf16(String),
+    Ascii(String),
+    Angle(u16),
+    BoolArray(Vec<bool>),
+    I8Array(Vec<i8>),
+    I16Array(Vec<i16>),
+    I32Array(Vec<i32>),
+    I64Array(Vec<i64>),
+    U8Array(Vec<u8>),
+    U16Array(Vec<u16>),
+    U32Array(Vec<u32>),
+    U64Array(Vec<u64>),
+    F32Array(Vec<f32>),
+    F64Array(Vec<f64>),
+    Coord2dArray(Vec<(f32, f32)>),
+    Coord3dArray(Vec<(f32, f32, f32)>),
+    Utf16Array(Vec<String>),
+    AsciiArray(Vec<String>),
+    AngleArray(Vec<u16>),
+    Record(RecordNode),
+    RecordBlock(RecordBlockNode),
+}
+
+/// This struct represents a record node, a named group of nodes.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct RecordNode {
+
+    /// Name of the record.
+    pub name: String,
+
+    /// Version of the record.
+    pub version: u8,
+
+    /// Nodes inside the record.
+    pub children: Vec<EsfNode>,
+}
+
+/// This struct represents a record block node, a named list of groups of nodes.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct RecordBlockNode {
+
+    /// Name of the record block.
+    pub name: String,
+
+    /// Version of the record block.
+    pub version: u8,
+
+    /// Groups of nodes inside the record block.
+    pub entries: Vec<Vec<EsfNode>>,
+}
+
+/// This struct holds the lists of record names and strings of an ESF PackedFile while we decode/encode it.
+struct StringTables {
+    record_names: Vec<String>,
+    record_name_indexes: HashMap<String, u16>,
+    strings_utf16: StringTable,
+    strings_ascii: StringTable,
+}
+
+/// This struct holds one of the lists of strings of an ESF PackedFile while encoding it.
+///
+/// The strings are in a `HashMap` with their index, so we don't have to go through the entire list for every string we find.
+struct StringTable {
+    indexes: HashMap<String, u32>,
+    next_index: u32,
+    new_strings: Vec<(String, u32)>,
+}
+
+//---------------------------------------------------------------------------//
+//                       Implementation of EsfFile
+//---------------------------------------------------------------------------//
+
+/// Implementation of `EsfFile`.
+impl EsfFile {
+
+    /// This function creates a new `EsfFile` with the provided node as root.
+    pub fn new(root_node: EsfNode) -> Self {
+        Self {
+            signature: SIGNATURE_CEAB,
+            unknown_1: 0,
+            creation_date: 0,
+            root_node,
+            record_names: vec![],
+            strings_utf16: vec![],
+            strings_ascii: vec![],
+        }
+    }
+
+    /// This function returns if the provided data corresponds to an ESF PackedFile we can decode or not, based on its signature.
+    pub fn is_esf(data: &[u8]) -> bool {
+        data.decode_integer_u32(0).map_or(false, |signature| signature == SIGNATURE_CEAB)
+    }
+
+    /// This function creates an `EsfFile` from a `&[u8]`.
+    pub fn read(packed_file_data: &[u8]) -> Result<Self> {
+        if packed_file_data.is_empty() { return Err(ErrorKind::PackedFileDataIsEmpty.into()) }
+        let mut index = 0;
+        let signature = packed_file_data.decode_packedfile_integer_u32(index, &mut index)?;
+        if signature != SIGNATURE_CEAB { return Err(ErrorKind::ESFUnsupportedSignature(signature).into()) }
+
+        let unknown_1 = packed_file_data.decode_packedfile_integer_u32(index, &mut index)?;
+        let creation_date = packed_file_data.decode_packedfile_integer_u32(index, &mut index)?;
+        let strings_offset = packed_file_data.decode_packedfile_integer_u32(index, &mut index)? as usize;
+
+        // The record names and strings are at the end of the file, but we need them to decode the nodes, so we get them first.
+        let mut strings_index = strings_offset;
+        let record_names_count = packed_file_data.decode_packedfile_integer_u16(strings_index, &mut strings_index)?;
+        let mut record_names = vec![];
+        for _ in 0..record_names_count {
+            record_names.push(packed_file_data.decode_packedfile_string_u8(strings_index, &mut strings_index)?);
+        }
+
+        let strings_utf16_count = packed_file_data.decode_packedfile_integer_u32(strings_index, &mut strings_index)?;
+        let mut strings_utf16 = vec![];
+        for _ in 0..strings_utf16_count {
+            let string = packed_file_data.decode_packedfile_string_u16(strings_index, &mut strings_index)?;
+            let string_index = packed_file_data.decode_packedfile_integer_u32(strings_index, &mut strings_index)?;
+            strings_utf16.push((string, string_index));
+        }
+
+        let strings_ascii_count = packed_file_data.decode_packedfile_integer_u32(strings_index, &mut strings_index)?;
+        let mut strings_ascii = vec![];
+        for _ in 0..strings_ascii_count {
+            let string = packed_file_data.decode_packedfile_string_u8(strings_index, &mut strings_index)?;
+            let string_index = packed_file_data.decode_packedfile_integer_u32(strings_index, &mut strings_index)?;
+            strings_ascii.push((string, string_index));
+        }
+
+        if strings_index < packed_file_data.len() { return Err(ErrorKind::PackedFileHasExtraData(strings_index, packed_file_data.len()).into()) }
+
+        let strings_utf16_by_index = strings_utf16.iter().map(|(string, string_index)| (*string_index, string)).collect::<HashMap<u32, &String>>();
+        let strings_ascii_by_index = strings_ascii.iter().map(|(string, string_index)| (*string_index, string)).collect::<HashMap<u32, &String>>();
+
+        let root_node = Self::read_node(
+            &packed_file_data[..strings_offset.min(packed_file_data.len())],
+            &mut index,
+            &record_names,
+            &strings_utf16_by_index,
+            &strings_ascii_by_index,
+            0
+        )?;
+
+        // If we are not where the strings start, it means we didn't parse the entire tree, which means this file is corrupt.
+        if index < strings_offset { return Err(ErrorKind::PackedFileHasExtraData(index, strings_offset).into()) }
+
+        Ok(Self {
+            signature,
+            unknown_1,
+            creation_date,
+            root_node,
+            record_names,
+            strings_utf16,
+            strings_ascii,
+        })
+    }
+
+    /// This function takes an `EsfFile` and encodes it to `Vec<u8>`.
+    ///
+    /// Record names and strings already in the file keep their indexes. New ones are added at the end of their lists.
+    pub fn save(&self) -> Result<Vec<u8>> {
+        let mut tables = StringTables {
+            record_names: self.record_names.to_vec(),
+            record_name_indexes: self.record_names.iter().enumerate().rev().map(|(index, name)| (name.to_owned(), index as u16)).collect(),
+            strings_utf16: StringTable::new(&self.strings_utf16),
+            strings_ascii: StringTable::new(&self.strings_ascii),
+        };
+
+        let mut packed_file = vec![];
+        packed_file.encode_integer_u32(self.signature);
+        packed_file.encode_integer_u32(self.unknown_1);
+        packed_file.encode_integer_u32(self.creation_date);
+        packed_file.encode_integer_u32(0);
+
+        Self::save_node(&self.root_node, &mut packed_file, &mut tables)?;
+
+        let strings_offset = packed_file.len() as u32;
+        packed_file.replace_integer_u32(STRINGS_OFFSET_POSITION, strings_offset)?;
+
+        packed_file.encode_integer_u16(tables.record_names.len() as u16);
+        for record_name in &tables.record_names {
+            packed_file.encode_packedfile_string_u8(record_name);
+        }
+
+        packed_file.encode_integer_u32((self.strings_utf16.len() + tables.strings_utf16.new_strings.len()) as u32);
+        for (string, index) in self.strings_utf16.iter().chain(tables.strings_utf16.new_strings.iter()) {
+            packed_file.encode_packedfile_string_u16(string);
+            packed_file.encode_integer_u32(*index);
+        }
+
+        packed_file.encode_integer_u32((self.strings_ascii.len() + tables.strings_ascii.new_strings.len()) as u32);
+        for (string, index) in self.strings_ascii.iter().chain(tables.strings_ascii.new_strings.iter()) {
+            packed_file.encode_packedfile_string_u8(string);
+            packed_file.encode_integer_u32(*index);
+        }
+
+        Ok(packed_file)
+    }
+
+    /// This function returns a reference to the root node of the `EsfFile`.
+    pub fn get_ref_root_node(&self) -> &EsfNode {
+        &self.root_node
+    }
+
+    /// This function returns a mutable reference to the root node of the `EsfFile`.
+    pub fn get_ref_mut_root_node(&mut self) -> &mut EsfNode {
+        &mut self.root_node
+    }
+
+    /// This function returns the creation date of the `EsfFile`.
+    pub fn get_creation_date(&self) -> u32 {
+        self.creation_date
+    }
+
+    /// This function decodes the node starting at `index`, moving the index to the end of the node.
+    ///
+    /// The depth is the amount of records this node is inside of. If it's over `MAX_NODE_DEPTH`, this returns an error.
+    fn read_node(
+        data: &[u8],
+        index: &mut usize,
+        record_names: &[String],
+        strings_utf16: &HashMap<u32, &String>,
+        strings_ascii: &HashMap<u32, &String>,
+        depth: u32,
+    ) -> Result<EsfNode> {
+        if depth > MAX_NODE_DEPTH { return Err(ErrorKind::ESFNodeTooDeep(MAX_NODE_DEPTH).into()) }
+        let node_type = data.decode_packedfile_integer_u8(*index, index)?;
+        let node = match node_type {
+            BOOL => EsfNode::Bool(data.decode_packedfile_bool(*index, index)?),
+            I8 => EsfNode::I8(data.decode_packedfile_integer_i8(*index, index)?),
+            I16 => EsfNode::I16(data.decode_packedfile_integer_i16(*index, index)?),
+            I32 => EsfNode::I32(data.decode_packedfile_integer_i32(*index, index)?),
+            I64 => EsfNode::I64(data.decode_packedfile_integer_i64(*index, index)?),
+            U8 => EsfNode::U8(data.decode_packedfile_integer_u8(*index, index)?),
+            U16 => EsfNode::U16(data.decode_packedfile_integer_u16(*index, index)?),
+            U32 => EsfNode::U32(data.decode_packedfile_integer_u32(*index, index)?),
+            U64 => EsfNode::U64(data.decode_packedfile_integer_u64(*index, index)?),
+            F32 => EsfNode::F32(data.decode_packedfile_float_f32(*index, index)?),
+            F64 => EsfNode::F64(data.decode_packedfile_float_f64(*index, index)?),
+            COORD_2D => {
+                let (x, y) = Self::read_coord_2d(data, index)?;
+                EsfNode::Coord2d(x, y)
+            }
+            COORD_3D => {
+                let (x, y, z) = Self::read_coord_3d(data, index)?;
+                EsfNode::Coord3d(x, y, z)
+            }
+            UTF16 => EsfNode::Utf16(Self::read_string(data, index, strings_utf16)?),
+            ASCII => EsfNode::Ascii(Self::read_string(data, index, strings_ascii)?),
+            ANGLE => EsfNode::Angle(data.decode_packedfile_integer_u16(*index, index)?),
+            BOOL_ARRAY => EsfNode::BoolArray(Self::read_array(data, index, |data, index| data.decode_packedfile_bool(*index, index))?),
+            I8_ARRAY => EsfNode::I8Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_i8(*index, index))?),
+            I16_ARRAY => EsfNode::I16Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_i16(*index, index))?),
+            I32_ARRAY => EsfNode::I32Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_i32(*index, index))?),
+            I64_ARRAY => EsfNode::I64Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_i64(*index, index))?),
+            U8_ARRAY => EsfNode::U8Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_u8(*index, index))?),
+            U16_ARRAY => EsfNode::U16Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_u16(*index, index))?),
+            U32_ARRAY => EsfNode::U32Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_u32(*index, index))?),
+            U64_ARRAY => EsfNode::U64Array(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_u64(*index, index))?),
+            F32_ARRAY => EsfNode::F32Array(Self::read_array(data, index, |data, index| data.decode_packedfile_float_f32(*index, index))?),
+            F64_ARRAY => EsfNode::F64Array(Self::read_array(data, index, |data, index| data.decode_packedfile_float_f64(*index, index))?),
+            COORD_2D_ARRAY => EsfNode::Coord2dArray(Self::read_array(data, index, Self::read_coord_2d)?),
+            COORD_3D_ARRAY => EsfNode::Coord3dArray(Self::read_array(data, index, Self::read_coord_3d)?),
+            UTF16_ARRAY => EsfNode::Utf16Array(Self::read_array(data, index, |data, index| Self::read_string(data, index, strings_utf16))?),
+            ASCII_ARRAY => EsfNode::AsciiArray(Self::read_array(data, index, |data, index| Self::read_string(data, index, strings_ascii))?),
+            ANGLE_ARRAY => EsfNode::AngleArray(Self::read_array(data, index, |data, index| data.decode_packedfile_integer_u16(*index, index))?),
+            RECORD => {
+                let name_index = data.decode_packedfile_integer_u16(*index, index)?;
+                let name = record_names.get(name_index as usize).ok_or(ErrorKind::ESFRecordNameNotFound(name_index))?.to_owned();
+                let version = data.decode_packedfile_integer_u8(*index, index)?;
+                let end_offset = data.decode_packedfile_integer_u32(*index, index)? as usize;
+
+                let mut children = vec![];
+                while *index < end_offset {
+                    children.push(Self::read_node(data, index, record_names, strings_utf16, strings_ascii, depth + 1)?);
+                }
+                if *index != end_offset { return Err(ErrorKind::ESFIncorrectNodeSize(end_offset, *index).into()) }
+
+                EsfNode::Record(RecordNode {
+                    name,
+                    version,
+                    children,
+                })
+            }
+            RECORD_BLOCK => {
+                let name_index = data.decode_packedfile_integer_u16(*index, index)?;
+                let name = record_names.get(name_index as usize).ok_or(ErrorKind::ESFRecordNameNotFound(name_index))?.to_owned();
+                let version = data.decode_packedfile_integer_u8(*index, index)?;
+                let end_offset = data.decode_packedfile_integer_u32(*index, index)? as usize;
+                let entry_count = data.decode_packedfile_integer_u32(*index, index)?;
+
+                let mut entries = vec![];
+                for _ in 0..entry_count {
+                    let entry_end_offset = data.decode_packedfile_integer_u32(*index, index)? as usize;
+                    let mut entry = vec![];
+                    while *index < entry_end_offset {
+                        entry.push(Self::read_node(data, index, record_names, strings_utf16, strings_ascii, depth + 1)?);
+                    }
+                    if *index != entry_end_offset { return Err(ErrorKind::ESFIncorrectNodeSize(entry_end_offset, *index).into()) }
+                    entries.push(entry);
+                }
+                if *index != end_offset { return Err(ErrorKind::ESFIncorrectNodeSize(end_offset, *index).into()) }
+
+                EsfNode::RecordBlock(RecordBlockNode {
+                    name,
+                    version,
+                    entries,
+                })
+            }
+            _ => return Err(ErrorKind::ESFUnsupportedDataType(node_type, *index - 1).into()),
+        };
+
+        Ok(node)
+    }
+
+    /// This function decodes an array node starting at `index` (after its type byte), using the provided function to decode each item.
+    fn read_array<T>(data: &[u8], index: &mut usize, read_item: impl Fn(&[u8], &mut usize) -> Result<T>) -> Result<Vec<T>> {
+        let end_offset = data.decode_packedfile_integer_u32(*index, index)? as usize;
+        let mut items = vec![];
+        while *index < end_offset {
+            items.push(read_item(data, index)?);
+        }
+        if *index != end_offset { return Err(ErrorKind::ESFIncorrectNodeSize(end_offset, *index).into()) }
+        Ok(items)
+    }
+
+    /// This function decodes a 2D coordinate starting at `index`, moving the index to the end of it.
+    fn read_coord_2d(data: &[u8], index: &mut usize) -> Result<(f32, f32)> {
+        Ok((
+            data.decode_packedfile_float_f32(*index, index)?,
+            data.decode_packedfile_float_f32(*index, index)?
+        ))
+    }
+
+    /// This function decodes a 3D coordinate starting at `index`, moving the index to the end of it.
+    fn read_coord_3d(data: &[u8], index: &mut usize) -> Result<(f32, f32, f32)> {
+        Ok((
+            data.decode_packedfile_float_f32(*index, index)?,
+            data.decode_packedfile_float_f32(*index, index)?,
+            data.decode_packedfile_float_f32(*index, index)?
+        ))
+    }
+
+    /// This function decodes the index of a string starting at `index`, and returns the string with that index from the provided list.
+    fn read_string(data: &[u8], index: &mut usize, strings: &HashMap<u32, &String>) -> Result<String> {
+        let string_index = data.decode_packedfile_integer_u32(*index, index)?;
+        strings.get(&string_index).map(|x| x.to_string()).ok_or_else(|| ErrorKind::ESFStringNotFound(string_index).into())
+    }
+
+    /// This function encodes the provided node at the end of `data`, adding any new record name or string to the provided tables.
+    fn save_node(node: &EsfNode, data: &mut Vec<u8>, tables: &mut StringTables) -> Result<()> {
+        match node {
+            EsfNode::Bool(value) => {
+                data.push(BOOL);
+                data.encode_bool(*value);
+            }
+            EsfNode::I8(value) => {
+                data.push(I8);
+                data.encode_integer_i8(*value);
+            }
+            EsfNode::I16(value) => {
+                data.push(I16);
+                data.encode_integer_i16(*value);
+            }
+            EsfNode::I32(value) => {
+                data.push(I32);
+                data.encode_integer_i32(*value);
+            }
+            EsfNode::I64(value) => {
+                data.push(I64);
+                data.encode_integer_i64(*value);
+            }
+            EsfNode::U8(value) => {
+                data.push(U8);
+                data.push(*value);
+            }
+            EsfNode::U16(value) => {
+                data.push(U16);
+                data.encode_integer_u16(*value);
+            }
+            EsfNode::U32(value) => {
+                data.push(U32);
+                data.encode_integer_u32(*value);
+            }
+            EsfNode::U64(value) => {
+                data.push(U64);
+                data.encode_integer_u64(*value);
+            }
+            EsfNode::F32(value) => {
+                data.push(F32);
+                data.encode_float_f32(*value);
+            }
+            EsfNode::F64(value) => {
+                data.push(F64);
+                data.encode_float_f64(*value);
+            }
+            EsfNode::Coord2d(x, y) => {
+                data.push(COORD_2D);
+                data.encode_float_f32(*x);
+                data.encode_float_f32(*y);
+            }
+            EsfNode::Coord3d(x, y, z) => {
+                data.push(COORD_3D);
+                data.encode_float_f32(*x);
+                data.encode_float_f32(*y);
+                data.encode_float_f32(*z);
+            }
+            EsfNode::Utf16(value) => {
+                data.push(UTF16);
+                data.encode_integer_u32(tables.get_string_utf16_index(value));
+            }
+            EsfNode::Ascii(value) => {
+                data.push(ASCII);
+                data.encode_integer_u32(tables.get_string_ascii_index(value));
+            }
+            EsfNode::Angle(value) => {
+                data.push(ANGLE);
+                data.encode_integer_u16(*value);
+            }
+            EsfNode::BoolArray(values) => {
+                data.push(BOOL_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_bool(*value))?;
+            }
+            EsfNode::I8Array(values) => {
+                data.push(I8_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_i8(*value))?;
+            }
+            EsfNode::I16Array(values) => {
+                data.push(I16_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_i16(*value))?;
+            }
+            EsfNode::I32Array(values) => {
+                data.push(I32_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_i32(*value))?;
+            }
+            EsfNode::I64Array(values) => {
+                data.push(I64_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_i64(*value))?;
+            }
+            EsfNode::U8Array(values) => {
+                data.push(U8_ARRAY);
+                Self::save_array(data, values, |data, value| data.push(*value))?;
+            }
+            EsfNode::U16Array(values) => {
+                data.push(U16_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_u16(*value))?;
+            }
+            EsfNode::U32Array(values) => {
+                data.push(U32_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_u32(*value))?;
+            }
+            EsfNode::U64Array(values) => {
+                data.push(U64_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_u64(*value))?;
+            }
+            EsfNode::F32Array(values) => {
+                data.push(F32_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_float_f32(*value))?;
+            }
+            EsfNode::F64Array(values) => {
+                data.push(F64_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_float_f64(*value))?;
+            }
+            EsfNode::Coord2dArray(values) => {
+                data.push(COORD_2D_ARRAY);
+                Self::save_array(data, values, |data, (x, y)| {
+                    data.encode_float_f32(*x);
+                    data.encode_float_f32(*y);
+                })?;
+            }
+            EsfNode::Coord3dArray(values) => {
+                data.push(COORD_3D_ARRAY);
+                Self::save_array(data, values, |data, (x, y, z)| {
+                    data.encode_float_f32(*x);
+                    data.encode_float_f32(*y);
+                    data.encode_float_f32(*z);
+                })?;
+            }
+            EsfNode::Utf16Array(values) => {
+                data.push(UTF16_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_u32(tables.get_string_utf16_index(value)))?;
+            }
+            EsfNode::AsciiArray(values) => {
+                data.push(ASCII_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_u32(tables.get_string_ascii_index(value)))?;
+            }
+            EsfNode::AngleArray(values) => {
+                data.push(ANGLE_ARRAY);
+                Self::save_array(data, values, |data, value| data.encode_integer_u16(*value))?;
+            }
+            EsfNode::Record(record) => {
+                data.push(RECORD);
+                data.encode_integer_u16(tables.get_record_name_index(&record.name));
+                data.push(record.version);
+
+                let end_offset_position = data.len();
+                data.encode_integer_u32(0);
+                for child in &record.children {
+                    Self::save_node(child, data, tables)?;
+                }
+                Self::patch_end_offset(data, end_offset_position)?;
+            }
+            EsfNode::RecordBlock(record_block) => {
+                data.push(RECORD_BLOCK);
+                data.encode_integer_u16(tables.get_record_name_index(&record_block.name));
+                data.push(record_block.version);
+
+                let end_offset_position = data.len();
+                data.encode_integer_u32(0);
+                data.encode_integer_u32(record_block.entries.len() as u32);
+                for entry in &record_block.entries {
+                    let entry_end_offset_position = data.len();
+                    data.encode_integer_u32(0);
+                    for child in entry {
+                        Self::save_node(child, data, tables)?;
+                    }
+                    Self::patch_end_offset(data, entry_end_offset_position)?;
+                }
+                Self::patch_end_offset(data, end_offset_position)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// This function encodes the provided items as an array node at the end of `data` (after its type byte), using the provided function to encode each item.
+    fn save_array<T>(data: &mut Vec<u8>, items: &[T], mut save_item: impl FnMut(&mut Vec<u8>, &T)) -> Result<()> {
+        let end_offset_position = data.len();
+        data.encode_integer_u32(0);
+        for item in items {
+            save_item(data, item);
+        }
+        Self::patch_end_offset(data, end_offset_position)
+    }
+
+    /// This function writes the current length of `data` as an `u32` at the provided position. Used to set where nodes end.
+    fn patch_end_offset(data: &mut Vec<u8>, position: usize) -> Result<()> {
+        let end_offset = data.len() as u32;
+        data.replace_integer_u32(position, end_offset)
+    }
+}
+
+//---------------------------------------------------------------------------//
+//                       Implementation of StringTables
+//---------------------------------------------------------------------------//
+
+/// Implementation of `StringTables`.
+impl StringTables {
+
+    /// This function returns the index of the provided record name, adding it to the list if it's not there.
+    fn get_record_name_index(&mut self, name: &str) -> u16 {
+        match self.record_name_indexes.get(name) {
+            Some(index) => *index,
+            None => {
+                let index = self.record_names.len() as u16;
+                self.record_names.push(name.to_owned());
+                self.record_name_indexes.insert(name.to_owned(), index);
+                index
+            }
+        }
+    }
+
+    /// This function returns the index of the provided UTF-16 string, adding it to the list if it's not there.
+    fn get_string_utf16_index(&mut self, string: &str) -> u32 {
+        self.strings_utf16.get_string_index(string)
+    }
+
+    /// This function returns the index of the provided ASCII string, adding it to the list if it's not there.
+    fn get_string_ascii_index(&mut self, string: &str) -> u32 {
+        self.strings_ascii.get_string_index(string)
+    }
+}
+
+//---------------------------------------------------------------------------//
+//                       Implementation of StringTable
+//---------------------------------------------------------------------------//
+
+/// Implementation of `StringTable`.
+impl StringTable {
+
+    /// This function creates a new `StringTable` from the strings (and their indexes) already in an ESF PackedFile.
+    ///
+    /// If a string is more than once in the list, its first index is the one used.
+    fn new(strings: &[(String, u32)]) -> Self {
+        Self {
+            indexes: strings.iter().rev().map(|(string, index)| (string.to_owned(), *index)).collect(),
+            next_index: strings.iter().map(|(_, index)| index + 1).max().unwrap_or(0),
+            new_strings: vec![],
+        }
+    }
+
+    /// This function returns the index of the provided string, adding it to the list if it's not there.
+    ///
+    /// New strings get the index after the biggest one in the list.
+    fn get_string_index(&mut self, string: &str) -> u32 {
+        match self.indexes.get(string) {
+            Some(index) => *index,
+            None => {
+                let index = self.next_index;
+                self.next_index += 1;
+                self.indexes.insert(string.to_owned(), index);
+                self.new_strings.push((string.to_owned(), index));
+                index
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `Image` module, just to make sure we don't break it... again...
+!*/
+
+use std::fs::read;
+
+use rpfm_error::ErrorKind;
+
+use super::{DdsInfo, Image, ImageType};
+
+/// This function returns an `Image` with the data of the provided test file.
+fn get_test_image(file_name: &str) -> Image {
+    Image::read(&read(format!("../test_files/images/{}", file_name)).unwrap()).unwrap()
+}
+
+/// Test to make sure `dimensions()` can get the size of every supported format.
+#[test]
+fn test_dimensions() {
+    assert_eq!(get_test_image("test.png").dimensions().unwrap(), (20, 10));
+    assert_eq!(get_test_image("test.jpg").dimensions().unwrap(), (16, 8));
+    assert_eq!(get_test_image("test.tga").dimensions().unwrap(), (12, 6));
+    assert_eq!(get_test_image("test.dds").dimensions().unwrap(), (32, 16));
+
+    // Unsupported or broken images must fail. Broken ones don't even get read.
+    assert!(Image::read(b"GIF89a_not_supported").unwrap().dimensions().is_err());
+    assert_eq!(Image::read(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0]).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+    assert_eq!(Image::read(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10]).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+}
+
+/// Test to make sure `dds_info()` reads the format and mipmaps of DDS images, and fails with anything else.
+#[test]
+fn test_dds_info() {
+    let info = get_test_image("test.dds").dds_info().unwrap();
+    assert_eq!(info, DdsInfo {
+        width: 32,
+        height: 16,
+        mipmap_count: 6,
+        four_cc: "DXT1".to_owned(),
+        dxgi_format: None,
+    });
+
+    // Change the FourCC to DX10 and add a DXGI format (98, BC7_UNORM) after the header.
+    let mut data = get_test_image("test.dds").get_data().to_vec();
+    data.splice(84..88, b"DX10".iter().cloned());
+    data.splice(128..128, [98, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0].iter().cloned());
+    let info = Image::read(&data).unwrap().dds_info().unwrap();
+    assert_eq!(info.four_cc, "DX10");
+    assert_eq!(info.dxgi_format, Some(98));
+
+    assert!(get_test_image("test.png").dds_info().is_err());
+    assert_eq!(Image::read(b"DDS incomplete").unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+}
+
+/// Test to make sure `to_png()` converts every supported format to a valid PNG of the same size.
+#[test]
+fn test_to_png() {
+    for file_name in &["test.png", "test.jpg", "test.tga", "test.dds"] {
+        let image = get_test_image(file_name);
+        let png = Image::read(&image.to_png().unwrap()).unwrap();
+        assert!(png.get_data().starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]));
+        assert_eq!(png.dimensions().unwrap(), image.dimensions().unwrap());
+    }
+
+    // Unsupported or broken images must fail. Broken ones don't even get read.
+    assert!(Image::read(b"GIF89a_not_supported").unwrap().to_png().is_err());
+    assert_eq!(Image::read(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0]).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+}
+
+/// Test to make sure `thumbnail()` shrinks images to the requested size keeping their aspect ratio, and leaves small ones alone.
+#[test]
+fn test_thumbnail() {
+    for file_name in &["test.png", "test.dds"] {
+        let image = get_test_image(file_name);
+        let (width, height) = image.dimensions().unwrap();
+        assert_eq!(width, height * 2);
+
+        let thumbnail = Image::read(&image.thumbnail(8).unwrap()).unwrap();
+        assert!(thumbnail.get_data().starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]));
+        assert_eq!(thumbnail.dimensions().unwrap(), (8, 4));
+
+        let thumbnail = Image::read(&image.thumbnail(100).unwrap()).unwrap();
+        assert_eq!(thumbnail.dimensions().unwrap(), (width, height));
+    }
+
+    assert!(get_test_image("test.png").thumbnail(0).is_err());
+    assert!(Image::read(b"GIF89a_not_supported").unwrap().thumbnail(8).is_err());
+}
+
+/// Test to make sure the format of every supported image is detected when reading it.
+#[test]
+fn test_format() {
+    assert_eq!(get_test_image("test.png").format(), ImageType::Png);
+    assert_eq!(get_test_image("test.jpg").format(), ImageType::Jpeg);
+    assert_eq!(get_test_image("test.tga").format(), ImageType::Tga);
+    assert_eq!(get_test_image("test.dds").format(), ImageType::Dds);
+
+    assert_eq!(Image::read(b"GIF89a_not_supported").unwrap().format(), ImageType::Unknown);
+    assert_eq!(Image::new().format(), ImageType::Unknown);
+}
+
+/// Test to make sure only data with a valid TGA header is detected as a TGA image.
+#[test]
+fn test_format_tga() {
+    let data = read("../test_files/images/test.tga").unwrap();
+    assert_eq!(ImageType::from_data(&data), ImageType::Tga);
+
+    // Any of these makes the header invalid: a colormap in a colormap-less image, no colormap in a colormapped one,
+    // no width, an invalid pixel depth, or the reserved bits of the descriptor set.
+    for (index, value) in &[(1, 2), (2, 1), (5, 1), (12, 0), (16, 7), (17, 0xC0)] {
+        let mut data = data.to_vec();
+        data[*index] = *value;
+        if *index == 12 { data[13] = 0; }
+        assert_eq!(ImageType::from_data(&data), ImageType::Unknown);
+    }
+
+    // Data that happens to start with valid colormap and image type fields is not enough.
+    let mut data = vec![0, 0, 2];
+    data.extend_from_slice(b"this is not an image");
+    assert_eq!(ImageType::from_data(&data), ImageType::Unknown);
+
+    // TGA 2.0 images don't need their dimensions checked, as they have a signature at the end.
+    data.extend_from_slice(b"TRUEVISION-XFILE.\0");
+    assert_eq!(ImageType::from_data(&data), ImageType::Unknown);
+    data[3..8].copy_from_slice(&[0; 5]);
+    assert_eq!(ImageType::from_data(&data), ImageType::Tga);
+}
+
+/// Test to make sure valid images are read, and truncated ones are rejected when reading them.
+#[test]
+fn test_read_corrupted() {
+    for file_name in &["test.png", "test.jpg", "test.tga", "test.dds"] {
+        let data = read(format!("../test_files/images/{}", file_name)).unwrap();
+        assert_eq!(Image::read(&data).unwrap().get_data(), &*data);
+    }
+
+    // Truncated PNG, both in the middle of a chunk and without the IEND chunk.
+    let data = read("../test_files/images/test.png").unwrap();
+    assert_eq!(Image::read(&data[..data.len() / 2]).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+    assert_eq!(Image::read(&data[..data.len() - 12]).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+
+    // PNG with a chunk claiming to be bigger than the file.
+    let mut data = data.to_vec();
+    data[8..12].copy_from_slice(&[0x7F, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(Image::read(&data).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+
+    // Truncated images of the other formats.
+    for file_name in &["test.jpg", "test.tga", "test.dds"] {
+        let data = read(format!("../test_files/images/{}", file_name)).unwrap();
+        assert_eq!(Image::read(&data[..20]).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+    }
+
+    let data = read("../test_files/images/test.dds").unwrap();
+    assert_eq!(Image::read(&data[..200]).unwrap_err().kind(), &ErrorKind::ImageDataCorrupted);
+}
@@ -14,9 +14,16 @@ Module with all the code to interact with Image PackedFiles.
 Images... we really just get their that to memory. Nothing more.
 !*/
 
+use byteorder::{BigEndian, ByteOrder};
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
 use serde_derive::{Serialize, Deserialize};
 
-use rpfm_error::Result;
+use rpfm_error::{ErrorKind, Result};
+
+use crate::common::decoder::Decoder;
+
+#[cfg(test)]
+mod image_test;
 
 /// Extensions used by Image PackedFiles.
 pub const EXTENSIONS: [&str; 5] = [
@@ -27,6 +34,36 @@ pub const EXTENSIONS: [&str; 5] = [
     ".png",
 ];
 
+/// Signature found at the start of every PNG image.
+const PNG_SIGNATURE: &[u8] = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Signature found at the start of every JPEG image.
+const JPEG_SIGNATURE: &[u8] = &[0xFF, 0xD8];
+
+/// Signature found at the start of every DDS image.
+const DDS_SIGNATURE: &[u8] = b"DDS ";
+
+/// Size of the header of a DDS image, including the magic number.
+const DDS_HEADER_SIZE: usize = 128;
+
+/// Size of the extra header DDS images with the `DX10` FourCC have after the normal one.
+const DDS_HEADER_DX10_SIZE: usize = 20;
+
+/// Flag in a DDS header that marks the mipmap count as valid.
+const DDS_FLAG_MIPMAP_COUNT: u32 = 0x0002_0000;
+
+/// Flag in a DDS header that marks the pitch or linear size field as the size of the biggest mipmap.
+const DDS_FLAG_LINEAR_SIZE: u32 = 0x0008_0000;
+
+/// Size of the header of a TGA image.
+const TGA_HEADER_SIZE: usize = 18;
+
+/// Signature found at the end of TGA 2.0 images.
+const TGA_FOOTER_SIGNATURE: &[u8] = b"TRUEVISION-XFILE.\0";
+
+/// Flag in a DDS pixel format that marks the FourCC code as valid.
+const DDS_PIXEL_FORMAT_FLAG_FOURCC: u32 = 0x4;
+
 //---------------------------------------------------------------------------//
 //                              Enum & Structs
 //---------------------------------------------------------------------------//
@@ -37,6 +74,41 @@ pub struct Image {
 
     /// The raw_data of the image.
     data: Vec<u8>,
+
+    /// The format of the image, detected from his data when reading it.
+    format: ImageType,
+}
+
+/// This enum represents the formats of the images we can find in a PackedFile.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ImageType {
+    Png,
+    Jpeg,
+    Tga,
+    Dds,
+
+    /// For images we cannot identify.
+    Unknown,
+}
+
+/// This holds the info about the format of a DDS Image, as stored in his header.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct DdsInfo {
+
+    /// The width of the image (his biggest mipmap), in pixels.
+    pub width: u32,
+
+    /// The height of the image (his biggest mipmap), in pixels.
+    pub height: u32,
+
+    /// The amount of mipmaps in the image, including the full size one. Images without mipmaps have `1` here.
+    pub mipmap_count: u32,
+
+    /// The FourCC code of the pixel format, like `DXT1` or `DXT5`. Empty if the image is not compressed.
+    pub four_cc: String,
+
+    /// The DXGI format of the image. Only present if the FourCC is `DX10`, as only those images have this field.
+    pub dxgi_format: Option<u32>,
 }
 
 //---------------------------------------------------------------------------//
@@ -48,6 +120,7 @@ impl Default for Image {
     fn default() -> Self {
         Self {
             data: vec![],
+            format: ImageType::Unknown,
         }
     }
 }
@@ -60,10 +133,32 @@ impl Image {
         Self::default()
     }
 
+    /// This function returns if the provided data looks like an image we can find in a PackedFile, based on its signature.
+    ///
+    /// TGA images have no signature, so they're never detected by this.
+    pub fn is_image(data: &[u8]) -> bool {
+        data.starts_with(PNG_SIGNATURE) || data.starts_with(DDS_SIGNATURE) || data.starts_with(JPEG_SIGNATURE)
+    }
+
     /// This function creates a `Image` from a `Vec<u8>`.
+    ///
+    /// The structure of the image is checked (not decoded) first, so clearly broken images, like truncated ones, return an error.
+    /// Images of formats we cannot identify are not checked.
     pub fn read(packed_file_data: &[u8]) -> Result<Self> {
+        if packed_file_data.is_empty() { return Err(ErrorKind::PackedFileDataIsEmpty.into()) }
+        let format = ImageType::from_data(packed_file_data);
+        let is_valid = match format {
+            ImageType::Png => Self::is_png_valid(packed_file_data),
+            ImageType::Jpeg => Self::is_jpeg_valid(packed_file_data),
+            ImageType::Tga => Self::is_tga_valid(packed_file_data),
+            ImageType::Dds => Self::is_dds_valid(packed_file_data),
+            ImageType::Unknown => true,
+        };
+
+        if !is_valid { return Err(ErrorKind::ImageDataCorrupted.into()) }
         Ok(Self {
             data: packed_file_data.to_vec(),
+            format,
         })
     }
 
@@ -71,4 +166,253 @@ impl Image {
     pub fn get_data(&self) -> &[u8] {
         &self.data
     }
+
+    /// This function returns the format of the provided `Image`.
+    pub fn format(&self) -> ImageType {
+        self.format
+    }
+
+    /// This function returns the width and height of the provided `Image`, reading only his header.
+    ///
+    /// Supported formats are PNG, JPEG, TGA and DDS. For any other format, this returns an error.
+    pub fn dimensions(&self) -> Result<(u32, u32)> {
+        let data = &self.data;
+        match self.format {
+
+            // PNG: the IHDR chunk is always the first one, with the width and height (big endian) at the start of his data.
+            ImageType::Png => {
+                let header = data.get_bytes_checked(16, 8).map_err(|_| ErrorKind::ImageDecode("The PNG header is incomplete.".to_owned()))?;
+                Ok((BigEndian::read_u32(&header[0..4]), BigEndian::read_u32(&header[4..8])))
+            }
+
+            // DDS: the height and width come after the magic number, the size of the header and the flags.
+            ImageType::Dds => {
+                let height = data.decode_integer_u32(12).map_err(|_| ErrorKind::ImageDecode("The DDS header is incomplete.".to_owned()))?;
+                let width = data.decode_integer_u32(16).map_err(|_| ErrorKind::ImageDecode("The DDS header is incomplete.".to_owned()))?;
+                Ok((width, height))
+            }
+
+            // JPEG: we need to go through the segments until we find the Start Of Frame one.
+            ImageType::Jpeg => Self::jpeg_dimensions(data),
+
+            // TGA: the width and height are at the end of his header.
+            ImageType::Tga => {
+                let width = data.decode_integer_u16(12)?;
+                let height = data.decode_integer_u16(14)?;
+                Ok((u32::from(width), u32::from(height)))
+            }
+
+            ImageType::Unknown => Err(ErrorKind::ImageDecode("The format of this image is not supported.".to_owned()).into()),
+        }
+    }
+
+    /// This function returns the format info from the header of the provided DDS `Image`.
+    ///
+    /// If the image is not a DDS, this returns an error.
+    pub fn dds_info(&self) -> Result<DdsInfo> {
+        let data = &self.data;
+        if self.format != ImageType::Dds { return Err(ErrorKind::ImageDecode("This image is not a DDS.".to_owned()).into()) }
+        if data.len() < 128 { return Err(ErrorKind::ImageDecode("The DDS header is incomplete.".to_owned()).into()) }
+
+        let flags = data.decode_integer_u32(8)?;
+        let height = data.decode_integer_u32(12)?;
+        let width = data.decode_integer_u32(16)?;
+        let mipmap_count = data.decode_integer_u32(28)?;
+        let mipmap_count = if flags & DDS_FLAG_MIPMAP_COUNT != 0 && mipmap_count > 0 { mipmap_count } else { 1 };
+
+        let pixel_format_flags = data.decode_integer_u32(80)?;
+        let four_cc = if pixel_format_flags & DDS_PIXEL_FORMAT_FLAG_FOURCC != 0 {
+            data[84..88].iter().map(|x| *x as char).collect::<String>().trim_end_matches('\0').to_owned()
+        } else { String::new() };
+
+        // If the FourCC is DX10, there is an extra header after the normal one, starting with the DXGI format.
+        let dxgi_format = if four_cc == "DX10" {
+            Some(data.decode_integer_u32(128).map_err(|_| ErrorKind::ImageDecode("The DDS DX10 header is incomplete.".to_owned()))?)
+        } else { None };
+
+        Ok(DdsInfo {
+            width,
+            height,
+            mipmap_count,
+            four_cc,
+            dxgi_format,
+        })
+    }
+
+    /// This function decodes the provided `Image` and encodes it again as a PNG, returning the encoded PNG.
+    ///
+    /// Supported formats are PNG, JPEG, TGA and DDS (only DXT1, DXT3 and DXT5). For any other format, or if the image is broken, this returns an error.
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        Self::encode_png(&self.decode_image()?)
+    }
+
+    /// This function returns a smaller version of the provided `Image`, encoded as a PNG, for previews.
+    ///
+    /// The thumbnail keeps the aspect ratio of the image, with his biggest dimension being at most `max_dim` pixels.
+    /// Images already smaller than that keep their size. Supported formats are the same as in `to_png`.
+    pub fn thumbnail(&self, max_dim: u32) -> Result<Vec<u8>> {
+        if max_dim == 0 { return Err(ErrorKind::ImageDecode("The size of a thumbnail cannot be 0.".to_owned()).into()) }
+
+        let image = self.decode_image()?;
+        if image.width() <= max_dim && image.height() <= max_dim { Self::encode_png(&image) }
+        else { Self::encode_png(&image.thumbnail(max_dim, max_dim)) }
+    }
+
+    /// This function decodes the provided `Image` to pixels, using the format detected when reading it.
+    fn decode_image(&self) -> Result<DynamicImage> {
+        let format = match self.format {
+            ImageType::Png => ImageFormat::Png,
+            ImageType::Dds => ImageFormat::Dds,
+            ImageType::Jpeg => ImageFormat::Jpeg,
+            ImageType::Tga => ImageFormat::Tga,
+            ImageType::Unknown => return Err(ErrorKind::ImageDecode("The format of this image is not supported.".to_owned()).into()),
+        };
+
+        image::load_from_memory_with_format(&self.data, format).map_err(|error| ErrorKind::ImageDecode(format!("The image couldn't be decoded: {}", error)).into())
+    }
+
+    /// This function encodes the provided decoded image as a PNG, returning the encoded PNG.
+    fn encode_png(image: &DynamicImage) -> Result<Vec<u8>> {
+        let mut png = vec![];
+        image.write_to(&mut png, ImageOutputFormat::Png).map_err(|error| ErrorKind::ImageDecode(format!("The image couldn't be encoded as PNG: {}", error)))?;
+        Ok(png)
+    }
+
+    /// This function returns if the chunks of the provided PNG image are all complete, starting with the IHDR one and ending with the IEND one.
+    ///
+    /// The CRC of the chunks is not checked.
+    fn is_png_valid(data: &[u8]) -> bool {
+        let mut index = PNG_SIGNATURE.len();
+        let mut is_first_chunk = true;
+        while index + 8 <= data.len() {
+            let size = BigEndian::read_u32(&data[index..index + 4]) as usize;
+            let chunk_type = &data[index + 4..index + 8];
+            if is_first_chunk && chunk_type != b"IHDR" { return false }
+
+            // Data of the chunk, plus his length, type and CRC.
+            index = match index.checked_add(size).and_then(|x| x.checked_add(12)) {
+                Some(end) if end <= data.len() => end,
+                _ => return false,
+            };
+
+            if chunk_type == b"IEND" { return true }
+            is_first_chunk = false;
+        }
+        false
+    }
+
+    /// This function returns if the segments of the provided JPEG image are all complete, until the Start Of Scan one.
+    fn is_jpeg_valid(data: &[u8]) -> bool {
+        let mut index = JPEG_SIGNATURE.len();
+        while index + 2 <= data.len() {
+            if data[index] != 0xFF { return false }
+            let marker = data[index + 1];
+
+            // Fill bytes and markers without data. These have no length after them.
+            if marker == 0xFF { index += 1; continue; }
+            if marker == 0x01 || (0xD0..=0xD8).contains(&marker) { index += 2; continue; }
+            if index + 4 > data.len() { return false }
+
+            let end = index + 2 + BigEndian::read_u16(&data[index + 2..index + 4]) as usize;
+            if end > data.len() { return false }
+
+            // After the Start Of Scan segment goes the compressed data, which has no size.
+            if marker == 0xDA { return true }
+            index = end;
+        }
+        false
+    }
+
+    /// This function returns if the provided TGA image has the id, the colormap and (if it's not compressed) the pixels his header says it has.
+    fn is_tga_valid(data: &[u8]) -> bool {
+        let colormap_size = if data[1] == 1 {
+            usize::from(data.decode_integer_u16(5).unwrap_or(0)) * ((usize::from(data[7]) + 7) / 8)
+        } else { 0 };
+
+        // Compressed (RLE) images have no way to know their size without decompressing them.
+        let pixels_size = if [1, 2, 3].contains(&data[2]) {
+            let width = usize::from(data.decode_integer_u16(12).unwrap_or(0));
+            let height = usize::from(data.decode_integer_u16(14).unwrap_or(0));
+            width * height * ((usize::from(data[16]) + 7) / 8)
+        } else { 0 };
+
+        TGA_HEADER_SIZE + usize::from(data[0]) + colormap_size + pixels_size <= data.len()
+    }
+
+    /// This function returns if the provided DDS image has a complete header, and the biggest mipmap his header says it has.
+    fn is_dds_valid(data: &[u8]) -> bool {
+        if data.len() < DDS_HEADER_SIZE || data.decode_integer_u32(4).ok() != Some(DDS_HEADER_SIZE as u32 - 4) { return false }
+
+        let header_size = if &data[84..88] == b"DX10" { DDS_HEADER_SIZE + DDS_HEADER_DX10_SIZE } else { DDS_HEADER_SIZE };
+        let flags = data.decode_integer_u32(8).unwrap_or(0);
+        let linear_size = if flags & DDS_FLAG_LINEAR_SIZE != 0 { data.decode_integer_u32(20).unwrap_or(0) as usize } else { 0 };
+        header_size + linear_size <= data.len()
+    }
+
+    /// This function returns the width and height of a JPEG image, from the first Start Of Frame segment we find.
+    fn jpeg_dimensions(data: &[u8]) -> Result<(u32, u32)> {
+        let mut index = JPEG_SIGNATURE.len();
+        while index + 4 <= data.len() {
+            if data[index] != 0xFF { break; }
+            let marker = data[index + 1];
+
+            // Fill bytes and markers without data. These have no length after them.
+            if marker == 0xFF { index += 1; continue; }
+            if marker == 0x01 || (0xD0..=0xD8).contains(&marker) { index += 2; continue; }
+
+            // Start Of Frame markers (except DHT, JPG and DAC, which share the range). Height goes before width.
+            if (0xC0..=0xCF).contains(&marker) && marker != 0xC4 && marker != 0xC8 && marker != 0xCC {
+                let header = data.get_bytes_checked(index + 5, 4).map_err(|_| ErrorKind::ImageDecode("The JPEG header is incomplete.".to_owned()))?;
+                return Ok((u32::from(BigEndian::read_u16(&header[2..4])), u32::from(BigEndian::read_u16(&header[0..2]))))
+            }
+
+            index += 2 + BigEndian::read_u16(&data[index + 2..index + 4]) as usize;
+        }
+
+        Err(ErrorKind::ImageDecode("The JPEG header is incomplete.".to_owned()).into())
+    }
+}
+
+/// Implementation of `ImageType`.
+impl ImageType {
+
+    /// This function returns the format of the image with the provided data, based on its signature.
+    ///
+    /// TGA images have no signature at the start, so for them we check the colormap and image type fields of their header are valid instead,
+    /// and then either the signature at the end of TGA 2.0 images, or the dimensions and pixel depth of their header.
+    pub fn from_data(data: &[u8]) -> Self {
+        if data.starts_with(PNG_SIGNATURE) { Self::Png }
+        else if data.starts_with(DDS_SIGNATURE) { Self::Dds }
+        else if data.starts_with(JPEG_SIGNATURE) { Self::Jpeg }
+        else if Self::is_tga(data) { Self::Tga }
+        else { Self::Unknown }
+    }
+
+    /// This function returns if the provided data has a valid TGA header.
+    fn is_tga(data: &[u8]) -> bool {
+        if data.len() < TGA_HEADER_SIZE { return false }
+
+        // Colormapped images (1 and 9) need a colormap. The rest can have one, but if they don't, its fields must be empty.
+        let image_type = data[2];
+        let is_colormapped = [1, 9].contains(&image_type);
+        let is_colormap_valid = match data[1] {
+            0 => !is_colormapped && data[3..8].iter().all(|x| *x == 0),
+            1 => [15, 16, 24, 32].contains(&data[7]),
+            _ => false,
+        };
+
+        if ![1, 2, 3, 9, 10, 11].contains(&image_type) || !is_colormap_valid { return false }
+        if data.ends_with(TGA_FOOTER_SIGNATURE) { return true }
+
+        let width = data.decode_integer_u16(12).unwrap_or(0);
+        let height = data.decode_integer_u16(14).unwrap_or(0);
+        let valid_depths: &[u8] = match image_type {
+            1 | 9 => &[8, 16],
+            2 | 10 => &[15, 16, 24, 32],
+            _ => &[8, 16],
+        };
+
+        // The two upper bits of the image descriptor are reserved, and they must be zero.
+        width > 0 && height > 0 && valid_depths.contains(&data[16]) && data[17] & 0xC0 == 0
+    }
 }
@@ -17,29 +17,62 @@ you can find in a `PackFile`. Here, you can find some generic enums used by the
 For encoding/decoding/proper manipulation of the data in each type of `PackedFile`, check their respective submodules
 !*/
 
+use lazy_static::lazy_static;
 use rayon::prelude::*;
 
+use std::cmp::Ordering;
+use std::mem::size_of;
 use std::{fmt, fmt::Display};
 use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
 use rpfm_error::{Error, ErrorKind, Result};
 
 use crate::DEPENDENCY_DATABASE;
-use crate::packedfile::ca_vp8::CaVp8;
+use crate::packedfile::ca_vp8::{CaVp8, Frame};
+use crate::packedfile::esf::{EsfFile, EsfNode};
 use crate::packedfile::image::Image;
-use crate::packedfile::table::{db::DB, loc::Loc};
+use crate::packedfile::table::{csv_table::CsvTable, db::DB, DecodedData, loc::Loc};
 use crate::packedfile::text::{Text, TextType};
 use crate::packedfile::rigidmodel::RigidModel;
 use crate::packfile::packedfile::{PackedFile, RawPackedFile};
 use crate::schema::Schema;
 use crate::SCHEMA;
+use crate::SETTINGS;
 
 pub mod ca_vp8;
+pub mod esf;
 pub mod image;
 pub mod rigidmodel;
 pub mod table;
 pub mod text;
 
+#[cfg(test)]
+mod packedfile_test;
+
+/// Counter used to give each registered decoder its own id, so it can be unregistered later.
+static CUSTOM_DECODER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+
+    /// Decoders registered for `PackedFiles` we don't have a decoder for, or to replace our own decoders.
+    static ref CUSTOM_DECODERS: RwLock<Vec<RegisteredDecoder>> = RwLock::new(vec![]);
+
+    /// Extensions used by each type of Text PackedFile, built from `text::EXTENSIONS` so they can't drift apart.
+    static ref TEXT_EXTENSIONS_BY_TYPE: Vec<(TextType, Vec<&'static str>)> = {
+        let mut extensions: Vec<(TextType, Vec<&'static str>)> = vec![];
+        for (extension, text_type) in text::EXTENSIONS.iter() {
+            match extensions.iter_mut().find(|(x, _)| x == text_type) {
+                Some((_, list)) => list.push(extension),
+                None => extensions.push((*text_type, vec![extension])),
+            }
+        }
+        extensions
+    };
+}
+
 //---------------------------------------------------------------------------//
 //                              Enum & Structs
 //---------------------------------------------------------------------------//
@@ -54,13 +87,14 @@ pub enum DecodedPackedFile {
     AnimPack,
     AnimTable,
     CaVp8(CaVp8),
-    CEO,
+    CEO(EsfFile),
+    CsvTable(CsvTable),
     DB(DB),
     Image(Image),
     Loc(Loc),
     MatchedCombat,
     RigidModel(RigidModel),
-    StarPos,
+    StarPos(EsfFile),
     Text(Text),
     Unknown,
 }
@@ -91,6 +125,29 @@ pub enum PackedFileType {
     Unknown,
 }
 
+/// This is the type of the decoders that can be registered with `DecodedPackedFile::register_decoder`.
+pub type CustomDecoder = Box<dyn Fn(&RawPackedFile) -> Result<DecodedPackedFile> + Send + Sync>;
+
+/// This is the id of a decoder registered with `DecodedPackedFile::register_decoder`, used to unregister it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CustomDecoderId(usize);
+
+/// This struct represents a decoder registered to decode `PackedFiles` we don't support, or to replace one of our decoders.
+struct RegisteredDecoder {
+
+    /// Id of the decoder, returned when registering it.
+    id: CustomDecoderId,
+
+    /// Function to check if the decoder can decode the `PackedFile` with the provided path.
+    matcher: fn(&[String]) -> bool,
+
+    /// The decoder itself. It's shared so we don't have to keep the registry locked while decoding.
+    decoder: Arc<dyn Fn(&RawPackedFile) -> Result<DecodedPackedFile> + Send + Sync>,
+
+    /// If this decoder should be used instead of our own decoder for the `PackedFiles` it matches.
+    overrides_builtin: bool,
+}
+
 //----------------------------------------------------------------//
 // Implementations for `DecodedPackedFile`.
 //----------------------------------------------------------------//
@@ -100,7 +157,12 @@ impl DecodedPackedFile {
 
     /// This function decodes a `RawPackedFile` into a `DecodedPackedFile`, returning it.
     pub fn decode(raw_packed_file: &RawPackedFile) -> Result<Self> {
-        match PackedFileType::get_packed_file_type(raw_packed_file.get_path()) {
+        let packed_file_type = PackedFileType::get_packed_file_type(raw_packed_file.get_path());
+        if let Some(decoder) = Self::get_custom_decoder(raw_packed_file.get_path(), packed_file_type) {
+            return decoder(raw_packed_file)
+        }
+
+        match packed_file_type {
 
             PackedFileType::CaVp8 => {
                 let data = raw_packed_file.get_data()?;
@@ -112,7 +174,7 @@ impl DecodedPackedFile {
                 let schema = SCHEMA.read().unwrap();
                 match schema.deref() {
                     Some(schema) => {
-                        let name = raw_packed_file.get_path().get(1).ok_or_else(|| Error::from(ErrorKind::DBTableIsNotADBTable))?;
+                        let name = PackedFileType::get_db_table_name(raw_packed_file.get_path())?;
                         let data = raw_packed_file.get_data()?;
                         let packed_file = DB::read(&data, name, &schema, false)?;
                         Ok(DecodedPackedFile::DB(packed_file))
@@ -139,22 +201,36 @@ impl DecodedPackedFile {
                 }
             }
 
-            PackedFileType::Text(_) => {
+            PackedFileType::CEO => {
+                let data = raw_packed_file.get_data()?;
+                Ok(Self::read_esf(&data)?.map_or(DecodedPackedFile::Unknown, DecodedPackedFile::CEO))
+            }
+
+            PackedFileType::StarPos => {
+                let data = raw_packed_file.get_data()?;
+                Ok(Self::read_esf(&data)?.map_or(DecodedPackedFile::Unknown, DecodedPackedFile::StarPos))
+            }
+
+            PackedFileType::Text(text_type) => {
                 let data = raw_packed_file.get_data()?;
                 let mut packed_file = Text::read(&data)?;
-                let packed_file_type = PackedFileType::get_packed_file_type(raw_packed_file.get_path());
-                if let PackedFileType::Text(text_type) = packed_file_type {
-                    packed_file.set_text_type(text_type);
-                }
+                packed_file.set_text_type(text_type);
                 Ok(DecodedPackedFile::Text(packed_file))
             }
+
+            PackedFileType::Unknown => Self::decode_unknown(raw_packed_file, Self::is_text_detection_enabled()),
             _=> Ok(DecodedPackedFile::Unknown)
         }
     }
 
     /// This function decodes a `RawPackedFile` into a `DecodedPackedFile`, returning it.
     pub fn decode_no_locks(raw_packed_file: &RawPackedFile, schema: &Schema) -> Result<Self> {
-        match PackedFileType::get_packed_file_type(raw_packed_file.get_path()) {
+        let packed_file_type = PackedFileType::get_packed_file_type(raw_packed_file.get_path());
+        if let Some(decoder) = Self::get_custom_decoder(raw_packed_file.get_path(), packed_file_type) {
+            return decoder(raw_packed_file)
+        }
+
+        match packed_file_type {
             PackedFileType::CaVp8 => {
                 let data = raw_packed_file.get_data()?;
                 let packed_file = CaVp8::read(data)?;
@@ -162,7 +238,7 @@ impl DecodedPackedFile {
             }
 
             PackedFileType::DB => {
-                let name = raw_packed_file.get_path().get(1).ok_or_else(|| Error::from(ErrorKind::DBTableIsNotADBTable))?;
+                let name = PackedFileType::get_db_table_name(raw_packed_file.get_path())?;
                 let data = raw_packed_file.get_data()?;
                 let packed_file = DB::read(&data, name, &schema, false)?;
                 Ok(DecodedPackedFile::DB(packed_file))
@@ -170,8 +246,8 @@ impl DecodedPackedFile {
 
             PackedFileType::Image => {
                 let data = raw_packed_file.get_data()?;
-                let packed_file = Text::read(&data)?;
-                Ok(DecodedPackedFile::Text(packed_file))
+                let packed_file = Image::read(&data)?;
+                Ok(DecodedPackedFile::Image(packed_file))
             }
 
             PackedFileType::Loc => {
@@ -180,32 +256,230 @@ impl DecodedPackedFile {
                 Ok(DecodedPackedFile::Loc(packed_file))
             }
 
-            PackedFileType::Text(_) => {
+            PackedFileType::CEO => {
+                let data = raw_packed_file.get_data()?;
+                Ok(Self::read_esf(&data)?.map_or(DecodedPackedFile::Unknown, DecodedPackedFile::CEO))
+            }
+
+            PackedFileType::StarPos => {
+                let data = raw_packed_file.get_data()?;
+                Ok(Self::read_esf(&data)?.map_or(DecodedPackedFile::Unknown, DecodedPackedFile::StarPos))
+            }
+
+            PackedFileType::Text(text_type) => {
                 let data = raw_packed_file.get_data()?;
                 let mut packed_file = Text::read(&data)?;
-                let packed_file_type = PackedFileType::get_packed_file_type(raw_packed_file.get_path());
-                if let PackedFileType::Text(text_type) = packed_file_type {
-                    packed_file.set_text_type(text_type);
-                }
+                packed_file.set_text_type(text_type);
                 Ok(DecodedPackedFile::Text(packed_file))
             }
+
+            PackedFileType::Unknown => Self::decode_unknown(raw_packed_file, Self::is_text_detection_enabled()),
             _=> Ok(DecodedPackedFile::Unknown)
         }
     }
 
+    /// This function decodes the provided data as an ESF PackedFile.
+    ///
+    /// If the ESF uses a signature we don't support, this returns `None`, so the PackedFile can still be opened as `Unknown`.
+    fn read_esf(data: &[u8]) -> Result<Option<EsfFile>> {
+        match EsfFile::read(data) {
+            Ok(packed_file) => Ok(Some(packed_file)),
+            Err(error) => if let ErrorKind::ESFUnsupportedSignature(_) = error.kind() { Ok(None) } else { Err(error) },
+        }
+    }
+
+    /// This function returns if the `detect_text_in_unknown_packedfiles` setting is enabled.
+    fn is_text_detection_enabled() -> bool {
+        SETTINGS.read().unwrap().settings_bool.get("detect_text_in_unknown_packedfiles").copied().unwrap_or(false)
+    }
+
+    /// This function decodes a `RawPackedFile` of a type we don't know.
+    ///
+    /// If `detect_text` is true and it looks like text, it's decoded as plain text, so it can be edited. Otherwise, it stays `Unknown`.
+    fn decode_unknown(raw_packed_file: &RawPackedFile, detect_text: bool) -> Result<Self> {
+        if detect_text {
+            let data = raw_packed_file.get_data()?;
+            if Text::is_text(&data) {
+                return Ok(DecodedPackedFile::Text(Text::read(&data)?))
+            }
+        }
+        Ok(DecodedPackedFile::Unknown)
+    }
+
+    /// This function registers a decoder for the `PackedFiles` whose path is accepted by the provided matcher.
+    ///
+    /// The decoder is only used for `PackedFiles` we don't have a decoder for. For the rest, our own decoder is used.
+    /// If more than one registered decoder matches a path, the last one registered is used.
+    /// It returns the id of the decoder, to unregister it with `unregister_decoder`.
+    pub fn register_decoder(matcher: fn(&[String]) -> bool, decoder: CustomDecoder) -> CustomDecoderId {
+        Self::add_custom_decoder(matcher, decoder, false)
+    }
+
+    /// This function registers a decoder for the `PackedFiles` whose path is accepted by the provided matcher, replacing our own decoder for them.
+    ///
+    /// If more than one registered decoder matches a path, the last one registered is used.
+    /// It returns the id of the decoder, to unregister it with `unregister_decoder`.
+    pub fn register_decoder_override(matcher: fn(&[String]) -> bool, decoder: CustomDecoder) -> CustomDecoderId {
+        Self::add_custom_decoder(matcher, decoder, true)
+    }
+
+    /// This function unregisters the decoder with the provided id, returning if it was registered.
+    pub fn unregister_decoder(id: CustomDecoderId) -> bool {
+        let mut decoders = CUSTOM_DECODERS.write().unwrap();
+        let len = decoders.len();
+        decoders.retain(|x| x.id != id);
+        decoders.len() != len
+    }
+
+    /// This function unregisters all the registered decoders.
+    pub fn clear_decoders() {
+        CUSTOM_DECODERS.write().unwrap().clear();
+    }
+
+    /// This function adds a decoder to the list of registered decoders, returning its id.
+    fn add_custom_decoder(matcher: fn(&[String]) -> bool, decoder: CustomDecoder, overrides_builtin: bool) -> CustomDecoderId {
+        let id = CustomDecoderId(CUSTOM_DECODER_COUNTER.fetch_add(1, AtomicOrdering::SeqCst));
+        CUSTOM_DECODERS.write().unwrap().push(RegisteredDecoder {
+            id,
+            matcher,
+            decoder: Arc::from(decoder),
+            overrides_builtin,
+        });
+        id
+    }
+
+    /// This function returns the registered decoder that should be used for the `PackedFile` with the provided path and type, if any.
+    fn get_custom_decoder(path: &[String], packed_file_type: PackedFileType) -> Option<Arc<dyn Fn(&RawPackedFile) -> Result<DecodedPackedFile> + Send + Sync>> {
+        CUSTOM_DECODERS.read().unwrap().iter()
+            .rev()
+            .find(|x| (x.overrides_builtin || !packed_file_type.is_decodable()) && (x.matcher)(path))
+            .map(|x| x.decoder.clone())
+    }
+
+    /// This function decodes a CSV/TSV `RawPackedFile` into a `DecodedPackedFile::CsvTable`, returning it.
+    ///
+    /// CSV/TSV PackedFiles are decoded as `Text` by `decode`. Use this instead if you want them as a table.
+    /// If the `RawPackedFile` is not a CSV/TSV PackedFile, this returns an error.
+    pub fn decode_text_as_table(raw_packed_file: &RawPackedFile) -> Result<Self> {
+        match PackedFileType::get_packed_file_type(raw_packed_file.get_path()) {
+            PackedFileType::Text(text_type) if text_type == TextType::Csv || text_type == TextType::Tsv => {
+                let data = raw_packed_file.get_data()?;
+                let packed_file = CsvTable::read(&data, text_type)?;
+                Ok(DecodedPackedFile::CsvTable(packed_file))
+            }
+            _ => Err(ErrorKind::PackedFileTypeUnknown.into()),
+        }
+    }
+
     /// This function encodes a `DecodedPackedFile` into a `Vec<u8>`, returning it.
     ///
     /// Keep in mind this should only work for PackedFiles with saving support.
     pub fn encode(&self) -> Option<Result<Vec<u8>>> {
         match self {
             DecodedPackedFile::CaVp8(data) => Some(data.save()),
+            DecodedPackedFile::CEO(data) => Some(data.save()),
+            DecodedPackedFile::CsvTable(data) => Some(data.save()),
             DecodedPackedFile::DB(data) => Some(data.save()),
             DecodedPackedFile::Loc(data) => Some(data.save()),
+            DecodedPackedFile::StarPos(data) => Some(data.save()),
             DecodedPackedFile::Text(data) => Some(data.save()),
             _=> None,
         }
     }
 
+    /// This function encodes a `DecodedPackedFile` and checks if the encoded data is the same as the provided original data.
+    ///
+    /// This is meant to find decoders that lose data. It returns false if the data doesn't match, and an error if the PackedFile cannot be encoded.
+    pub fn verify_lossless(&self, original: &[u8]) -> Result<bool> {
+        let data = self.encode().ok_or_else(|| Error::from(ErrorKind::PackedFileTypeNotEncodable))??;
+        Ok(data == original)
+    }
+
+    /// This function returns if this `DecodedPackedFile` is a table (DB or Loc).
+    pub fn is_table(&self) -> bool {
+        PackedFileType::from(self).is_table()
+    }
+
+    /// This function returns if this `DecodedPackedFile` is a Text PackedFile, of any subtype.
+    pub fn is_text(&self) -> bool {
+        PackedFileType::from(self).is_text()
+    }
+
+    /// This function returns if this `DecodedPackedFile` is an Image.
+    pub fn is_image(&self) -> bool {
+        PackedFileType::from(self).is_image()
+    }
+
+    /// This function returns if this `DecodedPackedFile` is of a type we can decode.
+    pub fn is_decodable(&self) -> bool {
+        PackedFileType::from(self).is_decodable()
+    }
+
+    /// This function returns a rough estimate of the memory this `DecodedPackedFile` uses, in bytes.
+    ///
+    /// It's for deciding what to keep in memory, not for exact accounting: only the parts that grow with the size of the file are counted.
+    pub fn approx_memory(&self) -> usize {
+        size_of::<Self>() + match self {
+            DecodedPackedFile::CaVp8(data) => data.get_ref_frame_table().len() * size_of::<Frame>() + data.get_ref_frame_data().len(),
+            DecodedPackedFile::CEO(data) |
+            DecodedPackedFile::StarPos(data) => Self::approx_esf_node_memory(data.get_ref_root_node()),
+            DecodedPackedFile::CsvTable(data) => Self::approx_table_memory(data.get_ref_table_data()),
+            DecodedPackedFile::DB(data) => Self::approx_table_memory(data.get_ref_table_data()),
+            DecodedPackedFile::Image(data) => data.get_data().len(),
+            DecodedPackedFile::Loc(data) => Self::approx_table_memory(data.get_ref_table_data()),
+            DecodedPackedFile::RigidModel(data) => data.get_ref_data().len() + data.header.get_ref_texture_paths().iter().map(|x| x.len()).sum::<usize>(),
+            DecodedPackedFile::Text(data) => data.get_ref_contents().len(),
+            DecodedPackedFile::Anim |
+            DecodedPackedFile::AnimFragment |
+            DecodedPackedFile::AnimPack |
+            DecodedPackedFile::AnimTable |
+            DecodedPackedFile::MatchedCombat |
+            DecodedPackedFile::Unknown => 0,
+        }
+    }
+
+    /// This function returns a rough estimate of the memory used by the provided rows of a table, in bytes.
+    fn approx_table_memory(entries: &[Vec<DecodedData>]) -> usize {
+        entries.iter().flatten().map(|cell| size_of::<DecodedData>() + match cell {
+            DecodedData::StringU8(data) |
+            DecodedData::StringU16(data) => data.len(),
+            DecodedData::OptionalStringU8(data) |
+            DecodedData::OptionalStringU16(data) => data.as_ref().map_or(0, |x| x.len()),
+            DecodedData::Sequence(data) => Self::approx_table_memory(data.get_ref_table_data()),
+            DecodedData::Boolean(_) |
+            DecodedData::Float(_) |
+            DecodedData::Integer(_) |
+            DecodedData::LongInteger(_) => 0,
+        }).sum()
+    }
+
+    /// This function returns a rough estimate of the memory used by the provided ESF node and its children, in bytes.
+    fn approx_esf_node_memory(node: &EsfNode) -> usize {
+        size_of::<EsfNode>() + match node {
+            EsfNode::Utf16(data) |
+            EsfNode::Ascii(data) => data.len(),
+            EsfNode::BoolArray(data) => data.len(),
+            EsfNode::I8Array(data) => data.len(),
+            EsfNode::I16Array(data) => data.len() * 2,
+            EsfNode::I32Array(data) => data.len() * 4,
+            EsfNode::I64Array(data) => data.len() * 8,
+            EsfNode::U8Array(data) => data.len(),
+            EsfNode::U16Array(data) |
+            EsfNode::AngleArray(data) => data.len() * 2,
+            EsfNode::U32Array(data) => data.len() * 4,
+            EsfNode::U64Array(data) => data.len() * 8,
+            EsfNode::F32Array(data) => data.len() * 4,
+            EsfNode::F64Array(data) => data.len() * 8,
+            EsfNode::Coord2dArray(data) => data.len() * 8,
+            EsfNode::Coord3dArray(data) => data.len() * 12,
+            EsfNode::Utf16Array(data) |
+            EsfNode::AsciiArray(data) => data.iter().map(|x| size_of::<String>() + x.len()).sum(),
+            EsfNode::Record(data) => data.name.len() + data.children.iter().map(Self::approx_esf_node_memory).sum::<usize>(),
+            EsfNode::RecordBlock(data) => data.name.len() + data.entries.iter().flatten().map(Self::approx_esf_node_memory).sum::<usize>(),
+            _ => 0,
+        }
+    }
+
     /// This function updates a DB Table to its latest valid version, being the latest valid version the one in the data.pack or equivalent of the game.
     ///
     /// It returns both, old and new versions, or an error.
@@ -267,15 +541,89 @@ impl Display for PackedFileType {
     }
 }
 
+/// FromStr implementation of `PackedFileType`.
+///
+/// It accepts both, the text returned by `Display`, and the name of the variant (`Text` alone is `Text(TextType::Plain)`).
+impl FromStr for PackedFileType {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self> {
+        match string {
+            "Anim" => Ok(PackedFileType::Anim),
+            "AnimFragment" => Ok(PackedFileType::AnimFragment),
+            "AnimPack" => Ok(PackedFileType::AnimPack),
+            "AnimTable" => Ok(PackedFileType::AnimTable),
+            "CA_VP8" | "CaVp8" => Ok(PackedFileType::CaVp8),
+            "CEO" => Ok(PackedFileType::CEO),
+            "DB Table" | "DB" => Ok(PackedFileType::DB),
+            "Dependency PackFile List" | "DependencyPackFilesList" => Ok(PackedFileType::DependencyPackFilesList),
+            "Image" => Ok(PackedFileType::Image),
+            "Loc Table" | "Loc" => Ok(PackedFileType::Loc),
+            "Matched Combat" | "MatchedCombat" => Ok(PackedFileType::MatchedCombat),
+            "RigidModel" => Ok(PackedFileType::RigidModel),
+            "StartPos" | "StarPos" => Ok(PackedFileType::StarPos),
+            "Text" => Ok(PackedFileType::Text(TextType::Plain)),
+            "Unknown" => Ok(PackedFileType::Unknown),
+            _ => {
+                let text_type = string.strip_prefix("Text, type: ")
+                    .or_else(|| string.strip_prefix("Text(").and_then(|x| x.strip_suffix(')')))
+                    .ok_or_else(|| Error::from(ErrorKind::PackedFileTypeUnknown))?;
+
+                match text_type {
+                    "Html" => Ok(PackedFileType::Text(TextType::Html)),
+                    "Xml" => Ok(PackedFileType::Text(TextType::Xml)),
+                    "Lua" => Ok(PackedFileType::Text(TextType::Lua)),
+                    "Cpp" => Ok(PackedFileType::Text(TextType::Cpp)),
+                    "Markdown" => Ok(PackedFileType::Text(TextType::Markdown)),
+                    "Csv" => Ok(PackedFileType::Text(TextType::Csv)),
+                    "Tsv" => Ok(PackedFileType::Text(TextType::Tsv)),
+                    "Plain" => Ok(PackedFileType::Text(TextType::Plain)),
+                    _ => Err(ErrorKind::PackedFileTypeUnknown.into()),
+                }
+            }
+        }
+    }
+}
+
+/// Implementation of `Ord` for `PackedFileType`, so lists of files grouped by type are always sorted the same way.
+impl Ord for PackedFileType {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+/// Implementation of `PartialOrd` for `PackedFileType`.
+impl PartialOrd for PackedFileType {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 /// Implementation of `PackedFileType`.
 impl PackedFileType {
 
+    /// This function returns the key used to sort `PackedFileType`.
+    ///
+    /// DB Tables go first, then Loc Tables, Text PackedFiles and Images. The rest of the types go after them, sorted alphabetically.
+    pub fn sort_key(&self) -> (u8, String) {
+        let group = match self {
+            PackedFileType::DB => 0,
+            PackedFileType::Loc => 1,
+            PackedFileType::Text(_) => 2,
+            PackedFileType::Image => 3,
+            _ => 4,
+        };
+        (group, self.to_string())
+    }
+
     /// This function returns the type of the `PackedFile` at the provided path based on the path itself.
     pub fn get_packed_file_type(path: &[String]) -> Self {
         if let Some(packedfile_name) = path.last() {
             if packedfile_name.ends_with(table::loc::EXTENSION) { PackedFileType::Loc }
             else if packedfile_name.ends_with(rigidmodel::EXTENSION) { PackedFileType::RigidModel }
             else if packedfile_name.ends_with(ca_vp8::EXTENSION) { PackedFileType::CaVp8 }
+            else if packedfile_name.to_lowercase() == esf::NAME_STAR_POS { PackedFileType::StarPos }
+            else if packedfile_name.ends_with(esf::EXTENSION_CEO) { PackedFileType::CEO }
             else if let Some((_, text_type)) = text::EXTENSIONS.iter().find(|(x, _)| packedfile_name.ends_with(x)) {
                 PackedFileType::Text(*text_type)
             }
@@ -284,8 +632,9 @@ impl PackedFileType {
                 PackedFileType::Image
             }
 
-            // If it's in the "db" folder, it's a DB PackedFile (or you put something were it shouldn't be).
-            else if path[0].to_lowercase() == "db" { PackedFileType::DB }
+            // If it's in a table folder inside the "db" folder, it's a DB PackedFile (or you put something were it shouldn't be).
+            // Only the `db/table_name/file` layout counts. A file that has "db" in his name doesn't make it a DB.
+            else if Self::get_db_table_name(path).is_ok() { PackedFileType::DB }
 
             // Otherwise, we don't have a decoder for that PackedFile... yet.
             else { PackedFileType::Unknown }
@@ -295,6 +644,66 @@ impl PackedFileType {
         else { PackedFileType::Unknown }
     }
 
+    /// This function returns the name of the table of the DB `PackedFile` at the provided path.
+    ///
+    /// DB Tables are in a table folder inside the "db" folder, like `db/table_name/file`, so the table name is the second item of the path.
+    /// If the path is not the path of a DB Table, this returns an error.
+    pub fn get_db_table_name(path: &[String]) -> Result<&str> {
+        match path.get(1) {
+            Some(table_name) if path.len() == 3 && path[0].to_lowercase() == "db" => Ok(table_name),
+            _ => Err(ErrorKind::DBTableIsNotADBTable.into()),
+        }
+    }
+
+    /// This function returns the list of extensions used to identify `PackedFiles` of this type by their path.
+    ///
+    /// These are the same extensions `get_packed_file_type` uses. Types that are not identified by extension return an empty slice.
+    /// That includes `DB`, which is identified by being in the `db` folder instead, and `StarPos`, which is identified by its full name.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::CaVp8 => &[ca_vp8::EXTENSION],
+            Self::Image => &image::EXTENSIONS,
+            Self::Loc => &[table::loc::EXTENSION],
+            Self::RigidModel => &[rigidmodel::EXTENSION],
+            Self::CEO => &[esf::EXTENSION_CEO],
+            Self::StarPos => &[],
+            Self::Text(text_type) => TEXT_EXTENSIONS_BY_TYPE.iter()
+                .find(|(x, _)| x == text_type)
+                .map_or(&[], |(_, extensions)| extensions),
+            Self::Anim |
+            Self::AnimFragment |
+            Self::AnimPack |
+            Self::AnimTable |
+            Self::DB |
+            Self::DependencyPackFilesList |
+            Self::MatchedCombat |
+            Self::Unknown => &[],
+        }
+    }
+
+    /// This function returns a best-guess of the type of a `PackedFile` based only on the signature at the start of its data.
+    ///
+    /// Only types with a signature can be detected this way. ESF PackedFiles are returned as `StarPos`,
+    /// as both, StarPos and CEO PackedFiles, share the same signature. If nothing matches, this returns `Unknown`.
+    pub fn from_magic(data: &[u8]) -> Self {
+        if Loc::is_loc(data) { Self::Loc }
+        else if CaVp8::is_video(data) { Self::CaVp8 }
+        else if Image::is_image(data) { Self::Image }
+        else if RigidModel::is_rigidmodel(data) { Self::RigidModel }
+        else if EsfFile::is_esf(data) { Self::StarPos }
+        else { Self::Unknown }
+    }
+
+    /// This function returns the type of a `PackedFile` based on its path, falling back to its data if the path doesn't tell us anything.
+    ///
+    /// Useful for files with wrong or no extension.
+    pub fn detect(path: &[String], data: &[u8]) -> Self {
+        match Self::get_packed_file_type(path) {
+            Self::Unknown => Self::from_magic(data),
+            packed_file_type => packed_file_type,
+        }
+    }
+
     /// This function returns the type of the provided `PackedFile` based on the data it contains.
     pub fn get_packed_file_type_by_data(packed_file: &PackedFile) -> Self {
         match packed_file.get_raw_data() {
@@ -315,8 +724,7 @@ impl PackedFileType {
 
                     if Loc::is_loc(&data) { Self::Loc }
                     else if DB::read_header(&data).is_ok() { Self::DB }
-                    else if CaVp8::is_video(&data) { Self::CaVp8 }
-                    else { Self::Unknown }
+                    else { Self::from_magic(&data) }
                 }
 
                 else { Self::Unknown }
@@ -325,6 +733,92 @@ impl PackedFileType {
         }
     }
 
+    /// This function returns if this type is a table (DB or Loc).
+    pub fn is_table(self) -> bool {
+        match self {
+            Self::DB |
+            Self::Loc => true,
+            Self::Anim |
+            Self::AnimFragment |
+            Self::AnimPack |
+            Self::AnimTable |
+            Self::CaVp8 |
+            Self::CEO |
+            Self::DependencyPackFilesList |
+            Self::Image |
+            Self::MatchedCombat |
+            Self::RigidModel |
+            Self::StarPos |
+            Self::Text(_) |
+            Self::Unknown => false,
+        }
+    }
+
+    /// This function returns if this type is a Text PackedFile, of any subtype.
+    pub fn is_text(self) -> bool {
+        match self {
+            Self::Text(_) => true,
+            Self::Anim |
+            Self::AnimFragment |
+            Self::AnimPack |
+            Self::AnimTable |
+            Self::CaVp8 |
+            Self::CEO |
+            Self::DB |
+            Self::DependencyPackFilesList |
+            Self::Image |
+            Self::Loc |
+            Self::MatchedCombat |
+            Self::RigidModel |
+            Self::StarPos |
+            Self::Unknown => false,
+        }
+    }
+
+    /// This function returns if this type is an Image.
+    pub fn is_image(self) -> bool {
+        match self {
+            Self::Image => true,
+            Self::Anim |
+            Self::AnimFragment |
+            Self::AnimPack |
+            Self::AnimTable |
+            Self::CaVp8 |
+            Self::CEO |
+            Self::DB |
+            Self::DependencyPackFilesList |
+            Self::Loc |
+            Self::MatchedCombat |
+            Self::RigidModel |
+            Self::StarPos |
+            Self::Text(_) |
+            Self::Unknown => false,
+        }
+    }
+
+    /// This function returns if we have a decoder for this type.
+    ///
+    /// These are the types `DecodedPackedFile::decode` turns into something other than `DecodedPackedFile::Unknown`.
+    pub fn is_decodable(self) -> bool {
+        match self {
+            Self::CaVp8 |
+            Self::CEO |
+            Self::DB |
+            Self::Image |
+            Self::Loc |
+            Self::StarPos |
+            Self::Text(_) => true,
+            Self::Anim |
+            Self::AnimFragment |
+            Self::AnimPack |
+            Self::AnimTable |
+            Self::DependencyPackFilesList |
+            Self::MatchedCombat |
+            Self::RigidModel |
+            Self::Unknown => false,
+        }
+    }
+
     /// This function is a less strict version of the one implemented with the `Eq` trait.
     ///
     /// It performs an equality check between both provided types, ignoring the subtypes. This means,
@@ -383,13 +877,14 @@ impl From<&DecodedPackedFile> for PackedFileType {
             DecodedPackedFile::AnimPack => PackedFileType::AnimPack,
             DecodedPackedFile::AnimTable => PackedFileType::AnimTable,
             DecodedPackedFile::CaVp8(_) => PackedFileType::CaVp8,
-            DecodedPackedFile::CEO => PackedFileType::CEO,
+            DecodedPackedFile::CEO(_) => PackedFileType::CEO,
+            DecodedPackedFile::CsvTable(table) => PackedFileType::Text(table.get_text_type()),
             DecodedPackedFile::DB(_) => PackedFileType::DB,
             DecodedPackedFile::Image(_) => PackedFileType::Image,
             DecodedPackedFile::Loc(_) => PackedFileType::Loc,
             DecodedPackedFile::MatchedCombat => PackedFileType::MatchedCombat,
             DecodedPackedFile::RigidModel(_) => PackedFileType::RigidModel,
-            DecodedPackedFile::StarPos => PackedFileType::StarPos,
+            DecodedPackedFile::StarPos(_) => PackedFileType::StarPos,
             DecodedPackedFile::Text(text) => PackedFileType::Text(text.get_text_type()),
             DecodedPackedFile::Unknown => PackedFileType::Unknown,
         }
@@ -0,0 +1,441 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `PackedFile` module, just to make sure we don't break it... again...
+!*/
+
+use rpfm_error::ErrorKind;
+
+use std::sync::Mutex;
+
+use crate::SETTINGS;
+
+use crate::schema::{Definition, Field, FieldType};
+use super::*;
+use super::table::DecodedData;
+use super::text::TextType;
+
+lazy_static! {
+
+    /// Lock for the tests that register decoders, so clearing them in one test doesn't break the others.
+    static ref CUSTOM_DECODERS_TEST_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Test to make sure every extension `get_packed_file_type` recognizes can be found through `extensions()`.
+#[test]
+fn test_packed_file_type_extensions() {
+    let mut all_extensions = vec![table::loc::EXTENSION, rigidmodel::EXTENSION, ca_vp8::EXTENSION, esf::EXTENSION_CEO];
+    all_extensions.extend(text::EXTENSIONS.iter().map(|(x, _)| *x));
+    all_extensions.extend(image::EXTENSIONS.iter());
+
+    for extension in &all_extensions {
+        let path = vec!["folder".to_owned(), format!("file{}", extension)];
+        let packed_file_type = PackedFileType::get_packed_file_type(&path);
+        assert!(packed_file_type.extensions().contains(extension), "{} not found in {}", extension, packed_file_type);
+    }
+
+    assert_eq!(PackedFileType::Image.extensions(), &[".jpg", ".jpeg", ".tga", ".dds", ".png"]);
+    assert!(PackedFileType::Text(TextType::Lua).extensions().contains(&".lua"));
+    assert!(!PackedFileType::Text(TextType::Lua).extensions().contains(&".xml"));
+    assert!(PackedFileType::Text(TextType::Markdown).extensions().is_empty());
+    assert!(PackedFileType::DB.extensions().is_empty());
+}
+
+/// Test to make sure the table name of DB Tables is taken from the right folder, and that other paths are rejected.
+#[test]
+fn test_get_db_table_name() {
+    let path = |x: &[&str]| x.iter().map(|x| (*x).to_owned()).collect::<Vec<String>>();
+    assert_eq!(PackedFileType::get_db_table_name(&path(&["db", "units_tables", "data__"])).unwrap(), "units_tables");
+    assert_eq!(PackedFileType::get_db_table_name(&path(&["DB", "land_units_tables", "data__"])).unwrap(), "land_units_tables");
+
+    let error = &ErrorKind::DBTableIsNotADBTable;
+    assert_eq!(PackedFileType::get_db_table_name(&path(&[])).unwrap_err().kind(), error);
+    assert_eq!(PackedFileType::get_db_table_name(&path(&["data__"])).unwrap_err().kind(), error);
+    assert_eq!(PackedFileType::get_db_table_name(&path(&["db", "data__"])).unwrap_err().kind(), error);
+    assert_eq!(PackedFileType::get_db_table_name(&path(&["script", "units_tables", "data__"])).unwrap_err().kind(), error);
+
+    // The table name is always the second item of the path, so files in subfolders of a table folder, or db folders inside others, are not tables.
+    assert_eq!(PackedFileType::get_db_table_name(&path(&["db", "x_tables", "sub", "file"])).unwrap_err().kind(), error);
+    assert_eq!(PackedFileType::get_db_table_name(&path(&["extracted", "db", "x_tables", "file"])).unwrap_err().kind(), error);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["db", "x_tables", "sub", "file"])), PackedFileType::Unknown);
+}
+
+/// Test to make sure only files inside a table folder in the `db` folder are detected as DB Tables.
+#[test]
+fn test_packed_file_type_db_paths() {
+    let path = |x: &[&str]| x.iter().map(|x| (*x).to_owned()).collect::<Vec<String>>();
+
+    // Only the canonical layout counts.
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["db", "units_tables", "data__"])), PackedFileType::DB);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["DB", "units_tables", "data__"])), PackedFileType::DB);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["extracted", "db", "units_tables", "data__"])), PackedFileType::Unknown);
+
+    // Paths that just contain "db" somewhere.
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["db"])), PackedFileType::Unknown);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["db", "data__"])), PackedFileType::Unknown);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["script", "db"])), PackedFileType::Unknown);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["script", "units", "db"])), PackedFileType::Unknown);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["data", "units_tables", "my_db_file"])), PackedFileType::Unknown);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["db", "units_tables", "subfolder", "data__"])), PackedFileType::Unknown);
+}
+
+/// Test to make sure only startpos files are detected as StarPos, not every ESF file.
+#[test]
+fn test_packed_file_type_star_pos() {
+    let path = |x: &[&str]| x.iter().map(|x| (*x).to_owned()).collect::<Vec<String>>();
+
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["campaigns", "main_warhammer", "startpos.esf"])), PackedFileType::StarPos);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["campaigns", "main_warhammer", "StartPos.ESF"])), PackedFileType::StarPos);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["campaigns", "main_warhammer", "other.esf"])), PackedFileType::Unknown);
+    assert_eq!(PackedFileType::get_packed_file_type(&path(&["campaigns", "main_warhammer", "my_startpos.esf"])), PackedFileType::Unknown);
+}
+
+/// Test to make sure StarPos and CEO PackedFiles with an ESF signature we don't support are opened as `Unknown`, instead of failing.
+#[test]
+fn test_decode_esf_unsupported_signature() {
+    let data = vec![0xCA, 0xAB, 0, 0, 0, 0, 0, 0];
+    let schema = Schema::default();
+    let raw_packed_file = RawPackedFile::read_from_vec(vec!["campaigns".to_owned(), "main_warhammer".to_owned(), "startpos.esf".to_owned()], String::new(), 0, false, data.to_vec());
+    assert_eq!(DecodedPackedFile::decode(&raw_packed_file).unwrap(), DecodedPackedFile::Unknown);
+    assert_eq!(DecodedPackedFile::decode_no_locks(&raw_packed_file, &schema).unwrap(), DecodedPackedFile::Unknown);
+
+    let raw_packed_file = RawPackedFile::read_from_vec(vec!["campaigns".to_owned(), "mod.ccd".to_owned()], String::new(), 0, false, data.to_vec());
+    assert_eq!(DecodedPackedFile::decode(&raw_packed_file).unwrap(), DecodedPackedFile::Unknown);
+    assert_eq!(DecodedPackedFile::decode_no_locks(&raw_packed_file, &schema).unwrap(), DecodedPackedFile::Unknown);
+
+    // Other errors are still errors.
+    let raw_packed_file = RawPackedFile::read_from_vec(vec!["campaigns".to_owned(), "main_warhammer".to_owned(), "startpos.esf".to_owned()], String::new(), 0, false, vec![0xCE, 0xAB, 0, 0]);
+    assert!(DecodedPackedFile::decode(&raw_packed_file).is_err());
+    assert!(DecodedPackedFile::decode_no_locks(&raw_packed_file, &schema).is_err());
+}
+
+/// Test to make sure decoded Text PackedFiles get the type of text their extension implies.
+#[test]
+fn test_decoded_text_type() {
+    let schema = Schema::default();
+    let text_types = vec![
+        ("script.lua", TextType::Lua),
+        ("unit.variantmeshdefinition", TextType::Xml),
+        ("campaign.xml.material", TextType::Xml),
+        ("shader.inl", TextType::Cpp),
+        ("readme.html", TextType::Html),
+        ("data.csv", TextType::Csv),
+        ("data.tsv", TextType::Tsv),
+        ("notes.txt", TextType::Plain),
+    ];
+
+    for (name, text_type) in text_types {
+        let raw_packed_file = RawPackedFile::read_from_vec(vec!["text".to_owned(), name.to_owned()], String::new(), 0, false, b"text".to_vec());
+        match DecodedPackedFile::decode_no_locks(&raw_packed_file, &schema).unwrap() {
+            DecodedPackedFile::Text(text) => assert_eq!(text.get_text_type(), text_type, "{}", name),
+            _ => panic!("{} not decoded as Text", name),
+        }
+    }
+}
+
+/// Test to make sure PackedFiles with wrong or no extension get their type from their data.
+#[test]
+fn test_packed_file_type_detect() {
+    let path = |x: &[&str]| x.iter().map(|x| (*x).to_owned()).collect::<Vec<String>>();
+    let mut dds = b"DDS ".to_vec();
+    dds.extend_from_slice(&[0; 124]);
+
+    assert_eq!(PackedFileType::from_magic(&dds), PackedFileType::Image);
+    assert_eq!(PackedFileType::from_magic(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0]), PackedFileType::Image);
+    assert_eq!(PackedFileType::from_magic(&[0xFF, 0xD8, 0xFF, 0xE0]), PackedFileType::Image);
+    assert_eq!(PackedFileType::from_magic(b"CAMV0000"), PackedFileType::CaVp8);
+    assert_eq!(PackedFileType::from_magic(b"RMV2\x07\x00\x00\x00"), PackedFileType::RigidModel);
+    assert_eq!(PackedFileType::from_magic(&[0xCE, 0xAB, 0, 0]), PackedFileType::StarPos);
+    assert_eq!(PackedFileType::from_magic(b"just some text"), PackedFileType::Unknown);
+    assert_eq!(PackedFileType::from_magic(&[]), PackedFileType::Unknown);
+
+    // The path goes first, and the data is only checked if the path doesn't tell us anything.
+    assert_eq!(PackedFileType::detect(&path(&["ui", "skins", "texture"]), &dds), PackedFileType::Image);
+    assert_eq!(PackedFileType::detect(&path(&["ui", "skins", "texture.dds_old"]), &dds), PackedFileType::Image);
+    assert_eq!(PackedFileType::detect(&path(&["script", "texture.lua"]), &dds), PackedFileType::Text(TextType::Lua));
+    assert_eq!(PackedFileType::detect(&path(&["ui", "skins", "texture"]), b"just some text"), PackedFileType::Unknown);
+}
+
+/// Test to make sure `verify_lossless` reports decoded PackedFiles that don't encode back to their original data.
+#[test]
+fn test_verify_lossless() {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU16, is_key: true, ..Default::default() });
+    definition.fields.push(Field { name: "text".to_owned(), field_type: FieldType::StringU16, ..Default::default() });
+    definition.fields.push(Field { name: "tooltip".to_owned(), field_type: FieldType::Boolean, ..Default::default() });
+
+    let mut loc = Loc::new(&definition);
+    loc.set_table_data(&[vec![DecodedData::StringU16("key".to_owned()), DecodedData::StringU16("Text".to_owned()), DecodedData::Boolean(true)]]).unwrap();
+    let original = loc.save().unwrap();
+    assert!(DecodedPackedFile::Loc(loc.clone()).verify_lossless(&original).unwrap());
+
+    // Simulate a decoder that loses part of the data.
+    loc.set_table_data(&[vec![DecodedData::StringU16("key".to_owned()), DecodedData::StringU16(String::new()), DecodedData::Boolean(true)]]).unwrap();
+    assert!(!DecodedPackedFile::Loc(loc).verify_lossless(&original).unwrap());
+
+    assert_eq!(DecodedPackedFile::Unknown.verify_lossless(&original).unwrap_err().kind(), &ErrorKind::PackedFileTypeNotEncodable);
+}
+
+/// Test to make sure `PackedFileType` is always sorted in the same order.
+#[test]
+fn test_packed_file_type_order() {
+    let mut types = vec![
+        PackedFileType::Unknown,
+        PackedFileType::StarPos,
+        PackedFileType::Text(TextType::Plain),
+        PackedFileType::RigidModel,
+        PackedFileType::MatchedCombat,
+        PackedFileType::Loc,
+        PackedFileType::Image,
+        PackedFileType::DependencyPackFilesList,
+        PackedFileType::DB,
+        PackedFileType::CEO,
+        PackedFileType::CaVp8,
+        PackedFileType::AnimTable,
+        PackedFileType::AnimPack,
+        PackedFileType::AnimFragment,
+        PackedFileType::Anim,
+        PackedFileType::Text(TextType::Lua),
+    ];
+    types.sort();
+
+    assert_eq!(types, vec![
+        PackedFileType::DB,
+        PackedFileType::Loc,
+        PackedFileType::Text(TextType::Lua),
+        PackedFileType::Text(TextType::Plain),
+        PackedFileType::Image,
+        PackedFileType::Anim,
+        PackedFileType::AnimFragment,
+        PackedFileType::AnimPack,
+        PackedFileType::AnimTable,
+        PackedFileType::CaVp8,
+        PackedFileType::CEO,
+        PackedFileType::DependencyPackFilesList,
+        PackedFileType::MatchedCombat,
+        PackedFileType::RigidModel,
+        PackedFileType::StarPos,
+        PackedFileType::Unknown,
+    ]);
+}
+
+/// Test to make sure the category predicates of `PackedFileType` and `DecodedPackedFile` return the expected values for every type.
+#[test]
+fn test_packed_file_type_categories() {
+
+    // Type, is_table, is_text, is_image, is_decodable.
+    let types = vec![
+        (PackedFileType::Anim, false, false, false, false),
+        (PackedFileType::AnimFragment, false, false, false, false),
+        (PackedFileType::AnimPack, false, false, false, false),
+        (PackedFileType::AnimTable, false, false, false, false),
+        (PackedFileType::CaVp8, false, false, false, true),
+        (PackedFileType::CEO, false, false, false, true),
+        (PackedFileType::DB, true, false, false, true),
+        (PackedFileType::DependencyPackFilesList, false, false, false, false),
+        (PackedFileType::Image, false, false, true, true),
+        (PackedFileType::Loc, true, false, false, true),
+        (PackedFileType::MatchedCombat, false, false, false, false),
+        (PackedFileType::RigidModel, false, false, false, false),
+        (PackedFileType::StarPos, false, false, false, true),
+        (PackedFileType::Text(TextType::Plain), false, true, false, true),
+        (PackedFileType::Text(TextType::Lua), false, true, false, true),
+        (PackedFileType::Unknown, false, false, false, false),
+    ];
+
+    for (packed_file_type, is_table, is_text, is_image, is_decodable) in types {
+        assert_eq!(packed_file_type.is_table(), is_table, "{}", packed_file_type);
+        assert_eq!(packed_file_type.is_text(), is_text, "{}", packed_file_type);
+        assert_eq!(packed_file_type.is_image(), is_image, "{}", packed_file_type);
+        assert_eq!(packed_file_type.is_decodable(), is_decodable, "{}", packed_file_type);
+    }
+
+    // The ones in `DecodedPackedFile` just follow his type.
+    let loc = DecodedPackedFile::Loc(Loc::new(&Definition::new(1)));
+    assert!(loc.is_table() && !loc.is_text() && !loc.is_image() && loc.is_decodable());
+
+    let text = DecodedPackedFile::Text(Text::new());
+    assert!(!text.is_table() && text.is_text() && !text.is_image() && text.is_decodable());
+
+    let anim = DecodedPackedFile::Anim;
+    assert!(!anim.is_table() && !anim.is_text() && !anim.is_image() && !anim.is_decodable());
+    assert!(!DecodedPackedFile::AnimTable.is_decodable());
+    assert!(!DecodedPackedFile::Unknown.is_decodable());
+}
+
+/// Test to make sure every `PackedFileType` can be parsed back from his text.
+#[test]
+fn test_packed_file_type_from_str() {
+    let types = vec![
+        PackedFileType::Anim,
+        PackedFileType::AnimFragment,
+        PackedFileType::AnimPack,
+        PackedFileType::AnimTable,
+        PackedFileType::CaVp8,
+        PackedFileType::CEO,
+        PackedFileType::DB,
+        PackedFileType::DependencyPackFilesList,
+        PackedFileType::Image,
+        PackedFileType::Loc,
+        PackedFileType::MatchedCombat,
+        PackedFileType::RigidModel,
+        PackedFileType::StarPos,
+        PackedFileType::Text(TextType::Html),
+        PackedFileType::Text(TextType::Xml),
+        PackedFileType::Text(TextType::Lua),
+        PackedFileType::Text(TextType::Cpp),
+        PackedFileType::Text(TextType::Markdown),
+        PackedFileType::Text(TextType::Csv),
+        PackedFileType::Text(TextType::Tsv),
+        PackedFileType::Text(TextType::Plain),
+        PackedFileType::Unknown,
+    ];
+
+    for packed_file_type in types {
+        assert_eq!(packed_file_type.to_string().parse::<PackedFileType>().unwrap(), packed_file_type);
+        assert_eq!(format!("{:?}", packed_file_type).parse::<PackedFileType>().unwrap(), packed_file_type);
+    }
+
+    assert_eq!("Text".parse::<PackedFileType>().unwrap(), PackedFileType::Text(TextType::Plain));
+    for string in &["", "db table", "Text, type: Python", "Text(Lua", "Dependency PackFile List "] {
+        assert_eq!(string.parse::<PackedFileType>().unwrap_err().kind(), &ErrorKind::PackedFileTypeUnknown);
+    }
+}
+
+/// Test to make sure unknown PackedFiles that look like text are decoded as text, but only if it's enabled.
+#[test]
+fn test_decode_unknown_as_text() {
+    let json = RawPackedFile::read_from_vec(vec!["ui".to_owned(), "data.json".to_owned()], "test.pack".to_owned(), 0, false, b"{ \"key\": 1 }\n".to_vec());
+    let binary = RawPackedFile::read_from_vec(vec!["ui".to_owned(), "data.bin".to_owned()], "test.pack".to_owned(), 0, false, vec![0, 1, 2, 0xFF, 0xFE, 0]);
+
+    assert_eq!(DecodedPackedFile::decode_unknown(&json, false).unwrap(), DecodedPackedFile::Unknown);
+    assert_eq!(DecodedPackedFile::decode_unknown(&binary, true).unwrap(), DecodedPackedFile::Unknown);
+
+    // Both ways of decoding follow the setting, whatever its current value is. We don't change it, as other tests may be reading it.
+    let detect_text = SETTINGS.read().unwrap().settings_bool["detect_text_in_unknown_packedfiles"];
+    let schema = Schema::default();
+    for raw_packed_file in &[&json, &binary] {
+        let decoded = DecodedPackedFile::decode_unknown(raw_packed_file, detect_text).unwrap();
+        assert_eq!(DecodedPackedFile::decode(raw_packed_file).unwrap(), decoded);
+        assert_eq!(DecodedPackedFile::decode_no_locks(raw_packed_file, &schema).unwrap(), decoded);
+    }
+
+    match DecodedPackedFile::decode_unknown(&json, true).unwrap() {
+        DecodedPackedFile::Text(text) => {
+            assert_eq!(text.get_text_type(), TextType::Plain);
+            assert_eq!(text.get_ref_contents(), "{ \"key\": 1 }\n");
+        }
+        _ => panic!("The .json file was not decoded as text."),
+    }
+}
+
+/// Test to make sure decoding empty data returns a clear error instead of a misleading one, except for Text PackedFiles.
+#[test]
+fn test_decode_empty_data() {
+    let schema = Schema::default();
+    let error = &ErrorKind::PackedFileDataIsEmpty;
+    assert_eq!(CaVp8::read(vec![]).unwrap_err().kind(), error);
+    assert_eq!(DB::read(&[], "units_tables", &schema, false).unwrap_err().kind(), error);
+    assert_eq!(EsfFile::read(&[]).unwrap_err().kind(), error);
+    assert_eq!(Image::read(&[]).unwrap_err().kind(), error);
+    assert_eq!(Loc::read(&[], &schema, false).unwrap_err().kind(), error);
+    assert_eq!(RigidModel::read(&[]).unwrap_err().kind(), error);
+
+    // Empty text files are valid text files.
+    assert_eq!(Text::read(&[]).unwrap().get_ref_contents(), "");
+
+    // And the same goes when decoding them through a `RawPackedFile`.
+    let raw_packed_file = RawPackedFile::read_from_vec(vec!["ui".to_owned(), "image.png".to_owned()], String::new(), 0, false, vec![]);
+    assert_eq!(DecodedPackedFile::decode(&raw_packed_file).unwrap_err().kind(), error);
+}
+
+/// Test to make sure registered decoders are used for the paths they match, but don't replace our own decoders unless they're overrides.
+#[test]
+fn test_register_decoder() {
+    let _lock = CUSTOM_DECODERS_TEST_LOCK.lock().unwrap_or_else(|error| error.into_inner());
+    let custom = DecodedPackedFile::register_decoder(
+        |path| path.last().map_or(false, |x| x.ends_with(".rpfm_test_custom") || x.ends_with(".rpfm_test_custom.txt")),
+        Box::new(|raw_packed_file| Ok(DecodedPackedFile::Text(Text::read(&raw_packed_file.get_data()?)?)))
+    );
+    let custom_override = DecodedPackedFile::register_decoder_override(
+        |path| path.last().map_or(false, |x| x.ends_with(".rpfm_test_override.txt")),
+        Box::new(|_| Ok(DecodedPackedFile::Unknown))
+    );
+
+    let raw_packed_file = |name: &str| RawPackedFile::read_from_vec(vec!["custom".to_owned(), name.to_owned()], String::new(), 0, false, b"Custom data.".to_vec());
+    let schema = Schema::default();
+    for decoded in &[DecodedPackedFile::decode(&raw_packed_file("file.rpfm_test_custom")).unwrap(), DecodedPackedFile::decode_no_locks(&raw_packed_file("file.rpfm_test_custom"), &schema).unwrap()] {
+        match decoded {
+            DecodedPackedFile::Text(text) => assert_eq!(text.get_ref_contents(), "Custom data."),
+            _ => panic!("Custom decoder not used."),
+        }
+    }
+
+    // Text files have a decoder already, so the normal decoder wins over the non-override one.
+    let decoded = DecodedPackedFile::decode(&raw_packed_file("file.rpfm_test_custom.txt")).unwrap();
+    assert_eq!(PackedFileType::from(&decoded), PackedFileType::Text(TextType::Plain));
+
+    assert_eq!(DecodedPackedFile::decode(&raw_packed_file("file.rpfm_test_override.txt")).unwrap(), DecodedPackedFile::Unknown);
+    assert_eq!(DecodedPackedFile::decode_no_locks(&raw_packed_file("file.rpfm_test_override.txt"), &schema).unwrap(), DecodedPackedFile::Unknown);
+    assert_eq!(DecodedPackedFile::decode(&raw_packed_file("file.rpfm_test_other")).unwrap(), DecodedPackedFile::Unknown);
+
+    // Once unregistered, our own decoders are used again.
+    assert!(DecodedPackedFile::unregister_decoder(custom));
+    assert!(DecodedPackedFile::unregister_decoder(custom_override));
+    assert!(!DecodedPackedFile::unregister_decoder(custom));
+    assert!(DecodedPackedFile::get_custom_decoder(&["custom".to_owned(), "file.rpfm_test_custom".to_owned()], PackedFileType::Unknown).is_none());
+    assert_eq!(PackedFileType::from(&DecodedPackedFile::decode(&raw_packed_file("file.rpfm_test_override.txt")).unwrap()), PackedFileType::Text(TextType::Plain));
+}
+
+/// Test to make sure clearing the registered decoders removes all of them.
+#[test]
+fn test_clear_decoders() {
+    let _lock = CUSTOM_DECODERS_TEST_LOCK.lock().unwrap_or_else(|error| error.into_inner());
+    DecodedPackedFile::register_decoder(|path| path.last().map_or(false, |x| x.ends_with(".rpfm_test_clear")), Box::new(|_| Ok(DecodedPackedFile::Unknown)));
+    DecodedPackedFile::register_decoder_override(|path| path.last().map_or(false, |x| x.ends_with(".rpfm_test_clear.txt")), Box::new(|_| Ok(DecodedPackedFile::Unknown)));
+    assert!(DecodedPackedFile::get_custom_decoder(&["file.rpfm_test_clear".to_owned()], PackedFileType::Unknown).is_some());
+
+    DecodedPackedFile::clear_decoders();
+    assert!(DecodedPackedFile::get_custom_decoder(&["file.rpfm_test_clear".to_owned()], PackedFileType::Unknown).is_none());
+    assert!(DecodedPackedFile::get_custom_decoder(&["file.rpfm_test_clear.txt".to_owned()], PackedFileType::Text(TextType::Plain)).is_none());
+}
+
+/// Test to make sure the estimated memory of a `DecodedPackedFile` grows with its contents.
+#[test]
+fn test_approx_memory() {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU8, is_key: true, ..Default::default() });
+    definition.fields.push(Field { name: "value".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+
+    let mut sizes = vec![];
+    let mut db = DB::new("test_tables", None, &definition);
+    for row in 0..4 {
+        sizes.push(DecodedPackedFile::DB(db.clone()).approx_memory());
+        db.add_row(vec![DecodedData::StringU8(format!("key_{}", row)), DecodedData::Integer(row)]).unwrap();
+    }
+    assert!(sizes.windows(2).all(|x| x[0] < x[1]));
+
+    // Longer strings in the same amount of rows take more memory too.
+    let mut db_long = DB::new("test_tables", None, &definition);
+    for row in 0..4 {
+        db_long.add_row(vec![DecodedData::StringU8(format!("key_{}_but_longer", row)), DecodedData::Integer(row)]).unwrap();
+    }
+    assert!(DecodedPackedFile::DB(db_long).approx_memory() > DecodedPackedFile::DB(db).approx_memory());
+
+    let mut text = Text::new();
+    text.set_contents("Short.");
+    let text_short = DecodedPackedFile::Text(text.clone()).approx_memory();
+    text.set_contents("A bit longer than the other one.");
+    assert!(DecodedPackedFile::Text(text).approx_memory() > text_short);
+
+    assert!(DecodedPackedFile::Image(Image::read(b"GIF89a_bigger_image").unwrap()).approx_memory() > DecodedPackedFile::Image(Image::read(b"GIF89a").unwrap()).approx_memory());
+    assert!(DecodedPackedFile::Unknown.approx_memory() > 0);
+}
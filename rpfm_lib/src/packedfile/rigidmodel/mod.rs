@@ -14,17 +14,70 @@ Module with all the code to interact with RigidModel PackedFiles.
 RigidModel PackedFiles are 3D models used by Total War games since Empire.
 This is basically a rewrite in Rust of the work done by Phazer on his tool.
 Because I want to avoid more C++ libs if posible.
+
+For now we only decode the header of the file, and the parts of the groups (meshes) of each lod we need to find their textures.
+The structure of the parts we know is:
+
+- Header (`140` bytes):
+    - Signature (`RMV2`).
+    - Version (`u32`).
+    - Amount of lods (`u32`).
+    - Skeleton id (`StringU8` 0-padded to `128` bytes).
+- Lod headers (`20` bytes for version `6`, `28` bytes for version `7`):
+    - Amount of groups (`u32`).
+    - Size of the vertices data (`u32`).
+    - Size of the indices data (`u32`).
+    - Offset of the first group of the lod, from the start of the file (`u32`).
+    - Zoom factor (`f32`).
+    - Two unknown `u32`, only in version `7`.
+- Each group of each lod:
+    - Material (`u32`).
+    - Size of the entire group (`u32`).
+    - Fixed part of the group header we don't decode yet (material info, bounding box, name, texture directory, transforms...).
+    - Amount of attachment points (`u32`), at byte `783` of the group.
+    - Amount of textures (`u32`).
+    - Attachment points (`84` bytes each).
+    - Textures:
+        - Type of texture (`u32`).
+        - Path of the texture (`StringU8` 0-padded to `256` bytes).
+    - Vertex and index data.
+
+As the paths of the textures have a fixed size, they can be changed without having to change any other part of the file.
 !*/
 
 use serde_derive::{Serialize, Deserialize};
 
+use rpfm_error::{ErrorKind, Result};
+
+use crate::common::{decoder::Decoder, encoder::Encoder};
+
+#[cfg(test)]
+mod rigidmodel_test;
+
 /// This represents the value that every RigidModel PackedFile has in their 0-4 bytes. A.k.a it's signature or preamble.
-#[allow(dead_code)]
 const PACKED_FILE_TYPE: &str = "RMV2";
 
 /// Extension used by RigidModel PackedFiles.
 pub const EXTENSION: &str = ".rigid_model_v2";
 
+/// Size of the header of a RigidModel, and of the skeleton id inside it.
+const HEADER_SIZE: usize = 140;
+const SKELETON_ID_SIZE: usize = 128;
+
+/// Size of the header of each lod, depending on the version of the RigidModel.
+const LOD_HEADER_SIZE_V6: usize = 20;
+const LOD_HEADER_SIZE_V7: usize = 28;
+
+/// Offsets of the size of the group, and of the amount of attachment points, from the start of each group.
+const GROUP_SIZE_OFFSET: usize = 4;
+const GROUP_ATTACHMENT_POINTS_COUNT_OFFSET: usize = 783;
+
+/// Size of each attachment point of a group.
+const ATTACHMENT_POINT_SIZE: usize = 84;
+
+/// Size of the path of each texture of a group. The path is preceded by the type of the texture (`u32`).
+const TEXTURE_PATH_SIZE: usize = 256;
+
 //---------------------------------------------------------------------------//
 //                              Enum & Structs
 //---------------------------------------------------------------------------//
@@ -32,13 +85,16 @@ pub const EXTENSION: &str = ".rigid_model_v2";
 /// This struct contains a RigidModel decoded in memory.
 #[derive(Clone, Debug,PartialEq, Serialize, Deserialize)]
 pub struct RigidModel {
-    pub header: Header,
+    pub header: RigidModelHeader,
     //pub packed_file_data: RigidModelData,
+
+    /// The data of the RigidModel, as we don't decode all of it yet.
+    data: Vec<u8>,
 }
 
-/// This struct represents the header of a RigidModel.
+/// This struct represents the header of a RigidModel, with the info we can get from it without decoding the geometry.
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
-pub struct Header {
+pub struct RigidModelHeader {
 
     /// The version of the RigidModel. The supported versions per game are:
     /// - `6`: Attila or older.
@@ -46,8 +102,14 @@ pub struct Header {
     /// - `8`: Three Kingdoms.
     version: u32,
 
+    /// The amount of lods (levels of detail) of this RigidModel.
+    lods_count: u32,
+
     /// The skeleton used by this RigidModel.
-    skeleton_id: Vec<u8>,
+    skeleton_id: String,
+
+    /// The paths of the textures used by the groups of this RigidModel, in the order they're in the file.
+    texture_paths: Vec<String>,
 }
 
 //---------------------------------------------------------------------------//
@@ -60,8 +122,147 @@ impl RigidModel {
     /// This function creates a new empty `Decal` RigidModel.
     pub fn new_decal() -> Self {
         Self {
-            header: Header::default(),
+            header: RigidModelHeader::default(),
+            data: vec![],
+        }
+    }
+
+    /// This function creates a `RigidModel` from a `&[u8]`.
+    ///
+    /// Only the header is decoded. The rest of the data is kept as it is, so it can be saved back without changes.
+    pub fn read(packed_file_data: &[u8]) -> Result<Self> {
+        if packed_file_data.is_empty() { return Err(ErrorKind::PackedFileDataIsEmpty.into()) }
+        Ok(Self {
+            header: Self::read_header(packed_file_data)?,
+            data: packed_file_data.to_vec(),
+        })
+    }
+
+    /// This function takes a `RigidModel` and encodes it to `Vec<u8>`.
+    pub fn save(&self) -> Result<Vec<u8>> {
+        Ok(self.data.to_vec())
+    }
+
+    /// This function returns the data of this RigidModel, as we don't decode all of it yet.
+    pub fn get_ref_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// This function returns the paths of the textures used by the groups of this RigidModel, in the order they're in the file.
+    pub fn get_texture_paths(&self) -> Vec<String> {
+        self.header.texture_paths.to_vec()
+    }
+
+    /// This function replaces all the texture paths of this RigidModel equal to `old` with `new`, returning how many of them were replaced.
+    ///
+    /// Texture paths have a fixed size in the file, so nothing else needs to be changed for the RigidModel to keep working.
+    /// If the new path doesn't fit in that size with its null terminator, this returns an error and nothing is changed.
+    pub fn replace_texture_path(&mut self, old: &str, new: &str) -> Result<usize> {
+        if new.len() >= TEXTURE_PATH_SIZE { return Err(ErrorKind::RigidModelTexturePathTooLong(new.to_owned()).into()) }
+
+        let offsets = Self::get_texture_path_offsets(&self.data, self.header.version, self.header.lods_count)?;
+        let mut encoded_path = vec![];
+        encoded_path.encode_string_u8_0padded(&(new.to_owned(), TEXTURE_PATH_SIZE))?;
+
+        let mut count = 0;
+        for (offset, path) in offsets.iter().zip(self.header.texture_paths.iter_mut()) {
+            if path == old {
+                self.data[*offset..*offset + TEXTURE_PATH_SIZE].copy_from_slice(&encoded_path);
+                *path = new.to_owned();
+                count += 1;
+            }
         }
+        Ok(count)
+    }
+
+    /// This function returns if the provided data corresponds to a RigidModel or not, based on its signature.
+    pub fn is_rigidmodel(data: &[u8]) -> bool {
+        data.starts_with(PACKED_FILE_TYPE.as_bytes())
+    }
+
+    /// This function reads the header of the provided RigidModel data, without decoding his geometry.
+    ///
+    /// Only versions `6` and `7` are supported. For others, or for data that's not a RigidModel, this returns an error.
+    pub fn read_header(packed_file_data: &[u8]) -> Result<RigidModelHeader> {
+        if !Self::is_rigidmodel(packed_file_data) { return Err(ErrorKind::RigidModelNotSupportedFile.into()) }
+
+        let version = packed_file_data.decode_integer_u32(4)?;
+        let lods_count = packed_file_data.decode_integer_u32(8)?;
+        let (skeleton_id, _) = packed_file_data.decode_string_u8_0padded(12, SKELETON_ID_SIZE)?;
+        let texture_paths = Self::get_texture_path_offsets(packed_file_data, version, lods_count)?
+            .iter()
+            .map(|offset| packed_file_data.decode_string_u8_0padded(*offset, TEXTURE_PATH_SIZE).map(|(path, _)| path))
+            .collect::<Result<Vec<String>>>()?;
+
+        Ok(RigidModelHeader {
+            version,
+            lods_count,
+            skeleton_id,
+            texture_paths,
+        })
+    }
+
+    /// This function returns the offsets of the paths of all the textures of the provided RigidModel data, going through the groups of each lod.
+    fn get_texture_path_offsets(packed_file_data: &[u8], version: u32, lods_count: u32) -> Result<Vec<usize>> {
+        let lod_header_size = match version {
+            6 => LOD_HEADER_SIZE_V6,
+            7 => LOD_HEADER_SIZE_V7,
+            _ => return Err(ErrorKind::RigidModelNotSupportedType.into()),
+        };
+
+        let mut offsets = vec![];
+        for lod in 0..lods_count as usize {
+            let lod_header_offset = HEADER_SIZE + lod * lod_header_size;
+            let groups_count = packed_file_data.decode_integer_u32(lod_header_offset)?;
+            let mut group_offset = packed_file_data.decode_integer_u32(lod_header_offset + 12)? as usize;
+
+            for _ in 0..groups_count {
+                let group_size = packed_file_data.decode_integer_u32(group_offset + GROUP_SIZE_OFFSET)? as usize;
+
+                // Groups of size 0 would make us loop over the same group forever.
+                if group_size == 0 { return Err(ErrorKind::RigidModelDecode("Group with size 0 found.".to_owned()).into()) }
+
+                let attachment_points_count = packed_file_data.decode_integer_u32(group_offset + GROUP_ATTACHMENT_POINTS_COUNT_OFFSET)? as usize;
+                let textures_count = packed_file_data.decode_integer_u32(group_offset + GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 4)? as usize;
+
+                // The textures go after the attachment points, and each path goes after the type of his texture.
+                let textures_offset = group_offset + GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 8 + attachment_points_count * ATTACHMENT_POINT_SIZE;
+                for texture in 0..textures_count {
+                    let offset = textures_offset + texture * (4 + TEXTURE_PATH_SIZE) + 4;
+                    if offset + TEXTURE_PATH_SIZE > group_offset + group_size || offset + TEXTURE_PATH_SIZE > packed_file_data.len() {
+                        return Err(ErrorKind::RigidModelTextureDirectoryNotFound.into())
+                    }
+                    offsets.push(offset);
+                }
+
+                group_offset += group_size;
+            }
+        }
+        Ok(offsets)
+    }
+}
+
+/// Implementation of RigidModelHeader.
+impl RigidModelHeader {
+
+    /// This function returns the version of the RigidModel.
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// This function returns the amount of lods of the RigidModel.
+    pub fn get_lods_count(&self) -> u32 {
+        self.lods_count
+    }
+
+    /// This function returns a reference to the skeleton id of the RigidModel.
+    pub fn get_ref_skeleton_id(&self) -> &str {
+        &self.skeleton_id
+    }
+
+    /// This function returns a reference to the paths of the textures used by the RigidModel, in the order they're in the file.
+    pub fn get_ref_texture_paths(&self) -> &[String] {
+        &self.texture_paths
     }
 }
 /*
@@ -0,0 +1,153 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `RigidModel` module, just to make sure we don't break it... again...
+!*/
+
+use rpfm_error::ErrorKind;
+
+use super::*;
+
+/// This function returns the data of a group with the provided textures and attachment points, for testing purpouses.
+fn get_test_group(textures: &[&str], attachment_points_count: usize) -> Vec<u8> {
+    let size = GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 8 + attachment_points_count * ATTACHMENT_POINT_SIZE + textures.len() * (4 + TEXTURE_PATH_SIZE) + 16;
+    let mut data = vec![0; size];
+    data[GROUP_SIZE_OFFSET..GROUP_SIZE_OFFSET + 4].copy_from_slice(&(size as u32).to_le_bytes());
+    data[GROUP_ATTACHMENT_POINTS_COUNT_OFFSET..GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 4].copy_from_slice(&(attachment_points_count as u32).to_le_bytes());
+    data[GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 4..GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 8].copy_from_slice(&(textures.len() as u32).to_le_bytes());
+
+    let mut offset = GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 8 + attachment_points_count * ATTACHMENT_POINT_SIZE;
+    for (index, texture) in textures.iter().enumerate() {
+        data[offset..offset + 4].copy_from_slice(&(index as u32).to_le_bytes());
+        data[offset + 4..offset + 4 + texture.len()].copy_from_slice(texture.as_bytes());
+        offset += 4 + TEXTURE_PATH_SIZE;
+    }
+
+    // The vertex and index data, which we don't care about.
+    data[size - 16..].copy_from_slice(&[0xAB; 16]);
+    data
+}
+
+/// This function returns the data of a RigidModel with the provided version and lods, each one with his groups, for testing purpouses.
+fn get_test_data(version: u32, lods: &[Vec<Vec<u8>>]) -> Vec<u8> {
+    let lod_header_size = if version == 6 { LOD_HEADER_SIZE_V6 } else { LOD_HEADER_SIZE_V7 };
+    let mut data = PACKED_FILE_TYPE.as_bytes().to_vec();
+    data.extend_from_slice(&version.to_le_bytes());
+    data.extend_from_slice(&(lods.len() as u32).to_le_bytes());
+    let mut skeleton_id = b"humanoid01".to_vec();
+    skeleton_id.resize(SKELETON_ID_SIZE, 0);
+    data.extend_from_slice(&skeleton_id);
+
+    let mut group_offset = HEADER_SIZE + lods.len() * lod_header_size;
+    for (index, groups) in lods.iter().enumerate() {
+        data.extend_from_slice(&(groups.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&(group_offset as u32).to_le_bytes());
+        data.extend_from_slice(&100.0f32.to_le_bytes());
+        if version == 7 {
+            data.extend_from_slice(&(index as u32).to_le_bytes());
+            data.extend_from_slice(&[0; 4]);
+        }
+        group_offset += groups.iter().map(|x| x.len()).sum::<usize>();
+    }
+
+    lods.iter().flatten().for_each(|x| data.extend_from_slice(x));
+    data
+}
+
+/// Test to make sure we can read the header of a RigidModel, including the textures of all his lods.
+#[test]
+fn test_read_header() {
+    let lods = vec![
+        vec![get_test_group(&["variantmeshes/wh_variantmodels/hu1/emp/tex/body_diffuse.dds", "variantmeshes/wh_variantmodels/hu1/emp/tex/body_normal.dds"], 2), get_test_group(&[], 0)],
+        vec![get_test_group(&["variantmeshes/wh_variantmodels/hu1/emp/tex/body_lod1.dds"], 0)],
+    ];
+
+    let header = RigidModel::read_header(&get_test_data(7, &lods)).unwrap();
+    assert_eq!(header.get_version(), 7);
+    assert_eq!(header.get_lods_count(), 2);
+    assert_eq!(header.get_ref_skeleton_id(), "humanoid01");
+    assert_eq!(header.get_ref_texture_paths(), &[
+        "variantmeshes/wh_variantmodels/hu1/emp/tex/body_diffuse.dds".to_owned(),
+        "variantmeshes/wh_variantmodels/hu1/emp/tex/body_normal.dds".to_owned(),
+        "variantmeshes/wh_variantmodels/hu1/emp/tex/body_lod1.dds".to_owned(),
+    ]);
+
+    // Attila ones have smaller lod headers.
+    let header = RigidModel::read_header(&get_test_data(6, &lods[1..])).unwrap();
+    assert_eq!(header.get_version(), 6);
+    assert_eq!(header.get_lods_count(), 1);
+    assert_eq!(header.get_ref_texture_paths(), &["variantmeshes/wh_variantmodels/hu1/emp/tex/body_lod1.dds".to_owned()]);
+}
+
+/// Test to make sure broken or unsupported RigidModels are rejected.
+#[test]
+fn test_read_header_errors() {
+    let lods = vec![vec![get_test_group(&["tex/diffuse.dds"], 0)]];
+    let data = get_test_data(7, &lods);
+    assert_eq!(RigidModel::read_header(b"RMV1").unwrap_err().kind(), &ErrorKind::RigidModelNotSupportedFile);
+    assert_eq!(RigidModel::read_header(&get_test_data(8, &lods)).unwrap_err().kind(), &ErrorKind::RigidModelNotSupportedType);
+    assert!(RigidModel::read_header(&data[..data.len() - 300]).is_err());
+
+    // A group saying it has more textures than fit in it.
+    let mut broken_data = data.to_vec();
+    let textures_count_offset = HEADER_SIZE + LOD_HEADER_SIZE_V7 + GROUP_ATTACHMENT_POINTS_COUNT_OFFSET + 4;
+    broken_data[textures_count_offset] = 2;
+    assert_eq!(RigidModel::read_header(&broken_data).unwrap_err().kind(), &ErrorKind::RigidModelTextureDirectoryNotFound);
+
+    // A group of size 0 must not make us loop forever.
+    let mut broken_data = data;
+    broken_data[HEADER_SIZE + LOD_HEADER_SIZE_V7 + GROUP_SIZE_OFFSET] = 0;
+    broken_data[HEADER_SIZE + LOD_HEADER_SIZE_V7 + GROUP_SIZE_OFFSET + 1] = 0;
+    assert_eq!(RigidModel::read_header(&broken_data).unwrap_err().kind(), &ErrorKind::RigidModelDecode("Group with size 0 found.".to_owned()));
+}
+
+/// Test to make sure replacing texture paths only changes the paths, and survives a save and read cycle.
+#[test]
+fn test_replace_texture_path() {
+    let lods = vec![
+        vec![get_test_group(&["tex/body_diffuse.dds", "tex/body_normal.dds"], 1)],
+        vec![get_test_group(&["tex/body_diffuse.dds"], 0)],
+    ];
+    let data = get_test_data(7, &lods);
+    let mut rigid_model = RigidModel::read(&data).unwrap();
+    assert_eq!(rigid_model.save().unwrap(), data);
+
+    let new_path = "variantmeshes/wh_variantmodels/hu1/emp/tex/new_body_diffuse.dds";
+    assert_eq!(rigid_model.replace_texture_path("tex/body_diffuse.dds", new_path).unwrap(), 2);
+    assert_eq!(rigid_model.replace_texture_path("tex/missing.dds", "tex/other.dds").unwrap(), 0);
+    assert_eq!(rigid_model.get_texture_paths(), vec![new_path.to_owned(), "tex/body_normal.dds".to_owned(), new_path.to_owned()]);
+
+    // The size of the file doesn't change, and everything else is kept as it was.
+    let new_data = rigid_model.save().unwrap();
+    assert_eq!(new_data.len(), data.len());
+    assert_eq!(new_data, get_test_data(7, &[
+        vec![get_test_group(&[new_path, "tex/body_normal.dds"], 1)],
+        vec![get_test_group(&[new_path], 0)],
+    ]));
+
+    let rigid_model_new = RigidModel::read(&new_data).unwrap();
+    assert_eq!(rigid_model_new.get_texture_paths(), rigid_model.get_texture_paths());
+    assert_eq!(rigid_model_new, rigid_model);
+
+    // Shorter paths have to clean the rest of the old one.
+    assert_eq!(rigid_model.replace_texture_path(new_path, "a.dds").unwrap(), 2);
+    assert_eq!(RigidModel::read(&rigid_model.save().unwrap()).unwrap().get_texture_paths(), vec!["a.dds".to_owned(), "tex/body_normal.dds".to_owned(), "a.dds".to_owned()]);
+
+    // Paths need space for their null terminator, so paths of the full size don't fit.
+    let too_long = "a".repeat(TEXTURE_PATH_SIZE);
+    assert_eq!(rigid_model.replace_texture_path("a.dds", &too_long).unwrap_err().kind(), &ErrorKind::RigidModelTexturePathTooLong(too_long.to_owned()));
+    assert_eq!(rigid_model.get_texture_paths()[0], "a.dds");
+
+    let longest = "a".repeat(TEXTURE_PATH_SIZE - 1);
+    assert_eq!(rigid_model.replace_texture_path("a.dds", &longest).unwrap(), 2);
+    assert_eq!(RigidModel::read(&rigid_model.save().unwrap()).unwrap().get_texture_paths()[0], longest);
+}
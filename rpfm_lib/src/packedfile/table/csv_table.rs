@@ -0,0 +1,168 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module with all the code to interact with CSV/TSV Text PackedFiles as tables.
+
+By default, CSV and TSV PackedFiles are decoded as `Text`. This module allows to decode them as a table instead,
+so they can be edited in a grid. The first line of the file is used as the name of the columns, and every column is a `StringU8`.
+!*/
+
+use csv::{QuoteStyle, ReaderBuilder, WriterBuilder};
+
+use rpfm_error::{ErrorKind, Result};
+
+use super::DecodedData;
+use super::Table;
+
+use crate::packedfile::text::TextType;
+use crate::schema::*;
+
+//---------------------------------------------------------------------------//
+//                              Enum & Structs
+//---------------------------------------------------------------------------//
+
+/// This stores the data of a decoded CSV/TSV PackedFile in memory.
+#[derive(PartialEq, Clone, Debug)]
+pub struct CsvTable {
+
+    /// The type of the file, so we know if we have to use commas (`Csv`) or tabs (`Tsv`) as delimiter.
+    text_type: TextType,
+
+    /// If the lines of the file should end in `\r\n` instead of `\n`.
+    is_crlf: bool,
+
+    /// If the last line of the file should end with a line ending.
+    ends_with_newline: bool,
+
+    /// The table's data. His definition contains one `StringU8` field for each column in the header of the file.
+    table: Table,
+}
+
+//---------------------------------------------------------------------------//
+//                           Implementation of CsvTable
+//---------------------------------------------------------------------------//
+
+/// Implementation of `CsvTable`.
+impl CsvTable {
+
+    /// This function creates a new empty `CsvTable` of the provided type, with the provided columns.
+    ///
+    /// This fails if the provided type is neither `TextType::Csv` nor `TextType::Tsv`.
+    pub fn new(text_type: TextType, columns: &[String]) -> Result<Self> {
+        Self::get_delimiter(text_type)?;
+
+        let mut definition = Definition::new(0);
+        definition.fields = columns.iter().map(|x| Field { name: x.to_owned(), ..Default::default() }).collect();
+        Ok(Self {
+            text_type,
+            is_crlf: false,
+            ends_with_newline: true,
+            table: Table::new(&definition),
+        })
+    }
+
+    /// This function returns the type of the file this table is for.
+    pub fn get_text_type(&self) -> TextType {
+        self.text_type
+    }
+
+    /// This function returns a reference to the definition of this table, with one field for each column.
+    pub fn get_ref_definition(&self) -> &Definition {
+        self.table.get_ref_definition()
+    }
+
+    /// This function returns a reference to the entries of this table.
+    pub fn get_ref_table_data(&self) -> &[Vec<DecodedData>] {
+        self.table.get_ref_table_data()
+    }
+
+    /// This function returns the amount of entries in this table.
+    pub fn get_entry_count(&self) -> usize {
+        self.table.get_entry_count()
+    }
+
+    /// This function replaces the data of this table with the one provided.
+    ///
+    /// This can (and will) fail if the data is not of the format defined by the definition of the table.
+    pub fn set_table_data(&mut self, data: &[Vec<DecodedData>]) -> Result<()> {
+        self.table.set_table_data(data)
+    }
+
+    /// This function creates a `CsvTable` from a `&[u8]`, using the delimiter of the provided type.
+    ///
+    /// All the rows must have the same amount of columns as the header. Otherwise, this returns an error.
+    pub fn read(packed_file_data: &[u8], text_type: TextType) -> Result<Self> {
+        let delimiter = Self::get_delimiter(text_type)?;
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .quoting(text_type == TextType::Csv)
+            .has_headers(false)
+            .from_reader(packed_file_data);
+
+        let mut records = reader.records();
+        let columns = match records.next() {
+            Some(header) => header?.iter().map(|x| x.to_owned()).collect::<Vec<String>>(),
+            None => vec![],
+        };
+
+        let mut csv_table = Self::new(text_type, &columns)?;
+        csv_table.is_crlf = packed_file_data.windows(2).any(|x| x == b"\r\n");
+        csv_table.ends_with_newline = packed_file_data.is_empty() || packed_file_data.ends_with(b"\n");
+
+        let mut entries = vec![];
+        for record in records {
+            entries.push(record?.iter().map(|x| DecodedData::StringU8(x.to_owned())).collect::<Vec<DecodedData>>());
+        }
+
+        csv_table.table.set_table_data(&entries)?;
+        Ok(csv_table)
+    }
+
+    /// This function takes a `CsvTable` and encodes it to `Vec<u8>`, keeping the line endings of the original file.
+    pub fn save(&self) -> Result<Vec<u8>> {
+        let delimiter = Self::get_delimiter(self.text_type)?;
+        let mut writer = WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote_style(if self.text_type == TextType::Csv { QuoteStyle::Necessary } else { QuoteStyle::Never })
+            .terminator(if self.is_crlf { csv::Terminator::CRLF } else { csv::Terminator::Any(b'\n') })
+            .has_headers(false)
+            .from_writer(vec![]);
+
+        let columns = &self.get_ref_definition().fields;
+        if !columns.is_empty() {
+            writer.write_record(columns.iter().map(|x| &x.name))?;
+        }
+
+        for entry in self.get_ref_table_data() {
+            writer.write_record(entry.iter().map(|x| match x {
+                DecodedData::StringU8(data) => data.to_owned(),
+                _ => String::new(),
+            }))?;
+        }
+
+        let mut data = writer.into_inner().map_err(|_| ErrorKind::TSVErrorGeneric)?;
+        if !self.ends_with_newline {
+            let line_ending_len = if self.is_crlf { 2 } else { 1 };
+            data.truncate(data.len().saturating_sub(line_ending_len));
+        }
+
+        Ok(data)
+    }
+
+    /// This function returns the delimiter used by the provided type of file.
+    fn get_delimiter(text_type: TextType) -> Result<u8> {
+        match text_type {
+            TextType::Csv => Ok(b','),
+            TextType::Tsv => Ok(b'\t'),
+            _ => Err(ErrorKind::PackedFileTypeUnknown.into()),
+        }
+    }
+}
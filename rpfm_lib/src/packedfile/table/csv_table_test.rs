@@ -0,0 +1,71 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `CsvTable` module, just to make sure we don't break it... again...
+!*/
+
+use crate::packedfile::DecodedPackedFile;
+use crate::packedfile::text::TextType;
+use crate::packfile::packedfile::RawPackedFile;
+use super::DecodedData;
+use super::csv_table::CsvTable;
+
+/// Test to make sure TSV files are decoded into rows, and encoded back to the same text.
+#[test]
+fn test_read_save_tsv() {
+    let data = b"key\tvalue\nfirst\t1\nsecond\t\"2\"\n";
+    let table = CsvTable::read(data, TextType::Tsv).unwrap();
+    assert_eq!(table.get_ref_definition().fields.iter().map(|x| x.name.to_owned()).collect::<Vec<String>>(), vec!["key", "value"]);
+    assert_eq!(table.get_ref_table_data(), &[
+        vec![DecodedData::StringU8("first".to_owned()), DecodedData::StringU8("1".to_owned())],
+        vec![DecodedData::StringU8("second".to_owned()), DecodedData::StringU8("\"2\"".to_owned())],
+    ]);
+    assert_eq!(table.save().unwrap(), data.to_vec());
+
+    // Line endings and the lack of a final one must be kept.
+    let data = b"key\tvalue\r\nfirst\t1";
+    assert_eq!(CsvTable::read(data, TextType::Tsv).unwrap().save().unwrap(), data.to_vec());
+
+    // Rows with a different amount of columns than the header are invalid.
+    assert!(CsvTable::read(b"key\tvalue\nfirst\n", TextType::Tsv).is_err());
+}
+
+/// Test to make sure CSV files with quoted fields are decoded into rows, and encoded back to the same text.
+#[test]
+fn test_read_save_csv() {
+    let data = b"key,value\nfirst,\"1,5\"\n";
+    let table = CsvTable::read(data, TextType::Csv).unwrap();
+    assert_eq!(table.get_ref_table_data()[0][1], DecodedData::StringU8("1,5".to_owned()));
+    assert_eq!(table.save().unwrap(), data.to_vec());
+
+    assert!(CsvTable::read(data, TextType::Xml).is_err());
+}
+
+/// Test to make sure only CSV/TSV PackedFiles can be decoded as tables, and only when explicitly asked.
+#[test]
+fn test_decode_text_as_table() {
+    let data = b"key\tvalue\nfirst\t1\n".to_vec();
+    let raw_packed_file = RawPackedFile::read_from_vec(vec!["text".to_owned(), "test.tsv".to_owned()], "test.pack".to_owned(), 0, false, data.to_vec());
+    match DecodedPackedFile::decode(&raw_packed_file).unwrap() {
+        DecodedPackedFile::Text(_) => {},
+        decoded => panic!("Unexpected decoded PackedFile: {:?}", decoded),
+    }
+
+    let decoded = DecodedPackedFile::decode_text_as_table(&raw_packed_file).unwrap();
+    match decoded {
+        DecodedPackedFile::CsvTable(ref table) => assert_eq!(table.get_entry_count(), 1),
+        ref decoded => panic!("Unexpected decoded PackedFile: {:?}", decoded),
+    }
+    assert_eq!(decoded.encode().unwrap().unwrap(), data);
+
+    let raw_packed_file = RawPackedFile::read_from_vec(vec!["text".to_owned(), "test.xml".to_owned()], "test.pack".to_owned(), 0, false, data);
+    assert!(DecodedPackedFile::decode_text_as_table(&raw_packed_file).is_err());
+}
@@ -13,10 +13,14 @@ Module with all the code to interact with DB Tables.
 
 DB Tables are the files which controls a lot of the parameters used ingame, like units data,
 effects data, projectile parameters.... It's what modders use the most.
+
+Their data is just the cells of each row, one after another, with no padding or alignment between them.
+Boolean cells are not bit-packed: each one takes a full byte (`0` or `1`), even when there are many of them in a row.
 !*/
 
 use bincode::deserialize;
 use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
 use uuid::Uuid;
 
 use std::collections::BTreeMap;
@@ -24,7 +28,7 @@ use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::PathBuf;
 
-use rpfm_error::{ErrorKind, Result};
+use rpfm_error::{Error, ErrorKind, Result};
 
 use crate::assembly_kit::table_data::RawTable;
 use crate::common::{decoder::Decoder, encoder::Encoder};
@@ -37,7 +41,7 @@ use crate::packfile::packedfile::PackedFile;
 use crate::schema::*;
 use crate::SETTINGS;
 use super::DecodedData;
-use super::Table;
+use super::{MergeConflict, Table};
 
 /// If this sequence is found, the DB Table has a GUID after it.
 const GUID_MARKER: &[u8] = &[253, 254, 252, 255];
@@ -67,6 +71,41 @@ pub struct DB {
     table: Table,
 }
 
+/// This struct is the JSON representation of a `DB`, used by `DB::to_json` and `DB::from_json`.
+#[derive(Serialize, Deserialize)]
+struct DBJson {
+
+    /// The name of the table.
+    table: String,
+
+    /// The version of the table.
+    version: i32,
+
+    /// UUID of the table.
+    uuid: String,
+
+    /// The mysterious byte of the table.
+    mysterious_byte: bool,
+
+    /// The columns of the table, in the order of his definition.
+    columns: Vec<DBJsonColumn>,
+
+    /// The rows of the table, as returned by `Table::rows_to_json`.
+    rows: Value,
+}
+
+/// This struct is the JSON representation of a column of a `DB`.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct DBJsonColumn {
+
+    /// The name of the column.
+    name: String,
+
+    /// The type of the column. For sequences, this includes the definition of the sequence.
+    #[serde(rename = "type")]
+    field_type: FieldType,
+}
+
 //---------------------------------------------------------------------------//
 //                           Implementation of DB
 //---------------------------------------------------------------------------//
@@ -88,6 +127,31 @@ impl DB {
         }
     }
 
+    /// This function creates a new empty `DB` from his name and version, getting his definition from the provided `Schema`.
+    ///
+    /// This fails if the schema doesn't have a definition for that version of the table.
+    pub fn new_from_schema(
+        name: &str,
+        version: i32,
+        schema: &Schema
+    ) -> Result<Self> {
+        let definition = Self::definition_from_schema(name, version, schema)?;
+        Ok(Self::new(name, None, definition))
+    }
+
+    /// This function returns the definition of the provided version of a table from the provided `Schema`.
+    ///
+    /// If there is no definition, the error tells if the schema doesn't know about the table at all, or just about that version of it.
+    fn definition_from_schema<'a>(name: &str, version: i32, schema: &'a Schema) -> Result<&'a Definition> {
+        match schema.definition_for(name, version) {
+            Some(definition) => Ok(definition),
+            None => {
+                schema.get_ref_versioned_file_db(name)?;
+                Err(ErrorKind::SchemaDefinitionNotFound.into())
+            }
+        }
+    }
+
     /// This function returns a copy of the name of this DB Table.
     pub fn get_table_name(&self) -> String {
         self.name.to_owned()
@@ -128,6 +192,16 @@ impl DB {
         self.table.get_entry_count()
     }
 
+    /// This function returns the amount of entries in this DB Table. Akin to `get_entry_count()`.
+    pub fn len(&self) -> usize {
+        self.table.get_entry_count()
+    }
+
+    /// This function returns if this DB Table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.table.get_entry_count() == 0
+    }
+
     /// This function replaces the definition of this table with the one provided.
     ///
     /// This updates the table's data to follow the format marked by the new definition, so you can use it to *update* the version of your table.
@@ -142,6 +216,126 @@ impl DB {
         self.table.set_table_data(data)
     }
 
+    /// This function checks that every row of this DB Table has the amount of cells his definition expects, and that each cell is of the expected type.
+    ///
+    /// If the table is not valid, it returns an error with the first (1-based) row and column with problems.
+    pub fn validate(&self) -> Result<()> {
+        self.table.validate()
+    }
+
+    /// This function returns a new row for this DB Table, with each cell set to the default value of his field.
+    ///
+    /// If the field has a default value in the schema, that value is used. Otherwise, we use the default for his type.
+    pub fn default_row(&self) -> Vec<DecodedData> {
+        self.table.get_new_row()
+    }
+
+    /// This function returns a copy of the cell at the provided row and column of this DB Table, along with its position, so an edit of it can be undone with `restore_cell`.
+    ///
+    /// If there is no cell at that position, it returns an error.
+    pub fn snapshot_cell(&self, row: usize, column: usize) -> Result<(usize, usize, DecodedData)> {
+        self.table.snapshot_cell(row, column)
+    }
+
+    /// This function puts back the cell of a snapshot taken with `snapshot_cell` in its position of this DB Table.
+    ///
+    /// This can (and will) fail if there is no cell at that position anymore, or if the cell is not of the type of its column.
+    pub fn restore_cell(&mut self, snapshot: (usize, usize, DecodedData)) -> Result<()> {
+        self.table.restore_cell(snapshot)
+    }
+
+    /// This function adds a new row at the end of this DB Table.
+    ///
+    /// This can (and will) fail if the row is not of the format defined by the definition of the table.
+    pub fn add_row(&mut self, row: Vec<DecodedData>) -> Result<()> {
+        self.table.add_row(row)
+    }
+
+    /// This function inserts a new row at the provided position of this DB Table.
+    ///
+    /// This can (and will) fail if the row is not of the format defined by the definition of the table, or if the position is after the end of the table.
+    pub fn insert_row(&mut self, index: usize, row: Vec<DecodedData>) -> Result<()> {
+        self.table.insert_row(index, row)
+    }
+
+    /// This function removes the row at the provided position of this DB Table, returning it.
+    ///
+    /// If there is no row at that position, it returns an error.
+    pub fn remove_row(&mut self, index: usize) -> Result<Vec<DecodedData>> {
+        self.table.remove_row(index)
+    }
+
+    /// This function adds a new column at the end of this DB Table, with the default value of the field in each row.
+    ///
+    /// If there is already a column with the same name, it returns an error.
+    pub fn add_column(&mut self, field: &Field) -> Result<()> {
+        self.table.add_column(field)
+    }
+
+    /// This function removes the provided column from this DB Table, with his cell in each row.
+    ///
+    /// If the column doesn't exist, it returns an error.
+    pub fn remove_column(&mut self, column: &str) -> Result<()> {
+        self.table.remove_column(column)
+    }
+
+    /// This function changes the name of the provided column of this DB Table.
+    ///
+    /// If the column doesn't exist, or there is already another column with the new name, it returns an error.
+    pub fn rename_column(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        self.table.rename_column(old_name, new_name)
+    }
+
+    /// This function serializes the rows at the provided positions of this DB Table to a TSV string, so they can be copied to the clipboard.
+    ///
+    /// If any of the positions is not in the table, it returns an error.
+    pub fn serialize_rows(&self, indices: &[usize]) -> Result<String> {
+        self.table.serialize_rows(indices)
+    }
+
+    /// This function parses rows serialized with `serialize_rows`, checking they can be pasted into this DB Table.
+    ///
+    /// This fails if the columns of the rows are not of the same types as the ones of this table, or if any of the cells cannot be parsed.
+    pub fn deserialize_rows(&self, data: &str) -> Result<Vec<Vec<DecodedData>>> {
+        self.table.deserialize_rows(data)
+    }
+
+    /// This function sorts the rows of this DB Table by the values of the provided column.
+    ///
+    /// The sort is stable, and numeric columns are sorted by value, not lexically. If the column doesn't exist, it returns an error.
+    pub fn sort_by_column(&mut self, column: &str, ascending: bool) -> Result<()> {
+        self.table.sort_by_column(column, ascending)
+    }
+
+    /// This function returns the distinct values of the provided column of this DB Table, as strings, in the order they first appear.
+    ///
+    /// Useful to get the values can be chosen for a column. If the column doesn't exist, it returns an error.
+    pub fn column_unique_values(&self, column: &str) -> Result<Vec<String>> {
+        self.table.column_unique_values(column)
+    }
+
+    /// This function returns the indexes of the rows of this DB Table with broken references in the provided column.
+    ///
+    /// A reference is broken if his value is not in the referenced column of the referenced DB Table. Empty values are not references,
+    /// so they're never broken. If any of the columns doesn't exist, it returns an error.
+    pub fn check_references(&self, column: &str, referenced: &Self, ref_column: &str) -> Result<Vec<usize>> {
+        self.table.check_references(column, &referenced.table, ref_column)
+    }
+
+    /// This function merges the changes both, `mine` and `theirs`, made over the `base` DB Table, returning the merged table and the cells where they conflict.
+    ///
+    /// The merged table keeps the name, UUID and mysterious byte of `mine`. Check `Table::merge_three_way` for how the rows are merged.
+    /// All three tables must have the same definition, or this returns an error.
+    pub fn merge_three_way(base: &Self, mine: &Self, theirs: &Self) -> Result<(Self, Vec<MergeConflict>)> {
+        let (table, conflicts) = Table::merge_three_way(&base.table, &mine.table, &theirs.table)?;
+        Ok((Self {
+            name: mine.name.to_owned(),
+            mysterious_byte: mine.mysterious_byte,
+            uuid: mine.uuid.to_owned(),
+            table,
+        }, conflicts))
+    }
+
     /// This function creates a `DB` from a `Vec<u8>`.
     pub fn read(
         packed_file_data: &[u8],
@@ -149,6 +343,7 @@ impl DB {
         schema: &Schema,
         return_incomplete: bool
     ) -> Result<Self> {
+        if packed_file_data.is_empty() { return Err(ErrorKind::PackedFileDataIsEmpty.into()) }
 
         // Get the header of the `DB`.
         let (version, mysterious_byte, uuid, entry_count, mut index) = Self::read_header(&packed_file_data)?;
@@ -174,16 +369,18 @@ impl DB {
         { return Err(ErrorKind::DBTableContainsListField.into()) }
 
         // Try to get the table_definition for this table, if exists.
-        let versioned_file = schema.get_ref_versioned_file_db(&name);
-        if versioned_file.is_err() && entry_count == 0 { return Err(ErrorKind::TableEmptyWithNoDefinition.into()) }
-        let definition = versioned_file?.get_version(version);
-        if definition.is_err() && entry_count == 0 { return Err(ErrorKind::TableEmptyWithNoDefinition.into()) }
+        let definition = match Self::definition_from_schema(name, version, schema) {
+            Ok(definition) => definition,
+            Err(_) if entry_count == 0 => return Err(ErrorKind::TableEmptyWithNoDefinition.into()),
+            Err(error) => return Err(error),
+        };
 
         // Then try to decode all the entries.
-        let mut table = Table::new(definition?);
+        let mut table = Table::new(definition);
         table.decode(&packed_file_data, entry_count, &mut index, return_incomplete)?;
 
         // If we are not in the last byte, it means we didn't parse the entire file, which means this file is corrupt.
+        if index < packed_file_data.len() { return Err(ErrorKind::PackedFileHasExtraData(index, packed_file_data.len()).into()) }
         if index != packed_file_data.len() { return Err(ErrorKind::PackedFileSizeIsNotWhatWeExpect(packed_file_data.len(), index).into()) }
 
         // If we've reached this, we've succesfully decoded the table.
@@ -195,8 +392,79 @@ impl DB {
         })
     }
 
+    /// This function creates a new `DB` from a `Vec<u8>`, keeping all the rows it could decode before finding an error.
+    ///
+    /// If the header of the table is broken or we don't have a definition for it, we have nothing to show, so this returns an error.
+    /// Otherwise, it returns the `DB` with all the rows decoded before the first broken one, and the error that stopped the decoding,
+    /// if any. Useful to see what's wrong with a broken table.
+    pub fn read_lossy(
+        packed_file_data: &[u8],
+        name: &str,
+        schema: &Schema,
+    ) -> Result<(Self, Option<Error>)> {
+        let (version, mysterious_byte, uuid, entry_count, mut index) = Self::read_header(packed_file_data)?;
+        let definition = schema.definition_for(name, version).ok_or_else(|| Error::from(ErrorKind::SchemaDefinitionNotFound))?;
+
+        let mut table = Table::new(definition);
+        let error = match table.decode(packed_file_data, entry_count, &mut index, false) {
+            Ok(_) if index < packed_file_data.len() => Some(ErrorKind::PackedFileHasExtraData(index, packed_file_data.len()).into()),
+            Ok(_) => None,
+            Err(error) => Some(ErrorKind::TableRowDecodeError(table.get_entry_count() as u32 + 1, error.to_string()).into()),
+        };
+
+        let db = Self {
+            name: name.to_owned(),
+            mysterious_byte,
+            uuid,
+            table,
+        };
+        Ok((db, error))
+    }
+
+    /// This function creates a new `DB` from a `Vec<u8>`, trying every known version of the table instead of the one in his header.
+    ///
+    /// The first definition that decodes the entire data, without leaving bytes behind, wins. It returns the `DB` and the version of
+    /// the definition used to decode it. Keep in mind that, if that version is not the one in the header, the `DB` will be saved with the new one.
+    pub fn read_autodetect(
+        packed_file_data: &[u8],
+        name: &str,
+        schema: &Schema,
+    ) -> Result<(Self, i32)> {
+        let (_, mysterious_byte, uuid, entry_count, index) = Self::read_header(packed_file_data)?;
+        let versioned_file = schema.versioned_file_db_for(name).ok_or_else(|| Error::from(ErrorKind::SchemaVersionedFileNotFound))?;
+
+        let mut versions_tried = vec![];
+        for definition in versioned_file.get_version_list() {
+            versions_tried.push(definition.version);
+
+            let mut index = index;
+            let mut table = Table::new(definition);
+            if table.decode(packed_file_data, entry_count, &mut index, false).is_ok() && index == packed_file_data.len() {
+                let db = Self {
+                    name: name.to_owned(),
+                    mysterious_byte,
+                    uuid,
+                    table,
+                };
+                return Ok((db, definition.version))
+            }
+        }
+
+        Err(ErrorKind::DBTableVersionNotDetected(name.to_owned(), versions_tried).into())
+    }
+
     /// This function takes a `DB` and encodes it to `Vec<u8>`.
+    ///
+    /// The encoding is deterministic: rows and fields are encoded in the order they're stored, so the same table always encodes to the same bytes.
+    /// The only exception is the UUID, which is regenerated on each save unless the `disable_uuid_regeneration_on_db_tables` setting is enabled.
     pub fn save(&self) -> Result<Vec<u8>> {
+        self.save_keeping_uuid(SETTINGS.read().unwrap().settings_bool["disable_uuid_regeneration_on_db_tables"])
+    }
+
+    /// This function takes a `DB` and encodes it to `Vec<u8>`, keeping its UUID if `keep_uuid` is true and it has one.
+    ///
+    /// Otherwise, a new UUID is generated, like in `save`.
+    pub(crate) fn save_keeping_uuid(&self, keep_uuid: bool) -> Result<Vec<u8>> {
         let mut packed_file: Vec<u8> = vec![];
 
         // Napoleon and Empire do not have GUID, and adding it to their tables crash both games.
@@ -204,7 +472,7 @@ impl DB {
         let game_selected = GAME_SELECTED.read().unwrap().to_owned();
         if game_selected != KEY_EMPIRE && game_selected != KEY_NAPOLEON {
             packed_file.extend_from_slice(GUID_MARKER);
-            if SETTINGS.read().unwrap().settings_bool["disable_uuid_regeneration_on_db_tables"] && !self.uuid.is_empty() {
+            if keep_uuid && !self.uuid.is_empty() {
                 packed_file.encode_packedfile_string_u16(&self.uuid);
             }
             else {
@@ -222,6 +490,62 @@ impl DB {
         Ok(packed_file)
     }
 
+    /// This function takes a `DB` and encodes it to `Vec<u8>`, making sure the encoded data decodes back to the same `DB`.
+    ///
+    /// This is slower than `save`, as it decodes the table after encoding it. Use it when you want to be sure you're not saving garbage.
+    pub fn save_verified(&self, schema: &Schema) -> Result<Vec<u8>> {
+        let data = self.save()?;
+        self.verify_encoded_data(data, schema)
+    }
+
+    /// This function returns this `DB` as a JSON string, with his name, version, columns and rows, for external tools.
+    ///
+    /// Cells keep their types: numbers and booleans are JSON numbers and booleans, and absent optional strings are `null`.
+    pub fn to_json(&self) -> Result<String> {
+        let json = DBJson {
+            table: self.name.to_owned(),
+            version: self.table.definition.version,
+            uuid: self.uuid.to_owned(),
+            mysterious_byte: self.mysterious_byte,
+            columns: self.table.definition.fields.iter().map(|x| DBJsonColumn { name: x.name.to_owned(), field_type: x.field_type.clone() }).collect(),
+            rows: self.table.rows_to_json(),
+        };
+        Ok(serde_json::to_string_pretty(&json)?)
+    }
+
+    /// This function creates a `DB` from a JSON string made by `to_json`, getting his definition from the provided `Schema`.
+    ///
+    /// The columns in the JSON must be the same as the ones of the definition, and every cell must be valid for the type of its column.
+    /// Otherwise, this returns an error.
+    pub fn from_json(json: &str, schema: &Schema) -> Result<Self> {
+        let json: DBJson = serde_json::from_str(json)?;
+        let definition = Self::definition_from_schema(&json.table, json.version, schema)?;
+
+        let columns = definition.fields.iter().map(|x| DBJsonColumn { name: x.name.to_owned(), field_type: x.field_type.clone() }).collect::<Vec<DBJsonColumn>>();
+        if columns != json.columns {
+            let expected = definition.fields.iter().map(|x| x.name.to_owned()).collect::<Vec<String>>().join(", ");
+            let real = json.columns.iter().map(|x| x.name.to_owned()).collect::<Vec<String>>().join(", ");
+            return Err(ErrorKind::DBTableJsonIncompatibleColumns(expected, real).into())
+        }
+
+        Ok(Self {
+            name: json.table,
+            mysterious_byte: json.mysterious_byte,
+            uuid: json.uuid,
+            table: Table::rows_from_json(definition, &json.rows)?,
+        })
+    }
+
+    /// This function checks that the provided data decodes to this same `DB`, returning the data if it does.
+    ///
+    /// The UUID is ignored, as it can be regenerated on save.
+    pub(crate) fn verify_encoded_data(&self, data: Vec<u8>, schema: &Schema) -> Result<Vec<u8>> {
+        match Self::read(&data, &self.name, schema, false) {
+            Ok(db) if db.mysterious_byte == self.mysterious_byte && db.table == self.table => Ok(data),
+            _ => Err(ErrorKind::DBTableSaveVerificationFailed.into()),
+        }
+    }
+
     /// This functions decodes the header part of a `DB` from a `Vec<u8>`.
     ///
     /// The data returned is:
@@ -342,8 +666,8 @@ impl DB {
                                 DecodedData::LongInteger(ref entry) => reference_data = format!("{}", entry),
                                 DecodedData::StringU8(ref entry) |
                                 DecodedData::StringU16(ref entry) |
-                                DecodedData::OptionalStringU8(ref entry) |
-                                DecodedData::OptionalStringU16(ref entry) => reference_data = entry.to_owned(),
+                                DecodedData::OptionalStringU8(Some(ref entry)) |
+                                DecodedData::OptionalStringU16(Some(ref entry)) => reference_data = entry.to_owned(),
                                 _ => {}
                             }
                         }
@@ -358,8 +682,8 @@ impl DB {
                                     DecodedData::LongInteger(ref entry) => lookup_data.push(format!("{}", entry)),
                                     DecodedData::StringU8(ref entry) |
                                     DecodedData::StringU16(ref entry) |
-                                    DecodedData::OptionalStringU8(ref entry) |
-                                    DecodedData::OptionalStringU16(ref entry) => lookup_data.push(entry.to_owned()),
+                                    DecodedData::OptionalStringU8(Some(ref entry)) |
+                                    DecodedData::OptionalStringU16(Some(ref entry)) => lookup_data.push(entry.to_owned()),
                                     _ => {}
                                 }
                             }
@@ -399,8 +723,8 @@ impl DB {
                         DecodedData::LongInteger(ref entry) => reference_data = format!("{}", entry),
                         DecodedData::StringU8(ref entry) |
                         DecodedData::StringU16(ref entry) |
-                        DecodedData::OptionalStringU8(ref entry) |
-                        DecodedData::OptionalStringU16(ref entry) => reference_data = entry.to_owned(),
+                        DecodedData::OptionalStringU8(Some(ref entry)) |
+                        DecodedData::OptionalStringU16(Some(ref entry)) => reference_data = entry.to_owned(),
                         _ => {}
                     }
                 }
@@ -415,8 +739,8 @@ impl DB {
                             DecodedData::LongInteger(ref entry) => lookup_data.push(format!("{}", entry)),
                             DecodedData::StringU8(ref entry) |
                             DecodedData::StringU16(ref entry) |
-                            DecodedData::OptionalStringU8(ref entry) |
-                            DecodedData::OptionalStringU16(ref entry) => lookup_data.push(entry.to_owned()),
+                            DecodedData::OptionalStringU8(Some(ref entry)) |
+                            DecodedData::OptionalStringU16(Some(ref entry)) => lookup_data.push(entry.to_owned()),
                             _ => {}
                         }
                     }
@@ -458,8 +782,8 @@ impl DB {
                                 DecodedData::LongInteger(ref entry) => reference_data = format!("{}", entry),
                                 DecodedData::StringU8(ref entry) |
                                 DecodedData::StringU16(ref entry) |
-                                DecodedData::OptionalStringU8(ref entry) |
-                                DecodedData::OptionalStringU16(ref entry) => reference_data = entry.to_owned(),
+                                DecodedData::OptionalStringU8(Some(ref entry)) |
+                                DecodedData::OptionalStringU16(Some(ref entry)) => reference_data = entry.to_owned(),
                                 _ => {}
                             }
                         }
@@ -474,8 +798,8 @@ impl DB {
                                     DecodedData::LongInteger(ref entry) => lookup_data.push(format!("{}", entry)),
                                     DecodedData::StringU8(ref entry) |
                                     DecodedData::StringU16(ref entry) |
-                                    DecodedData::OptionalStringU8(ref entry) |
-                                    DecodedData::OptionalStringU16(ref entry) => lookup_data.push(entry.to_owned()),
+                                    DecodedData::OptionalStringU8(Some(ref entry)) |
+                                    DecodedData::OptionalStringU16(Some(ref entry)) => lookup_data.push(entry.to_owned()),
                                     _ => {}
                                 }
                             }
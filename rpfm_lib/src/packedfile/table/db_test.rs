@@ -0,0 +1,801 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `DB` module, just to make sure we don't break it... again...
+!*/
+
+use rpfm_error::ErrorKind;
+
+use crate::common::encoder::Encoder;
+use crate::schema::*;
+use super::{DecodedData, MergeConflict, Table};
+use super::db::DB;
+
+/// This function returns a Schema with a simple two-column table, for testing purpouses.
+fn get_test_schema() -> Schema {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field {
+        name: "key".to_owned(),
+        field_type: FieldType::StringU8,
+        is_key: true,
+        ..Default::default()
+    });
+    definition.fields.push(Field {
+        name: "value".to_owned(),
+        field_type: FieldType::Integer,
+        ..Default::default()
+    });
+
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::DB("test_tables".to_owned(), vec![definition]));
+    schema
+}
+
+/// Test to make sure we can build a DB Table from the schema, and that it survives a save/read cycle.
+#[test]
+fn test_new_from_schema_and_add_row() {
+    let schema = get_test_schema();
+
+    // Check that we can only build tables the schema knows about.
+    assert!(DB::new_from_schema("test_tables", 2, &schema).is_err());
+    assert!(DB::new_from_schema("missing_tables", 1, &schema).is_err());
+
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    assert_eq!(db.get_entry_count(), 0);
+
+    // Check that rows with the wrong length or the wrong types are rejected.
+    assert!(db.add_row(vec![DecodedData::StringU8("key".to_owned())]).is_err());
+    assert!(db.add_row(vec![DecodedData::Integer(1), DecodedData::StringU8("key".to_owned())]).is_err());
+    assert_eq!(db.get_entry_count(), 0);
+
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_2".to_owned()), DecodedData::Integer(-2)]).unwrap();
+    assert_eq!(db.get_entry_count(), 2);
+
+    // Check that the encoded table decodes back to the same data.
+    let data = db.save().unwrap();
+    let db_new = DB::read(&data, "test_tables", &schema, false).unwrap();
+    assert_eq!(db.get_ref_definition(), db_new.get_ref_definition());
+    assert_eq!(db.get_ref_table_data(), db_new.get_ref_table_data());
+}
+
+/// Test to make sure the default rows follow the definition of the table, and the default values in it.
+#[test]
+fn test_default_row() {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "bool".to_owned(), field_type: FieldType::Boolean, ..Default::default() });
+    definition.fields.push(Field { name: "float".to_owned(), field_type: FieldType::Float, ..Default::default() });
+    definition.fields.push(Field { name: "integer".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+    definition.fields.push(Field { name: "long_integer".to_owned(), field_type: FieldType::LongInteger, ..Default::default() });
+    definition.fields.push(Field { name: "string_u8".to_owned(), field_type: FieldType::StringU8, ..Default::default() });
+    definition.fields.push(Field { name: "optional_string_u16".to_owned(), field_type: FieldType::OptionalStringU16, ..Default::default() });
+    definition.fields.push(Field { name: "bool_default".to_owned(), field_type: FieldType::Boolean, default_value: Some("TRUE".to_owned()), ..Default::default() });
+    definition.fields.push(Field { name: "integer_default".to_owned(), field_type: FieldType::Integer, default_value: Some("5".to_owned()), ..Default::default() });
+    definition.fields.push(Field { name: "string_default".to_owned(), field_type: FieldType::StringU16, default_value: Some("default".to_owned()), ..Default::default() });
+    definition.fields.push(Field { name: "integer_broken_default".to_owned(), field_type: FieldType::Integer, default_value: Some("five".to_owned()), ..Default::default() });
+
+    let db = DB::new("test_tables", None, &definition);
+    let row = db.default_row();
+    assert_eq!(row, vec![
+        DecodedData::Boolean(false),
+        DecodedData::Float(0.0),
+        DecodedData::Integer(0),
+        DecodedData::LongInteger(0),
+        DecodedData::StringU8(String::new()),
+        DecodedData::OptionalStringU16(None),
+        DecodedData::Boolean(true),
+        DecodedData::Integer(5),
+        DecodedData::StringU16("default".to_owned()),
+        DecodedData::Integer(0),
+    ]);
+
+    // The default row must always be a valid row for the table.
+    for (cell, field) in row.iter().zip(definition.fields.iter()) {
+        assert!(cell.is_field_type_correct(field.field_type.clone()));
+    }
+}
+
+/// Test to make sure the validation of a DB Table finds the rows and cells not following his definition.
+#[test]
+fn test_validate() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)]).unwrap();
+    assert!(db.validate().is_ok());
+
+    // A row with a missing cell.
+    let mut table = Table::new(db.get_ref_definition());
+    table.entries = vec![
+        vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)],
+        vec![DecodedData::StringU8("key_2".to_owned())],
+    ];
+    let db = DB::from(table);
+    assert_eq!(db.validate().unwrap_err().kind(), &ErrorKind::TableInvalidRowFieldCount(2, 2, 1));
+
+    // A cell with the wrong type.
+    let mut table = Table::new(db.get_ref_definition());
+    table.entries = vec![
+        vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)],
+        vec![DecodedData::StringU8("key_2".to_owned()), DecodedData::Integer(2)],
+        vec![DecodedData::StringU8("key_3".to_owned()), DecodedData::Float(3.0)],
+    ];
+    let db = DB::from(table);
+    assert_eq!(db.validate().unwrap_err().kind(), &ErrorKind::TableInvalidCellFieldType(3, 2, "Integer".to_owned(), "Float".to_owned()));
+}
+
+/// Test to make sure the verified save works with normal tables, and detects broken encoded data.
+#[test]
+fn test_save_verified() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_2".to_owned()), DecodedData::Integer(2)]).unwrap();
+
+    let data = db.save_verified(&schema).unwrap();
+    assert_eq!(DB::read(&data, "test_tables", &schema, false).unwrap().get_ref_table_data(), db.get_ref_table_data());
+
+    // Simulate a broken encoder by changing the last integer of the encoded data.
+    let mut broken_data = db.save().unwrap();
+    *broken_data.last_mut().unwrap() = 1;
+    assert_eq!(db.verify_encoded_data(broken_data, &schema).unwrap_err().kind(), &ErrorKind::DBTableSaveVerificationFailed);
+
+    // Simulate a broken encoder by losing the last byte of the encoded data.
+    let mut broken_data = db.save().unwrap();
+    broken_data.pop();
+    assert_eq!(db.verify_encoded_data(broken_data, &schema).unwrap_err().kind(), &ErrorKind::DBTableSaveVerificationFailed);
+}
+
+/// Test to make sure we can detect the version of a table by trying all the definitions we have for it.
+#[test]
+fn test_read_autodetect() {
+    let mut schema = get_test_schema();
+    let mut definition_v2 = schema.get_ref_last_definition_db("test_tables").unwrap().clone();
+    definition_v2.version = 2;
+    definition_v2.fields.push(Field { name: "flag".to_owned(), field_type: FieldType::Boolean, ..Default::default() });
+    schema.get_ref_mut_versioned_file_db("test_tables").unwrap().add_version(&definition_v2);
+
+    // A table that only decodes with the first version.
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_2".to_owned()), DecodedData::Integer(2)]).unwrap();
+    let data = db.save().unwrap();
+
+    let (db_new, version) = DB::read_autodetect(&data, "test_tables", &schema).unwrap();
+    assert_eq!(version, 1);
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+
+    // A table that only decodes with the second version.
+    let mut db = DB::new_from_schema("test_tables", 2, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1), DecodedData::Boolean(true)]).unwrap();
+    let data = db.save().unwrap();
+
+    let (db_new, version) = DB::read_autodetect(&data, "test_tables", &schema).unwrap();
+    assert_eq!(version, 2);
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+
+    // A table no version can decode.
+    let mut broken_data = data.to_vec();
+    broken_data.extend_from_slice(&[3, 3, 3]);
+    let versions = schema.get_ref_versioned_file_db("test_tables").unwrap().get_version_list().iter().map(|x| x.version).collect::<Vec<i32>>();
+    let error = DB::read_autodetect(&broken_data, "test_tables", &schema).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::DBTableVersionNotDetected("test_tables".to_owned(), versions));
+}
+
+/// Test to make sure data left after the last row of a table is reported, instead of silently ignored.
+#[test]
+fn test_read_extra_data() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)]).unwrap();
+
+    let mut data = db.save().unwrap();
+    let consumed = data.len();
+    data.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+    assert_eq!(DB::read(&data, "test_tables", &schema, false).unwrap_err().kind(), &ErrorKind::PackedFileHasExtraData(consumed, consumed + 4));
+}
+
+/// Test to make sure the lossy read keeps the rows decoded before the broken one.
+#[test]
+fn test_read_lossy() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_2".to_owned()), DecodedData::Integer(2)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_3".to_owned()), DecodedData::Integer(3)]).unwrap();
+
+    let data = db.save().unwrap();
+    let (db_new, error) = DB::read_lossy(&data, "test_tables", &schema).unwrap();
+    assert!(error.is_none());
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+
+    // Cut the integer of the last row in half.
+    let truncated_data = &data[..data.len() - 2];
+    let (db_new, error) = DB::read_lossy(truncated_data, "test_tables", &schema).unwrap();
+    assert_eq!(db_new.get_ref_table_data(), &db.get_ref_table_data()[..2]);
+    match error.unwrap().kind() {
+        ErrorKind::TableRowDecodeError(row, _) => assert_eq!(*row, 3),
+        error => panic!("Unexpected error: {:?}", error),
+    }
+
+    // Without a definition there is nothing to show.
+    assert!(DB::read_lossy(&data, "missing_tables", &schema).is_err());
+}
+
+/// Test to make sure sorting by a column sorts numbers by value, and keeps the order of rows with the same value.
+#[test]
+fn test_sort_by_column() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(10)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(9)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("c".to_owned()), DecodedData::Integer(10)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(-1)]).unwrap();
+    let keys = |db: &DB| db.get_ref_table_data().iter().map(|x| format!("{:?}{:?}", x[0], x[1])).collect::<Vec<String>>();
+
+    db.sort_by_column("value", true).unwrap();
+    assert_eq!(keys(&db), vec![
+        "StringU8(\"a\")Integer(-1)",
+        "StringU8(\"a\")Integer(9)",
+        "StringU8(\"b\")Integer(10)",
+        "StringU8(\"c\")Integer(10)",
+    ]);
+
+    db.sort_by_column("value", false).unwrap();
+    assert_eq!(keys(&db), vec![
+        "StringU8(\"b\")Integer(10)",
+        "StringU8(\"c\")Integer(10)",
+        "StringU8(\"a\")Integer(9)",
+        "StringU8(\"a\")Integer(-1)",
+    ]);
+
+    db.sort_by_column("key", true).unwrap();
+    assert_eq!(keys(&db), vec![
+        "StringU8(\"a\")Integer(9)",
+        "StringU8(\"a\")Integer(-1)",
+        "StringU8(\"b\")Integer(10)",
+        "StringU8(\"c\")Integer(10)",
+    ]);
+
+    assert_eq!(db.sort_by_column("missing", true).unwrap_err().kind(), &ErrorKind::TableColumnNotFound("missing".to_owned()));
+}
+
+/// Test to make sure rows can be inserted and removed at any position, and positions outside the table are rejected.
+#[test]
+fn test_insert_remove_row() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    let row = |key: &str| vec![DecodedData::StringU8(key.to_owned()), DecodedData::Integer(0)];
+
+    db.insert_row(0, row("b")).unwrap();
+    db.insert_row(0, row("a")).unwrap();
+    db.insert_row(2, row("d")).unwrap();
+    db.insert_row(2, row("c")).unwrap();
+    assert_eq!(db.get_ref_table_data(), &[row("a"), row("b"), row("c"), row("d")]);
+
+    assert_eq!(db.insert_row(5, row("e")).unwrap_err().kind(), &ErrorKind::TableRowIndexOutOfBounds(5, 4));
+    assert!(db.insert_row(0, vec![DecodedData::Integer(1)]).is_err());
+    assert_eq!(db.get_entry_count(), 4);
+
+    assert_eq!(db.remove_row(1).unwrap(), row("b"));
+    assert_eq!(db.remove_row(2).unwrap(), row("d"));
+    assert_eq!(db.remove_row(2).unwrap_err().kind(), &ErrorKind::TableRowIndexOutOfBounds(2, 2));
+    assert_eq!(db.get_ref_table_data(), &[row("a"), row("c")]);
+}
+
+/// Test to make sure the length of a DB Table follows the rows we add and remove.
+#[test]
+fn test_len() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    let row = |key: &str| vec![DecodedData::StringU8(key.to_owned()), DecodedData::Integer(0)];
+    assert_eq!(db.len(), 0);
+    assert!(db.is_empty());
+
+    db.add_row(row("a")).unwrap();
+    db.insert_row(0, row("b")).unwrap();
+    assert_eq!(db.len(), 2);
+    assert!(!db.is_empty());
+
+    db.remove_row(0).unwrap();
+    assert_eq!(db.len(), 1);
+    db.remove_row(0).unwrap();
+    assert_eq!(db.len(), 0);
+    assert!(db.is_empty());
+}
+
+/// Test to make sure copied rows can be pasted back, including the stuff that needs quoting, but only into compatible tables.
+#[test]
+fn test_serialize_rows() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(1)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("tab\there, \"quotes\"\nand lines".to_owned()), DecodedData::Integer(-2)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("c".to_owned()), DecodedData::Integer(3)]).unwrap();
+
+    let data = db.serialize_rows(&[2, 1]).unwrap();
+    assert!(data.starts_with("key:StringU8\tvalue:Integer\n"));
+    assert_eq!(db.deserialize_rows(&data).unwrap(), vec![db.get_ref_table_data()[2].to_vec(), db.get_ref_table_data()[1].to_vec()]);
+    assert_eq!(db.deserialize_rows(&db.serialize_rows(&[]).unwrap()).unwrap(), Vec::<Vec<DecodedData>>::new());
+    assert_eq!(db.serialize_rows(&[3]).unwrap_err().kind(), &ErrorKind::TableRowIndexOutOfBounds(3, 3));
+
+    // Column names don't matter, but their types do.
+    assert_eq!(db.deserialize_rows("id:StringU8\tnumber:Integer\nb\t2\n").unwrap(), vec![vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(2)]]);
+    assert_eq!(db.deserialize_rows("key:StringU8\tvalue:Float\nb\t2\n").unwrap_err().kind(), &ErrorKind::TableRowsIncompatibleColumns("StringU8, Integer".to_owned(), "StringU8, Float".to_owned()));
+    assert_eq!(db.deserialize_rows("key:StringU8\tvalue:Integer\nb\tnot a number\n").unwrap_err().kind(), &ErrorKind::TableCellParseError("not a number".to_owned(), "Integer".to_owned()));
+    assert_eq!(db.deserialize_rows("key:StringU8\tvalue:Integer\nb\n").unwrap_err().kind(), &ErrorKind::TableRowWrongFieldCount(2, 1));
+}
+
+/// Test to make sure the same table always encodes to the same bytes.
+#[test]
+fn test_save_deterministic() {
+    let schema = get_test_schema();
+    let get_test_db = || {
+        let mut db = DB::new("test_tables", Some("3c5d9b5c-4a43-4b1e-8c57-5c0a4e1f8e7a"), schema.get_ref_versioned_file_db("test_tables").unwrap().get_version(1).unwrap());
+        for index in 0..50 {
+            db.add_row(vec![DecodedData::StringU8(format!("key_{}", index)), DecodedData::Integer(index * 7 - 100)]).unwrap();
+        }
+        db
+    };
+
+    // With the UUID regeneration enabled, everything but the UUID must be the same.
+    let uuid_end = 4 + 2 + 36 * 2;
+    let data = get_test_db().save_keeping_uuid(false).unwrap();
+    assert_eq!(&data[uuid_end..], &get_test_db().save_keeping_uuid(false).unwrap()[uuid_end..]);
+    assert_eq!(&data[uuid_end..], &get_test_db().save().unwrap()[uuid_end..]);
+
+    // With it disabled, everything must be the same. We don't change the setting, as other tests may be reading it.
+    let data = get_test_db().save_keeping_uuid(true).unwrap();
+    for _ in 0..100 {
+        assert_eq!(get_test_db().save_keeping_uuid(true).unwrap(), data);
+    }
+}
+
+/// Test to make sure adding, removing and renaming columns keeps the rows and the definition in sync.
+#[test]
+fn test_column_operations() {
+    let mut schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(1)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(2)]).unwrap();
+
+    db.add_column(&Field { name: "enabled".to_owned(), field_type: FieldType::Boolean, default_value: Some("true".to_owned()), ..Default::default() }).unwrap();
+    db.add_column(&Field { name: "name".to_owned(), field_type: FieldType::StringU16, ..Default::default() }).unwrap();
+    assert_eq!(db.add_column(&Field { name: "key".to_owned(), ..Default::default() }).unwrap_err().kind(), &ErrorKind::TableColumnAlreadyExists("key".to_owned()));
+    assert_eq!(db.get_ref_definition().fields.iter().map(|x| &*x.name).collect::<Vec<&str>>(), vec!["key", "value", "enabled", "name"]);
+    assert_eq!(db.get_ref_table_data()[1], vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(2), DecodedData::Boolean(true), DecodedData::StringU16(String::new())]);
+
+    db.remove_column("value").unwrap();
+    assert_eq!(db.remove_column("value").unwrap_err().kind(), &ErrorKind::TableColumnNotFound("value".to_owned()));
+    assert_eq!(db.get_ref_definition().fields.len(), 3);
+    assert!(db.get_ref_table_data().iter().all(|x| x.len() == 3));
+    assert_eq!(db.get_ref_table_data()[0], vec![DecodedData::StringU8("a".to_owned()), DecodedData::Boolean(true), DecodedData::StringU16(String::new())]);
+
+    db.rename_column("name", "display_name").unwrap();
+    assert_eq!(db.rename_column("name", "other").unwrap_err().kind(), &ErrorKind::TableColumnNotFound("name".to_owned()));
+    assert_eq!(db.rename_column("key", "enabled").unwrap_err().kind(), &ErrorKind::TableColumnAlreadyExists("enabled".to_owned()));
+    assert_eq!(db.get_ref_definition().fields[2].name, "display_name");
+
+    // The changed table must still be valid, and decode back with his new definition.
+    db.validate().unwrap();
+    let mut definition = db.get_definition();
+    definition.version = 2;
+    db.set_definition(&definition);
+    schema.add_versioned_file(&VersionedFile::DB("test_tables".to_owned(), vec![definition]));
+    let db_new = DB::read(&db.save().unwrap(), "test_tables", &schema, false).unwrap();
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+}
+
+/// Test to make sure the unique values of a column are deduplicated, and returned in the order they first appear.
+#[test]
+fn test_column_unique_values() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(10)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(9)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(10)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("c".to_owned()), DecodedData::Integer(9)]).unwrap();
+
+    assert_eq!(db.column_unique_values("key").unwrap(), vec!["b", "a", "c"]);
+    assert_eq!(db.column_unique_values("value").unwrap(), vec!["10", "9"]);
+    assert_eq!(db.column_unique_values("missing").unwrap_err().kind(), &ErrorKind::TableColumnNotFound("missing".to_owned()));
+}
+
+/// Test to make sure the references to another table are checked against the values of the referenced column.
+#[test]
+fn test_check_references() {
+    let schema = get_test_schema();
+    let mut referenced = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    referenced.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(1)]).unwrap();
+    referenced.add_row(vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(2)]).unwrap();
+
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("b".to_owned()), DecodedData::Integer(2)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(1)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8(String::new()), DecodedData::Integer(1)]).unwrap();
+    assert!(db.check_references("key", &referenced, "key").unwrap().is_empty());
+
+    db.add_row(vec![DecodedData::StringU8("c".to_owned()), DecodedData::Integer(3)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::Integer(4)]).unwrap();
+    assert_eq!(db.check_references("key", &referenced, "key").unwrap(), vec![3]);
+    assert_eq!(db.check_references("value", &referenced, "value").unwrap(), vec![3, 4]);
+
+    assert_eq!(db.check_references("missing", &referenced, "key").unwrap_err().kind(), &ErrorKind::TableColumnNotFound("missing".to_owned()));
+    assert_eq!(db.check_references("key", &referenced, "missing").unwrap_err().kind(), &ErrorKind::TableColumnNotFound("missing".to_owned()));
+}
+
+/// Test to make sure the definition and name of a decoded DB Table are available to render it, and follow his rows.
+#[test]
+fn test_definition_and_table_name() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("key_1".to_owned()), DecodedData::Integer(1)]).unwrap();
+
+    let db = DB::read(&db.save().unwrap(), "test_tables", &schema, false).unwrap();
+    assert_eq!(db.get_ref_table_name(), "test_tables");
+    assert_eq!(db.get_ref_definition(), schema.get_ref_versioned_file_db("test_tables").unwrap().get_version(1).unwrap());
+    assert!(db.get_ref_table_data().iter().all(|row| row.len() == db.get_ref_definition().fields.len()));
+    assert_eq!(db.get_ref_definition().fields.iter().map(|x| x.name.to_owned()).collect::<Vec<String>>(), vec!["key", "value"]);
+}
+
+/// Test to make sure consecutive boolean columns take one full byte each (no bit-packing), and survive a read/save cycle unchanged.
+#[test]
+fn test_consecutive_booleans() {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU8, is_key: true, ..Default::default() });
+    for index in 0..9 {
+        definition.fields.push(Field { name: format!("flag_{}", index), field_type: FieldType::Boolean, ..Default::default() });
+    }
+    definition.fields.push(Field { name: "value".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::DB("flags_tables".to_owned(), vec![definition]));
+
+    let mut db = DB::new_from_schema("flags_tables", 1, &schema).unwrap();
+    let flags = [true, false, true, true, false, false, true, false, true];
+    let mut row = vec![DecodedData::StringU8("a".to_owned())];
+    row.extend(flags.iter().map(|x| DecodedData::Boolean(*x)));
+    row.push(DecodedData::Integer(7));
+    db.add_row(row).unwrap();
+
+    // The row must be the key, one byte per flag, and then the integer.
+    let data = db.save().unwrap();
+    let row_data = &data[data.len() - 16..];
+    assert_eq!(row_data, &[1, 0, b'a', 1, 0, 1, 1, 0, 0, 1, 0, 1, 7, 0, 0, 0]);
+
+    let db_new = DB::read(&data, "flags_tables", &schema, false).unwrap();
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+
+    // The GUID in the header changes on each save, so we can only compare the data after it.
+    let new_data = db_new.save().unwrap();
+    assert_eq!(new_data.len(), data.len());
+    assert_eq!(&new_data[new_data.len() - 16..], row_data);
+
+    // Anything other than 0 or 1 in a boolean byte is not a boolean.
+    let mut broken_data = data.to_vec();
+    let broken_index = broken_data.len() - 6;
+    broken_data[broken_index] = 2;
+    assert!(DB::read(&broken_data, "flags_tables", &schema, false).is_err());
+}
+
+/// Test to make sure optional strings keep their presence flag, so absent and empty values survive a read/save cycle unchanged.
+#[test]
+fn test_optional_strings() {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU8, is_key: true, ..Default::default() });
+    definition.fields.push(Field { name: "value".to_owned(), field_type: FieldType::OptionalStringU8, ..Default::default() });
+
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::DB("optional_tables".to_owned(), vec![definition]));
+
+    let mut db = DB::new_from_schema("optional_tables", 1, &schema).unwrap();
+    db.add_row(vec![DecodedData::StringU8("a".to_owned()), DecodedData::OptionalStringU8(Some("text".to_owned()))]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("b".to_owned()), DecodedData::OptionalStringU8(None)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8("c".to_owned()), DecodedData::OptionalStringU8(Some(String::new()))]).unwrap();
+
+    // Present values start with a true flag, even if they're empty. Absent values are just a false flag.
+    let data = db.save().unwrap();
+    let row_data = &data[data.len() - 20..];
+    assert_eq!(row_data, &[1, 0, b'a', 1, 4, 0, b't', b'e', b'x', b't', 1, 0, b'b', 0, 1, 0, b'c', 1, 0, 0]);
+
+    let db_new = DB::read(&data, "optional_tables", &schema, false).unwrap();
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+
+    // The GUID in the header changes on each save, so we can only compare the data after it.
+    let new_data = db_new.save().unwrap();
+    assert_eq!(new_data.len(), data.len());
+    assert_eq!(&new_data[new_data.len() - 20..], row_data);
+}
+
+/// Test to make sure every kind of string field is decoded and encoded with the encoding of its field, without changing a single byte.
+#[test]
+fn test_string_fields() {
+    let mut definition = Definition::new(3);
+    for (name, field_type) in &[("string_u8", FieldType::StringU8), ("string_u16", FieldType::StringU16), ("optional_string_u8", FieldType::OptionalStringU8), ("optional_string_u16", FieldType::OptionalStringU16)] {
+        definition.fields.push(Field { name: (*name).to_owned(), field_type: field_type.clone(), ..Default::default() });
+    }
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::DB("string_tables".to_owned(), vec![definition]));
+
+    let long_string = "é".repeat(40_000);
+    let rows = vec![
+        ("Ünïcödé 日本語", "Łódź 😀", Some("ça va"), None),
+        ("", "", None, Some("Привет, мир")),
+        ("ascii", long_string.as_str(), Some(""), Some("")),
+    ];
+
+    // Build the table by hand, so we don't depend on the encoding logic we're testing.
+    let mut data = vec![253, 254, 252, 255];
+    data.encode_packedfile_string_u16("3c5d9b5c-4a43-4b1e-8c57-5c0a4e1f8e7a");
+    data.extend_from_slice(&[252, 253, 254, 255]);
+    data.encode_integer_i32(3);
+    data.encode_bool(true);
+    data.encode_integer_u32(rows.len() as u32);
+    for (string_u8, string_u16, optional_string_u8, optional_string_u16) in &rows {
+        data.encode_integer_u16(string_u8.len() as u16);
+        data.extend_from_slice(string_u8.as_bytes());
+        data.encode_integer_u16(string_u16.encode_utf16().count() as u16);
+        string_u16.encode_utf16().for_each(|x| data.extend_from_slice(&x.to_le_bytes()));
+        match optional_string_u8 {
+            Some(string) => {
+                data.push(1);
+                data.encode_integer_u16(string.len() as u16);
+                data.extend_from_slice(string.as_bytes());
+            }
+            None => data.push(0),
+        }
+        match optional_string_u16 {
+            Some(string) => {
+                data.push(1);
+                data.encode_integer_u16(string.encode_utf16().count() as u16);
+                string.encode_utf16().for_each(|x| data.extend_from_slice(&x.to_le_bytes()));
+            }
+            None => data.push(0),
+        }
+    }
+
+    let mut db = DB::read(&data, "string_tables", &schema, false).unwrap();
+    let expected = rows.iter().map(|(string_u8, string_u16, optional_string_u8, optional_string_u16)| vec![
+        DecodedData::StringU8((*string_u8).to_owned()),
+        DecodedData::StringU16((*string_u16).to_owned()),
+        DecodedData::OptionalStringU8(optional_string_u8.map(|x| x.to_owned())),
+        DecodedData::OptionalStringU16(optional_string_u16.map(|x| x.to_owned())),
+    ]).collect::<Vec<Vec<DecodedData>>>();
+    assert_eq!(db.get_ref_table_data(), &*expected);
+
+    // Everything but the UUID, which may be regenerated, must be exactly the same.
+    let uuid_end = 4 + 2 + 36 * 2;
+    let new_data = db.save().unwrap();
+    assert_eq!(new_data.len(), data.len());
+    assert_eq!(&new_data[uuid_end..], &data[uuid_end..]);
+
+    // Rows with strings of the wrong type are rejected.
+    db.add_row(vec![DecodedData::StringU16("a".to_owned()), DecodedData::StringU8("b".to_owned()), DecodedData::OptionalStringU8(None), DecodedData::OptionalStringU16(None)]).unwrap_err();
+    assert_eq!(db.get_entry_count(), rows.len());
+}
+
+/// Test to make sure DB Tables survive a JSON round-trip without losing the types of their cells, and that invalid JSON tables are rejected.
+#[test]
+fn test_json() {
+    let mut sequence_definition = Definition::new(0);
+    sequence_definition.fields.push(Field { name: "entry".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+
+    let mut definition = Definition::new(2);
+    for (name, field_type) in &[
+        ("key", FieldType::StringU8),
+        ("flag", FieldType::Boolean),
+        ("float", FieldType::Float),
+        ("integer", FieldType::Integer),
+        ("long_integer", FieldType::LongInteger),
+        ("string_u16", FieldType::StringU16),
+        ("optional_string_u8", FieldType::OptionalStringU8),
+        ("optional_string_u16", FieldType::OptionalStringU16),
+        ("sequence", FieldType::Sequence(sequence_definition.clone())),
+    ] {
+        definition.fields.push(Field { name: (*name).to_owned(), field_type: field_type.clone(), ..Default::default() });
+    }
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::DB("json_tables".to_owned(), vec![definition]));
+
+    let mut sequence = Table::new(&sequence_definition);
+    sequence.set_table_data(&[vec![DecodedData::Integer(1)], vec![DecodedData::Integer(-2)]]).unwrap();
+
+    let mut db = DB::new_from_schema("json_tables", 2, &schema).unwrap();
+    db.add_row(vec![
+        DecodedData::StringU8("1".to_owned()),
+        DecodedData::Boolean(true),
+        DecodedData::Float(0.1),
+        DecodedData::Integer(i32::MIN),
+        DecodedData::LongInteger(i64::MAX),
+        DecodedData::StringU16("Ünïcödé".to_owned()),
+        DecodedData::OptionalStringU8(None),
+        DecodedData::OptionalStringU16(Some(String::new())),
+        DecodedData::Sequence(sequence),
+    ]).unwrap();
+    db.add_row(vec![
+        DecodedData::StringU8("true".to_owned()),
+        DecodedData::Boolean(false),
+        DecodedData::Float(-3.0),
+        DecodedData::Integer(7),
+        DecodedData::LongInteger(-1),
+        DecodedData::StringU16(String::new()),
+        DecodedData::OptionalStringU8(Some("text".to_owned())),
+        DecodedData::OptionalStringU16(None),
+        DecodedData::Sequence(Table::new(&sequence_definition)),
+    ]).unwrap();
+
+    let json = db.to_json().unwrap();
+    let db_new = DB::from_json(&json, &schema).unwrap();
+    assert_eq!(db_new, db);
+    assert_eq!(db_new.uuid, db.uuid);
+    match db_new.get_ref_table_data()[0][2] {
+        DecodedData::Float(data) => assert_eq!(data.to_bits(), 0.1f32.to_bits()),
+        _ => panic!("The float column is not a float."),
+    }
+    assert_eq!(db_new.to_json().unwrap(), json);
+
+    // Cells must be of the JSON type of their column, and numbers must fit in it.
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["rows"][1][3] = serde_json::Value::from("7");
+    assert_eq!(DB::from_json(&value.to_string(), &schema).unwrap_err().kind(), &ErrorKind::TableJsonInvalidCell(2, 4, "\"7\"".to_owned(), "Integer".to_owned()));
+    value["rows"][1][3] = serde_json::Value::from(i64::from(i32::MAX) + 1);
+    assert!(DB::from_json(&value.to_string(), &schema).is_err());
+    value["rows"][1][3] = serde_json::Value::from(7);
+    value["rows"][1][1] = serde_json::Value::from(0);
+    assert!(DB::from_json(&value.to_string(), &schema).is_err());
+    value["rows"][1][1] = serde_json::Value::from(false);
+    assert_eq!(DB::from_json(&value.to_string(), &schema).unwrap(), db);
+
+    // Rows must have one value for each column, and the columns must be the ones of the definition.
+    value["rows"][0].as_array_mut().unwrap().pop();
+    assert_eq!(DB::from_json(&value.to_string(), &schema).unwrap_err().kind(), &ErrorKind::TableInvalidRowFieldCount(1, 9, 8));
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["columns"][1]["name"] = serde_json::Value::from("other_flag");
+    assert!(matches!(DB::from_json(&value.to_string(), &schema).unwrap_err().kind(), ErrorKind::DBTableJsonIncompatibleColumns(_, _)));
+
+    value["version"] = serde_json::Value::from(3);
+    assert!(DB::from_json(&value.to_string(), &schema).is_err());
+}
+
+/// Test to make sure cells whose values don't fit in their columns are rejected when saving, instead of saving a broken table.
+#[test]
+fn test_save_out_of_range() {
+    let schema = get_test_schema();
+    let mut db = DB::new_from_schema("test_tables", 1, &schema).unwrap();
+
+    // The biggest values that fit.
+    db.add_row(vec![DecodedData::StringU8("a".repeat(u16::MAX as usize)), DecodedData::Integer(i32::MAX)]).unwrap();
+    db.add_row(vec![DecodedData::StringU8(String::new()), DecodedData::Integer(i32::MIN)]).unwrap();
+    let db_new = DB::read(&db.save().unwrap(), "test_tables", &schema, false).unwrap();
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+
+    // One byte more, and the length no longer fits in his u16.
+    db.add_row(vec![DecodedData::StringU8("é".repeat(u16::MAX as usize / 2 + 1)), DecodedData::Integer(0)]).unwrap();
+    assert_eq!(db.save().unwrap_err().kind(), &ErrorKind::TableCellValueOutOfRange { row: 2, column: 0, value: "65536 bytes".to_owned() });
+
+    // UTF-16 strings count characters, not bytes.
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "string_u16".to_owned(), field_type: FieldType::StringU16, ..Default::default() });
+    definition.fields.push(Field { name: "optional_string_u16".to_owned(), field_type: FieldType::OptionalStringU16, ..Default::default() });
+    let mut db = DB::new("utf16_tables", None, &definition);
+    db.add_row(vec![DecodedData::StringU16("é".repeat(u16::MAX as usize)), DecodedData::OptionalStringU16(Some("é".repeat(u16::MAX as usize)))]).unwrap();
+    assert!(db.save().is_ok());
+
+    db.add_row(vec![DecodedData::StringU16(String::new()), DecodedData::OptionalStringU16(Some("a".repeat(u16::MAX as usize + 1)))]).unwrap();
+    assert_eq!(db.save().unwrap_err().kind(), &ErrorKind::TableCellValueOutOfRange { row: 1, column: 1, value: "65536 UTF-16 characters".to_owned() });
+}
+
+/// Test to make sure numeric cells at the limits of their columns, including infinite and NaN floats, survive being saved and read back.
+#[test]
+fn test_save_number_limits() {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "float".to_owned(), field_type: FieldType::Float, ..Default::default() });
+    definition.fields.push(Field { name: "integer".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+    definition.fields.push(Field { name: "long_integer".to_owned(), field_type: FieldType::LongInteger, ..Default::default() });
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::DB("number_tables".to_owned(), vec![definition.clone()]));
+
+    // The limits of each type, in both directions.
+    let mut db = DB::new("number_tables", None, &definition);
+    db.add_row(vec![DecodedData::Float(f32::MAX), DecodedData::Integer(i32::MAX), DecodedData::LongInteger(i64::MAX)]).unwrap();
+    db.add_row(vec![DecodedData::Float(f32::MIN), DecodedData::Integer(i32::MIN), DecodedData::LongInteger(i64::MIN)]).unwrap();
+    db.add_row(vec![DecodedData::Float(f32::INFINITY), DecodedData::Integer(0), DecodedData::LongInteger(0)]).unwrap();
+    db.add_row(vec![DecodedData::Float(f32::NEG_INFINITY), DecodedData::Integer(0), DecodedData::LongInteger(0)]).unwrap();
+    let db_new = DB::read(&db.save().unwrap(), "number_tables", &schema, false).unwrap();
+    assert_eq!(db_new.get_ref_table_data(), db.get_ref_table_data());
+
+    // NaN is not equal to itself, so check its bits instead.
+    db.add_row(vec![DecodedData::Float(f32::NAN), DecodedData::Integer(0), DecodedData::LongInteger(0)]).unwrap();
+    let db_new = DB::read(&db.save().unwrap(), "number_tables", &schema, false).unwrap();
+    match db_new.get_ref_table_data()[4][0] {
+        DecodedData::Float(data) => assert_eq!(data.to_bits(), f32::NAN.to_bits()),
+        _ => panic!("The float column is not a float."),
+    }
+}
+
+/// This function returns a DB Table with the provided rows, of `key` (the key column), `value` and `text` columns, for testing the merges.
+fn get_merge_test_db(rows: &[(&str, i32, &str)]) -> DB {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU8, is_key: true, ..Default::default() });
+    definition.fields.push(Field { name: "value".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+    definition.fields.push(Field { name: "text".to_owned(), field_type: FieldType::StringU8, ..Default::default() });
+
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::DB("merge_tables".to_owned(), vec![definition]));
+
+    let mut db = DB::new_from_schema("merge_tables", 1, &schema).unwrap();
+    for (key, value, text) in rows {
+        db.add_row(vec![DecodedData::StringU8((*key).to_owned()), DecodedData::Integer(*value), DecodedData::StringU8((*text).to_owned())]).unwrap();
+    }
+    db
+}
+
+/// Test to make sure changes to different rows and cells from both sides are all applied in a three-way merge.
+#[test]
+fn test_merge_three_way() {
+    let base = get_merge_test_db(&[("a", 1, "x"), ("b", 2, "y"), ("c", 3, "z"), ("d", 4, "w")]);
+    let mine = get_merge_test_db(&[("a", 10, "x"), ("c", 3, "z"), ("d", 4, "w"), ("e", 5, "m")]);
+    let theirs = get_merge_test_db(&[("a", 1, "xx"), ("b", 2, "y"), ("d", 4, "w"), ("f", 6, "t")]);
+
+    let (merged, conflicts) = DB::merge_three_way(&base, &mine, &theirs).unwrap();
+    assert!(conflicts.is_empty());
+    assert_eq!(merged.get_ref_table_data(), get_merge_test_db(&[("a", 10, "xx"), ("d", 4, "w"), ("e", 5, "m"), ("f", 6, "t")]).get_ref_table_data());
+
+    // Merging with no changes from one side just gives the other side.
+    let (merged, conflicts) = DB::merge_three_way(&base, &mine, &base).unwrap();
+    assert!(conflicts.is_empty());
+    assert_eq!(merged, mine);
+}
+
+/// Test to make sure cells changed by both sides in different ways are reported as conflicts, keeping our value.
+#[test]
+fn test_merge_three_way_conflicts() {
+    let base = get_merge_test_db(&[("a", 1, "x"), ("b", 2, "y")]);
+    let mine = get_merge_test_db(&[("a", 10, "x"), ("b", 2, "mine"), ("g", 7, "new")]);
+    let theirs = get_merge_test_db(&[("a", 20, "x"), ("g", 8, "new")]);
+
+    let (merged, conflicts) = DB::merge_three_way(&base, &mine, &theirs).unwrap();
+    assert_eq!(merged.get_ref_table_data(), mine.get_ref_table_data());
+    assert_eq!(conflicts, vec![
+        MergeConflict { row: 0, column: "value".to_owned(), base: Some(DecodedData::Integer(1)), mine: Some(DecodedData::Integer(10)), theirs: Some(DecodedData::Integer(20)) },
+        MergeConflict { row: 1, column: "text".to_owned(), base: Some(DecodedData::StringU8("y".to_owned())), mine: Some(DecodedData::StringU8("mine".to_owned())), theirs: None },
+        MergeConflict { row: 2, column: "value".to_owned(), base: None, mine: Some(DecodedData::Integer(7)), theirs: Some(DecodedData::Integer(8)) },
+    ]);
+
+    // Tables with different definitions cannot be merged.
+    let mut other = theirs;
+    other.add_column(&Field { name: "extra".to_owned(), ..Default::default() }).unwrap();
+    assert_eq!(DB::merge_three_way(&base, &mine, &other).unwrap_err().kind(), &ErrorKind::TableMergeIncompatibleDefinitions);
+}
+
+/// Test to make sure an edited cell can be restored from a snapshot, and that broken snapshots are rejected.
+#[test]
+fn test_snapshot_restore_cell() {
+    let mut db = get_merge_test_db(&[("a", 1, "x"), ("b", 2, "y")]);
+    let original = db.clone();
+    let snapshot = db.snapshot_cell(1, 2).unwrap();
+    assert_eq!(snapshot, (1, 2, DecodedData::StringU8("y".to_owned())));
+
+    let mut data = db.get_table_data();
+    data[1][2] = DecodedData::StringU8("edited".to_owned());
+    db.set_table_data(&data).unwrap();
+    assert_ne!(db, original);
+
+    db.restore_cell(snapshot).unwrap();
+    assert_eq!(db, original);
+
+    // Out of bounds positions, both when taking the snapshot and when restoring it, and cells of the wrong type.
+    assert_eq!(db.snapshot_cell(2, 0).unwrap_err().kind(), &ErrorKind::TableRowIndexOutOfBounds(2, 2));
+    assert_eq!(db.snapshot_cell(0, 3).unwrap_err().kind(), &ErrorKind::TableColumnIndexOutOfBounds(3, 3));
+    assert_eq!(db.restore_cell((5, 0, DecodedData::StringU8("c".to_owned()))).unwrap_err().kind(), &ErrorKind::TableRowIndexOutOfBounds(5, 2));
+    assert_eq!(db.restore_cell((0, 1, DecodedData::StringU8("1".to_owned()))).unwrap_err().kind(), &ErrorKind::TableWrongFieldType("StringU8".to_owned(), "Integer".to_owned()));
+    assert_eq!(db, original);
+}
@@ -15,11 +15,14 @@ Loc Tables are the files which contain all the localisation strings used by the
 They're just tables with a key, a text, and a boolean column.
 !*/
 
+use csv::ReaderBuilder;
+
+use std::collections::HashSet;
 use std::path::PathBuf;
 
 use rpfm_error::{ErrorKind, Result};
 
-use crate::common::{decoder::Decoder, encoder::Encoder};
+use crate::common::{decoder::Decoder, encoder::Encoder, parse_str};
 use super::DecodedData;
 use super::Table;
 
@@ -40,6 +43,9 @@ pub const TSV_NAME_LOC: &str = "Loc PackedFile";
 /// Extension used by Loc PackedFiles.
 pub const EXTENSION: &str = ".loc";
 
+/// Columns of the header of the CSV files CA's localisation tools use for Loc Tables.
+const CA_CSV_HEADER: [&str; 3] = ["key", "text", "tooltip"];
+
 //---------------------------------------------------------------------------//
 //                              Enum & Structs
 //---------------------------------------------------------------------------//
@@ -50,6 +56,23 @@ pub struct Loc {
 
     /// The table's data, containing all the stuff needed to decode/encode it.
     table: Table,
+
+    /// The keys found more than once when this Loc Table was read.
+    duplicate_keys: Vec<String>,
+}
+
+/// This struct contains a summary of the contents of a Loc Table, to check how much of it is translated.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct LocStats {
+
+    /// Amount of entries in the table.
+    pub entries: usize,
+
+    /// Amount of entries with an empty text, which usually means they're not translated.
+    pub empty_values: usize,
+
+    /// Amount of entries marked as tooltips.
+    pub tooltips: usize,
 }
 
 //---------------------------------------------------------------------------//
@@ -63,6 +86,7 @@ impl Loc {
     pub fn new(definition: &Definition) -> Self {
         Self {
         	table: Table::new(definition),
+            duplicate_keys: vec![],
         }
     }
 
@@ -99,6 +123,16 @@ impl Loc {
         self.table.get_entry_count()
     }
 
+    /// This function returns the amount of entries in this Loc Table. Akin to `get_entry_count()`.
+    pub fn len(&self) -> usize {
+        self.table.get_entry_count()
+    }
+
+    /// This function returns if this Loc Table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.table.get_entry_count() == 0
+    }
+
     /// This function replaces the definition of this table with the one provided.
     ///
     /// This updates the table's data to follow the format marked by the new definition, so you can use it to *update* the version of your table.
@@ -113,8 +147,29 @@ impl Loc {
         self.table.set_table_data(data)
     }
 
+    /// This function returns the keys that were found more than once when this Loc Table was read, in the order they were found.
+    ///
+    /// Changing the entries later doesn't update it. To check the current entries, use `check_duplicate_keys`.
+    pub fn duplicate_keys(&self) -> &[String] {
+        &self.duplicate_keys
+    }
+
+    /// This function checks that no key is used by more than one entry of this Loc Table.
+    ///
+    /// If one is, this returns a `LocDuplicateKey` error with the first key found repeated.
+    pub fn check_duplicate_keys(&self) -> Result<()> {
+        match Self::find_duplicate_keys(&self.table.entries).into_iter().next() {
+            Some(key) => Err(ErrorKind::LocDuplicateKey { key }.into()),
+            None => Ok(()),
+        }
+    }
+
     /// This function creates a new `Loc` from a `Vec<u8>`.
+    ///
+    /// If the data ends before all the entries the header says it has, this returns an error with both counts.
+    /// Entries with duplicated keys are all kept, as the game just uses the last one. The repeated keys can be checked with `duplicate_keys`.
     pub fn read(packed_file_data: &[u8], schema: &Schema, return_incomplete: bool) -> Result<Self> {
+        if packed_file_data.is_empty() { return Err(ErrorKind::PackedFileDataIsEmpty.into()) }
 
         let (version, entry_count) = Self::read_header(packed_file_data)?;
 
@@ -128,14 +183,24 @@ impl Loc {
         // Then try to decode all the entries.
         let mut index = HEADER_SIZE as usize;
         let mut table = Table::new(&definition);
-        table.decode(&packed_file_data, entry_count, &mut index, return_incomplete)?;
+        if let Err(error) = table.decode(&packed_file_data, entry_count, &mut index, return_incomplete) {
+
+            // If we ran out of data between cells, the file has less entries than it says.
+            if !return_incomplete && index == packed_file_data.len() {
+                return Err(ErrorKind::LocEntryCountMismatch { declared: entry_count, found: table.entries.len() as u32 }.into())
+            }
+            return Err(error)
+        }
 
         // If we are not in the last byte, it means we didn't parse the entire file, which means this file is corrupt.
+        if index < packed_file_data.len() { return Err(ErrorKind::PackedFileHasExtraData(index, packed_file_data.len()).into()) }
         if index != packed_file_data.len() { return Err(ErrorKind::PackedFileSizeIsNotWhatWeExpect(packed_file_data.len(), index).into()) }
 
         // If we've reached this, we've succesfully decoded the table.
+        let duplicate_keys = Self::find_duplicate_keys(&table.entries);
         Ok(Self {
             table,
+            duplicate_keys,
         })
     }
 
@@ -174,6 +239,74 @@ impl Loc {
         Ok(packed_file)
     }
 
+    /// This function removes the entry with the provided key from the Loc Table, returning if there was an entry to remove.
+    ///
+    /// If more than one entry has that key, only the first one is removed. The rest of the entries keep their order.
+    pub fn remove(&mut self, key: &str) -> bool {
+        match self.table.entries.iter().position(|x| Self::get_key_and_text(x).map_or(false, |(entry_key, _)| entry_key == key)) {
+            Some(position) => {
+                self.table.entries.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// This function keeps only the entries of the Loc Table for which the provided function, called with their key and text, returns `true`.
+    ///
+    /// The remaining entries keep their order.
+    pub fn retain(&mut self, f: impl Fn(&str, &str) -> bool) {
+        self.table.entries.retain(|x| Self::get_key_and_text(x).map_or(true, |(key, text)| f(key, text)));
+    }
+
+    /// This function sorts the entries of the Loc Table by their key, so equal Loc Tables always get saved the same way.
+    ///
+    /// The comparison is case-sensitive and byte-wise, so `Key` goes before `key`. The sort is stable, so entries with the same key
+    /// keep their order. Entries without a valid key (which should never happen) go first.
+    pub fn sort_by_key(&mut self) {
+        self.table.entries.sort_by(|x, y| Self::get_key(x).cmp(&Self::get_key(y)));
+    }
+
+    /// This function returns if the entries of the Loc Table are sorted by their key, in the same order `sort_by_key` uses.
+    pub fn is_sorted(&self) -> bool {
+        self.table.entries.windows(2).all(|x| Self::get_key(&x[0]) <= Self::get_key(&x[1]))
+    }
+
+    /// This function returns a summary of the entries of the Loc Table, with how many of them have an empty text or are tooltips.
+    pub fn stats(&self) -> LocStats {
+        let entries = self.get_ref_table_data();
+        LocStats {
+            entries: entries.len(),
+            empty_values: entries.iter().filter(|x| matches!(Self::get_key_and_text(x), Some((_, text)) if text.is_empty())).count(),
+            tooltips: entries.iter().filter(|x| matches!(x.get(2), Some(DecodedData::Boolean(true)))).count(),
+        }
+    }
+
+    /// This function returns the keys used by more than one of the provided entries, in the order they're found repeated.
+    fn find_duplicate_keys(entries: &[Vec<DecodedData>]) -> Vec<String> {
+        let mut keys = HashSet::new();
+        let mut duplicate_keys = vec![];
+        for key in entries.iter().filter_map(|x| Self::get_key(x)) {
+            if !keys.insert(key) && !duplicate_keys.iter().any(|x| x == key) {
+                duplicate_keys.push(key.to_owned());
+            }
+        }
+        duplicate_keys
+    }
+
+    /// This function returns the key of the provided entry, if it has one.
+    fn get_key(entry: &[DecodedData]) -> Option<&str> {
+        Self::get_key_and_text(entry).map(|(key, _)| key)
+    }
+
+    /// This function returns the key and the text of the provided entry, if it has them.
+    fn get_key_and_text(entry: &[DecodedData]) -> Option<(&str, &str)> {
+        match (entry.get(0), entry.get(1)) {
+            (Some(DecodedData::StringU16(key)), Some(DecodedData::StringU16(text))) => Some((key, text)),
+            _ => None,
+        }
+    }
+
     /// This function is used to optimize the size of a Loc Table.
     ///
     /// It scans every line to check if it's a vanilla line, and remove it in that case. Also, if the entire
@@ -220,6 +353,240 @@ impl Loc {
     ) -> Result<()> {
         self.table.export_tsv(path, table_name)
     }
+
+    /// This function exports the Loc Table to the contents of a gettext PO file, for translators.
+    ///
+    /// Each row becomes an entry with the key as `msgctxt`, the text as `msgid` and an empty `msgstr` for the translation.
+    /// The tooltip flag is kept in a `#. tooltip:` comment, so `from_po` can restore it.
+    ///
+    /// If any row doesn't have the key, text and tooltip columns of a Loc Table, this returns an error with the number of that row.
+    pub fn to_po(&self) -> Result<String> {
+        let mut po = String::from("msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+        for (index, row) in self.get_ref_table_data().iter().enumerate() {
+            let (key, text, tooltip) = Self::get_row_values(row, index + 1)?;
+
+            po.push_str(if tooltip { "\n#. tooltip: true\n" } else { "\n#. tooltip: false\n" });
+            po.push_str("msgctxt ");
+            po.push_str(&po_quote(key));
+            po.push_str("\nmsgid ");
+            po.push_str(&po_quote(text));
+            po.push_str("\nmsgstr \"\"\n");
+        }
+        Ok(po)
+    }
+
+    /// This function creates a new `Loc` from the contents of a gettext PO file.
+    ///
+    /// The text of each row is the `msgstr` of its entry if it has been translated, or the `msgid` otherwise.
+    /// The header entry, the one with an empty `msgid` and without `msgctxt`, is ignored. Any other entry without `msgctxt` returns an error.
+    pub fn from_po(po: &str) -> Result<Self> {
+        let mut table = Table::new(&Self::get_default_definition());
+        let mut entry = POEntry::default();
+        let mut keyword: Option<&str> = None;
+
+        for (index, line) in po.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+
+            // New entries start after an empty line, or with their comment or context.
+            if line.is_empty() || ((line.starts_with('#') || line.starts_with("msgctxt ")) && entry.msgid.is_some()) {
+                keyword = None;
+                entry.add_to_table(&mut table, line_number)?;
+                entry = POEntry::default();
+                if line.is_empty() { continue; }
+            }
+
+            if let Some(tooltip) = line.strip_prefix("#. tooltip:") {
+                match parse_str(tooltip.trim()) {
+                    Ok(tooltip) => entry.tooltip = tooltip,
+                    Err(_) => return Err(ErrorKind::LocPOParse(line_number, "Invalid tooltip flag.".to_owned()).into()),
+                }
+            }
+            else if line.starts_with('#') { continue; }
+            else if line.starts_with('"') {
+                match keyword.and_then(|keyword| entry.get_ref_mut_field(keyword)) {
+                    Some(Some(field)) => field.push_str(&po_unquote(line, line_number)?),
+                    _ => return Err(ErrorKind::LocPOParse(line_number, "String without keyword.".to_owned()).into()),
+                }
+            }
+            else {
+                let (new_keyword, value) = match line.find(' ') {
+                    Some(position) => (&line[..position], po_unquote(line[position..].trim(), line_number)?),
+                    None => return Err(ErrorKind::LocPOParse(line_number, format!("Invalid line: {}", line)).into()),
+                };
+
+                match entry.get_ref_mut_field(new_keyword) {
+                    Some(field) => *field = Some(value),
+                    None => return Err(ErrorKind::LocPOParse(line_number, format!("Unknown keyword: {}", new_keyword)).into()),
+                }
+                keyword = Some(new_keyword);
+            }
+        }
+
+        entry.add_to_table(&mut table, po.lines().count())?;
+        Ok(Self::from(table))
+    }
+
+    /// This function exports the Loc Table to the contents of a CSV file in the format CA's localisation tools use.
+    ///
+    /// That's a `key,text,tooltip` header followed by one line per row, with the tooltip flag as `true` or `false`,
+    /// and lines ending in `\r\n`. Values with commas, quotes or newlines are quoted, with their quotes doubled.
+    ///
+    /// If any row doesn't have the key, text and tooltip columns of a Loc Table, this returns an error with the number of that row.
+    pub fn to_ca_csv(&self) -> Result<String> {
+        let mut csv = CA_CSV_HEADER.join(",");
+        csv.push_str("\r\n");
+        for (index, row) in self.get_ref_table_data().iter().enumerate() {
+            let (key, text, tooltip) = Self::get_row_values(row, index + 1)?;
+
+            csv.push_str(&csv_quote(key));
+            csv.push(',');
+            csv.push_str(&csv_quote(text));
+            csv.push_str(if tooltip { ",true\r\n" } else { ",false\r\n" });
+        }
+        Ok(csv)
+    }
+
+    /// This function creates a new `Loc` from the contents of a CSV file in the format CA's localisation tools use.
+    ///
+    /// The file must start with the `key,text,tooltip` header, and every row must have these three columns. Check `to_ca_csv` for more info about the format.
+    pub fn from_ca_csv(csv: &str) -> Result<Loc> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(csv.as_bytes());
+
+        let mut records = reader.records();
+        match records.next() {
+            Some(header) => if header?.iter().ne(CA_CSV_HEADER.iter().copied()) {
+                return Err(ErrorKind::LocCaCsvParse(1, format!("The header is not \"{}\".", CA_CSV_HEADER.join(","))).into())
+            },
+            None => return Err(ErrorKind::LocCaCsvParse(1, "The file is empty.".to_owned()).into()),
+        }
+
+        let mut table = Table::new(&Self::get_default_definition());
+        for record in records {
+            let record = record?;
+            let line_number = record.position().map_or(0, |x| x.line() as usize + 1);
+            if record.len() != CA_CSV_HEADER.len() {
+                return Err(ErrorKind::LocCaCsvParse(line_number, format!("Expected {} columns, but found {}.", CA_CSV_HEADER.len(), record.len())).into())
+            }
+
+            let tooltip = parse_str(&record[2]).map_err(|_| ErrorKind::LocCaCsvParse(line_number, "Invalid tooltip flag.".to_owned()))?;
+            table.add_row(vec![DecodedData::StringU16(record[0].to_owned()), DecodedData::StringU16(record[1].to_owned()), DecodedData::Boolean(tooltip)])?;
+        }
+
+        Ok(Self::from(table))
+    }
+
+    /// This function returns the key, text and tooltip of the provided row, or an error with the provided row number if it doesn't have them.
+    fn get_row_values(row: &[DecodedData], row_number: usize) -> Result<(&str, &str, bool)> {
+        match row {
+            [DecodedData::StringU16(key), DecodedData::StringU16(text), DecodedData::Boolean(tooltip)] => Ok((key, text, *tooltip)),
+            _ => Err(ErrorKind::LocInvalidRow(row_number).into()),
+        }
+    }
+
+    /// This function returns the definition used for Loc Tables imported from formats without one, like PO or CA's CSV, with the columns of CA's CSV header.
+    fn get_default_definition() -> Definition {
+        let mut definition = Definition::new(1);
+        definition.fields.push(Field { name: CA_CSV_HEADER[0].to_owned(), field_type: FieldType::StringU16, is_key: true, ..Default::default() });
+        definition.fields.push(Field { name: CA_CSV_HEADER[1].to_owned(), field_type: FieldType::StringU16, ..Default::default() });
+        definition.fields.push(Field { name: CA_CSV_HEADER[2].to_owned(), field_type: FieldType::Boolean, ..Default::default() });
+        definition
+    }
+}
+
+/// This struct holds the data of a PO entry while we parse it.
+#[derive(Default)]
+struct POEntry {
+    tooltip: bool,
+    msgctxt: Option<String>,
+    msgid: Option<String>,
+    msgstr: Option<String>,
+}
+
+/// Implementation of `POEntry`.
+impl POEntry {
+
+    /// This function returns a mutable reference to the field of the entry corresponding to the provided keyword, if any.
+    fn get_ref_mut_field(&mut self, keyword: &str) -> Option<&mut Option<String>> {
+        match keyword {
+            "msgctxt" => Some(&mut self.msgctxt),
+            "msgid" => Some(&mut self.msgid),
+            "msgstr" => Some(&mut self.msgstr),
+            _ => None,
+        }
+    }
+
+    /// This function adds the entry to the provided table as a new row, if it's an entry with context.
+    ///
+    /// Empty entries and the header are skipped. Incomplete entries return an error.
+    fn add_to_table(&self, table: &mut Table, line_number: usize) -> Result<()> {
+        match self.msgctxt {
+            Some(ref key) => {
+                let text = match (&self.msgid, &self.msgstr) {
+                    (_, Some(msgstr)) if !msgstr.is_empty() => msgstr.to_owned(),
+                    (Some(msgid), _) => msgid.to_owned(),
+                    (None, _) => return Err(ErrorKind::LocPOParse(line_number, format!("Entry without msgid: {}", key)).into()),
+                };
+
+                table.add_row(vec![DecodedData::StringU16(key.to_owned()), DecodedData::StringU16(text), DecodedData::Boolean(self.tooltip)])
+            }
+            None => match self.msgid {
+                Some(ref msgid) if !msgid.is_empty() => Err(ErrorKind::LocPOParse(line_number, format!("Entry without msgctxt: {}", msgid)).into()),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// This function turns a string into a quoted PO string, escaping it and splitting it in multiple lines if it has newlines.
+fn po_quote(string: &str) -> String {
+    let escape = |string: &str| string.replace('\\', "\\\\").replace('"', "\\\"").replace('\t', "\\t").replace('\r', "\\r").replace('\n', "\\n");
+    if string.contains('\n') {
+        let mut quoted = String::from("\"\"");
+        for line in string.split_inclusive('\n') {
+            quoted.push_str("\n\"");
+            quoted.push_str(&escape(line));
+            quoted.push('"');
+        }
+        quoted
+    }
+    else { format!("\"{}\"", escape(string)) }
+}
+
+/// This function quotes a value for a CSV file, if it has commas, quotes or newlines, doubling his quotes.
+fn csv_quote(string: &str) -> String {
+    if string.contains(|x| x == ',' || x == '"' || x == '\n' || x == '\r') { format!("\"{}\"", string.replace('"', "\"\"")) }
+    else { string.to_owned() }
+}
+
+/// This function turns a quoted PO string back into a normal string, unescaping it.
+fn po_unquote(string: &str, line_number: usize) -> Result<String> {
+    if string.len() < 2 || !string.starts_with('"') || !string.ends_with('"') {
+        return Err(ErrorKind::LocPOParse(line_number, format!("Invalid quoted string: {}", string)).into())
+    }
+
+    let mut unquoted = String::with_capacity(string.len());
+    let mut chars = string[1..string.len() - 1].chars();
+    while let Some(character) = chars.next() {
+        if character == '\\' {
+            match chars.next() {
+                Some('n') => unquoted.push('\n'),
+                Some('t') => unquoted.push('\t'),
+                Some('r') => unquoted.push('\r'),
+                Some('\\') => unquoted.push('\\'),
+                Some('"') => unquoted.push('"'),
+                _ => return Err(ErrorKind::LocPOParse(line_number, format!("Invalid escape sequence in: {}", string)).into()),
+            }
+        }
+        else if character == '"' {
+            return Err(ErrorKind::LocPOParse(line_number, format!("Unescaped quote in: {}", string)).into())
+        }
+        else { unquoted.push(character); }
+    }
+    Ok(unquoted)
 }
 
 /// Implementation to create a `Loc` from a `Table`.
@@ -227,6 +594,7 @@ impl From<Table> for Loc {
     fn from(table: Table) -> Self {
         Self {
             table,
+            duplicate_keys: vec![],
         }
     }
 }
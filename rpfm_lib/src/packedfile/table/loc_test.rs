@@ -0,0 +1,269 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `Loc` module, just to make sure we don't break it... again...
+!*/
+
+use rpfm_error::ErrorKind;
+
+use crate::schema::*;
+use super::{DecodedData, Table};
+use super::loc::{Loc, LocStats};
+
+/// This function returns the definition of a Loc Table, for testing purpouses.
+fn get_test_definition() -> Definition {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU16, is_key: true, ..Default::default() });
+    definition.fields.push(Field { name: "text".to_owned(), field_type: FieldType::StringU16, ..Default::default() });
+    definition.fields.push(Field { name: "tooltip".to_owned(), field_type: FieldType::Boolean, ..Default::default() });
+    definition
+}
+
+/// This function returns a Loc Table with the provided rows, for testing purpouses.
+fn get_test_loc(rows: &[(&str, &str, bool)]) -> Loc {
+    let mut table = Table::new(&get_test_definition());
+    for (key, text, tooltip) in rows {
+        table.add_row(vec![DecodedData::StringU16((*key).to_owned()), DecodedData::StringU16((*text).to_owned()), DecodedData::Boolean(*tooltip)]).unwrap();
+    }
+    Loc::from(table)
+}
+
+/// Test to make sure a Loc Table survives a PO export/import cycle, including the stuff that needs escaping.
+#[test]
+fn test_po_round_trip() {
+    let loc = get_test_loc(&[
+        ("units_onscreen_name_wh_main_emp_inf_swordsmen", "Swordsmen", true),
+        ("multi_line", "First line.\nSecond line.\n\nFourth line, after an empty one.", false),
+        ("trailing_newline", "Text ending in a newline\n", true),
+        ("escapes", "\"Quoted\", with a \\ backslash,\ta tab and a \r carriage return.", false),
+        ("empty", "", false),
+        ("unicode", "Ñoño ünïcödé テキスト", true),
+    ]);
+
+    let po = loc.to_po().unwrap();
+    assert!(po.contains("msgctxt \"units_onscreen_name_wh_main_emp_inf_swordsmen\"\nmsgid \"Swordsmen\"\nmsgstr \"\"\n"));
+    assert!(po.contains("msgid \"\"\n\"First line.\\n\"\n\"Second line.\\n\"\n\"\\n\"\n\"Fourth line, after an empty one.\"\n"));
+    assert!(po.contains("msgid \"\\\"Quoted\\\", with a \\\\ backslash,\\ta tab and a \\r carriage return.\"\n"));
+
+    let loc_new = Loc::from_po(&po).unwrap();
+    assert_eq!(loc, loc_new);
+}
+
+/// Test to make sure translated entries replace the original text, and broken PO files are rejected.
+#[test]
+fn test_from_po() {
+    let po = "\
+# Translator comment.
+#. tooltip: true
+msgctxt \"key_1\"
+msgid \"Original\"
+msgstr \"Traducido\"
+#. tooltip: false
+msgctxt \"key_2\"
+msgid \"Not translated\"
+msgstr \"\"
+";
+    let loc = Loc::from_po(po).unwrap();
+    assert_eq!(loc, get_test_loc(&[("key_1", "Traducido", true), ("key_2", "Not translated", false)]));
+
+    let error = Loc::from_po("msgctxt \"key\"\nmsgid \"Bad \\q escape\"\n").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::LocPOParse(2, "Invalid escape sequence in: \"Bad \\q escape\"".to_owned()));
+
+    assert!(Loc::from_po("msgctxt \"key\"\nmsgid \"Unterminated\n").is_err());
+    assert!(Loc::from_po("msgctxt \"key\"\nmsgfoo \"Unknown\"\n").is_err());
+
+    // Entries without context are not skipped, unless they're the header.
+    let error = Loc::from_po("msgid \"\"\nmsgstr \"\"\n\nmsgid \"No context\"\nmsgstr \"\"\n").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::LocPOParse(5, "Entry without msgctxt: No context".to_owned()));
+}
+
+/// Test to make sure rows without the columns of a Loc Table are reported when exporting them to PO or CA's CSV format, instead of silently skipped.
+#[test]
+fn test_export_invalid_row() {
+    let mut definition = get_test_definition();
+    definition.fields[2].field_type = FieldType::Integer;
+    let mut table = Table::new(&definition);
+    table.add_row(vec![DecodedData::StringU16("key".to_owned()), DecodedData::StringU16("text".to_owned()), DecodedData::Integer(1)]).unwrap();
+    let loc = Loc::from(table);
+    assert_eq!(loc.to_po().unwrap_err().kind(), &ErrorKind::LocInvalidRow(1));
+    assert_eq!(loc.to_ca_csv().unwrap_err().kind(), &ErrorKind::LocInvalidRow(1));
+}
+
+/// Test to make sure data left after the last row of a Loc Table is reported, instead of silently ignored.
+#[test]
+fn test_read_extra_data() {
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::Loc(vec![get_test_definition()]));
+
+    let loc = get_test_loc(&[("key", "text", false)]);
+    let mut data = loc.save().unwrap();
+    assert_eq!(Loc::read(&data, &schema, false).unwrap(), loc);
+
+    let consumed = data.len();
+    data.push(0);
+    assert_eq!(Loc::read(&data, &schema, false).unwrap_err().kind(), &ErrorKind::PackedFileHasExtraData(consumed, consumed + 1));
+}
+
+/// Test to make sure removing entries by key only removes the first one with that key, and keeps the order of the rest.
+#[test]
+fn test_remove() {
+    let mut loc = get_test_loc(&[("key_1", "text_1", false), ("key_2", "text_2", true), ("key_3", "text_3", false), ("key_2", "text_2_again", false)]);
+    assert!(loc.remove("key_2"));
+    assert_eq!(loc, get_test_loc(&[("key_1", "text_1", false), ("key_3", "text_3", false), ("key_2", "text_2_again", false)]));
+
+    assert!(!loc.remove("missing_key"));
+    assert!(!loc.remove("text_1"));
+    assert_eq!(loc.get_entry_count(), 3);
+
+    assert!(loc.remove("key_2"));
+    assert!(!loc.remove("key_2"));
+    assert_eq!(loc, get_test_loc(&[("key_1", "text_1", false), ("key_3", "text_3", false)]));
+}
+
+/// Test to make sure retaining entries keeps only the ones the predicate accepts, in their original order.
+#[test]
+fn test_retain() {
+    let mut loc = get_test_loc(&[
+        ("units_onscreen_name_a", "A", false),
+        ("land_units_onscreen_name_b", "B", false),
+        ("units_onscreen_name_c", "", true),
+        ("units_onscreen_name_d", "D", false),
+    ]);
+
+    loc.retain(|key, _| !key.starts_with("units_"));
+    assert_eq!(loc, get_test_loc(&[("land_units_onscreen_name_b", "B", false)]));
+
+    let mut loc = get_test_loc(&[("key_1", "", false), ("key_2", "text", false), ("key_3", "", false)]);
+    loc.retain(|_, text| !text.is_empty());
+    assert_eq!(loc, get_test_loc(&[("key_2", "text", false)]));
+
+    loc.retain(|_, _| false);
+    assert_eq!(loc.get_entry_count(), 0);
+}
+
+/// Test to make sure sorting by key gives the same order no matter the original order of the entries.
+#[test]
+fn test_sort_by_key() {
+    let sorted = get_test_loc(&[("Key_b", "B", false), ("key_a", "A", false), ("key_b", "first", false), ("key_b", "second", true), ("key_c", "C", false)]);
+    assert!(sorted.is_sorted());
+
+    let mut loc = get_test_loc(&[("key_c", "C", false), ("key_b", "first", false), ("Key_b", "B", false), ("key_a", "A", false), ("key_b", "second", true)]);
+    assert!(!loc.is_sorted());
+    loc.sort_by_key();
+    assert!(loc.is_sorted());
+    assert_eq!(loc, sorted);
+    assert_eq!(loc.save().unwrap(), sorted.save().unwrap());
+
+    // Sorting again doesn't change anything.
+    loc.sort_by_key();
+    assert_eq!(loc, sorted);
+
+    assert!(get_test_loc(&[]).is_sorted());
+}
+
+/// Test to make sure the stats of a Loc Table count the untranslated entries and the tooltips properly.
+#[test]
+fn test_stats() {
+    let loc = get_test_loc(&[("key_a", "A", false), ("key_b", "", false), ("key_c", "C", true), ("key_d", "", true), ("key_e", " ", false)]);
+    assert_eq!(loc.stats(), LocStats { entries: 5, empty_values: 2, tooltips: 2 });
+    assert_eq!(get_test_loc(&[]).stats(), LocStats::default());
+}
+
+/// Test to make sure the length of a Loc Table follows the entries we add and remove.
+#[test]
+fn test_len() {
+    let mut loc = get_test_loc(&[]);
+    assert_eq!(loc.len(), 0);
+    assert!(loc.is_empty());
+
+    let mut loc_data = get_test_loc(&[("key_a", "A", false), ("key_b", "B", true), ("key_c", "C", false)]).get_table_data();
+    loc.set_table_data(&loc_data).unwrap();
+    assert_eq!(loc.len(), 3);
+    assert!(!loc.is_empty());
+
+    assert!(loc.remove("key_b"));
+    assert_eq!(loc.len(), 2);
+
+    loc_data.clear();
+    loc.set_table_data(&loc_data).unwrap();
+    assert_eq!(loc.len(), 0);
+    assert!(loc.is_empty());
+}
+
+/// Test to make sure truncated Loc Tables are rejected, reporting how many entries they say they have and how many they really have.
+#[test]
+fn test_read_truncated() {
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::Loc(vec![get_test_definition()]));
+
+    let loc = get_test_loc(&[("key_1", "text", false), ("key_2", "text", true), ("key_3", "text", false)]);
+    let data = loc.save().unwrap();
+
+    // Each entry is 2 + 10 bytes of key, 2 + 8 bytes of text and a boolean.
+    let entry_size = 23;
+    assert_eq!(Loc::read(&data[..data.len() - entry_size], &schema, false).unwrap_err().kind(), &ErrorKind::LocEntryCountMismatch { declared: 3, found: 2 });
+    assert_eq!(Loc::read(&data[..data.len() - entry_size * 2 - 1], &schema, false).unwrap_err().kind(), &ErrorKind::LocEntryCountMismatch { declared: 3, found: 0 });
+
+    // Entries cut in the middle of a cell are still broken, but not because of the count.
+    assert!(!matches!(Loc::read(&data[..data.len() - 5], &schema, false).unwrap_err().kind(), ErrorKind::LocEntryCountMismatch { .. }));
+    assert!(matches!(Loc::read(&data[..data.len() - entry_size], &schema, true).unwrap_err().kind(), ErrorKind::TableIncompleteError(_, _)));
+}
+
+/// Test to make sure Loc Tables with duplicated keys are read keeping all their entries, and the duplicates are only rejected when validating them.
+#[test]
+fn test_read_duplicate_keys() {
+    let mut schema = Schema::default();
+    schema.add_versioned_file(&VersionedFile::Loc(vec![get_test_definition()]));
+
+    let entries = [("a", "first", false), ("b", "text", false), ("a", "second", true), ("c", "text", false), ("a", "third", false), ("c", "other", false)];
+    let data = get_test_loc(&entries).save().unwrap();
+    let loc = Loc::read(&data, &schema, false).unwrap();
+    assert_eq!(loc.get_ref_table_data(), get_test_loc(&entries).get_ref_table_data());
+    assert_eq!(loc.duplicate_keys(), &["a".to_owned(), "c".to_owned()]);
+    assert_eq!(loc.check_duplicate_keys().unwrap_err().kind(), &ErrorKind::LocDuplicateKey { key: "a".to_owned() });
+    assert_eq!(loc.save().unwrap(), data);
+
+    let data = get_test_loc(&[("a", "text", false), ("b", "text", false)]).save().unwrap();
+    let loc = Loc::read(&data, &schema, false).unwrap();
+    assert!(loc.duplicate_keys().is_empty());
+    assert!(loc.check_duplicate_keys().is_ok());
+}
+
+/// Test to make sure a Loc Table survives an export/import cycle through CA's CSV format, including values that need quoting.
+#[test]
+fn test_ca_csv_round_trip() {
+    let loc = get_test_loc(&[
+        ("units_onscreen_name_wh_main_emp_inf_swordsmen", "Swordsmen", true),
+        ("commas", "One, two, and three.", false),
+        ("quotes", "He said \"Hold the line!\", and they held it.", true),
+        ("multi_line", "First line.\r\nSecond line.\nThird line.", false),
+        ("empty", "", false),
+        ("unicode", "Ñoño ünïcödé テキスト", true),
+    ]);
+
+    let csv = loc.to_ca_csv().unwrap();
+    assert!(csv.starts_with("key,text,tooltip\r\nunits_onscreen_name_wh_main_emp_inf_swordsmen,Swordsmen,true\r\n"));
+    assert!(csv.contains("\r\ncommas,\"One, two, and three.\",false\r\n"));
+    assert!(csv.contains("\r\nquotes,\"He said \"\"Hold the line!\"\", and they held it.\",true\r\n"));
+    assert!(csv.contains("\r\nempty,,false\r\n"));
+
+    let loc_new = Loc::from_ca_csv(&csv).unwrap();
+    assert_eq!(loc_new.get_ref_table_data(), loc.get_ref_table_data());
+    assert_eq!(loc_new.get_ref_definition().fields.iter().map(|x| &*x.name).collect::<Vec<&str>>(), vec!["key", "text", "tooltip"]);
+    assert_eq!(loc_new.to_ca_csv().unwrap(), csv);
+
+    // Files without the right header, with missing columns or with broken tooltip flags are rejected.
+    assert_eq!(Loc::from_ca_csv("").unwrap_err().kind(), &ErrorKind::LocCaCsvParse(1, "The file is empty.".to_owned()));
+    assert_eq!(Loc::from_ca_csv("key,value\r\n").unwrap_err().kind(), &ErrorKind::LocCaCsvParse(1, "The header is not \"key,text,tooltip\".".to_owned()));
+    assert_eq!(Loc::from_ca_csv("key,text,tooltip\r\nkey,text\r\n").unwrap_err().kind(), &ErrorKind::LocCaCsvParse(2, "Expected 3 columns, but found 2.".to_owned()));
+    assert_eq!(Loc::from_ca_csv("key,text,tooltip\r\na,b,true\r\nkey,text,maybe\r\n").unwrap_err().kind(), &ErrorKind::LocCaCsvParse(3, "Invalid tooltip flag.".to_owned()));
+    assert_eq!(Loc::from_ca_csv("key,text,tooltip\r\na,\"b\r\nc\",true\r\nkey,text,maybe\r\n").unwrap_err().kind(), &ErrorKind::LocCaCsvParse(4, "Invalid tooltip flag.".to_owned()));
+}
@@ -17,8 +17,12 @@ This module contains the struct `Table`, used to manage the decoded data of a ta
 use bincode::serialize;
 use csv::{QuoteStyle, ReaderBuilder, WriterBuilder};
 use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
 
 use std::{fmt, fmt::Display};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::PathBuf;
@@ -29,9 +33,22 @@ use crate::assembly_kit::table_data::RawTable;
 use crate::common::{decoder::Decoder, encoder::Encoder};
 use crate::schema::*;
 
+pub mod csv_table;
 pub mod db;
 pub mod loc;
 
+#[cfg(test)]
+mod csv_table_test;
+
+#[cfg(test)]
+mod db_test;
+
+#[cfg(test)]
+mod loc_test;
+
+#[cfg(test)]
+mod table_test;
+
 //---------------------------------------------------------------------------//
 //                              Enum & Structs
 //---------------------------------------------------------------------------//
@@ -49,10 +66,38 @@ pub struct Table {
     entries: Vec<Vec<DecodedData>>,
 }
 
+/// This struct represents a cell changed in different ways by both sides of a three-way merge of tables.
+///
+/// A side with `None` as value is a side that removed the row the cell is in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeConflict {
+
+    /// Index of the row in the merged table.
+    pub row: usize,
+
+    /// Name of the column of the cell.
+    pub column: String,
+
+    /// Value of the cell in the base table. `None` if the row was added by both sides.
+    pub base: Option<DecodedData>,
+
+    /// Value of the cell in our table. This is the value the merged table ends up with, if we didn't remove the row.
+    pub mine: Option<DecodedData>,
+
+    /// Value of the cell in their table.
+    pub theirs: Option<DecodedData>,
+}
+
 /// This enum is used to store different types of data in a unified way. Used, for example, to store the data from each field in a DB Table.
 ///
 /// NOTE: `Sequence` it's a recursive type. A Sequence/List means you got a repeated sequence of fields
 /// inside a single field. Used, for example, in certain model tables.
+///
+/// NOTE: Optional strings are `None` when their presence flag is not set, so an absent value and a present but empty one
+/// are saved back as they were read.
+///
+/// NOTE: The functions that build, check, show, parse and encode `DecodedData` must not use wildcard arms in their matches,
+/// so adding a new variant (or a new `FieldType`) doesn't compile until all of them support it. The same goes for `test_all_variants`.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum DecodedData {
     Boolean(bool),
@@ -61,8 +106,8 @@ pub enum DecodedData {
     LongInteger(i64),
     StringU8(String),
     StringU16(String),
-    OptionalStringU8(String),
-    OptionalStringU16(String),
+    OptionalStringU8(Option<String>),
+    OptionalStringU16(Option<String>),
     Sequence(Table)
 }
 
@@ -117,8 +162,8 @@ impl DecodedData {
             FieldType::LongInteger => DecodedData::LongInteger(0),
             FieldType::StringU8 => DecodedData::StringU8("".to_owned()),
             FieldType::StringU16 => DecodedData::StringU16("".to_owned()),
-            FieldType::OptionalStringU8 => DecodedData::OptionalStringU8("".to_owned()),
-            FieldType::OptionalStringU16 => DecodedData::OptionalStringU16("".to_owned()),
+            FieldType::OptionalStringU8 => DecodedData::OptionalStringU8(None),
+            FieldType::OptionalStringU16 => DecodedData::OptionalStringU16(None),
             FieldType::Sequence(definition) => DecodedData::Sequence(Table::new(definition)),
         }
     }
@@ -134,7 +179,105 @@ impl DecodedData {
             DecodedData::StringU16(_) => field_type == FieldType::StringU16,
             DecodedData::OptionalStringU8(_) => field_type == FieldType::OptionalStringU8,
             DecodedData::OptionalStringU16(_) => field_type == FieldType::OptionalStringU16,
-            DecodedData::Sequence(_) => if let FieldType::Sequence(_) = field_type { true } else { false },
+            DecodedData::Sequence(_) => matches!(field_type, FieldType::Sequence(_)),
+        }
+    }
+
+    /// This function returns the value of the provided `DecodedData` as a string, to show it to the user.
+    ///
+    /// Numbers are formatted the same way independently of the locale, and booleans are `true` or `false`.
+    /// The result can be parsed back to the same value with `from_str_typed`, except for sequences, which are shown as their entry count,
+    /// and absent optional strings, which are shown empty, so they're parsed back as present but empty values.
+    pub fn to_display_string(&self) -> String {
+        match self {
+            DecodedData::Boolean(data) => data.to_string(),
+            DecodedData::Float(data) => data.to_string(),
+            DecodedData::Integer(data) => data.to_string(),
+            DecodedData::LongInteger(data) => data.to_string(),
+            DecodedData::StringU8(data) |
+            DecodedData::StringU16(data) => data.to_owned(),
+            DecodedData::OptionalStringU8(data) |
+            DecodedData::OptionalStringU16(data) => data.clone().unwrap_or_default(),
+            DecodedData::Sequence(data) => format!("Sequence of {} entries", data.get_entry_count()),
+        }
+    }
+
+    /// This function parses the provided string as a `DecodedData` of the provided type. The reverse of `to_display_string`.
+    ///
+    /// Booleans accept `true`, `false`, `1` and `0`, in any case. Empty strings are only valid for string types,
+    /// and they're parsed as present but empty values for optional strings, as absent values are only meant to be set
+    /// explicitly, with `DecodedData::default`. Sequences cannot be parsed.
+    pub fn from_str_typed(value: &str, field_type: &FieldType) -> Result<Self> {
+        let error = || Error::from(ErrorKind::TableCellParseError(value.to_owned(), field_type.to_string()));
+        match field_type {
+            FieldType::Boolean => match &*value.to_lowercase() {
+                "true" | "1" => Ok(DecodedData::Boolean(true)),
+                "false" | "0" => Ok(DecodedData::Boolean(false)),
+                _ => Err(error()),
+            },
+            FieldType::Float => Ok(DecodedData::Float(value.parse::<f32>().map_err(|_| error())?)),
+            FieldType::Integer => Ok(DecodedData::Integer(value.parse::<i32>().map_err(|_| error())?)),
+            FieldType::LongInteger => Ok(DecodedData::LongInteger(value.parse::<i64>().map_err(|_| error())?)),
+            FieldType::StringU8 => Ok(DecodedData::StringU8(value.to_owned())),
+            FieldType::StringU16 => Ok(DecodedData::StringU16(value.to_owned())),
+            FieldType::OptionalStringU8 => Ok(DecodedData::OptionalStringU8(Some(value.to_owned()))),
+            FieldType::OptionalStringU16 => Ok(DecodedData::OptionalStringU16(Some(value.to_owned()))),
+            FieldType::Sequence(_) => Err(error()),
+        }
+    }
+
+    /// This function returns the value of the provided `DecodedData` as a JSON value.
+    ///
+    /// Booleans and numbers are kept as JSON booleans and numbers, absent optional strings are `null`,
+    /// and sequences are arrays of rows, like the ones returned by `Table::rows_to_json`.
+    pub fn to_json(&self) -> Value {
+        match self {
+            DecodedData::Boolean(data) => Value::from(*data),
+            DecodedData::Float(data) => Value::from(f64::from(*data)),
+            DecodedData::Integer(data) => Value::from(*data),
+            DecodedData::LongInteger(data) => Value::from(*data),
+            DecodedData::StringU8(data) |
+            DecodedData::StringU16(data) => Value::from(data.to_owned()),
+            DecodedData::OptionalStringU8(data) |
+            DecodedData::OptionalStringU16(data) => data.clone().map_or(Value::Null, Value::from),
+            DecodedData::Sequence(data) => data.rows_to_json(),
+        }
+    }
+
+    /// This function parses the provided JSON value as a `DecodedData` of the provided type. The reverse of `to_json`.
+    ///
+    /// If the value is not of the JSON type used for the provided type, or it's a number that doesn't fit in it, this returns `None`.
+    /// For sequences, the error of the rows that couldn't be parsed is returned instead.
+    pub fn from_json_typed(value: &Value, field_type: &FieldType) -> Result<Option<Self>> {
+        let data = match field_type {
+            FieldType::Boolean => value.as_bool().map(DecodedData::Boolean),
+            FieldType::Float => value.as_f64().map(|x| DecodedData::Float(x as f32)),
+            FieldType::Integer => value.as_i64().and_then(|x| i32::try_from(x).ok()).map(DecodedData::Integer),
+            FieldType::LongInteger => value.as_i64().map(DecodedData::LongInteger),
+            FieldType::StringU8 => value.as_str().map(|x| DecodedData::StringU8(x.to_owned())),
+            FieldType::StringU16 => value.as_str().map(|x| DecodedData::StringU16(x.to_owned())),
+            FieldType::OptionalStringU8 => if value.is_null() { Some(DecodedData::OptionalStringU8(None)) } else { value.as_str().map(|x| DecodedData::OptionalStringU8(Some(x.to_owned()))) },
+            FieldType::OptionalStringU16 => if value.is_null() { Some(DecodedData::OptionalStringU16(None)) } else { value.as_str().map(|x| DecodedData::OptionalStringU16(Some(x.to_owned()))) },
+            FieldType::Sequence(definition) => if value.is_array() { Some(DecodedData::Sequence(Table::rows_from_json(definition, value)?)) } else { None },
+        };
+        Ok(data)
+    }
+
+    /// This function compares the values of two `DecodedData`, so they can be sorted.
+    ///
+    /// Numbers are compared by value, strings lexically, and booleans with `false` first. Values of different types,
+    /// sequences and NaN floats are considered equal, so they keep their relative order in stable sorts.
+    pub fn cmp_value(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (DecodedData::Boolean(x), DecodedData::Boolean(y)) => x.cmp(y),
+            (DecodedData::Float(x), DecodedData::Float(y)) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+            (DecodedData::Integer(x), DecodedData::Integer(y)) => x.cmp(y),
+            (DecodedData::LongInteger(x), DecodedData::LongInteger(y)) => x.cmp(y),
+            (DecodedData::StringU8(x), DecodedData::StringU8(y)) |
+            (DecodedData::StringU16(x), DecodedData::StringU16(y)) => x.cmp(y),
+            (DecodedData::OptionalStringU8(x), DecodedData::OptionalStringU8(y)) |
+            (DecodedData::OptionalStringU16(x), DecodedData::OptionalStringU16(y)) => x.cmp(y),
+            _ => Ordering::Equal,
         }
     }
 }
@@ -250,6 +393,392 @@ impl Table {
         Ok(())
     }
 
+    /// This function checks that every row of this table has the amount of cells his definition expects, and that each cell is of the expected type.
+    ///
+    /// If the table is not valid, it returns an error with the first (1-based) row and column with problems.
+    pub fn validate(&self) -> Result<()> {
+        let fields = &self.definition.fields;
+        for (row_index, row) in self.entries.iter().enumerate() {
+            if row.len() != fields.len() { return Err(ErrorKind::TableInvalidRowFieldCount(row_index as u32 + 1, fields.len() as u32, row.len() as u32).into()) }
+            for (column_index, cell) in row.iter().enumerate() {
+                if !DecodedData::is_field_type_correct(cell, fields[column_index].field_type.clone()) {
+                    return Err(ErrorKind::TableInvalidCellFieldType(row_index as u32 + 1, column_index as u32 + 1, format!("{}", fields[column_index].field_type), format!("{}", cell)).into())
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// This function returns a new row for this table, with each cell set to the default value of his field.
+    ///
+    /// If the field has a default value in the schema, that value is used. Otherwise, we use the default for his type.
+    pub fn get_new_row(&self) -> Vec<DecodedData> {
+        self.definition.fields.iter().map(Self::get_default_cell).collect()
+    }
+
+    /// This function returns the default value of a cell of the provided field.
+    ///
+    /// If the field has a default value in the schema, that value is used. Otherwise, we use the default for his type.
+    fn get_default_cell(field: &Field) -> DecodedData {
+        match field.default_value {
+            Some(ref default_value) => match field.field_type {
+                FieldType::Boolean => DecodedData::Boolean(default_value.to_lowercase() == "true"),
+                FieldType::Float => DecodedData::Float(default_value.parse::<f32>().unwrap_or_default()),
+                FieldType::Integer => DecodedData::Integer(default_value.parse::<i32>().unwrap_or_default()),
+                FieldType::LongInteger => DecodedData::LongInteger(default_value.parse::<i64>().unwrap_or_default()),
+                FieldType::StringU8 => DecodedData::StringU8(default_value.to_owned()),
+                FieldType::StringU16 => DecodedData::StringU16(default_value.to_owned()),
+                FieldType::OptionalStringU8 => DecodedData::OptionalStringU8(Some(default_value.to_owned())),
+                FieldType::OptionalStringU16 => DecodedData::OptionalStringU16(Some(default_value.to_owned())),
+                FieldType::Sequence(ref definition) => DecodedData::Sequence(Table::new(definition)),
+            }
+            None => DecodedData::default(&field.field_type),
+        }
+    }
+
+    /// This function adds a new column at the end of this table, with the default value of the field in each row.
+    ///
+    /// If there is already a column with the same name, it returns an error.
+    pub fn add_column(&mut self, field: &Field) -> Result<()> {
+        if self.get_column_index(&field.name).is_ok() { return Err(ErrorKind::TableColumnAlreadyExists(field.name.to_owned()).into()) }
+
+        let cell = Self::get_default_cell(field);
+        self.entries.iter_mut().for_each(|row| row.push(cell.clone()));
+        self.definition.fields.push(field.clone());
+        Ok(())
+    }
+
+    /// This function removes the provided column from this table, with his cell in each row.
+    ///
+    /// If the column doesn't exist, it returns an error.
+    pub fn remove_column(&mut self, column: &str) -> Result<()> {
+        let column_index = self.get_column_index(column)?;
+        self.entries.iter_mut().filter(|row| column_index < row.len()).for_each(|row| { row.remove(column_index); });
+        self.definition.fields.remove(column_index);
+        Ok(())
+    }
+
+    /// This function changes the name of the provided column of this table.
+    ///
+    /// If the column doesn't exist, or there is already another column with the new name, it returns an error.
+    pub fn rename_column(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        let column_index = self.get_column_index(old_name)?;
+        if old_name != new_name && self.get_column_index(new_name).is_ok() { return Err(ErrorKind::TableColumnAlreadyExists(new_name.to_owned()).into()) }
+
+        self.definition.fields[column_index].name = new_name.to_owned();
+        Ok(())
+    }
+
+    /// This function returns a copy of the cell at the provided row and column, along with its position, so it can be restored later with `restore_cell`.
+    ///
+    /// If there is no cell at that position, it returns an error.
+    pub fn snapshot_cell(&self, row: usize, column: usize) -> Result<(usize, usize, DecodedData)> {
+        let cells = self.entries.get(row).ok_or_else(|| Error::from(ErrorKind::TableRowIndexOutOfBounds(row, self.entries.len())))?;
+        let cell = cells.get(column).ok_or_else(|| Error::from(ErrorKind::TableColumnIndexOutOfBounds(column, cells.len())))?;
+        Ok((row, column, cell.clone()))
+    }
+
+    /// This function puts back the cell of a snapshot taken with `snapshot_cell` in its position.
+    ///
+    /// This can (and will) fail if there is no cell at that position anymore, or if the cell is not of the type of its column.
+    pub fn restore_cell(&mut self, snapshot: (usize, usize, DecodedData)) -> Result<()> {
+        let (row, column, cell) = snapshot;
+        let row_count = self.entries.len();
+        let cells = self.entries.get_mut(row).ok_or_else(|| Error::from(ErrorKind::TableRowIndexOutOfBounds(row, row_count)))?;
+        let column_count = cells.len();
+        let old_cell = cells.get_mut(column).ok_or_else(|| Error::from(ErrorKind::TableColumnIndexOutOfBounds(column, column_count)))?;
+
+        let field_type = &self.definition.fields[column].field_type;
+        if !DecodedData::is_field_type_correct(&cell, field_type.clone()) {
+            return Err(ErrorKind::TableWrongFieldType(format!("{}", cell), format!("{}", field_type)).into())
+        }
+
+        *old_cell = cell;
+        Ok(())
+    }
+
+    /// This function adds a new row at the end of this table.
+    ///
+    /// This can (and will) fail if the row is not of the format defined by the definition of the table.
+    pub fn add_row(&mut self, row: Vec<DecodedData>) -> Result<()> {
+        self.insert_row(self.entries.len(), row)
+    }
+
+    /// This function inserts a new row at the provided position of this table, moving the rows after it down.
+    ///
+    /// This can (and will) fail if the row is not of the format defined by the definition of the table, or if the position is after the end of the table.
+    pub fn insert_row(&mut self, index: usize, row: Vec<DecodedData>) -> Result<()> {
+        if index > self.entries.len() { return Err(ErrorKind::TableRowIndexOutOfBounds(index, self.entries.len()).into()) }
+
+        // First, we need to make sure the row is exactly what we expect.
+        if row.len() != self.definition.fields.len() { return Err(ErrorKind::TableRowWrongFieldCount(self.definition.fields.len() as u32, row.len() as u32).into()) }
+        for (index, cell) in row.iter().enumerate() {
+
+            // Next, we need to ensure each cell is of the type we expected.
+            if !DecodedData::is_field_type_correct(cell, self.definition.fields[index].field_type.clone()) {
+                return Err(ErrorKind::TableWrongFieldType(format!("{}", cell), format!("{}", self.definition.fields[index].field_type)).into())
+            }
+        }
+
+        self.entries.insert(index, row);
+        Ok(())
+    }
+
+    /// This function removes the row at the provided position of this table, returning it.
+    ///
+    /// If there is no row at that position, it returns an error.
+    pub fn remove_row(&mut self, index: usize) -> Result<Vec<DecodedData>> {
+        if index >= self.entries.len() { return Err(ErrorKind::TableRowIndexOutOfBounds(index, self.entries.len()).into()) }
+        Ok(self.entries.remove(index))
+    }
+
+    /// This function sorts the rows of this table by the values of the provided column.
+    ///
+    /// The sort is stable, so rows with the same value in the column keep their current order. If the column doesn't exist, it returns an error.
+    pub fn sort_by_column(&mut self, column: &str, ascending: bool) -> Result<()> {
+        let column_index = self.get_column_index(column)?;
+        self.entries.sort_by(|x, y| {
+            let (x, y) = if ascending { (x, y) } else { (y, x) };
+            match (x.get(column_index), y.get(column_index)) {
+                (Some(x), Some(y)) => x.cmp_value(y),
+                _ => Ordering::Equal,
+            }
+        });
+        Ok(())
+    }
+
+    /// This function returns the distinct values of the provided column, as strings, in the order they first appear in the table.
+    ///
+    /// If the column doesn't exist, it returns an error.
+    pub fn column_unique_values(&self, column: &str) -> Result<Vec<String>> {
+        let column_index = self.get_column_index(column)?;
+        let mut values_found = HashSet::new();
+        Ok(self.entries.iter()
+            .filter_map(|row| row.get(column_index))
+            .map(|cell| cell.to_display_string())
+            .filter(|value| values_found.insert(value.to_owned()))
+            .collect())
+    }
+
+    /// This function merges the changes both, `mine` and `theirs`, made over the `base` table, returning the merged table and the cells where they conflict.
+    ///
+    /// Rows are matched by their key columns (or by the entire row, if the table has no keys). Changes made by only one side are applied.
+    /// When both sides changed the same cell in different ways, or one side removed a row the other changed, our version is kept
+    /// and the cells are reported as conflicts. All three tables must have the same definition, or this returns an error.
+    pub fn merge_three_way(base: &Self, mine: &Self, theirs: &Self) -> Result<(Self, Vec<MergeConflict>)> {
+        if base.definition != mine.definition || base.definition != theirs.definition {
+            return Err(ErrorKind::TableMergeIncompatibleDefinitions.into())
+        }
+
+        let base_rows = base.get_keyed_rows();
+        let mine_rows = mine.get_keyed_rows();
+        let theirs_rows = theirs.get_keyed_rows();
+        let mine_map = mine_rows.iter().cloned().collect::<HashMap<_, _>>();
+        let theirs_map = theirs_rows.iter().cloned().collect::<HashMap<_, _>>();
+        let base_map = base_rows.iter().cloned().collect::<HashMap<_, _>>();
+
+        let mut merged = Self::new(&base.definition);
+        let mut conflicts = vec![];
+        for (key, base_row) in &base_rows {
+            match (mine_map.get(key), theirs_map.get(key)) {
+                (None, None) => {},
+                (Some(row), None) | (None, Some(row)) => {
+
+                    // A row removed by one side is only kept if the other side changed it.
+                    if row != base_row {
+                        let is_mine = mine_map.contains_key(key);
+                        for (column, (cell, base_cell)) in row.iter().zip(base_row.iter()).enumerate() {
+                            if cell != base_cell {
+                                conflicts.push(MergeConflict {
+                                    row: merged.entries.len(),
+                                    column: base.definition.fields[column].name.to_owned(),
+                                    base: Some(base_cell.clone()),
+                                    mine: if is_mine { Some(cell.clone()) } else { None },
+                                    theirs: if is_mine { None } else { Some(cell.clone()) },
+                                });
+                            }
+                        }
+                        merged.entries.push(row.to_vec());
+                    }
+                }
+                (Some(mine_row), Some(theirs_row)) => {
+                    let row = merged.merge_rows(Some(base_row), mine_row, theirs_row, &mut conflicts);
+                    merged.entries.push(row);
+                }
+            }
+        }
+
+        // Rows added by one side are always kept. If both added the same row, we merge them like any other row.
+        for (key, mine_row) in mine_rows.iter().filter(|(key, _)| !base_map.contains_key(key)) {
+            let row = match theirs_map.get(key) {
+                Some(theirs_row) => merged.merge_rows(None, mine_row, theirs_row, &mut conflicts),
+                None => mine_row.to_vec(),
+            };
+            merged.entries.push(row);
+        }
+
+        for (_, theirs_row) in theirs_rows.iter().filter(|(key, _)| !base_map.contains_key(key) && !mine_map.contains_key(key)) {
+            merged.entries.push(theirs_row.to_vec());
+        }
+
+        Ok((merged, conflicts))
+    }
+
+    /// This function returns the rows of this table, with the key used to match them with the rows of other tables in a merge.
+    ///
+    /// The key is made of the values of the key columns (or of all the columns, if there are no key columns), and the amount of times
+    /// a row with the same values was found before it, so rows with duplicated keys are not mixed.
+    fn get_keyed_rows(&self) -> Vec<((Vec<String>, usize), &[DecodedData])> {
+        let key_columns = self.definition.fields.iter()
+            .enumerate()
+            .filter(|(_, field)| field.is_key)
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+
+        let mut times_found: HashMap<Vec<String>, usize> = HashMap::new();
+        self.entries.iter().map(|row| {
+            let key = if key_columns.is_empty() { row.iter().map(|x| x.to_display_string()).collect::<Vec<String>>() }
+            else { key_columns.iter().filter_map(|x| row.get(*x)).map(|x| x.to_display_string()).collect::<Vec<String>>() };
+
+            let times = times_found.entry(key.to_vec()).or_insert(0);
+            *times += 1;
+            ((key, *times - 1), &row[..])
+        }).collect()
+    }
+
+    /// This function merges the cells of a row changed by both sides of a three-way merge, adding the conflicts found to the provided list.
+    ///
+    /// The conflicts are reported as being in the next row of this table, so this has to be called right before adding the merged row to it.
+    fn merge_rows(&self, base: Option<&[DecodedData]>, mine: &[DecodedData], theirs: &[DecodedData], conflicts: &mut Vec<MergeConflict>) -> Vec<DecodedData> {
+        mine.iter().zip(theirs.iter()).enumerate().map(|(column, (mine_cell, theirs_cell))| {
+            let base_cell = base.and_then(|x| x.get(column));
+            if mine_cell == theirs_cell || Some(theirs_cell) == base_cell { mine_cell.clone() }
+            else if Some(mine_cell) == base_cell { theirs_cell.clone() }
+            else {
+                conflicts.push(MergeConflict {
+                    row: self.entries.len(),
+                    column: self.definition.fields[column].name.to_owned(),
+                    base: base_cell.cloned(),
+                    mine: Some(mine_cell.clone()),
+                    theirs: Some(theirs_cell.clone()),
+                });
+                mine_cell.clone()
+            }
+        }).collect()
+    }
+
+    /// This function returns the indexes of the rows whose value in the provided column is not in the referenced column of the referenced table.
+    ///
+    /// Empty values mean there is no reference, so they're never reported. If any of the columns doesn't exist, it returns an error.
+    pub fn check_references(&self, column: &str, referenced: &Self, ref_column: &str) -> Result<Vec<usize>> {
+        let column_index = self.get_column_index(column)?;
+        let referenced_values = referenced.column_unique_values(ref_column)?.into_iter().collect::<HashSet<String>>();
+        Ok(self.entries.iter()
+            .enumerate()
+            .filter_map(|(index, row)| row.get(column_index).map(|cell| (index, cell.to_display_string())))
+            .filter(|(_, value)| !value.is_empty() && !referenced_values.contains(value))
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// This function serializes the rows at the provided positions of this table to a TSV string, so they can be copied to the clipboard.
+    ///
+    /// The first line contains the name and type of each column, as `name:type`, so we can check the rows are compatible before pasting them.
+    /// If any of the positions is not in the table, it returns an error.
+    pub fn serialize_rows(&self, indices: &[usize]) -> Result<String> {
+        let mut writer = WriterBuilder::new()
+            .delimiter(b'\t')
+            .quote_style(QuoteStyle::Necessary)
+            .has_headers(false)
+            .from_writer(vec![]);
+
+        writer.write_record(self.definition.fields.iter().map(|x| format!("{}:{}", x.name, x.field_type)))?;
+        for index in indices {
+            let row = self.entries.get(*index).ok_or_else(|| Error::from(ErrorKind::TableRowIndexOutOfBounds(*index, self.entries.len())))?;
+            writer.write_record(row.iter().map(|x| x.to_display_string()))?;
+        }
+
+        let data = writer.into_inner().map_err(|_| ErrorKind::TSVErrorGeneric)?;
+        Ok(String::from_utf8(data)?)
+    }
+
+    /// This function parses rows serialized with `serialize_rows`, checking they can be pasted into this table. The reverse of `serialize_rows`.
+    ///
+    /// The types of the columns of the rows must be the same as the ones of this table, in the same order. Their names don't matter.
+    /// If they're not, or any of the cells cannot be parsed as the type of its column, it returns an error.
+    pub fn deserialize_rows(&self, data: &str) -> Result<Vec<Vec<DecodedData>>> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(data.as_bytes());
+
+        let mut records = reader.records();
+        let columns = match records.next() {
+            Some(header) => header?.iter().map(|x| x.to_owned()).collect::<Vec<String>>(),
+            None => vec![],
+        };
+
+        let expected_types = self.definition.fields.iter().map(|x| x.field_type.to_string()).collect::<Vec<String>>();
+        let types = columns.iter().map(|x| x.split_once(':').map(|(_, field_type)| field_type).unwrap_or_default()).collect::<Vec<&str>>();
+        if expected_types != types {
+            return Err(ErrorKind::TableRowsIncompatibleColumns(expected_types.join(", "), types.join(", ")).into())
+        }
+
+        let mut rows = vec![];
+        for record in records {
+            let record = record?;
+            if record.len() != self.definition.fields.len() { return Err(ErrorKind::TableRowWrongFieldCount(self.definition.fields.len() as u32, record.len() as u32).into()) }
+            rows.push(record.iter()
+                .zip(self.definition.fields.iter())
+                .map(|(value, field)| DecodedData::from_str_typed(value, &field.field_type))
+                .collect::<Result<Vec<DecodedData>>>()?);
+        }
+
+        Ok(rows)
+    }
+
+    /// This function returns the entries of the table as a JSON array of rows, each one being an array with the values of its cells.
+    pub fn rows_to_json(&self) -> Value {
+        Value::Array(self.entries.iter().map(|row| Value::Array(row.iter().map(|cell| cell.to_json()).collect())).collect())
+    }
+
+    /// This function creates a table with the provided definition from a JSON array of rows, like the one returned by `rows_to_json`.
+    ///
+    /// Every row must have one value for each field of the definition, and every value must be valid for the type of its field.
+    /// Otherwise, this returns an error with the first (1-based) row and column with problems, like `validate` does.
+    pub fn rows_from_json(definition: &Definition, rows: &Value) -> Result<Self> {
+        let rows = rows.as_array().ok_or(ErrorKind::JsonErrorData)?;
+        let mut entries = Vec::with_capacity(rows.len());
+        for (row_index, row) in rows.iter().enumerate() {
+            let row = row.as_array().ok_or(ErrorKind::JsonErrorData)?;
+            if row.len() != definition.fields.len() {
+                return Err(ErrorKind::TableInvalidRowFieldCount(row_index as u32 + 1, definition.fields.len() as u32, row.len() as u32).into())
+            }
+
+            let mut entry = Vec::with_capacity(row.len());
+            for (column, (value, field)) in row.iter().zip(definition.fields.iter()).enumerate() {
+                match DecodedData::from_json_typed(value, &field.field_type)? {
+                    Some(data) => entry.push(data),
+                    None => return Err(ErrorKind::TableJsonInvalidCell(row_index as u32 + 1, column as u32 + 1, value.to_string(), field.field_type.to_string()).into()),
+                }
+            }
+            entries.push(entry);
+        }
+
+        let mut table = Self::new(definition);
+        table.entries = entries;
+        Ok(table)
+    }
+
+    /// This function returns the index of the provided column in the definition of the table.
+    ///
+    /// If the column doesn't exist, it returns an error.
+    fn get_column_index(&self, column: &str) -> Result<usize> {
+        self.definition.fields.iter().position(|x| x.name == column).ok_or_else(|| Error::from(ErrorKind::TableColumnNotFound(column.to_owned())))
+    }
+
     /// This function decodes all the fields of a table from raw bytes.
     ///
     /// If return_incomplete == true, this function will return an error with the incompletely decoded table when it fails.
@@ -289,11 +818,11 @@ impl Table {
                         else { Err(ErrorKind::HelperDecodingEncodingError(format!("<p>Error trying to decode the <i><b>Row {}, Cell {}</b></i> as an <b><i>UTF-16 String</b></i> value: the value is not a valid UTF-16 String, or there are insufficient bytes left to decode it as an UTF-16 String.</p>", row + 1, column + 1))) }
                     }
                     FieldType::OptionalStringU8 => {
-                        if let Ok(data) = data.decode_packedfile_optional_string_u8(*index, &mut index) { Ok(DecodedData::OptionalStringU8(Self::escape_special_chars(&data))) }
+                        if let Ok(data) = Self::decode_optional_string(&data, index, false) { Ok(DecodedData::OptionalStringU8(data)) }
                         else { Err(ErrorKind::HelperDecodingEncodingError(format!("<p>Error trying to decode the <i><b>Row {}, Cell {}</b></i> as an <b><i>Optional UTF-8 String</b></i> value: the value is not a valid Optional UTF-8 String, or there are insufficient bytes left to decode it as an Optional UTF-8 String.</p>", row + 1, column + 1))) }
                     }
                     FieldType::OptionalStringU16 => {
-                        if let Ok(data) = data.decode_packedfile_optional_string_u16(*index, &mut index) { Ok(DecodedData::OptionalStringU16(Self::escape_special_chars(&data))) }
+                        if let Ok(data) = Self::decode_optional_string(&data, index, true) { Ok(DecodedData::OptionalStringU16(data)) }
                         else { Err(ErrorKind::HelperDecodingEncodingError(format!("<p>Error trying to decode the <i><b>Row {}, Cell {}</b></i> as an <b><i>Optional UTF-16 String</b></i> value: the value is not a valid Optional UTF-16 String, or there are insufficient bytes left to decode it as an Optional UTF-16 String.</p>", row + 1, column + 1))) }
                     }
 
@@ -319,9 +848,12 @@ impl Table {
     }
 
     /// This function encodes all the fields of a table to raw bytes.
+    ///
+    /// Numeric cells always fit in their columns, but the lengths of strings and sequences are encoded as numbers too.
+    /// If one of them is too big for the number it's encoded as, this returns an error instead of saving a broken table.
     fn encode(&self, mut packed_file: &mut Vec<u8>) -> Result<()> {
         let fields = &self.definition.fields;
-        for row in &self.entries {
+        for (row_index, row) in self.entries.iter().enumerate() {
 
             // First, we need to make sure all rows we're going to encode are exactly what we expect.
             if row.len() != fields.len() { return Err(ErrorKind::TableRowWrongFieldCount(fields.len() as u32, row.len() as u32).into()) }
@@ -338,12 +870,23 @@ impl Table {
                     DecodedData::Float(data) => packed_file.encode_float_f32(data),
                     DecodedData::Integer(data) => packed_file.encode_integer_i32(data),
                     DecodedData::LongInteger(data) => packed_file.encode_integer_i64(data),
-                    DecodedData::StringU8(ref data) => packed_file.encode_packedfile_string_u8(&Self::unescape_special_chars(&data)),
-                    DecodedData::StringU16(ref data) => packed_file.encode_packedfile_string_u16(&Self::unescape_special_chars(&data)),
-                    DecodedData::OptionalStringU8(ref data) => packed_file.encode_packedfile_optional_string_u8(&Self::unescape_special_chars(&data)),
-                    DecodedData::OptionalStringU16(ref data) => packed_file.encode_packedfile_optional_string_u16(&Self::unescape_special_chars(&data)),
+                    DecodedData::StringU8(ref data) => {
+                        let data = Self::unescape_special_chars(&data);
+                        Self::check_string_length(&data, false, row_index, index)?;
+                        packed_file.encode_packedfile_string_u8(&data)
+                    },
+                    DecodedData::StringU16(ref data) => {
+                        let data = Self::unescape_special_chars(&data);
+                        Self::check_string_length(&data, true, row_index, index)?;
+                        packed_file.encode_packedfile_string_u16(&data)
+                    },
+                    DecodedData::OptionalStringU8(ref data) => Self::encode_optional_string(&mut packed_file, data, false, row_index, index)?,
+                    DecodedData::OptionalStringU16(ref data) => Self::encode_optional_string(&mut packed_file, data, true, row_index, index)?,
                     DecodedData::Sequence(ref data) => {
                         if let FieldType::Sequence(_) = fields[index].field_type {
+                            if data.entries.len() > u32::MAX as usize {
+                                return Err(ErrorKind::TableCellValueOutOfRange { row: row_index as u32, column: index as u32, value: format!("{} entries", data.entries.len()) }.into())
+                            }
                             packed_file.encode_integer_u32(data.entries.len() as u32);
                             data.encode(&mut packed_file)?;
                         }
@@ -409,8 +952,8 @@ impl Table {
                             FieldType::LongInteger => entry.push(DecodedData::LongInteger(field.parse::<i64>().map_err(|_| Error::from(ErrorKind::ImportTSVIncorrectRow(row, column)))?)),
                             FieldType::StringU8 => entry.push(DecodedData::StringU8(field.to_owned())),
                             FieldType::StringU16 => entry.push(DecodedData::StringU16(field.to_owned())),
-                            FieldType::OptionalStringU8 => entry.push(DecodedData::OptionalStringU8(field.to_owned())),
-                            FieldType::OptionalStringU16 => entry.push(DecodedData::OptionalStringU16(field.to_owned())),
+                            FieldType::OptionalStringU8 => entry.push(DecodedData::OptionalStringU8(Some(field.to_owned()))),
+                            FieldType::OptionalStringU16 => entry.push(DecodedData::OptionalStringU16(Some(field.to_owned()))),
 
                             // For now fail on Sequences. These are a bit special and I don't know if the're even possible in TSV.
                             FieldType::Sequence(_) => return Err(ErrorKind::ImportTSVIncorrectRow(row, column).into())
@@ -484,8 +1027,8 @@ impl Table {
                             FieldType::LongInteger => entry.push(DecodedData::LongInteger(field.parse::<i64>().map_err(|_| Error::from(ErrorKind::ImportTSVIncorrectRow(row, column)))?)),
                             FieldType::StringU8 => entry.push(DecodedData::StringU8(field.to_owned())),
                             FieldType::StringU16 => entry.push(DecodedData::StringU16(field.to_owned())),
-                            FieldType::OptionalStringU8 => entry.push(DecodedData::OptionalStringU8(field.to_owned())),
-                            FieldType::OptionalStringU16 => entry.push(DecodedData::OptionalStringU16(field.to_owned())),
+                            FieldType::OptionalStringU8 => entry.push(DecodedData::OptionalStringU8(Some(field.to_owned()))),
+                            FieldType::OptionalStringU16 => entry.push(DecodedData::OptionalStringU16(Some(field.to_owned()))),
                             FieldType::Sequence(_) => return Err(ErrorKind::ImportTSVIncorrectRow(row, column).into())
                         }
                     }
@@ -609,6 +1152,48 @@ impl Table {
     fn unescape_special_chars(data: &str)-> String {
          data.replace("\\\\t", "\t").replace("\\\\n", "\n")
     }
+
+    /// This function decodes an optional string, keeping if it was present or not, so it can be encoded back to the same bytes.
+    fn decode_optional_string(data: &[u8], index: &mut usize, is_utf16: bool) -> Result<Option<String>> {
+        let start = *index;
+        if !data.decode_packedfile_bool(*index, index)? { return Ok(None) }
+
+        let string = if is_utf16 { data.decode_packedfile_string_u16(*index, index) } else { data.decode_packedfile_string_u8(*index, index) };
+        match string {
+            Ok(string) => Ok(Some(Self::escape_special_chars(&string))),
+            Err(error) => {
+                *index = start;
+                Err(error)
+            }
+        }
+    }
+
+    /// This function encodes an optional string, writing only his presence flag if it's absent.
+    fn encode_optional_string(packed_file: &mut Vec<u8>, data: &Option<String>, is_utf16: bool, row: usize, column: usize) -> Result<()> {
+        match data {
+            Some(data) => {
+                let data = Self::unescape_special_chars(data);
+                Self::check_string_length(&data, is_utf16, row, column)?;
+                packed_file.encode_bool(true);
+                if is_utf16 { packed_file.encode_packedfile_string_u16(&data) }
+                else { packed_file.encode_packedfile_string_u8(&data) }
+            }
+            None => packed_file.encode_bool(false),
+        }
+        Ok(())
+    }
+
+    /// This function checks the length of the provided string fits in the `u16` it's encoded with, in bytes for UTF-8 strings or in characters for UTF-16 ones.
+    ///
+    /// The row and column are the position of the cell with the string, for the error.
+    fn check_string_length(data: &str, is_utf16: bool, row: usize, column: usize) -> Result<()> {
+        let length = if is_utf16 { data.encode_utf16().count() } else { data.len() };
+        if length > u16::MAX as usize {
+            let value = format!("{} {}", length, if is_utf16 { "UTF-16 characters" } else { "bytes" });
+            return Err(ErrorKind::TableCellValueOutOfRange { row: row as u32, column: column as u32, value }.into())
+        }
+        Ok(())
+    }
 }
 
 /// Implementation of `From<&RawTable>` for `Table`.
@@ -633,8 +1218,8 @@ impl From<&RawTable> for Table {
                                 FieldType::LongInteger => DecodedData::LongInteger(if let Ok(data) = field.field_data.parse::<i64>() { data } else { 0 }),
                                 FieldType::StringU8 => DecodedData::StringU8(if field.field_data == "Frodo Best Waifu" { String::new() } else { field.field_data.to_string() }),
                                 FieldType::StringU16 => DecodedData::StringU16(if field.field_data == "Frodo Best Waifu" { String::new() } else { field.field_data.to_string() }),
-                                FieldType::OptionalStringU8 => DecodedData::OptionalStringU8(if field.field_data == "Frodo Best Waifu" { String::new() } else { field.field_data.to_string() }),
-                                FieldType::OptionalStringU16 => DecodedData::OptionalStringU16(if field.field_data == "Frodo Best Waifu" { String::new() } else { field.field_data.to_string() }),
+                                FieldType::OptionalStringU8 => DecodedData::OptionalStringU8(if field.field_data == "Frodo Best Waifu" { None } else { Some(field.field_data.to_string()) }),
+                                FieldType::OptionalStringU16 => DecodedData::OptionalStringU16(if field.field_data == "Frodo Best Waifu" { None } else { Some(field.field_data.to_string()) }),
 
                                 // This type is not used in the raw tables so, if we find it, we skip it.
                                 FieldType::Sequence(_) => continue,
@@ -645,7 +1230,7 @@ impl From<&RawTable> for Table {
 
                     // If the field doesn't exist, we create it empty.
                     if !exists {
-                        entry.push(DecodedData::OptionalStringU8(String::new()));
+                        entry.push(DecodedData::OptionalStringU8(None));
                     }
                 }
                 table.entries.push(entry);
@@ -0,0 +1,153 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `Table` module, just to make sure we don't break it... again...
+!*/
+
+use std::collections::HashSet;
+
+use rpfm_error::ErrorKind;
+
+use crate::schema::*;
+use super::{DecodedData, Table};
+
+/// Test to make sure every type of `DecodedData` survives being shown as a string and parsed back.
+#[test]
+fn test_display_string_round_trip() {
+    let values = vec![
+        (DecodedData::Boolean(true), FieldType::Boolean, "true"),
+        (DecodedData::Boolean(false), FieldType::Boolean, "false"),
+        (DecodedData::Float(1.5), FieldType::Float, "1.5"),
+        (DecodedData::Float(-0.1), FieldType::Float, "-0.1"),
+        (DecodedData::Float(3.0), FieldType::Float, "3"),
+        (DecodedData::Integer(-42), FieldType::Integer, "-42"),
+        (DecodedData::LongInteger(9_000_000_000), FieldType::LongInteger, "9000000000"),
+        (DecodedData::StringU8("text".to_owned()), FieldType::StringU8, "text"),
+        (DecodedData::StringU16("tëxt".to_owned()), FieldType::StringU16, "tëxt"),
+        (DecodedData::OptionalStringU8(Some(String::new())), FieldType::OptionalStringU8, ""),
+        (DecodedData::OptionalStringU16(Some("text".to_owned())), FieldType::OptionalStringU16, "text"),
+    ];
+
+    for (data, field_type, string) in &values {
+        assert_eq!(data.to_display_string(), *string);
+        assert_eq!(&DecodedData::from_str_typed(string, field_type).unwrap(), data);
+    }
+
+    // Absent optional strings are shown as empty, but empty strings are not parsed back as absent.
+    assert_eq!(DecodedData::OptionalStringU8(None).to_display_string(), "");
+    assert_ne!(DecodedData::from_str_typed("", &FieldType::OptionalStringU8).unwrap(), DecodedData::OptionalStringU8(None));
+}
+
+/// Test to make sure parsing strings as `DecodedData` accepts the alternative forms of booleans, and rejects invalid values.
+#[test]
+fn test_from_str_typed() {
+    assert_eq!(DecodedData::from_str_typed("TRUE", &FieldType::Boolean).unwrap(), DecodedData::Boolean(true));
+    assert_eq!(DecodedData::from_str_typed("0", &FieldType::Boolean).unwrap(), DecodedData::Boolean(false));
+    assert_eq!(DecodedData::from_str_typed("", &FieldType::StringU8).unwrap(), DecodedData::StringU8(String::new()));
+
+    assert_eq!(DecodedData::from_str_typed("yes", &FieldType::Boolean).unwrap_err().kind(), &ErrorKind::TableCellParseError("yes".to_owned(), "Boolean".to_owned()));
+    assert!(DecodedData::from_str_typed("", &FieldType::Integer).is_err());
+    assert!(DecodedData::from_str_typed("1,5", &FieldType::Float).is_err());
+    assert_eq!(DecodedData::from_str_typed("3.4028235e38", &FieldType::Float).unwrap(), DecodedData::Float(f32::MAX));
+    assert!(DecodedData::from_str_typed("1.5", &FieldType::Integer).is_err());
+    assert!(DecodedData::from_str_typed("9000000000", &FieldType::Integer).is_err());
+    assert!(DecodedData::from_str_typed("", &FieldType::Sequence(Definition::new(1))).is_err());
+
+    let sequence = DecodedData::Sequence(Table::new(&Definition::new(1)));
+    assert_eq!(sequence.to_display_string(), "Sequence of 0 entries");
+}
+
+/// This function returns the `FieldType` of the provided `DecodedData`, for testing purpouses.
+///
+/// This has no wildcard arms, so adding a variant to `DecodedData` doesn't compile until it's added here and to `test_all_variants`.
+fn get_field_type(data: &DecodedData) -> FieldType {
+    match data {
+        DecodedData::Boolean(_) => FieldType::Boolean,
+        DecodedData::Float(_) => FieldType::Float,
+        DecodedData::Integer(_) => FieldType::Integer,
+        DecodedData::LongInteger(_) => FieldType::LongInteger,
+        DecodedData::StringU8(_) => FieldType::StringU8,
+        DecodedData::StringU16(_) => FieldType::StringU16,
+        DecodedData::OptionalStringU8(_) => FieldType::OptionalStringU8,
+        DecodedData::OptionalStringU16(_) => FieldType::OptionalStringU16,
+        DecodedData::Sequence(table) => FieldType::Sequence(table.get_definition()),
+    }
+}
+
+/// Test to make sure every variant of `DecodedData` can be checked, shown, parsed and encoded.
+#[test]
+fn test_all_variants() {
+    let mut sequence_definition = Definition::new(1);
+    sequence_definition.fields.push(Field { name: "entry".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+    let mut sequence = Table::new(&sequence_definition);
+    sequence.add_row(vec![DecodedData::Integer(7)]).unwrap();
+
+    let values = vec![
+        DecodedData::Boolean(true),
+        DecodedData::Float(0.5),
+        DecodedData::Integer(-1),
+        DecodedData::LongInteger(-1),
+        DecodedData::StringU8("text".to_owned()),
+        DecodedData::StringU16("tëxt".to_owned()),
+        DecodedData::OptionalStringU8(Some("text".to_owned())),
+        DecodedData::OptionalStringU16(None),
+        DecodedData::Sequence(sequence),
+    ];
+
+    // One of each variant, and no more.
+    assert_eq!(values.iter().map(|x| x.to_string()).collect::<HashSet<String>>().len(), values.len());
+
+    let mut definition = Definition::new(1);
+    for (index, data) in values.iter().enumerate() {
+        let field_type = get_field_type(data);
+        assert!(data.is_field_type_correct(field_type.clone()));
+        assert!(DecodedData::default(&field_type).is_field_type_correct(field_type.clone()));
+
+        // Sequences are shown as their entry count, so they cannot be parsed back. Absent optional strings are parsed back as empty ones.
+        if !matches!(data, DecodedData::Sequence(_) | DecodedData::OptionalStringU16(None)) {
+            assert_eq!(&DecodedData::from_str_typed(&data.to_display_string(), &field_type).unwrap(), data);
+        }
+
+
+        definition.fields.push(Field { name: format!("field_{}", index), field_type, ..Default::default() });
+    }
+
+    let mut table = Table::new(&definition);
+    table.add_row(values).unwrap();
+    let mut data = vec![];
+    table.encode(&mut data).unwrap();
+
+    let mut decoded = Table::new(&definition);
+    let mut index = 0;
+    decoded.decode(&data, 1, &mut index, false).unwrap();
+    assert_eq!(index, data.len());
+    assert_eq!(decoded, table);
+}
+
+/// Test to make sure importing a TSV parses empty optional strings the same way `from_str_typed` does.
+#[test]
+fn test_import_tsv_empty_optional_string() {
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU8, ..Default::default() });
+    definition.fields.push(Field { name: "value".to_owned(), field_type: FieldType::OptionalStringU8, ..Default::default() });
+
+    let mut path = std::env::temp_dir();
+    path.push("rpfm_test_import_tsv_empty_optional_string.tsv");
+    std::fs::write(&path, "test_tables\t1\nkey\tvalue\na\t\nb\ttext\n").unwrap();
+    let table = Table::import_tsv(&definition, &path, "test_tables").unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(table.get_table_data(), vec![
+        vec![DecodedData::StringU8("a".to_owned()), DecodedData::from_str_typed("", &FieldType::OptionalStringU8).unwrap()],
+        vec![DecodedData::StringU8("b".to_owned()), DecodedData::OptionalStringU8(Some("text".to_owned()))],
+    ]);
+    assert_eq!(table.get_table_data()[0][1], DecodedData::OptionalStringU8(Some(String::new())));
+}
@@ -17,10 +17,15 @@ The only thing to take into account is that this only work for UTF-8 encoded fil
 
 use serde_derive::{Serialize, Deserialize};
 
-use rpfm_error::{ErrorKind, Result};
+use std::borrow::Cow;
+
+use rpfm_error::{Error, ErrorKind, Result};
 
 use crate::common::{decoder::Decoder, encoder::Encoder};
 
+#[cfg(test)]
+mod text_test;
+
 /// UTF-8 BOM (Byte Order Mark).
 const BOM_UTF_8: [u8;3] = [0xEF,0xBB,0xBF];
 
@@ -45,8 +50,8 @@ pub const EXTENSIONS: [(&str, TextType); 21] = [
     (".benchmark", TextType::Xml),
     (".cindyscene", TextType::Xml),
     (".cindyscenemanager", TextType::Xml),
-    (".csv", TextType::Plain),
-    (".tsv", TextType::Plain),
+    (".csv", TextType::Csv),
+    (".tsv", TextType::Tsv),
     (".tai", TextType::Plain),
     (".battle_speech_camera", TextType::Plain),
     (".bob", TextType::Plain),
@@ -70,7 +75,12 @@ pub struct Text {
     text_type: TextType,
 
     /// The text inside the PackedFile.
-    contents: String
+    contents: String,
+
+    /// If the text should end with a line ending when saved. `None` means the text is saved as it is.
+    ///
+    /// When decoding it from a `PackedFile`, this is what the original data had, so edits don't add or remove it by accident.
+    ends_with_newline: Option<bool>,
 }
 
 /// This enum contains the list of encoding RPFM supports.
@@ -93,6 +103,8 @@ pub enum TextType {
     Lua,
     Cpp,
     Markdown,
+    Csv,
+    Tsv,
     Plain,
 }
 
@@ -107,6 +119,7 @@ impl Default for Text {
             encoding: SupportedEncodings::Utf8,
             text_type: TextType::Plain,
             contents: String::new(),
+            ends_with_newline: None,
         }
     }
 }
@@ -119,22 +132,57 @@ impl Text {
         Self::default()
     }
 
+    /// This function returns if the provided data looks like a text file, to find text files with extensions we don't know about.
+    ///
+    /// To pass, the data must be valid UTF-8 (or UTF-16 with his BOM), and it must not have control characters other than tabs and line endings.
+    /// Empty data doesn't pass, as there is no way to know what it is.
+    pub fn is_text(packed_file_data: &[u8]) -> bool {
+        let contents = if packed_file_data.len() > 2 && packed_file_data[0..3] == BOM_UTF_8 { packed_file_data.decode_string_u8(3, packed_file_data.len() - 3) }
+        else if packed_file_data.len() > 1 && packed_file_data[0..2] == BOM_UTF_16_LE { packed_file_data.decode_string_u16(2, packed_file_data.len() - 2) }
+        else { packed_file_data.decode_string_u8(0, packed_file_data.len()) };
+
+        match contents {
+            Ok(contents) => !contents.is_empty() && contents.chars().all(|x| !x.is_control() || x == '\t' || x == '\n' || x == '\r'),
+            Err(_) => false,
+        }
+    }
+
+    /// This function creates a `Text` from a `Vec<u8>`, if the data is not bigger than the provided limit, in bytes.
+    ///
+    /// Meant to avoid decoding huge files as text by accident. If the data is over the limit, this returns a `TextTooLarge` error
+    /// without trying to decode it. Otherwise, it works like `read`.
+    pub fn read_with_limit(packed_file_data: &[u8], max_bytes: usize) -> Result<Self> {
+        if packed_file_data.len() > max_bytes {
+            return Err(ErrorKind::TextTooLarge { size: packed_file_data.len(), limit: max_bytes }.into())
+        }
+
+        Self::read(packed_file_data)
+    }
+
     /// This function creates a `Text` from a `Vec<u8>`.
+    ///
+    /// Unlike tables, the entire data is decoded as text, so there can be no extra data left behind after decoding it.
+    ///
+    /// Data that is not valid UTF-8 is decoded as ISO-8859-1, with or without an UTF-8 BOM. If UTF-16 data is not valid,
+    /// the error contains the position of the first invalid sequence, counting the BOM.
     pub fn read(packed_file_data: &[u8]) -> Result<Self> {
 
         // First, check for BOMs. 2 bytes for UTF-16 BOMs, 3 for UTF-8. If no BOM is found, we assume UTF-8 or ISO5589-1.
-        let (packed_file_data, guessed_encoding) = if packed_file_data.is_empty() { (packed_file_data, SupportedEncodings::Utf8) }
-        else if packed_file_data.len() > 2 && packed_file_data[0..3] == BOM_UTF_8 { (&packed_file_data[3..], SupportedEncodings::Utf8) }
-        //else if packed_file_data.len() > 1 && packed_file_data[0..2] == BOM_UTF_16_BE { (&packed_file_data[2..], SupportedEncodings::UTF16_BE) }
-        else if packed_file_data.len() > 1 && packed_file_data[0..2] == BOM_UTF_16_LE { (&packed_file_data[2..], SupportedEncodings::Utf16Le) }
-        else { (packed_file_data, SupportedEncodings::Utf8) };
+        let (bom_size, guessed_encoding) = if packed_file_data.is_empty() { (0, SupportedEncodings::Utf8) }
+        else if packed_file_data.len() > 2 && packed_file_data[0..3] == BOM_UTF_8 { (3, SupportedEncodings::Utf8) }
+        //else if packed_file_data.len() > 1 && packed_file_data[0..2] == BOM_UTF_16_BE { (2, SupportedEncodings::UTF16_BE) }
+        else if packed_file_data.len() > 1 && packed_file_data[0..2] == BOM_UTF_16_LE { (2, SupportedEncodings::Utf16Le) }
+        else { (0, SupportedEncodings::Utf8) };
+        let data = &packed_file_data[bom_size..];
 
         // This is simple: we try to decode it depending on what the guesser gave us. If all fails, return error.
         let (encoding, contents) = match guessed_encoding {
             SupportedEncodings::Utf8 | SupportedEncodings::Iso8859_1 => {
-                match packed_file_data.decode_string_u8(0, packed_file_data.len()) {
-                    Ok(string) => (SupportedEncodings::Utf8, string),
-                    Err(_) => match packed_file_data.decode_string_u8_iso_8859_1(0, packed_file_data.len()) {
+                match std::str::from_utf8(data) {
+                    Ok(string) => (SupportedEncodings::Utf8, string.to_owned()),
+
+                    // Some tools add an UTF-8 BOM to files that are not UTF-8, so the BOM alone doesn't rule out ISO-8859-1.
+                    Err(_) => match data.decode_string_u8_iso_8859_1(0, data.len()) {
                         Ok(string) => (SupportedEncodings::Iso8859_1, string),
                         Err(_) => return Err(ErrorKind::TextDecodeWrongEncodingOrNotATextFile.into()),
                     }
@@ -142,36 +190,62 @@ impl Text {
             }
 
             SupportedEncodings::Utf16Le => {
-                match packed_file_data.decode_string_u16(0, packed_file_data.len()) {
-                    Ok(string) => (SupportedEncodings::Utf16Le, string),
-                    Err(_) => return Err(ErrorKind::TextDecodeWrongEncodingOrNotATextFile.into()),
+                let mut contents = String::with_capacity(data.len() / 2);
+                let mut offset = bom_size;
+                for character in char::decode_utf16(data.chunks_exact(2).map(|x| u16::from_le_bytes([x[0], x[1]]))) {
+                    match character {
+                        Ok(character) => {
+                            contents.push(character);
+                            offset += character.len_utf16() * 2;
+                        }
+                        Err(_) => return Err(Self::get_decode_error(offset, &contents)),
+                    }
                 }
+
+                // A lone byte at the end is half a character.
+                if data.len() % 2 != 0 { return Err(Self::get_decode_error(offset, &contents)) }
+                (SupportedEncodings::Utf16Le, contents)
             }
         };
 
         // Without the path we can't know the text type, so we left it as plain, and overwrite it later.
         let text_type = TextType::Plain;
+        let ends_with_newline = Some(contents.ends_with('\n'));
 
         Ok(Self {
             encoding,
             text_type,
             contents,
+            ends_with_newline,
         })
     }
 
+    /// This function returns the error for an invalid sequence at the provided offset, with the line and column it's at.
+    ///
+    /// The contents are the ones decoded before the invalid sequence.
+    fn get_decode_error(offset: usize, contents: &str) -> Error {
+        let line = contents.matches('\n').count() + 1;
+        let column = contents.rsplit('\n').next().map_or(0, |x| x.chars().count()) + 1;
+        ErrorKind::TextDecodeFailed { offset, line, column }.into()
+    }
+
     /// This function takes a `Text` and encodes it to `Vec<u8>`.
     ///
+    /// If we know if the text should end with a line ending or not, the one at the end of the contents is added or removed to match it.
+    /// Empty texts are always saved empty.
+    ///
     /// TODO: Make this save other than UTF-8.
     pub fn save(&self) -> Result<Vec<u8>> {
+        let contents = self.get_contents_to_save();
         let mut data = vec![];
         match self.encoding {
-            SupportedEncodings::Utf8 => data.encode_string_u8(&self.contents),
-            SupportedEncodings::Iso8859_1 => data.encode_string_u8_iso_8859_1(&self.contents),
+            SupportedEncodings::Utf8 => data.encode_string_u8(&contents),
+            SupportedEncodings::Iso8859_1 => data.encode_string_u8_iso_8859_1(&contents),
 
             // For UTF-16 we always have to add the BOM. Otherwise we have no way to easely tell what this file is.
             SupportedEncodings::Utf16Le => {
                 data.append(&mut BOM_UTF_16_LE.to_vec());
-                data.encode_string_u16(&self.contents)
+                data.encode_string_u16(&contents)
             },
         }
 
@@ -193,18 +267,115 @@ impl Text {
         self.contents = contents.to_owned();
     }
 
+    /// This function returns if the text file should end with a line ending when saved, if we know it.
+    pub fn get_ends_with_newline(&self) -> Option<bool> {
+        self.ends_with_newline
+    }
+
+    /// This function sets if the text file should end with a line ending when saved. `None` saves the contents as they are.
+    pub fn set_ends_with_newline(&mut self, ends_with_newline: Option<bool>) {
+        self.ends_with_newline = ends_with_newline;
+    }
+
     /// This function sets the encoding used to save the text file.
     pub fn set_encoding(&mut self, encoding: SupportedEncodings) {
         self.encoding = encoding;
     }
 
-    /// This function returns a reference to the contents of the text file.
+    /// This function returns the type of text of the text file.
+    ///
+    /// When decoding it from a `PackedFile`, this is guessed from the extension of his path. Otherwise it's `TextType::Plain`.
     pub fn get_text_type(&self) -> TextType {
         self.text_type
     }
 
-    /// This function sets the encoding used to save the text file.
+    /// This function sets the type of text of the text file, overriding the one guessed from his path.
     pub fn set_text_type(&mut self, text_type: TextType) {
         self.text_type = text_type;
     }
+
+    /// This function returns the lines of the text file, without their line endings.
+    pub fn get_lines(&self) -> Vec<&str> {
+        self.contents.lines().collect()
+    }
+
+    /// This function replaces the line at the provided index with the provided one.
+    ///
+    /// The line endings of the file are kept as they were. If the line doesn't exist, this returns an error.
+    pub fn replace_line(&mut self, index: usize, line: &str) -> Result<()> {
+        self.edit_lines(|lines| {
+            if index >= lines.len() { return Err(ErrorKind::TextLineOutOfBounds(index, lines.len()).into()) }
+            lines[index] = line.to_owned();
+            Ok(())
+        })
+    }
+
+    /// This function inserts the provided line at the provided index, moving the lines after it down.
+    ///
+    /// The index can be the amount of lines of the file, to add the line at the end. If it's past that, this returns an error.
+    pub fn insert_line(&mut self, index: usize, line: &str) -> Result<()> {
+        self.edit_lines(|lines| {
+            if index > lines.len() { return Err(ErrorKind::TextLineOutOfBounds(index, lines.len()).into()) }
+            lines.insert(index, line.to_owned());
+            Ok(())
+        })
+    }
+
+    /// This function removes the line at the provided index, moving the lines after it up.
+    ///
+    /// If the line doesn't exist, this returns an error.
+    pub fn remove_line(&mut self, index: usize) -> Result<()> {
+        self.edit_lines(|lines| {
+            if index >= lines.len() { return Err(ErrorKind::TextLineOutOfBounds(index, lines.len()).into()) }
+            lines.remove(index);
+            Ok(())
+        })
+    }
+
+    /// This function replaces all the appearances of `find` in the text file with `replace`, returning how many of them were replaced.
+    ///
+    /// Appearances are searched from the start of the file, and they don't overlap. The encoding and the line ending at the end of the file
+    /// are kept as they were when saving it. If `find` is empty, nothing is replaced.
+    pub fn replace_all(&mut self, find: &str, replace: &str) -> usize {
+        if find.is_empty() { return 0 }
+
+        let count = self.contents.matches(find).count();
+        if count > 0 {
+            self.contents = self.contents.replace(find, replace);
+        }
+        count
+    }
+
+    /// This function returns the contents of the text file with the line ending at the end added or removed, if needed.
+    fn get_contents_to_save(&self) -> Cow<str> {
+        match self.ends_with_newline {
+            Some(true) if !self.contents.is_empty() && !self.contents.ends_with('\n') => {
+                let line_ending = if self.contents.contains("\r\n") { "\r\n" } else { "\n" };
+                Cow::from(format!("{}{}", self.contents, line_ending))
+            }
+            Some(false) if self.contents.ends_with('\n') => {
+                let contents = &self.contents[..self.contents.len() - 1];
+                Cow::from(contents.strip_suffix('\r').unwrap_or(contents))
+            }
+            _ => Cow::from(&self.contents),
+        }
+    }
+
+    /// This function applies the provided edit to the lines of the text file, and rebuilds the contents from them.
+    ///
+    /// To not mess with diffs, the contents are rebuilt with the line ending the file already used (`\r\n` if it's found, `\n` otherwise),
+    /// and keeping the newline at the end of the file if it had one. If the edit fails, the contents are not changed.
+    fn edit_lines<F: FnOnce(&mut Vec<String>) -> Result<()>>(&mut self, edit: F) -> Result<()> {
+        let line_ending = if self.contents.contains("\r\n") { "\r\n" } else { "\n" };
+        let ends_with_line_ending = self.contents.ends_with('\n');
+
+        let mut lines = self.contents.lines().map(|x| x.to_owned()).collect::<Vec<String>>();
+        edit(&mut lines)?;
+
+        self.contents = lines.join(line_ending);
+        if ends_with_line_ending && !lines.is_empty() {
+            self.contents.push_str(line_ending);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,200 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `Text` module, just to make sure we don't break it... again...
+!*/
+
+use rpfm_error::ErrorKind;
+
+use super::{SupportedEncodings, Text};
+
+/// This function returns a `Text` with the provided contents, for testing purpouses.
+fn get_test_text(contents: &str) -> Text {
+    let mut text = Text::new();
+    text.set_contents(contents);
+    text
+}
+
+/// Test to make sure the lines are returned without their line endings.
+#[test]
+fn test_get_lines() {
+    assert_eq!(get_test_text("first\nsecond\nthird\n").get_lines(), vec!["first", "second", "third"]);
+    assert_eq!(get_test_text("first\r\nsecond\r\nthird").get_lines(), vec!["first", "second", "third"]);
+    assert!(get_test_text("").get_lines().is_empty());
+}
+
+/// Test to make sure replacing lines keeps the line endings of the file.
+#[test]
+fn test_replace_line() {
+    let mut text = get_test_text("first\r\nsecond\r\nthird\r\n");
+    text.replace_line(1, "new_second").unwrap();
+    assert_eq!(text.get_ref_contents(), "first\r\nnew_second\r\nthird\r\n");
+
+    assert_eq!(text.replace_line(3, "fourth").unwrap_err().kind(), &ErrorKind::TextLineOutOfBounds(3, 3));
+    assert_eq!(text.get_ref_contents(), "first\r\nnew_second\r\nthird\r\n");
+}
+
+/// Test to make sure inserting lines works at any position, including the end of the file.
+#[test]
+fn test_insert_line() {
+    let mut text = get_test_text("first\nthird");
+    text.insert_line(1, "second").unwrap();
+    assert_eq!(text.get_ref_contents(), "first\nsecond\nthird");
+
+    text.insert_line(0, "zero").unwrap();
+    text.insert_line(4, "fourth").unwrap();
+    assert_eq!(text.get_ref_contents(), "zero\nfirst\nsecond\nthird\nfourth");
+
+    assert_eq!(text.insert_line(6, "sixth").unwrap_err().kind(), &ErrorKind::TextLineOutOfBounds(6, 5));
+
+    let mut text = Text::new();
+    text.insert_line(0, "first").unwrap();
+    assert_eq!(text.get_ref_contents(), "first");
+}
+
+/// Test to make sure removing lines keeps the line endings of the file.
+#[test]
+fn test_remove_line() {
+    let mut text = get_test_text("first\r\nsecond\r\nthird\r\n");
+    text.remove_line(0).unwrap();
+    assert_eq!(text.get_ref_contents(), "second\r\nthird\r\n");
+
+    text.remove_line(1).unwrap();
+    assert_eq!(text.get_ref_contents(), "second\r\n");
+
+    assert_eq!(text.remove_line(1).unwrap_err().kind(), &ErrorKind::TextLineOutOfBounds(1, 1));
+
+    text.remove_line(0).unwrap();
+    assert_eq!(text.get_ref_contents(), "");
+}
+
+/// Test to make sure the entire data is decoded as text, including whatever may look like junk at the end.
+#[test]
+fn test_read_all_data() {
+    let text = Text::read(b"first\nsecond\n\x01\x02").unwrap();
+    assert_eq!(text.get_ref_contents(), "first\nsecond\n\u{1}\u{2}");
+    assert_eq!(text.save().unwrap(), b"first\nsecond\n\x01\x02");
+}
+
+/// Test to make sure editing a text file doesn't add or remove the line ending at the end of it.
+#[test]
+fn test_save_keeps_newline_at_eof() {
+    let mut text = Text::read(b"first\nsecond").unwrap();
+    assert_eq!(text.get_ends_with_newline(), Some(false));
+    text.set_contents("first\nsecond\nthird\n");
+    assert_eq!(text.save().unwrap(), b"first\nsecond\nthird");
+
+    let mut text = Text::read(b"first\r\nsecond\r\n").unwrap();
+    assert_eq!(text.get_ends_with_newline(), Some(true));
+    text.set_contents("first\r\nsecond\r\nthird");
+    assert_eq!(text.save().unwrap(), b"first\r\nsecond\r\nthird\r\n");
+
+    // Unless told otherwise, new text files are saved as they are.
+    let mut text = get_test_text("first\n");
+    assert_eq!(text.save().unwrap(), b"first\n");
+    text.set_ends_with_newline(Some(false));
+    assert_eq!(text.save().unwrap(), b"first");
+    text.set_contents("");
+    text.set_ends_with_newline(Some(true));
+    assert!(text.save().unwrap().is_empty());
+}
+
+/// Test to make sure replacing text replaces every appearance, and keeps the encoding and line endings of the file.
+#[test]
+fn test_replace_all() {
+    let mut text = Text::read(b"local unit = get_unit()\r\nunit:kill(unit)\r\n").unwrap();
+    assert_eq!(text.replace_all("unit", "character"), 4);
+    assert_eq!(text.get_ref_contents(), "local character = get_character()\r\ncharacter:kill(character)\r\n");
+    assert_eq!(text.save().unwrap(), b"local character = get_character()\r\ncharacter:kill(character)\r\n");
+
+    // Appearances don't overlap, and the replacement text is not searched again.
+    let mut text = get_test_text("aaaa");
+    assert_eq!(text.replace_all("aa", "a"), 2);
+    assert_eq!(text.get_ref_contents(), "aa");
+    assert_eq!(text.replace_all("b", "c"), 0);
+    assert_eq!(text.get_ref_contents(), "aa");
+
+    let mut text = get_test_text("first\nsecond");
+    text.set_encoding(SupportedEncodings::Utf16Le);
+    assert_eq!(text.replace_all("\n", " "), 1);
+    assert_eq!(text.get_encoding(), SupportedEncodings::Utf16Le);
+    assert_eq!(text.get_ref_contents(), "first second");
+}
+
+/// Test to make sure replacing an empty text does nothing.
+#[test]
+fn test_replace_all_empty() {
+    let mut text = get_test_text("first\nsecond\n");
+    assert_eq!(text.replace_all("", "x"), 0);
+    assert_eq!(text.get_ref_contents(), "first\nsecond\n");
+
+    let mut text = Text::new();
+    assert_eq!(text.replace_all("", ""), 0);
+    assert_eq!(text.get_ref_contents(), "");
+}
+
+/// Test to make sure only data that looks like text is detected as text.
+#[test]
+fn test_is_text() {
+    assert!(Text::is_text(b"{\n\t\"key\": \"value\"\r\n}"));
+    assert!(Text::is_text("Ünicode text".as_bytes()));
+    assert!(Text::is_text(&[0xEF, 0xBB, 0xBF, b'a']));
+    assert!(Text::is_text(&[0xFF, 0xFE, b'a', 0, b'b', 0]));
+
+    assert!(!Text::is_text(b""));
+    assert!(!Text::is_text(&[0xFF, 0xFE]));
+    assert!(!Text::is_text(b"text\0with a null"));
+    assert!(!Text::is_text(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]));
+    assert!(!Text::is_text(&[0xFF, 0xFE, b'a']));
+}
+
+/// Test to make sure invalid sequences in Text PackedFiles are reported with their position.
+#[test]
+fn test_read_invalid_sequence() {
+
+    // Invalid UTF-8 falls back to ISO-8859-1, with or without an UTF-8 BOM.
+    let mut data = vec![0xEF, 0xBB, 0xBF];
+    data.extend_from_slice(b"first line\ns\xEBcond line");
+    let text = Text::read(&data).unwrap();
+    assert_eq!(text.get_encoding(), SupportedEncodings::Iso8859_1);
+    assert_eq!(text.get_ref_contents(), "first line\nsëcond line");
+
+    let text = Text::read(&data[3..]).unwrap();
+    assert_eq!(text.get_encoding(), SupportedEncodings::Iso8859_1);
+    assert_eq!(text.get_ref_contents(), "first line\nsëcond line");
+
+    // UTF-16 with a lone surrogate, or a lone byte at the end.
+    let mut data = vec![0xFF, 0xFE];
+    data.extend("a\r\nb".encode_utf16().flat_map(|x| x.to_le_bytes()));
+    data.extend_from_slice(&[0x00, 0xD8, b'c', 0]);
+    assert_eq!(Text::read(&data).unwrap_err().kind(), &ErrorKind::TextDecodeFailed { offset: 10, line: 2, column: 2 });
+
+    let mut data = vec![0xFF, 0xFE];
+    data.extend("😀".encode_utf16().flat_map(|x| x.to_le_bytes()));
+    data.push(b'a');
+    assert_eq!(Text::read(&data).unwrap_err().kind(), &ErrorKind::TextDecodeFailed { offset: 6, line: 1, column: 2 });
+}
+
+/// Test to make sure Text PackedFiles over the size limit are rejected, and the ones up to it are decoded normally.
+#[test]
+fn test_read_with_limit() {
+    let data = b"0123456789".to_vec();
+    let text = Text::read_with_limit(&data, 10).unwrap();
+    assert_eq!(text, Text::read(&data).unwrap());
+    assert_eq!(Text::read_with_limit(&data, 9).unwrap_err().kind(), &ErrorKind::TextTooLarge { size: 10, limit: 9 });
+
+    // The BOM counts for the limit.
+    let mut data = vec![0xEF, 0xBB, 0xBF];
+    data.extend_from_slice(b"0123456789");
+    assert!(Text::read_with_limit(&data, 13).is_ok());
+    assert_eq!(Text::read_with_limit(&data, 12).unwrap_err().kind(), &ErrorKind::TextTooLarge { size: 13, limit: 12 });
+    assert!(Text::read_with_limit(&[], 0).is_ok());
+}
@@ -14,18 +14,85 @@ use xz2::read::XzDecoder;
 use xz2::stream::Stream;
 
 use std::env::temp_dir;
-use std::fs::File;
+use std::fs::{File, remove_file};
 use std::io::prelude::*;
 use std::io::{BufReader, Read, SeekFrom};
+use std::path::PathBuf;
 
+use std::process;
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::u64;
 
 use rpfm_error::{Error, ErrorKind, Result};
 use crate::common::encoder::Encoder;
 use crate::common::decoder::Decoder;
+use super::{Codec, CompressionSettings, PFHVersion};
 
-/// This function decompress the data of a PackedFile, returning the decompressed data.
+/// Counter used to give each compression his own temporary files, so multiple PackedFiles can be compressed at the same time.
+static COMPRESSION_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// CA's compressed files mark a dictionary of 4MB in their header, so we cannot let 7z use bigger ones.
+const MAX_DICTIONARY_SIZE: &str = "-md=4m";
+
+/// Properties of the LZMA data in CA's compressed files: the default lc/lp/pb values, followed by the 4MB dictionary size.
+const LZMA_PROPERTIES: [u8; 5] = [0x5D, 0x00, 0x00, 0x40, 0x00];
+
+/// This struct represents a temporary file, which gets removed from disk when this is dropped, even if we return early due to an error.
+struct TempFile(PathBuf);
+
+/// Implementation of `Drop` for `TempFile`.
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.0);
+    }
+}
+
+/// Implementation of `Codec`.
+///
+/// To support a new compression scheme, add its variant to `Codec`, map its `PFHVersion` to it here, and add its implementation to these functions.
+impl Codec {
+
+    /// This function returns the `Codec` used by PackFiles of the provided `PFHVersion`, or `None` if they don't support compression.
+    pub fn from_pfh_version(pfh_version: PFHVersion) -> Option<Self> {
+        match pfh_version {
+            PFHVersion::PFH5 => Some(Codec::Lzma1),
+            PFHVersion::PFH4 | PFHVersion::PFH3 | PFHVersion::PFH2 | PFHVersion::PFH0 => None,
+        }
+    }
+
+    /// This function decompress the provided data with this `Codec`, returning the decompressed data.
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Codec::Lzma1 => decompress_data(data),
+        }
+    }
+
+    /// This function compress the provided data with this `Codec`, using the provided settings, returning the compressed data.
+    pub fn compress(self, data: &[u8], settings: &CompressionSettings) -> Result<Vec<u8>> {
+        match self {
+            Codec::Lzma1 => compress_data(data, settings),
+        }
+    }
+
+    /// This function returns the size the provided data compressed with this `Codec` will have once decompressed, without decompressing it.
+    pub fn get_decompressed_size(self, data: &[u8]) -> Result<u32> {
+        match self {
+            Codec::Lzma1 => get_decompressed_size(data),
+        }
+    }
+}
+
+/// Implementation of trait `Default` for `Codec`.
+impl Default for Codec {
+
+    /// This function returns the `Codec` of the latest `PFHVersion`.
+    fn default() -> Self {
+        Codec::Lzma1
+    }
+}
+
+/// This function decompress the data of a PackedFile with `Codec::Lzma1`, returning the decompressed data.
 pub fn decompress_data(data: &[u8]) -> Result<Vec<u8>> {
     if !data.is_empty() {
         if data.len() >= 9 {
@@ -54,33 +121,52 @@ pub fn decompress_data(data: &[u8]) -> Result<Vec<u8>> {
     else { Ok(vec![]) }
 }
 
-/// This function compress the data of a PackedFile, returning the compressed data.
+/// This function returns the size the data of a PackedFile compressed with `Codec::Lzma1` will have once decompressed, without decompressing it.
+///
+/// CA's compressed files start with their decompressed size, so we only need the first bytes of the data for this.
+pub fn get_decompressed_size(data: &[u8]) -> Result<u32> {
+    if !data.is_empty() {
+        if data.len() >= 9 { data.decode_integer_u32(0) }
+        else { Err(ErrorKind::PackedFileDataCouldNotBeDecompressed.into()) }
+    }
+    else { Ok(0) }
+}
+
+/// This function compress the data of a PackedFile with `Codec::Lzma1`, returning the compressed data.
 ///
 /// Now, some explanation: CA uses Non-Streamed LZMA1 (or LZMA Alone) compressed files.
 /// Xz, the `standard` linux lib to deal with LZMA files has a fucking exception for
 /// Non-Streamed LZMA1 files. So we can decode from it, but not encode to it.
 /// So we do it the hard way: write the uncompressed file to disk, call 7z, compress it
-/// to 7z LZMA1 Level 3 format, read the compressed file, and remove the 7z part.
+/// to 7z LZMA1 format (with the level in the provided settings), read the compressed file, and remove the 7z part.
 /// Sadly, this means we have to ship 7z with RPFM. But hey, we're not the ones doing a
 /// fucking exception to a known format because we don't want to support the original format.
-pub fn compress_data(data: &[u8]) -> Result<Vec<u8>> {
+pub fn compress_data(data: &[u8], settings: &CompressionSettings) -> Result<Vec<u8>> {
 
-    // Prepare both paths, uncompressed and compressed.
-    let mut uncompressed_path = temp_dir();
-    let mut compressed_path = temp_dir();
-    uncompressed_path.push("frodo_best_waifu");
-    compressed_path.push("frodo_bestest_waifu.7z");
+    // Prepare both paths, uncompressed and compressed. They're unique per call, so we can compress in parallel.
+    // As the temporary files are not reused, they're removed once we're done with them, no matter how we leave this function.
+    let id = format!("{}_{}", process::id(), COMPRESSION_COUNTER.fetch_add(1, Ordering::SeqCst));
+    let uncompressed_file = TempFile(temp_dir().join(format!("frodo_best_waifu_{}", id)));
+    let compressed_file = TempFile(temp_dir().join(format!("frodo_bestest_waifu_{}.7z", id)));
 
     // Get the data into the uncompressed file, and launch 7z.
-    File::create(&uncompressed_path)?.write_all(data)?;
-    Command::new("7z").arg("a").arg("-m0=lzma").arg("-mx=3").arg(&compressed_path).arg(&uncompressed_path).output()?;
-
-    // Get the compressed LZMA data (and only that data) from the compressed file. To get it, we know:
-    // - The header of a 7z file is 32 bytes.
-    // - The bytes 12-16 are the offset of the footer from the end of the header.
-    // - We have just one file, so the offset is the exact lenght of that file.
-    // - Then we read the offset from the end of the header. And done.
-    let mut reader = BufReader::new(File::open(&compressed_path)?);
+    File::create(&uncompressed_file.0)?.write_all(data)?;
+    let level = settings.get_clamped_level();
+    Command::new("7z").arg("a").arg("-m0=lzma").arg(format!("-mx={}", level)).arg(MAX_DICTIONARY_SIZE).arg(&compressed_file.0).arg(&uncompressed_file.0).output()?;
+
+    let mut reader = BufReader::new(File::open(&compressed_file.0)?);
+    let compressed_data = get_lzma_data_from_7z(&mut reader)?;
+    Ok(add_ca_header(data.len(), compressed_data))
+}
+
+/// This function returns the compressed LZMA data (and only that data) from a 7z file with just one file compressed with LZMA1.
+///
+/// To get it, we know:
+/// - The header of a 7z file is 32 bytes.
+/// - The bytes 12-16 are the offset of the footer from the end of the header.
+/// - We have just one file, so the offset is the exact lenght of that file.
+/// - Then we read the offset from the end of the header. And done.
+pub(super) fn get_lzma_data_from_7z<R: Read + Seek>(reader: &mut R) -> Result<Vec<u8>> {
     let mut footer_offset = vec![0; 4];
     reader.seek(SeekFrom::Start(12))?;
     reader.read_exact(&mut footer_offset)?;
@@ -89,11 +175,14 @@ pub fn compress_data(data: &[u8]) -> Result<Vec<u8>> {
     let mut compressed_data = vec![0; compressed_data_lenght as usize];
     reader.seek(SeekFrom::Start(32))?;
     reader.read_exact(&mut compressed_data)?;
+    Ok(compressed_data)
+}
 
+/// This function adds the header CA uses in their compressed files to the provided LZMA data: the decompressed size, followed by the LZMA properties.
+pub(super) fn add_ca_header(decompressed_size: usize, mut compressed_data: Vec<u8>) -> Vec<u8> {
     let mut fixed_data = vec![];
-    fixed_data.encode_integer_i32(data.len() as i32);
-    fixed_data.extend_from_slice(&[0x5D, 0x00, 0x00, 0x40, 0x00]);
+    fixed_data.encode_integer_i32(decompressed_size as i32);
+    fixed_data.extend_from_slice(&LZMA_PROPERTIES);
     fixed_data.append(&mut compressed_data);
-
-    Ok(fixed_data)
+    fixed_data
 }
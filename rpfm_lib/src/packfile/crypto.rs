@@ -10,9 +10,13 @@
 
 // Here should be all the functions related with encryption/decryption.
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::num::Wrapping;
 
+use rpfm_error::{ErrorKind, Result};
+
+use crate::common::decoder::Decoder;
+use crate::common::encoder::Encoder;
+
 // Old key used in Arena, and all the way back to Shogun 2.
 // static INDEX_STRING_KEY: &str = "L2{B3dPL7L*v&+Q3ZsusUhy[BGQn(Uq$f>JQdnvdlf{-K:>OssVDr#TlYU|13B}r";
 
@@ -31,21 +35,44 @@ pub fn decrypt_index_item_file_length(ciphertext: u32, packed_files_after_this_o
     !packed_files_after_this_one ^ ciphertext ^ INDEX_U32_KEY
 }
 
+/// This function encrypts the size of a PackedFile. It's the same operation as decrypting it, so it requires the same things:
+/// - 'plaintext': the size of the PackedFile.
+/// - 'packed_files_after_this_one': the amount of items after this one in the Index.
+pub fn encrypt_index_item_file_length(plaintext: u32, packed_files_after_this_one: u32) -> u32 {
+    decrypt_index_item_file_length(plaintext, packed_files_after_this_one)
+}
+
 /// This function decrypts the path of a PackedFile. Requires:
 /// - 'ciphertext': the encrypted data of the PackedFile, read from the begining of the encrypted path.
 /// - 'decrypted_size': the decrypted size of the PackedFile.
 /// - 'offset': offset to know in what position of the index we should continue decoding the next entry.
-pub fn decrypt_index_item_filename(ciphertext: &[u8], decrypted_size: u8, offset: &mut usize) -> String {
-    let mut path: String = String::new();
+///
+/// If the data ends before the end of the path, or the path is not valid UTF-8, this returns an error.
+pub fn decrypt_index_item_filename(ciphertext: &[u8], decrypted_size: u8, offset: &mut usize) -> Result<String> {
+    let mut path = vec![];
     let mut index = 0;
     loop {
-        let character = ciphertext[index] ^ !decrypted_size ^ INDEX_STRING_KEY[index % INDEX_STRING_KEY.len()];
+        let character = ciphertext.get(index).ok_or(ErrorKind::PackFileIndexesNotComplete)? ^ !decrypted_size ^ INDEX_STRING_KEY[index % INDEX_STRING_KEY.len()];
         index += 1;
         if character == 0 { break; }
-        path.push(character as char);
+        path.push(character);
     }
+
+    // The path is UTF-8, like the ones in unencrypted indexes and the ones `encrypt_index_item_filename` generates.
+    let (path, _) = path.decode_string_u8_0terminated(0)?;
     *offset += index;
-    path
+    Ok(path)
+}
+
+/// This function encrypts the path of a PackedFile, including the 00 at the end of it. Requires:
+/// - 'plaintext': the path of the PackedFile, with `\\` as separator.
+/// - 'decrypted_size': the decrypted size of the PackedFile.
+pub fn encrypt_index_item_filename(plaintext: &str, decrypted_size: u8) -> Vec<u8> {
+    plaintext.as_bytes().iter()
+        .chain(&[0])
+        .enumerate()
+        .map(|(index, character)| character ^ !decrypted_size ^ INDEX_STRING_KEY[index % INDEX_STRING_KEY.len()])
+        .collect()
 }
 
 // Function to decrypt a PackedFile's data. Just needs the data to decrypt.
@@ -65,8 +92,8 @@ pub fn decrypt_packed_file(ciphertext: &[u8]) -> Vec<u8> {
 
         let mut prod = (DATA_KEY * Wrapping(u64::from(!edi))).0;
         let esi = edi as usize;
-        prod ^= (&ciphertext[esi..esi + 8]).read_u64::<LittleEndian>().unwrap();
-        plaintext.write_u64::<LittleEndian>(prod).unwrap();
+        prod ^= ciphertext.decode_integer_u64(esi).unwrap();
+        plaintext.encode_integer_u64(prod);
         edi += 8
     }
 
@@ -74,3 +101,11 @@ pub fn decrypt_packed_file(ciphertext: &[u8]) -> Vec<u8> {
     plaintext.truncate(size);
     plaintext
 }
+
+// Function to encrypt a PackedFile's data. Just needs the data to encrypt.
+//
+// The encryption is just a XOR with a keystream generated from the position of each 8 bytes pack, so encrypting
+// is the exact same operation as decrypting. The encrypted data has the same size as the original one.
+pub fn encrypt_packed_file(plaintext: &[u8]) -> Vec<u8> {
+    decrypt_packed_file(plaintext)
+}
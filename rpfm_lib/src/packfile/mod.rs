@@ -22,14 +22,21 @@ so you don't have to worry about that.
 use bitflags::bitflags;
 use csv::ReaderBuilder;
 use itertools::{Itertools, Either};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
 use serde_derive::{Serialize, Deserialize};
 use rayon::prelude::*;
 
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
 use std::{fmt, fmt::Display};
+use std::hash::Hasher;
 use std::fs::{DirBuilder, File};
 use std::io::{prelude::*, BufReader, BufWriter, SeekFrom, Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::SystemTime;
 
 use rpfm_error::{Error, ErrorKind, Result};
 
@@ -40,13 +47,13 @@ use crate::SCHEMA;
 use crate::SETTINGS;
 use crate::SUPPORTED_GAMES;
 use crate::common::{*, decoder::Decoder, encoder::Encoder};
-use crate::packfile::compression::*;
 use crate::packfile::crypto::*;
 use crate::packfile::packedfile::*;
 use crate::packedfile::{DecodedPackedFile, PackedFileType};
 use crate::packedfile::table::DecodedData;
 use crate::packedfile::table::db::DB;
 use crate::packedfile::table::loc::{Loc, TSV_NAME_LOC};
+use crate::schema::Schema;
 
 mod compression;
 mod crypto;
@@ -86,12 +93,27 @@ const FILE_TYPE_RELEASE: u32 = 1;
 const FILE_TYPE_PATCH: u32 = 2;
 const FILE_TYPE_MOD: u32 = 3;
 const FILE_TYPE_MOVIE: u32 = 4;
+
+/// These are the compression levels supported when compressing PackedFiles. The default one is the one vanilla files use.
+pub const MIN_COMPRESSION_LEVEL: u32 = 1;
+pub const MAX_COMPRESSION_LEVEL: u32 = 9;
+pub const DEFAULT_COMPRESSION_LEVEL: u32 = 3;
+
+/// Size of the extra data at the end of the header of PackFiles with an extended header.
+const EXTENDED_HEADER_DATA_SIZE: usize = 20;
+
+/// Size of the extra data at the end of PFH5 PackFiles with an extended header.
+const EXTENDED_FOOTER_DATA_SIZE: usize = 256;
+
+/// Size of the chunks used to read a file from disk when hashing it.
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
 bitflags! {
 
     /// This represents the bitmasks a PackFile can have applied to his type.
     ///
-    /// Keep in mind that this lib supports decoding PackFiles with any of these flags enabled,
-    /// but it only supports enconding for the `HAS_INDEX_WITH_TIMESTAMPS` flag.
+    /// Keep in mind that this lib supports decoding and encoding PackFiles with any of these flags enabled.
+    /// When saving, `HAS_ENCRYPTED_DATA` is set or cleared depending on if the PackedFiles should be encrypted or not.
     pub struct PFHFlags: u32 {
 
         /// Used to specify that the header of the PackFile is extended by 20 bytes. Used in Arena.
@@ -113,7 +135,7 @@ bitflags! {
 //---------------------------------------------------------------------------//
 
 /// This `Struct` stores the data of the PackFile in memory, along with some extra data needed to manipulate the PackFile.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct PackFile {
 
     /// The path of the PackFile on disk, if exists. If not, then this should be empty.
@@ -139,8 +161,73 @@ pub struct PackFile {
     /// The list of PackedFiles this PackFile contains.
     packed_files: Vec<PackedFile>,
 
+    /// Index of the positions of the PackedFiles in the list, by path, so we don't have to search the entire list to find one.
+    ///
+    /// This has to be rebuilt every time the list of PackedFiles or the path of any of them changes.
+    packed_files_index: PackedFilesIndex,
+
+    /// The extra data at the end of the header and the end of the PackFile, if it has an extended header. Only used in Arena.
+    ///
+    /// We don't know what they are, so we just keep them as they are to write them back when saving.
+    extended_header_data: Vec<u8>,
+    extended_footer_data: Vec<u8>,
+
     /// Notes added to the PackFile. Exclusive of this lib.
     notes: Option<String>,
+
+    /// Settings used to compress the PackedFiles that need it when saving the PackFile. Exclusive of this lib.
+    compression_settings: CompressionSettings,
+
+    /// Info about the file on disk the last time we read it or saved it, to know if someone else changed it. Exclusive of this lib.
+    source_file: SourceFile,
+}
+
+/// This struct holds the index of the positions of the `PackedFiles` of a `PackFile` in its list, by path.
+///
+/// Lookups only get a shared reference to the `PackFile`, but they have to rebuild the index if it may no longer match the list,
+/// so the positions are behind a lock.
+#[derive(Debug, Default)]
+struct PackedFilesIndex {
+
+    /// The positions of the PackedFiles in the list, by path.
+    positions: RwLock<HashMap<Vec<String>, usize>>,
+
+    /// If the positions may no longer match the list of PackedFiles, because we gave out mutable references to them.
+    ///
+    /// If this is true, the positions are rebuilt the next time someone looks for a path.
+    outdated: AtomicBool,
+}
+
+/// This struct holds what we know about the file of a `PackFile` on disk, as it was when we read it or saved it for the last time.
+///
+/// Hashing a file means reading all of it, so only its size and modification time are stored when reading or saving.
+/// The hash is calculated the first time someone asks for it.
+#[derive(Debug, Default)]
+struct SourceFile {
+
+    /// The size and modification time of the file.
+    metadata: Option<(u64, SystemTime)>,
+
+    /// The hash of the file, once someone has asked for it.
+    hash: RwLock<Option<u64>>,
+}
+
+/// This struct contains the settings used to compress PackedFiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionSettings {
+
+    /// The compression level, from `MIN_COMPRESSION_LEVEL` (fastest) to `MAX_COMPRESSION_LEVEL` (smallest). Values out of that range are clamped.
+    pub level: u32,
+}
+
+/// This enum represents the compression schemes used for the data of the PackedFiles.
+///
+/// Each `PFHVersion` supporting compression uses one of these. Check `Codec::from_pfh_version` to know which one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+
+    /// Non-Streamed LZMA1 (or LZMA Alone), with CA's tweaked header. Used in PFH5.
+    Lzma1,
 }
 
 /// This struct is a reduced version of the `PackFile` one, used to pass just the needed data to an UI.
@@ -171,6 +258,57 @@ pub struct PackFileInfo {
     pub timestamp: i64,
 }
 
+/// This struct contains the sizes of the data of the `PackedFiles` of a `PackFile`, as stored and once decompressed.
+///
+/// Get it from `PackFile::compression_report`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompressionReport {
+
+    /// The sizes of each `PackedFile`, in the same order they're in the `PackFile`.
+    pub packed_files: Vec<CompressionReportEntry>,
+
+    /// The size of the data of all the `PackedFiles`, as stored in the `PackFile`.
+    pub compressed_size: u64,
+
+    /// The size of the data of all the `PackedFiles` once decompressed.
+    pub decompressed_size: u64,
+}
+
+/// This struct contains the sizes of the data of a `PackedFile`, as stored and once decompressed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressionReportEntry {
+
+    /// The path of the `PackedFile`.
+    pub path: Vec<String>,
+
+    /// The size of the data of the `PackedFile`, as stored in the `PackFile`. For not compressed `PackedFiles`, this is the same as his decompressed size.
+    pub compressed_size: u32,
+
+    /// The size of the data of the `PackedFile` once decompressed.
+    pub decompressed_size: u32,
+}
+
+/// This struct represents a problem found in a `PackFile` by `PackFile::validate_all`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+
+    /// The path of the `PackedFile` with the problem.
+    pub path: Vec<String>,
+
+    /// What's wrong with the `PackedFile`.
+    pub description: String,
+}
+
+/// This struct contains the changes between two `PackFiles`, file by file.
+///
+/// Get it from `PackFile::diff`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackFileDiff {
+
+    /// The path of every `PackedFile` in any of the `PackFiles`, sorted by path, with how it changed.
+    pub packed_files: Vec<(Vec<String>, PackedFileDiffState)>,
+}
+
 /// This struct represents the entire **Manifest.txt** from the /data folder.
 ///
 /// Private for now, because I see no public use for this.
@@ -192,6 +330,11 @@ struct ManifestEntry {
 }
 
 /// This enum represents the **Version** of a PackFile.
+///
+/// The version is the *Id/Preamble* at the start of the header of the PackFile, and it depends on the game the PackFile is for.
+/// Each version is used by a family of games, as documented in each variant. Games that share a version share the PackFile format,
+/// but not their tables, so a PackFile of the right version can still be incompatible with a game of the family.
+/// For the exact versions each game uses, check the `pfh_version` of the game in `SUPPORTED_GAMES`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PFHVersion {
 
@@ -274,6 +417,23 @@ pub enum CompressionState {
     Disabled,
 }
 
+/// This enum indicates how a `PackedFile` changed between two `PackFiles`, as returned by `PackFile::diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackedFileDiffState {
+
+    /// The `PackedFile` is only in the new `PackFile`.
+    Added,
+
+    /// The `PackedFile` is only in the old `PackFile`.
+    Removed,
+
+    /// The `PackedFile` is in both `PackFiles`, but with different data.
+    Modified,
+
+    /// The `PackedFile` is in both `PackFiles`, with the same data.
+    Unchanged,
+}
+
 //---------------------------------------------------------------------------//
 //                             Enum Implementations
 //---------------------------------------------------------------------------//
@@ -345,6 +505,18 @@ impl PFHVersion {
             _ => Err(ErrorKind::PackFileIsNotAPackFile.into()),
         }
     }
+
+    /// This function returns if PackFiles of this `PFHVersion` can contain encrypted data.
+    ///
+    /// We haven't found a single encrypted PFH3/PFH2/PFH0 PackFile, so we only support encryption in PFH5 and PFH4.
+    pub fn supports_encryption(self) -> bool {
+        matches!(self, PFHVersion::PFH5 | PFHVersion::PFH4)
+    }
+
+    /// This function returns if PackFiles of this `PFHVersion` can contain compressed data.
+    pub fn supports_compression(self) -> bool {
+        Codec::from_pfh_version(self).is_some()
+    }
 }
 
 /// Display implementation of `PFHVersion`.
@@ -456,8 +628,14 @@ impl PackFile {
 
             pack_files: vec![],
             packed_files: vec![],
+            packed_files_index: PackedFilesIndex::default(),
 
-            notes: None
+            extended_header_data: vec![],
+            extended_footer_data: vec![],
+
+            notes: None,
+            compression_settings: CompressionSettings::default(),
+            source_file: SourceFile::default(),
         }
     }
 
@@ -472,8 +650,14 @@ impl PackFile {
 
             pack_files: vec![],
             packed_files: vec![],
+            packed_files_index: PackedFilesIndex::default(),
+
+            extended_header_data: vec![],
+            extended_footer_data: vec![],
 
             notes: None,
+            compression_settings: CompressionSettings::default(),
+            source_file: SourceFile::default(),
         }
     }
 
@@ -548,7 +732,7 @@ impl PackFile {
             .map(|packed_file| packed_file.get_path().to_vec())
             .collect::<Vec<Vec<String>>>());
         self.packed_files.append(&mut packed_files_new);
-
+        self.rebuild_packed_files_index();
 
         // Now we deal with the problematic ones. If we set them to overwrite the conflicting files...we just replace them.
         if overwrite {
@@ -599,9 +783,57 @@ impl PackFile {
             self.packed_files.append(&mut packed_files_conflict);
         }
 
+        self.rebuild_packed_files_index();
+
         Ok(destination_paths)
     }
 
+    /// This function adds a `PackedFile` to an existing `PackFile`, failing if there is already a `PackedFile` with its path.
+    ///
+    /// Unlike `add_packed_file`, this never renames the `PackedFile`. If its path is empty, contains empty parts or is one
+    /// of the reserved paths, this returns an error and the `PackFile` is left untouched.
+    pub fn add_file(&mut self, packed_file: PackedFile) -> Result<()> {
+        Self::check_path_for_add_file(packed_file.get_path())?;
+        if self.contains_path(packed_file.get_path()) {
+            return Err(ErrorKind::DuplicatedPackedFile(packed_file.get_path().to_vec()).into())
+        }
+
+        self.add_file_unchecked(packed_file);
+        Ok(())
+    }
+
+    /// This function adds a `PackedFile` to an existing `PackFile`, replacing the `PackedFile` with its path if there is one.
+    ///
+    /// The path is checked the same way `add_file` checks it.
+    pub fn add_file_overwrite(&mut self, mut packed_file: PackedFile) -> Result<()> {
+        Self::check_path_for_add_file(packed_file.get_path())?;
+        match self.get_packed_file_position(packed_file.get_path()) {
+            Some(position) => {
+                packed_file.get_ref_mut_raw().set_packfile_name(&self.get_file_name());
+                self.packed_files[position] = packed_file;
+            }
+            None => self.add_file_unchecked(packed_file),
+        }
+        Ok(())
+    }
+
+    /// This function checks if the provided path can be used for a new `PackedFile` added through `add_file`.
+    fn check_path_for_add_file(path: &[String]) -> Result<()> {
+        if path.is_empty() || path.iter().any(|x| x.is_empty()) { return Err(ErrorKind::EmptyInput.into()) }
+        if Self::get_reserved_packed_file_names().iter().any(|x| x == path) { return Err(ErrorKind::ReservedFiles.into()) }
+        Ok(())
+    }
+
+    /// This function adds a `PackedFile` at the end of the list of `PackedFiles`, updating the path index without rebuilding it.
+    ///
+    /// It doesn't check for conflicts, so make sure the path is not already in the `PackFile` before calling it.
+    fn add_file_unchecked(&mut self, mut packed_file: PackedFile) {
+        packed_file.get_ref_mut_raw().set_packfile_name(&self.get_file_name());
+        self.update_packed_files_index();
+        self.packed_files_index.positions.get_mut().unwrap().insert(packed_file.get_path().to_vec(), self.packed_files.len());
+        self.packed_files.push(packed_file);
+    }
+
     /// This function is used to add a file from disk to a `PackFile`, turning it into a `PackedFile`.
     ///
     /// In case of conflict, if overwrite is set to true, the current `PackedFile` in the conflicting path
@@ -752,6 +984,7 @@ impl PackFile {
     /// This can fail if you pass it an empty path.
     pub fn set_file_path(&mut self, path: &Path) -> Result<()> {
         if path.components().count() == 0 { return Err(ErrorKind::EmptyInput.into()) }
+        if self.file_path != path { self.source_file = SourceFile::default(); }
         self.file_path = path.to_path_buf();
 
         // We have to change the name of the PackFile in all his `PackedFiles` too.
@@ -760,6 +993,125 @@ impl PackFile {
         Ok(())
     }
 
+    /// This function returns the size all the PackedFiles of the provided `PackFile` will take once decompressed.
+    ///
+    /// Useful to know how much space we need before extracting the entire `PackFile`. The data of the PackedFiles is not decompressed for this.
+    pub fn total_decompressed_size(&self) -> Result<u64> {
+        self.packed_files.par_iter().map(|x| x.get_ref_raw().get_decompressed_size().map(u64::from)).sum()
+    }
+
+    /// This function returns the amount of memory used by the data of all the `PackedFiles` of the provided `PackFile`, in bytes.
+    ///
+    /// Only the data loaded to memory counts, so `PackedFiles` still on disk (like the ones not yet used with lazy loading) count as 0.
+    /// The decoded data of the `PackedFiles` is not included.
+    pub fn memory_usage(&self) -> usize {
+        self.packed_files.iter().map(|x| x.get_ref_raw().get_memory_usage()).sum()
+    }
+
+    /// This function returns the sizes of the data of all the `PackedFiles` of the provided `PackFile`, as stored and once decompressed.
+    ///
+    /// Useful to know how much space the compression saves. Like with `total_decompressed_size`, the data of the PackedFiles is not decompressed for this.
+    pub fn compression_report(&self) -> Result<CompressionReport> {
+        let packed_files = self.packed_files.par_iter().map(|x| {
+            let raw = x.get_ref_raw();
+            Ok(CompressionReportEntry {
+                path: raw.get_path().to_vec(),
+                compressed_size: raw.get_size(),
+                decompressed_size: raw.get_decompressed_size()?,
+            })
+        }).collect::<Result<Vec<CompressionReportEntry>>>()?;
+
+        Ok(CompressionReport {
+            compressed_size: packed_files.iter().map(|x| u64::from(x.compressed_size)).sum(),
+            decompressed_size: packed_files.iter().map(|x| u64::from(x.decompressed_size)).sum(),
+            packed_files,
+        })
+    }
+
+    /// This function compares the provided `PackFile` (the old one) with another one (the new one), returning how each `PackedFile` changed.
+    ///
+    /// `PackedFiles` are matched by path, and compared by their decompressed and decrypted data, so changes in compression don't count as changes.
+    /// If the data of any `PackedFile` in both `PackFiles` cannot be read, this returns an error.
+    pub fn diff(&self, other: &PackFile) -> Result<PackFileDiff> {
+        let mut packed_files = self.packed_files.par_iter().map(|packed_file| {
+            let path = packed_file.get_path();
+            let state = match other.get_ref_packed_file_by_path(path) {
+                Some(other_packed_file) => {
+                    let raw = packed_file.get_ref_raw();
+                    let other_raw = other_packed_file.get_ref_raw();
+                    if raw.get_decompressed_size()? != other_raw.get_decompressed_size()? || raw.get_data()? != other_raw.get_data()? {
+                        PackedFileDiffState::Modified
+                    } else { PackedFileDiffState::Unchanged }
+                }
+                None => PackedFileDiffState::Removed,
+            };
+            Ok((path.to_vec(), state))
+        }).collect::<Result<Vec<(Vec<String>, PackedFileDiffState)>>>()?;
+
+        packed_files.extend(other.packed_files.iter()
+            .filter(|x| !self.packedfile_exists(x.get_path()))
+            .map(|x| (x.get_path().to_vec(), PackedFileDiffState::Added)));
+
+        packed_files.sort_by(|x, y| x.0.cmp(&y.0));
+        Ok(PackFileDiff {
+            packed_files,
+        })
+    }
+
+    /// This function checks all the `PackedFiles` of the provided `PackFile` for problems, returning all the ones it finds.
+    ///
+    /// It checks that the paths are not empty nor duplicated (ignoring the case, as the games do), that the data can be read
+    /// and decompressed to the size it should have, and that the `PackedFiles` we have a decoder for can be decoded.
+    /// Unlike most checks, this doesn't stop on the first problem.
+    pub fn validate_all(&self, schema: &Schema) -> Vec<ValidationIssue> {
+        let mut issues = self.packed_files.par_iter()
+            .flat_map(|packed_file| Self::validate_packed_file(packed_file.get_ref_raw(), schema))
+            .collect::<Vec<ValidationIssue>>();
+
+        let mut paths = self.packed_files.iter().map(|x| (x.get_path().join("\\").to_lowercase(), x.get_path())).collect::<Vec<(String, &[String])>>();
+        paths.sort_by(|x, y| x.0.cmp(&y.0));
+        for paths in paths.windows(2).filter(|x| x[0].0 == x[1].0) {
+            issues.push(ValidationIssue {
+                path: paths[1].1.to_vec(),
+                description: "There is more than one PackedFile with this path.".to_owned(),
+            });
+        }
+
+        issues
+    }
+
+    /// This function checks the provided `RawPackedFile` for problems, for `validate_all`.
+    fn validate_packed_file(raw_packed_file: &RawPackedFile, schema: &Schema) -> Vec<ValidationIssue> {
+        let path = raw_packed_file.get_path();
+        let issue = |description: String| ValidationIssue { path: path.to_vec(), description };
+        let mut issues = vec![];
+        if path.is_empty() || path.iter().any(|x| x.is_empty()) {
+            issues.push(issue("The path of this PackedFile is empty, or has empty folders.".to_owned()));
+        }
+
+        let data = match raw_packed_file.get_data() {
+            Ok(data) => data,
+            Err(error) => {
+                issues.push(issue(format!("The data of this PackedFile cannot be read or decompressed: {}", error)));
+                return issues;
+            }
+        };
+
+        if raw_packed_file.get_compression_state() {
+            match raw_packed_file.get_decompressed_size() {
+                Ok(size) if size as usize == data.len() => {},
+                Ok(size) => issues.push(issue(format!("The data of this PackedFile should be {} bytes once decompressed, but it's {} bytes.", size, data.len()))),
+                Err(error) => issues.push(issue(format!("The decompressed size of this PackedFile cannot be read: {}", error))),
+            }
+        }
+
+        if let Err(error) = DecodedPackedFile::decode_no_locks(raw_packed_file, schema) {
+            issues.push(issue(format!("This PackedFile cannot be decoded: {}", error)));
+        }
+
+        issues
+    }
+
     /// This function returns the current compression state of the provided `PackFile`.
     ///
     /// To get more info about the different compression states, check the `CompressionState` enum.
@@ -788,8 +1140,8 @@ impl PackFile {
     /// This function returns if the `PackFile` is editable or not.
     ///
     /// By *if is editable or not* I mean *If you can save it or not*. The conditions under which a PackFile is not editable are:
-    /// - All PackFiles with extended header or encrypted parts are not editable.
-    /// - All PackFiles of type `Mod` or `Movie` are editable.
+    /// - All PackFiles of an unknown type are not editable.
+    /// - All PackFiles of type `Mod` or `Movie` are editable, including the ones with extended header or encrypted parts.
     /// - If you say CA PackFiles are not editable:
     ///   - All PackFiles of type `Boot`, `Release` or `Patch` are not editable.
     /// - If you say CA PackFiles are editable:
@@ -798,11 +1150,6 @@ impl PackFile {
 
         // If it's this very specific type, don't save under any circunstance.
         if let PFHFileType::Other(_) = self.pfh_file_type { false }
-
-        // If ANY of these bitmask is detected in the PackFile, disable all saving.
-        else if self.bitmask.contains(PFHFlags::HAS_ENCRYPTED_DATA) ||
-            self.bitmask.contains(PFHFlags::HAS_ENCRYPTED_INDEX) ||
-            self.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) { false }
         else {
             self.pfh_file_type == PFHFileType::Mod ||
             self.pfh_file_type == PFHFileType::Movie ||
@@ -817,12 +1164,62 @@ impl PackFile {
 
     /// This function returns a reference to the `PackedFile` with the provided path, if exists.
     pub fn get_ref_packed_file_by_path(&self, path: &[String]) -> Option<&PackedFile> {
-        self.packed_files.par_iter().find_any(|x| x.get_path() == path)
+        self.get_packed_file_position(path).map(|x| &self.packed_files[x])
     }
 
     /// This function returns a mutable reference to the `PackedFile` with the provided path, if exists.
+    ///
+    /// If the path of the `PackedFile` is changed through this reference, the path index is rebuilt the next time someone looks for a path.
+    /// Use `rename_file` to change its path without that penalty.
     pub fn get_ref_mut_packed_file_by_path(&mut self, path: &[String]) -> Option<&mut PackedFile> {
-        self.packed_files.par_iter_mut().find_any(|x| x.get_path() == path)
+        let position = self.get_packed_file_position(path)?;
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
+        Some(&mut self.packed_files[position])
+    }
+
+    /// This function checks if a `PackedFile` with the provided path exists in the `PackFile`, without searching the entire list.
+    pub fn contains_path(&self, path: &[String]) -> bool {
+        self.get_packed_file_position(path).is_some()
+    }
+
+    /// This function returns a reference to the `PackedFile` with the provided path, if exists, without searching the entire list.
+    pub fn get_by_path(&self, path: &[String]) -> Option<&PackedFile> {
+        self.get_packed_file_position(path).map(|x| &self.packed_files[x])
+    }
+
+    /// This function returns the position in the list of PackedFiles of the `PackedFile` with the provided path, if exists.
+    ///
+    /// If we gave out mutable references to the PackedFiles since the last time the index was rebuilt, it's rebuilt first.
+    /// If the index still doesn't match the list (for example, if there are duplicated paths) this falls back to searching the entire list.
+    /// Hits in the index are always checked against the path of the PackedFile.
+    fn get_packed_file_position(&self, path: &[String]) -> Option<usize> {
+        if self.packed_files_index.outdated.load(AtomicOrdering::SeqCst) {
+            self.packed_files_index.rebuild(&self.packed_files);
+        }
+
+        let positions = self.packed_files_index.positions.read().unwrap();
+        if positions.len() == self.packed_files.len() {
+            match positions.get(path).copied() {
+                Some(position) if self.packed_files.get(position).map_or(false, |packed_file| packed_file.get_path() == path) => Some(position),
+                Some(_) => self.packed_files.par_iter().position_any(|x| x.get_path() == path),
+                None => None,
+            }
+        }
+        else {
+            self.packed_files.par_iter().position_any(|x| x.get_path() == path)
+        }
+    }
+
+    /// This function rebuilds the index of PackedFile paths. Remember to call it after any change to the list of PackedFiles or their paths.
+    fn rebuild_packed_files_index(&mut self) {
+        self.packed_files_index.rebuild(&self.packed_files);
+    }
+
+    /// This function rebuilds the index of PackedFile paths, but only if it may be out of sync with the list of PackedFiles.
+    fn update_packed_files_index(&mut self) {
+        if self.packed_files_index.outdated.load(AtomicOrdering::SeqCst) || self.packed_files_index.positions.get_mut().unwrap().len() != self.packed_files.len() {
+            self.rebuild_packed_files_index();
+        }
     }
 
     /// This function returns a copy of all the `PackedFiles` in the provided paths.
@@ -837,6 +1234,7 @@ impl PackFile {
 
     /// This function returns a mutable reference of all the `PackedFiles` in the provided paths.
     pub fn get_ref_mut_packed_files_by_paths(&mut self, paths: Vec<&[String]>) -> Vec<&mut PackedFile> {
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
         self.packed_files.par_iter_mut().filter(|x| paths.contains(&x.get_path())).collect()
     }
 
@@ -852,6 +1250,7 @@ impl PackFile {
 
     /// This function returns a mutable reference of all the `PackedFiles` starting with the provided path.
     pub fn get_ref_mut_packed_files_by_path_start(&mut self, path: &[String]) -> Vec<&mut PackedFile> {
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
         self.packed_files.par_iter_mut().filter(|x| x.get_path().starts_with(path) && !path.is_empty() && x.get_path().len() > path.len()).collect()
     }
 
@@ -877,6 +1276,7 @@ impl PackFile {
 
     /// This function returns a mutable reference of all the `PackedFiles` ending with the provided path.
     pub fn get_ref_mut_packed_files_by_path_end(&mut self, path: &[String]) -> Vec<&mut PackedFile> {
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
         self.packed_files.par_iter_mut().filter(|x| x.get_path().ends_with(path) && !path.is_empty()).collect()
     }
 
@@ -892,9 +1292,17 @@ impl PackFile {
 
     /// This function returns a mutable reference of all the `PackedFiles` ending with the provided extension.
     pub fn get_ref_mut_packed_files_by_extension(&mut self, extension: &str) -> Vec<&mut PackedFile> {
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
         self.packed_files.par_iter_mut().filter(|x| x.get_path().last().unwrap().ends_with(extension) && !extension.is_empty()).collect()
     }
 
+    /// This function returns a reference of all the `PackedFiles` with a path matching the provided glob pattern.
+    ///
+    /// For the syntax of the pattern, check `path_matches_glob`. Keep in mind the matching is case-insensitive.
+    pub fn find_by_glob(&self, pattern: &str) -> Vec<&PackedFile> {
+        self.packed_files.par_iter().filter(|x| path_matches_glob(x.get_path(), pattern)).collect()
+    }
+
     /// This function returns a copy of all the PackedFiles in the current PackFile of the provided type.
     ///
     /// If `strict_match_mode` is enabled, only the PackedFiles of the specified type and subtype will be returned.
@@ -919,11 +1327,33 @@ impl PackFile {
             }).collect()
     }
 
+    /// This function returns a reference of all the PackedFiles in the current PackFile of the provided type, detected from their paths.
+    ///
+    /// This is `get_ref_packed_files_by_type` with `strict_match_mode` enabled, for when you want to show only one type of PackedFile.
+    pub fn files_of_type(&self, packed_file_type: PackedFileType) -> Vec<&PackedFile> {
+        self.get_ref_packed_files_by_type(packed_file_type, true)
+    }
+
+    /// This function returns a reference of all the PackedFiles in the current PackFile, sorted by their timestamp.
+    ///
+    /// PackedFiles without timestamp (timestamp 0) always go at the end. PackedFiles with the same timestamp are sorted by their path,
+    /// case-insensitive and in ascending order, whatever the order of the timestamps is.
+    pub fn files_sorted_by_timestamp(&self, newest_first: bool) -> Vec<&PackedFile> {
+        let mut packed_files = self.packed_files.iter().collect::<Vec<&PackedFile>>();
+        packed_files.sort_by_cached_key(|x| {
+            let timestamp = x.get_ref_raw().get_timestamp();
+            let order = if newest_first { -(timestamp as i128) } else { timestamp as i128 };
+            (timestamp == 0, order, x.get_path().join("/").to_lowercase())
+        });
+        packed_files
+    }
+
     /// This function returns a mutable reference of all the PackedFiles in the current PackFile of the provided type.
     ///
     /// If `strict_match_mode` is enabled, only the PackedFiles of the specified type and subtype will be returned.
     /// NOTE: This does not garantee the provided PackedFiles are of the type. Just that they `match` the type.
     pub fn get_ref_mut_packed_files_by_type(&mut self, packed_file_type: PackedFileType, strict_match_mode: bool) -> Vec<&mut PackedFile> {
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
         self.packed_files.par_iter_mut()
             .filter(|x| {
                 let y = PackedFileType::get_packed_file_type(x.get_path());
@@ -960,6 +1390,7 @@ impl PackFile {
     /// If `strict_match_mode` is enabled, only the PackedFiles of the specified type and subtype will be returned.
     /// NOTE: This does not garantee the provided PackedFiles are of the type. Just that they `match` one of the types.
     pub fn get_ref_mut_packed_files_by_types(&mut self, packed_file_types: &[PackedFileType], strict_match_mode: bool) -> Vec<&mut PackedFile> {
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
         self.packed_files.par_iter_mut()
             .filter(|x| {
                 let y = PackedFileType::get_packed_file_type(x.get_path());
@@ -967,6 +1398,21 @@ impl PackFile {
             }).collect()
     }
 
+    /// This function returns a reference of all the `PackedFiles` in the current `PackFile` that came from the provided source `PackFile`.
+    ///
+    /// This is useful when we load multiple PackFiles as one, as each `PackedFile` keeps the name of the `PackFile` it was loaded from.
+    pub fn files_from_source(&self, packfile_name: &str) -> Vec<&PackedFile> {
+        self.packed_files.par_iter().filter(|x| x.get_ref_raw().get_packfile_name() == packfile_name).collect()
+    }
+
+    /// This function returns the sorted list of names of the `PackFiles` the `PackedFiles` in the current `PackFile` came from.
+    pub fn list_source_packfiles(&self) -> Vec<String> {
+        let mut packfile_names = self.packed_files.par_iter().map(|x| x.get_ref_raw().get_packfile_name().to_owned()).collect::<Vec<String>>();
+        packfile_names.sort();
+        packfile_names.dedup();
+        packfile_names
+    }
+
     /// This function returns a copy of all `PackedFiles` in the provided `PackFile`.
     pub fn get_packed_files_all(&self) -> Vec<PackedFile> {
         self.packed_files.clone()
@@ -979,6 +1425,7 @@ impl PackFile {
 
     /// This function returns a mutable reference of all the `PackedFiles` in the provided `PackFile`.
     pub fn get_ref_mut_packed_files_all(&mut self) -> Vec<&mut PackedFile> {
+        self.packed_files_index.outdated.store(true, AtomicOrdering::SeqCst);
         self.packed_files.par_iter_mut().collect()
     }
 
@@ -1006,9 +1453,27 @@ impl PackFile {
     pub fn remove_packed_file_by_path(&mut self, path: &[String]) {
         if let Some(position) = self.packed_files.par_iter().position_any(|x| x.get_path() == path) {
             self.packed_files.remove(position);
+            self.rebuild_packed_files_index();
         }
     }
 
+    /// This function moves the `PackedFile` with the provided path from this `PackFile` to the provided one, without copying his data.
+    ///
+    /// The path is checked the same way `add_file` checks it. If there is no `PackedFile` with the path in this `PackFile`,
+    /// or there is already one in the destination, this fails and both PackFiles are left as they were.
+    pub fn move_file_to(&mut self, path: &[String], dest: &mut PackFile) -> Result<()> {
+        let position = self.get_packed_file_position(path).ok_or_else(|| Error::from(ErrorKind::PackedFileNotFound))?;
+        Self::check_path_for_add_file(path)?;
+        if dest.packedfile_exists(path) {
+            return Err(ErrorKind::DuplicatedPackedFile(path.to_vec()).into())
+        }
+
+        let packed_file = self.packed_files.remove(position);
+        self.rebuild_packed_files_index();
+        dest.add_file_unchecked(packed_file);
+        Ok(())
+    }
+
     /// This function removes, if exists, all `PackedFile` starting with the provided path from the `PackFile`.
     pub fn remove_packed_files_by_path_start(&mut self, path: &[String]) {
         let positions: Vec<usize> = self.packed_files.iter()
@@ -1019,6 +1484,7 @@ impl PackFile {
         for position in positions.iter().rev() {
             self.packed_files.remove(*position);
         }
+        self.rebuild_packed_files_index();
     }
 
     /// This function removes, if exists, all `PackedFile` ending with the provided path from the `PackFile`.
@@ -1031,6 +1497,17 @@ impl PackFile {
         for position in positions.iter().rev() {
             self.packed_files.remove(*position);
         }
+        self.rebuild_packed_files_index();
+    }
+
+    /// This function removes all the `PackedFile` whose path matches the provided glob pattern from the `PackFile`, returning how many were removed.
+    ///
+    /// Check `path_matches_glob` for the syntax of the pattern. If nothing matches, this does nothing and returns 0.
+    pub fn remove_by_glob(&mut self, pattern: &str) -> usize {
+        let old_len = self.packed_files.len();
+        self.packed_files.retain(|x| !path_matches_glob(x.get_path(), pattern));
+        self.rebuild_packed_files_index();
+        old_len - self.packed_files.len()
     }
 
     /// This function removes, if exists, all `PackedFile` of the provided types from the `PackFile`.
@@ -1106,6 +1583,67 @@ impl PackFile {
         }
     }
 
+    /// This function returns the data of the `PackedFile` with the provided path, without extracting it to disk.
+    ///
+    /// If the `PackedFile` is decoded, his current decoded data is the one returned. If not, and the `PackFile` was opened with lazy-loading,
+    /// only the data of this `PackedFile` is read from disk, without loading it to memory. If there is no `PackedFile` with that path, this returns an error.
+    pub fn extract_one(&self, path: &[String]) -> Result<Vec<u8>> {
+        match self.get_ref_packed_file_by_path(path) {
+            Some(packed_file) => match packed_file.get_ref_decoded().encode() {
+                Some(data) => data,
+                None => packed_file.get_ref_raw().get_data(),
+            }
+            None => Err(ErrorKind::PackedFileNotFound.into())
+        }
+    }
+
+    /// This function extracts all the `PackedFiles` with a path matching the provided glob pattern to the provided folder, returning the paths they were extracted to.
+    ///
+    /// If `flatten` is false, the folders of each `PackedFile` are recreated inside `base`. If it's true, all of them are extracted directly into `base`,
+    /// and when more than one has the same name, the second one gets `_1` added to the end of its name (before the extension), the third one `_2`, etc.
+    /// As for the rest of the extraction functions, files already on disk are overwritten. Check `path_matches_glob` for the syntax of the pattern.
+    pub fn extract_by_glob(&self, pattern: &str, base: &Path, flatten: bool) -> Result<Vec<PathBuf>> {
+        let mut extracted_paths: Vec<PathBuf> = vec![];
+        for packed_file in self.find_by_glob(pattern) {
+            let path = packed_file.get_path();
+            let file_name = path.last().unwrap();
+            let destination_path = if flatten {
+                let file_path = Path::new(file_name);
+                let mut destination_path = base.join(file_name);
+                let mut suffix = 1;
+
+                // Paths are compared lowercased, as some filesystems don't care about the case either.
+                while extracted_paths.iter().any(|x| x.to_string_lossy().to_lowercase() == destination_path.to_string_lossy().to_lowercase()) {
+                    let mut new_name = file_path.file_stem().unwrap_or_default().to_os_string();
+                    new_name.push(format!("_{}", suffix));
+                    if let Some(extension) = file_path.extension() {
+                        new_name.push(".");
+                        new_name.push(extension);
+                    }
+
+                    destination_path = base.join(new_name);
+                    suffix += 1;
+                }
+                destination_path
+            } else {
+                base.join(path.iter().collect::<PathBuf>())
+            };
+
+            if let Some(parent) = destination_path.parent() {
+                DirBuilder::new().recursive(true).create(parent)?;
+            }
+
+            let data = self.extract_one(path)?;
+            let mut file = BufWriter::new(File::create(&destination_path)?);
+            if file.write_all(&data).and_then(|_| file.flush()).is_err() {
+                return Err(ErrorKind::ExtractError(path.to_vec()).into());
+            }
+            extracted_paths.push(destination_path);
+        }
+
+        Ok(extracted_paths)
+    }
+
     /// This function extract, if exists, all `PackedFile` of the provided types from the `PackFile` to disk.
     ///
     /// As this can fail for some files, and work for others, we return `Ok(amount_files_extracted)` only if all files were extracted correctly.
@@ -1115,6 +1653,19 @@ impl PackFile {
         item_types: &[PathType],
         extracted_path: &PathBuf,
     ) -> Result<u32> {
+        self.extract_packed_files_by_type_with_progress(item_types, extracted_path, |_, _| {})
+    }
+
+    /// This function works like `extract_packed_files_by_type`, but it reports the progress of the extraction to the provided callback.
+    ///
+    /// The callback receives `(done, total)`, and it's called once after each `PackedFile` is processed, successfully or not,
+    /// so it's called exactly `total` times, with `done` going from 1 to `total`. If there is nothing to extract, it's never called.
+    pub fn extract_packed_files_by_type_with_progress(
+        &mut self,
+        item_types: &[PathType],
+        extracted_path: &PathBuf,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<u32> {
 
         // These variables are here to keep track of what we have extracted and what files failed.
         let mut files_extracted = 0;
@@ -1134,53 +1685,38 @@ impl PackFile {
             }
         }
 
-        // Then we act, depending on the combination of items.
-        match contents {
+        // Then we get the paths of the `PackedFiles` to extract, depending on the combination of items,
+        // so we know how many of them we have to extract before starting.
+        let paths = match contents {
 
             // Any combination of files and folders.
+            // For folders we check each PackedFile to see if it starts with the folder's path (it's in the folder).
+            // There should be no duplicates here thanks to the filters from before.
             1 | 2 | 3 => {
-
-                // For folders we check each PackedFile to see if it starts with the folder's path (it's in the folder).
-                // There should be no duplicates here thanks to the filters from before.
+                let mut paths = vec![];
                 for item_type in &item_types_clean {
                     match item_type {
-
-                        // For individual `PackedFiles`, we extract them one by one.
-                        PathType::File(path) => {
-                            match self.extract_packed_file_by_path(path, extracted_path) {
-                                Ok(_) => files_extracted += 1,
-                                Err(_) => error_files.push(format!("{:?}", path)),
-                            }
-                        },
-
-                        PathType::Folder(path) => {
-                            for packed_file in self.get_ref_mut_packed_files_by_path_start(path) {
-                                match packed_file.extract_packed_file(extracted_path) {
-                                    Ok(_) => files_extracted += 1,
-                                    Err(_) => error_files.push(format!("{:?}", path)),
-                                }
-                            }
-                        },
-
+                        PathType::File(path) => paths.push(path.to_vec()),
+                        PathType::Folder(path) => paths.extend(self.get_ref_packed_files_by_path_start(path).iter().map(|x| x.get_path().to_vec())),
                         _ => unreachable!(),
                     }
                 }
+                paths
             },
 
             // If the `PackFile` is selected, just extract it and everything will get extracted with it.
-            4 | 5 | 6 | 7 => {
-
-                // For each PackedFile we have, just extracted in the folder we got, under the PackFile's folder.
-                for packed_file in self.get_ref_mut_packed_files_all() {
-                    match packed_file.extract_packed_file(extracted_path) {
-                        Ok(_) => files_extracted += 1,
-                        Err(_) => error_files.push(format!("{:?}", packed_file.get_path())),
-                    }
-                }
-            },
+            4 | 5 | 6 | 7 => self.get_packed_files_all_paths(),
 
             // No paths selected, none selected, invalid path selected, or invalid value.
             0 | 8..=255 => return Err(ErrorKind::NonExistantFile.into()),
+        };
+
+        for (index, path) in paths.iter().enumerate() {
+            match self.extract_packed_file_by_path(path, extracted_path) {
+                Ok(_) => files_extracted += 1,
+                Err(_) => error_files.push(format!("{:?}", path)),
+            }
+            progress(index + 1, paths.len());
         }
 
         // If there is any error in the list, report it.
@@ -1193,11 +1729,107 @@ impl PackFile {
         Ok(files_extracted)
     }
 
+    /// This function decodes all the `PackedFiles` of the provided types, reporting the progress to the provided callback.
+    ///
+    /// The callback receives `(done, total)`, and it's called once after each `PackedFile` is decoded, successfully or not,
+    /// so it's called exactly `total` times, with `done` going from 1 to `total`. The decoding is done sequentially to keep that order.
+    ///
+    /// It returns the paths of the `PackedFiles` that failed to decode.
+    pub fn decode_packed_files_by_types_with_progress(
+        &mut self,
+        packed_file_types: &[PackedFileType],
+        schema: &Schema,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Vec<Vec<String>> {
+        let mut packed_files = self.get_ref_mut_packed_files_by_types(packed_file_types, false);
+        let total = packed_files.len();
+        let mut error_files = vec![];
+        for (index, packed_file) in packed_files.iter_mut().enumerate() {
+            if packed_file.decode_no_locks(schema).is_err() {
+                error_files.push(packed_file.get_path().to_vec());
+            }
+            progress(index + 1, total);
+        }
+
+        error_files
+    }
+
+    /// This function replaces the data of multiple `PackedFiles` of the `PackFile` at once.
+    ///
+    /// Their decoded data is removed from the cache, and the new data gets compressed (in parallel with the rest) when saving the `PackFile`,
+    /// if the `PackedFiles` should be compressed. If any of the paths is not in the `PackFile`, this returns an error and nothing is changed.
+    pub fn set_data_batch(&mut self, entries: Vec<(Vec<String>, Vec<u8>)>) -> Result<()> {
+        self.update_packed_files_index();
+        let indexes = entries.iter()
+            .map(|(path, _)| self.get_packed_file_position(path).ok_or_else(|| Error::from(ErrorKind::PackedFileNotFound)))
+            .collect::<Result<Vec<usize>>>()?;
+
+        for (index, (_, data)) in indexes.into_iter().zip(entries.into_iter()) {
+            let packed_file = &mut self.packed_files[index];
+            packed_file.set_decoded(&DecodedPackedFile::Unknown);
+            packed_file.set_raw_data(&data);
+        }
+        Ok(())
+    }
+
     /// This function enables/disables compression in all `PackedFiles` inside the `PackFile`. Partial compression is not supported.
     pub fn toggle_compression(&mut self, enable: bool) {
         self.packed_files.par_iter_mut().for_each(|x| x.get_ref_mut_raw().set_should_be_compressed(enable));
     }
 
+    /// This function sets if all the `PackedFiles` inside the `PackFile` should be compressed when saving it.
+    ///
+    /// Unlike `toggle_compression`, this checks the `PFHVersion` of the `PackFile` first, and fails without changing anything
+    /// if we try to enable compression in a version that doesn't support it.
+    pub fn set_all_compressed(&mut self, state: bool) -> Result<()> {
+        if state && !self.pfh_version.supports_compression() {
+            return Err(ErrorKind::PackFileVersionDoesNotSupportCompression(self.pfh_version.to_string()).into())
+        }
+
+        self.toggle_compression(state);
+        Ok(())
+    }
+
+    /// This function sets if all the `PackedFiles` inside the `PackFile` should be encrypted when saving it, and for which `PFHVersion`.
+    ///
+    /// If either the provided version or the one of the `PackFile` doesn't support encryption, this fails without changing anything.
+    pub fn set_all_encrypted(&mut self, version: Option<PFHVersion>) -> Result<()> {
+        if let Some(version) = version {
+            if let Some(unsupported) = [version, self.pfh_version].iter().find(|x| !x.supports_encryption()) {
+                return Err(ErrorKind::PackFileVersionDoesNotSupportEncryption(unsupported.to_string()).into())
+            }
+        }
+
+        self.packed_files.par_iter_mut().for_each(|x| x.get_ref_mut_raw().set_should_be_encrypted(version));
+        Ok(())
+    }
+
+    /// This function marks for compression only the `PackedFiles` of the provided `PackFile` bigger than `min_size` once decompressed.
+    ///
+    /// Compressing smaller files is not worth it, as they can even get bigger. Already compressed `PackedFiles` whose compressed data
+    /// is not smaller than their decompressed data are not marked either. `PackedFiles` whose size cannot be read are left as they are.
+    pub fn optimize_compression(&mut self, min_size: u32) {
+        self.packed_files.par_iter_mut().for_each(|x| {
+            let raw = x.get_ref_mut_raw();
+            if let Ok(decompressed_size) = raw.get_decompressed_size() {
+                let shrinks = !raw.get_compression_state() || raw.get_size() < decompressed_size;
+                raw.set_should_be_compressed(decompressed_size > min_size && shrinks);
+            }
+        });
+    }
+
+    /// This function returns the settings used to compress the `PackedFiles` of the provided `PackFile` when saving it.
+    pub fn get_compression_settings(&self) -> CompressionSettings {
+        self.compression_settings
+    }
+
+    /// This function sets the settings used to compress the `PackedFiles` of the provided `PackFile` when saving it.
+    ///
+    /// Only `PackedFiles` that get compressed during the save use them. Already compressed ones are saved as they are.
+    pub fn set_compression_settings(&mut self, compression_settings: CompressionSettings) {
+        self.compression_settings = compression_settings;
+    }
+
     /// This function returns the notes contained within the provided `PackFile`.
     pub fn get_notes(&self) -> &Option<String> {
         &self.notes
@@ -1219,6 +1851,8 @@ impl PackFile {
     }
 
     /// This function returns the `PFHVersion` of the provided `PackFile`.
+    ///
+    /// For PackFiles read from disk, this is the version in their header. Check `PFHVersion` for the games each version is used by.
     pub fn get_pfh_version(&self) -> PFHVersion {
         self.pfh_version
     }
@@ -1258,14 +1892,22 @@ impl PackFile {
         self.bitmask = bitmask;
     }
 
+    /// This function returns if the index of the provided `PackFile` stores the timestamps of his `PackedFiles`.
+    ///
+    /// If this is false, the timestamps of the `PackedFiles` are not tracked, so a timestamp of 0 doesn't mean the epoch.
+    pub fn has_timestamps(&self) -> bool {
+        self.bitmask.contains(PFHFlags::HAS_INDEX_WITH_TIMESTAMPS)
+    }
+
     /// This function remove all `PackedFiles` from a `PackFile`.
     pub fn remove_all_packedfiles(&mut self) {
         self.packed_files = vec![];
+        self.rebuild_packed_files_index();
     }
 
     /// This function checks if a `PackedFile` with a certain path exists in a `PackFile`.
     pub fn packedfile_exists(&self, path: &[String]) -> bool {
-        self.packed_files.par_iter().any(|x| x.get_path() == path)
+        self.get_packed_file_position(path).is_some()
     }
 
     /// This function checks if a folder with `PackedFiles` in it exists in a `PackFile`.
@@ -1359,12 +2001,45 @@ impl PackFile {
         match self.get_ref_mut_packed_file_by_path(source_path) {
             Some(packed_file) => {
                 packed_file.get_ref_mut_raw().set_path(&destination_path)?;
+                let destination_path = packed_file.get_path().to_vec();
+                self.rebuild_packed_files_index();
                 Ok(destination_path)
             },
             None => Err(ErrorKind::PackedFileNotFound.into())
         }
     }
 
+    /// This function changes the path of the `PackedFile` with the provided path, updating the path index with it.
+    ///
+    /// Unlike `move_packedfile`, this doesn't resolve collisions: if there is already a `PackedFile` in the new path, or there is no `PackedFile`
+    /// in the old one, it returns an error. The path of the `PackedFile` and the path index are either both updated, or none of them are.
+    /// Like the games, the check for collisions ignores the case, so changing only the case of the path of a `PackedFile` is fine.
+    pub fn rename_file(&mut self, old: &[String], new: &[String]) -> Result<()> {
+        let new = normalize_path(new, false);
+        if new.is_empty() { return Err(ErrorKind::EmptyInput.into()) }
+        if old == &*new { return Err(ErrorKind::PathsAreEqual.into()) }
+        if Self::get_reserved_packed_file_names().contains(&new) { return Err(ErrorKind::ReservedFiles.into()) }
+
+        self.update_packed_files_index();
+        let position = self.get_packed_file_position(old).ok_or_else(|| Error::from(ErrorKind::PackedFileNotFound))?;
+        let new_lowercase = new.join("\\").to_lowercase();
+        if self.packed_files.iter().enumerate().any(|(index, x)| index != position && x.get_path().join("\\").to_lowercase() == new_lowercase) {
+            return Err(ErrorKind::DuplicatedPackedFile(new).into())
+        }
+
+        self.packed_files[position].get_ref_mut_raw().set_path(&new)?;
+
+        // If the index was out of sync, we cannot just update the entry, so rebuild it entirely.
+        let positions = self.packed_files_index.positions.get_mut().unwrap();
+        if positions.remove(old) == Some(position) {
+            positions.insert(new, position);
+        }
+        else {
+            self.rebuild_packed_files_index();
+        }
+        Ok(())
+    }
+
     /// This function allows you to change the name of a folder inside a `PackFile`.
     ///
     /// By default this append a `_number` to the file names in case of collision. If you want it to overwrite instead,
@@ -1395,6 +2070,40 @@ impl PackFile {
         Ok(successes)
     }
 
+    /// This function replaces the provided folder (path prefix) with a new one in the paths of all the `PackedFiles` inside it.
+    ///
+    /// Unlike `move_folder`, this doesn't resolve collisions: if any of the new paths is already used by a `PackedFile` not in the folder
+    /// (ignoring the case, as the games do), nothing gets renamed and it returns an error with that path. It returns how many `PackedFiles` were renamed.
+    pub fn rename_folder(&mut self, old_prefix: &[String], new_prefix: &[String]) -> Result<usize> {
+        let new_prefix = normalize_path(new_prefix, false);
+        if old_prefix.is_empty() || new_prefix.is_empty() { return Err(ErrorKind::EmptyInput.into()) }
+        if old_prefix == &*new_prefix { return Err(ErrorKind::PathsAreEqual.into()) }
+
+        let is_in_folder = |path: &[String]| path.starts_with(old_prefix) && path.len() > old_prefix.len();
+        let other_paths = self.packed_files.iter()
+            .map(|x| x.get_path())
+            .filter(|x| !is_in_folder(x))
+            .map(|x| x.join("\\").to_lowercase())
+            .collect::<HashSet<String>>();
+
+        let reserved_names = Self::get_reserved_packed_file_names();
+        let mut new_paths = vec![];
+        for (position, packed_file) in self.packed_files.iter().enumerate().filter(|(_, x)| is_in_folder(x.get_path())) {
+            let mut new_path = new_prefix.to_vec();
+            new_path.extend_from_slice(&packed_file.get_path()[old_prefix.len()..]);
+            if reserved_names.contains(&new_path) { return Err(ErrorKind::ReservedFiles.into()) }
+            if other_paths.contains(&new_path.join("\\").to_lowercase()) { return Err(ErrorKind::DuplicatedPackedFile(new_path).into()) }
+            new_paths.push((position, new_path));
+        }
+
+        for (position, new_path) in &new_paths {
+            self.packed_files[*position].get_ref_mut_raw().set_path(new_path)?;
+        }
+
+        self.rebuild_packed_files_index();
+        Ok(new_paths.len())
+    }
+
     /// This function is used to rename one or more `PackedFile`/Folder inside a `PackFile`.
     ///
     /// It returns the list of "Original Path/New Path" of each renamed PackedFile.
@@ -1479,9 +2188,9 @@ impl PackFile {
                                         DecodedData::Integer(ref entry) => entry.to_string(),
                                         DecodedData::LongInteger(ref entry) => entry.to_string(),
                                         DecodedData::StringU8(ref entry) |
-                                        DecodedData::StringU16(ref entry) |
+                                        DecodedData::StringU16(ref entry) => entry.to_owned(),
                                         DecodedData::OptionalStringU8(ref entry) |
-                                        DecodedData::OptionalStringU16(ref entry) => entry.to_owned(),
+                                        DecodedData::OptionalStringU16(ref entry) => entry.clone().unwrap_or_default(),
                                         _ => "NoData".to_owned()
                                     };
 
@@ -1500,7 +2209,7 @@ impl PackFile {
                                 broken_columns.dedup();
                                 let mut broken_columns = broken_columns.iter().map(|x| format!("{},", *x + 1)).collect::<String>();
                                 broken_columns.pop();
-                                broken_tables.push(format!("Table: {}/{}, Column/s: {}", &path[1], &path[2], broken_columns));
+                                broken_tables.push(format!("Table: {}/{}, Column/s: {}", PackedFileType::get_db_table_name(path).unwrap_or_default(), path.last().unwrap(), broken_columns));
                             }
                         }
                     }
@@ -1927,20 +2636,24 @@ impl PackFile {
                     match packed_file.decode_return_ref_no_locks(schema) {
                         Ok(data) => match data {
                             DecodedPackedFile::DB(data) => {
+                                let table_name = match PackedFileType::get_db_table_name(&path) {
+                                    Ok(table_name) => table_name,
+                                    Err(error) => return error_list.push((path.join("\\"), error)),
+                                };
 
                                 // His name will be "db_name_file_name.tsv". If that's taken, we'll add an index until we find one available.
-                                let mut name = format!("{}_{}.tsv", path[1], path.last().unwrap().to_owned());
+                                let mut name = format!("{}_{}.tsv", table_name, path.last().unwrap().to_owned());
                                 let mut export_path = export_path.to_path_buf();
 
                                 // Checks to avoid overwriting exported files go here, in an infinite loop of life and death.
                                 let mut index = 1;
                                 while exported_files.contains(&name) {
-                                    name = format!("{}_{}_{}.tsv", path[1], path.last().unwrap().to_owned(), index);
+                                    name = format!("{}_{}_{}.tsv", table_name, path.last().unwrap().to_owned(), index);
                                     index += 1;
                                 }
 
                                 export_path.push(name.to_owned());
-                                match data.export_tsv(&export_path, &path[1]) {
+                                match data.export_tsv(&export_path, table_name) {
                                     Ok(_) => exported_files.push(name),
                                     Err(error) => error_list.push((packed_file.get_path().join("\\"), error)),
                                 }
@@ -2226,23 +2939,181 @@ impl PackFile {
         }
     }
 
+    /// This function reads the `PackFile` in the provided path like `read` with lazy loading does,
+    /// but getting the data of the `PackedFiles` from a memory map of the file, instead of reading it from the file on each access.
+    ///
+    /// The file must not be changed by anyone else while the `PackFile` is open.
+    #[cfg(feature = "mmap")]
+    pub fn read_mapped(file_path: &PathBuf) -> Result<Self> {
+        let mut pack_file = Self::read(file_path, true)?;
+
+        // This is unsafe because the file can be changed under our feet, same as it can happen with lazy loading.
+        let map = Arc::new(unsafe { Mmap::map(&File::open(&file_path)?)? });
+        pack_file.packed_files.iter_mut().for_each(|x| x.get_ref_mut_raw().map_data(&map));
+        Ok(pack_file)
+    }
+
+    /// This function reads the `PackFile` in the provided path like `read` with lazy loading does, optionally checking his compressed data.
+    ///
+    /// If `verify` is true, the data of every compressed `PackedFile` is decompressed while opening the `PackFile`, so corrupted data is found now
+    /// instead of when someone tries to use it. If any of them fails, this returns an error with all the `PackedFiles` that failed.
+    pub fn open_with_verification(file_path: &PathBuf, verify: bool) -> Result<Self> {
+        let pack_file = Self::read(file_path, true)?;
+        if verify {
+            let mut errors = pack_file.packed_files.par_iter()
+                .map(|x| x.get_ref_raw())
+                .filter(|x| x.get_compression_state())
+                .filter_map(|raw_packed_file| {
+                    let error = match raw_packed_file.get_data() {
+                        Ok(data) => match raw_packed_file.get_decompressed_size() {
+                            Ok(size) if size as usize == data.len() => return None,
+                            Ok(size) => format!("expected {} bytes once decompressed, but got {} bytes.", size, data.len()),
+                            Err(error) => error.to_string(),
+                        },
+                        Err(error) => error.to_string(),
+                    };
+                    Some(format!("{}: {}", raw_packed_file.get_path().join("/"), error))
+                })
+                .collect::<Vec<String>>();
+
+            if !errors.is_empty() {
+                errors.sort();
+                return Err(ErrorKind::PackFileCorruptedCompressedData(errors).into())
+            }
+        }
+        Ok(pack_file)
+    }
+
+    /// This function returns a hash of the contents of the file in the provided path.
+    ///
+    /// The file is read in chunks, so this doesn't need to load the entire file to memory. The hash is only meant to detect changes
+    /// in the file while we're running, so don't store it anywhere.
+    pub fn file_hash(path: &Path) -> Result<u64> {
+        let mut file = File::open(path)?;
+        let mut buffer = vec![0; HASH_CHUNK_SIZE];
+        let mut hasher = DefaultHasher::new();
+        loop {
+            match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(size) => hasher.write(&buffer[..size]),
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(hasher.finish())
+    }
+
+    /// This function returns the `PFHVersion` of the PackFile in the provided path, reading only the start of its header.
+    ///
+    /// Useful to know which games a PackFile is for before reading it. If the file is not a PackFile, this returns an error.
+    pub fn read_pfh_version(file_path: &Path) -> Result<PFHVersion> {
+        let mut preamble = vec![0; 4];
+        File::open(file_path)?.read_exact(&mut preamble).map_err(|_| Error::from(ErrorKind::PackFileHeaderNotComplete))?;
+        PFHVersion::get_version(&preamble.decode_string_u8(0, 4)?)
+    }
+
+    /// This function returns the hash of the file of this `PackFile`, as it was when we read it or saved it for the last time.
+    ///
+    /// Compare it with `file_hash` to know if the file has been changed by someone else since then, or just use `has_source_changed`.
+    /// The hash is calculated (and cached) the first time this is called, so the first call has to read the entire file.
+    ///
+    /// If the `PackFile` has never been read from or saved to disk, or the file already changed when the hash is first asked for, this returns `None`.
+    pub fn source_hash(&self) -> Option<u64> {
+        if let Some(hash) = *self.source_file.hash.read().unwrap() { return Some(hash) }
+
+        let metadata = self.source_file.metadata?;
+        if Self::file_metadata(&self.file_path).ok()? != metadata { return None }
+
+        let hash = Self::file_hash(&self.file_path).ok()?;
+        *self.source_file.hash.write().unwrap() = Some(hash);
+        Some(hash)
+    }
+
+    /// This function returns if the file of this `PackFile` has been changed by someone else since we read it or saved it for the last time.
+    ///
+    /// If the size and modification time of the file are the ones we remember, the file is considered unchanged. Otherwise, if we know
+    /// the hash of the file (check `source_hash`), the file is hashed again to be sure. If the `PackFile` has never been read from or
+    /// saved to disk, this returns false.
+    pub fn has_source_changed(&self) -> bool {
+        let metadata = match self.source_file.metadata {
+            Some(metadata) => metadata,
+            None => return false,
+        };
+
+        match Self::file_metadata(&self.file_path) {
+            Ok(current_metadata) if current_metadata == metadata => false,
+            Ok(_) => match *self.source_file.hash.read().unwrap() {
+                Some(hash) => Self::file_hash(&self.file_path).map_or(true, |current_hash| current_hash != hash),
+                None => true,
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// This function returns the size and modification time of the file in the provided path.
+    fn file_metadata(path: &Path) -> Result<(u64, SystemTime)> {
+        let metadata = std::fs::metadata(path)?;
+        Ok((metadata.len(), metadata.modified()?))
+    }
+
+    /// This function remembers the size and modification time of the file of this `PackFile`, forgetting its hash.
+    fn update_source_file(&mut self) -> Result<()> {
+        self.source_file = SourceFile {
+            metadata: Some(Self::file_metadata(&self.file_path)?),
+            hash: RwLock::new(None),
+        };
+        Ok(())
+    }
+
     /// This function reads the content of a PackFile into a `PackFile` struct.
     pub fn read(
         file_path: &PathBuf,
         use_lazy_loading: bool
     ) -> Result<Self> {
+        Self::read_from_path(file_path, use_lazy_loading)
+    }
+
+    /// This function reads only the header and the indexes of the PackFile in the provided path, like `read` with lazy loading does.
+    ///
+    /// Not a single byte of the data of the PackedFiles is read until someone asks for it, so this is the fastest way to list the contents
+    /// of a big PackFile. The only exception are the notes of the PackFile, which are read so they're not lost if it's saved. The hash
+    /// of the file is not calculated either, until someone asks for it with `source_hash`.
+    pub fn open_index_only(file_path: &Path) -> Result<Self> {
+        Self::read_from_path(&file_path.to_path_buf(), true)
+    }
+
+    /// This function reads the content of the PackFile in the provided path into a `PackFile` struct.
+    fn read_from_path(
+        file_path: &PathBuf,
+        use_lazy_loading: bool,
+    ) -> Result<Self> {
 
         // Check if what we received is even a `PackFile`.
         if !file_path.file_name().unwrap().to_string_lossy().to_string().ends_with(".pack") { return Err(ErrorKind::OpenPackFileInvalidExtension.into()) }
 
-        // Prepare the PackFile to be read and the virtual PackFile to be written.
-        let mut pack_file = BufReader::new(File::open(&file_path)?);
+        let file = Arc::new(File::open(&file_path)?);
+        let mut reader = BufReader::new(&*file);
+        Self::read_from_reader(&mut reader, file.clone(), file_path, use_lazy_loading)
+    }
+
+    /// This function reads the content of a PackFile into a `PackFile` struct.
+    ///
+    /// The header, the indexes and the notes are read from `reader`, while the data of the PackedFiles is left in `file`, which
+    /// has to be the same file, to be loaded when needed (or right away, if `use_lazy_loading` is false).
+    fn read_from_reader<R: Read + Seek>(
+        pack_file: &mut R,
+        file: Arc<File>,
+        file_path: &Path,
+        use_lazy_loading: bool,
+    ) -> Result<Self> {
+
+        // Prepare the virtual PackFile to be written.
         let pack_file_name = file_path.file_name().unwrap().to_string_lossy().to_string();
         let mut pack_file_decoded = Self::new();
 
         // First, we do some quick checkings to ensure it's a valid PackFile.
         // 24 is the bare minimum that we need to check how a PackFile should be internally, so any file with less than that is not a valid PackFile.
-        let pack_file_len = pack_file.get_ref().metadata()?.len();
+        let pack_file_len = file.metadata()?.len();
         if pack_file_len < 24 { return Err(ErrorKind::PackFileHeaderNotComplete.into()) }
 
         // Create a little buffer to read the basic data from the header of the PackFile.
@@ -2267,18 +3138,20 @@ impl PackFile {
                 if (pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) && pack_file_len < 48) ||
                     (!pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) && pack_file_len < 28) { return Err(ErrorKind::PackFileHeaderNotComplete.into()) }
 
-                if pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) { buffer = vec![0; 48]; }
-                else { buffer = vec![0; 28]; }
+                if pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) { buffer.resize(48, 0); }
+                else { buffer.resize(28, 0); }
             }
 
-            PFHVersion::PFH3 | PFHVersion::PFH2 => buffer = vec![0; 32],
-            PFHVersion::PFH0 => buffer = vec![0; 24],
+            PFHVersion::PFH3 | PFHVersion::PFH2 => buffer.resize(32, 0),
+            PFHVersion::PFH0 => {},
         }
 
-        // Restore the cursor of the BufReader to 0, so we can read the full header in one go. The first 24 bytes are
-        // already decoded but, for the sake of clarity in the positions of the rest of the header stuff, we do this.
-        pack_file.seek(SeekFrom::Start(0))?;
-        pack_file.read_exact(&mut buffer)?;
+        // Read the rest of the header after the first 24 bytes we already have, so the positions of the rest of the header stuff
+        // are the same as in the file, without reading the first bytes twice.
+        pack_file.read_exact(&mut buffer[24..])?;
+        if pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) && buffer.len() > 28 {
+            pack_file_decoded.extended_header_data = buffer[28..].to_vec();
+        }
 
         // The creation time is a bit of an asshole. Depending on the PackFile Version/Id/Preamble, it uses a type, another or it doesn't exists.
         // Keep in mind that we store his raw value. If you want his legible value, you have to convert it yourself. PFH0 doesn't have it.
@@ -2291,10 +3164,8 @@ impl PackFile {
         // Ensure the PackFile has all the data needed for the index. If the PackFile's data is encrypted
         // and the PackFile is PFH5, due to how the encryption works, the data should start in a multiple of 8.
         let mut data_position = u64::from(buffer.len() as u32 + pack_file_index_size + packed_file_index_size);
-        if pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_DATA) &&
-            pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) &&
-            pack_file_decoded.pfh_version == PFHVersion::PFH5 {
-            data_position = if (data_position % 8) > 0 { data_position + 8 - (data_position % 8) } else { data_position };
+        if pack_file_decoded.has_padded_data(pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_DATA)) {
+            data_position += Self::get_padding_size(data_position);
         }
         if pack_file_len < data_position { return Err(ErrorKind::PackFileIndexesNotComplete.into()) }
 
@@ -2342,9 +3213,13 @@ impl PackFile {
 
         // Prepare the needed stuff to read the PackedFiles.
         let mut index_position: usize = 0;
-        let pack_file = Arc::new(Mutex::new(pack_file));
         for packed_files_to_decode in (0..packed_file_count).rev() {
 
+            // If the index ends before we got all the PackedFiles the header says we have, the PackFile is broken.
+            if index_position + packed_file_index_path_offset >= packed_file_index.len() {
+                return Err(ErrorKind::PackFileFileCountMismatch { declared: packed_file_count, found: packed_file_count - packed_files_to_decode - 1 }.into())
+            }
+
             // Get his size. If it's encrypted, decrypt it first.
             let size = if pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_INDEX) {
                 let encrypted_size = packed_file_index.decode_integer_u32(index_position)?;
@@ -2370,29 +3245,33 @@ impl PackFile {
                 }
             } else { 0 };
 
-            // Update his offset, and get his compression data if it has it.
+            // Update his offset, and get his compression data if it has it. Arena PackFiles don't have it.
             index_position += packed_file_index_path_offset;
-            let is_compressed = if let PFHVersion::PFH5 = pack_file_decoded.pfh_version {
+            let is_compressed = if pack_file_decoded.pfh_version.supports_compression() && !pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) {
                 if let Ok(true) = packed_file_index.decode_bool(index_position - 1) { true }
                 else { false }
             } else { false };
 
             // Get his path. Like the PackFile index, it's a StringU8 terminated in 00. We get it and split it in folders for easy use.
             let path = if pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_INDEX) {
-                decrypt_index_item_filename(&packed_file_index[index_position..], size as u8, &mut index_position)
+                decrypt_index_item_filename(&packed_file_index[index_position..], size as u8, &mut index_position)?
             }
             else { packed_file_index.decode_packedfile_string_u8_0terminated(index_position, &mut index_position)? };
             let path = path.split('\\').map(|x| x.to_owned()).collect::<Vec<String>>();
 
+            // If the index claims the data goes past the end of the file, the index is broken. Fail now, instead of when reading the data.
+            let data_end = data_position.saturating_add(u64::from(size));
+            if data_end > pack_file_len { return Err(ErrorKind::PackedFileSizeExceedsFile(path, data_end, pack_file_len).into()) }
+
             // Once we are done, we create the and add it to the PackedFile list.
-            let raw_data = RawPackedFile::read_from_data(
+            let mut raw_data = RawPackedFile::read_from_data(
                 path,
                 pack_file_name.to_string(),
                 timestamp,
                 is_compressed,
                 if pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_DATA) { Some(pack_file_decoded.pfh_version) } else { None },
                 PackedFileData::OnDisk(
-                    pack_file.clone(),
+                    file.clone(),
                     data_position,
                     size,
                     is_compressed,
@@ -2400,45 +3279,88 @@ impl PackFile {
                 )
             );
 
-            let packed_file = PackedFile::new_from_raw(&raw_data);
+            if let Some(codec) = Codec::from_pfh_version(pack_file_decoded.pfh_version) {
+                raw_data.set_compression_codec(codec);
+            }
 
             // If this is a notes PackedFile, save the notes and forget about the PackedFile. Otherwise, save the PackedFile.
-            if packed_file.get_path() == ["notes.rpfm_reserved"] {
-                if let Ok(data) = packed_file.get_ref_raw().get_data() {
-                    if let Ok(data) = data.decode_string_u8(0, data.len()) {
-                        pack_file_decoded.notes = Some(data);
+            // The notes are read from the reader, like the indexes, so they don't count as reading the data of the PackedFiles.
+            if raw_data.get_path() == ["notes.rpfm_reserved"] {
+                let mut data = vec![0; size as usize];
+                if pack_file.seek(SeekFrom::Start(data_position)).is_ok() && pack_file.read_exact(&mut data).is_ok() {
+                    let is_encrypted = if pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_DATA) { Some(pack_file_decoded.pfh_version) } else { None };
+                    let mut notes = RawPackedFile::read_from_data(raw_data.get_path().to_vec(), pack_file_name.to_string(), timestamp, is_compressed, is_encrypted, PackedFileData::OnMemory(data, is_compressed, is_encrypted));
+                    notes.set_compression_codec(raw_data.get_compression_codec());
+                    if let Ok(data) = notes.get_data() {
+                        if let Ok(data) = data.decode_string_u8(0, data.len()) {
+                            pack_file_decoded.notes = Some(data);
+                        }
                     }
                 }
             }
             else {
-                pack_file_decoded.packed_files.push(packed_file);
+                pack_file_decoded.packed_files.push(PackedFile::new_from_raw(&raw_data));
             }
 
             // Then we move our data position. For encrypted files in PFH5 PackFiles (only ARENA) we have to start the next one in a multiple of 8.
-            if pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_DATA) &&
-                pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) &&
-                pack_file_decoded.pfh_version == PFHVersion::PFH5 {
-                let padding = 8 - (size % 8);
-                let padded_size = if padding < 8 { size + padding } else { size };
-                data_position += u64::from(padded_size);
+            if pack_file_decoded.has_padded_data(pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_DATA)) {
+                data_position += u64::from(size) + Self::get_padding_size(u64::from(size));
             }
             else { data_position += u64::from(size); }
         }
 
+        // If there are more PackedFiles in the index than the header says, the PackFile is broken too.
+        // NOTE: We can only count the extra ones if the index is not encrypted, as their keys depend on the declared amount.
+        if index_position < packed_file_index.len() && !pack_file_decoded.bitmask.contains(PFHFlags::HAS_ENCRYPTED_INDEX) {
+            let mut found = packed_file_count;
+            while index_position < packed_file_index.len() {
+                index_position += packed_file_index_path_offset;
+                index_position += packed_file_index.get(index_position..).and_then(|x| x.iter().position(|x| *x == 0)).map_or(packed_file_index.len(), |x| x + 1);
+                found += 1;
+            }
+            return Err(ErrorKind::PackFileFileCountMismatch { declared: packed_file_count, found }.into())
+        }
+
         // If at this point we have not reached the end of the PackFile, there is something wrong with it.
         // NOTE: Arena PackFiles have extra data at the end. If we detect one of those PackFiles, take that into account.
         if pack_file_decoded.pfh_version == PFHVersion::PFH5 && pack_file_decoded.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) {
-            if data_position + 256 != pack_file_len { return Err(ErrorKind::PackFileSizeIsNotWhatWeExpect(pack_file_len, data_position).into()) }
+            if data_position + EXTENDED_FOOTER_DATA_SIZE as u64 != pack_file_len { return Err(ErrorKind::PackFileSizeIsNotWhatWeExpect(pack_file_len, data_position).into()) }
+
+            let mut footer = vec![0; EXTENDED_FOOTER_DATA_SIZE];
+            pack_file.seek(SeekFrom::Start(data_position))?;
+            pack_file.read_exact(&mut footer)?;
+            pack_file_decoded.extended_footer_data = footer;
         }
         else if data_position != pack_file_len { return Err(ErrorKind::PackFileSizeIsNotWhatWeExpect(pack_file_len, data_position).into()) }
 
         // If we disabled lazy-loading, load every PackedFile to memory.
         if !use_lazy_loading { for packed_file in &mut pack_file_decoded.packed_files { packed_file.get_ref_mut_raw().load_data()?; }}
+        pack_file_decoded.rebuild_packed_files_index();
+        pack_file_decoded.update_source_file()?;
 
         // Return our PackFile.
         Ok(pack_file_decoded)
     }
 
+    /// This function rebuilds the index of the `PackFile` from his current `PackedFiles`, to leave it as it'll be saved.
+    ///
+    /// This means sorting the `PackedFiles` in the same (case-insensitive) order the index uses, and re-encoding the decoded ones,
+    /// so the sizes in the index (and with them, the offsets of the data) match their current data. If two `PackedFiles` have the same
+    /// path, ignoring the case as the games do, this returns an error with the offending path.
+    pub fn rebuild_index(&mut self) -> Result<()> {
+        self.packed_files.sort_unstable_by_key(|x| x.get_path().join("\\").to_lowercase());
+        self.rebuild_packed_files_index();
+        if let Some(packed_files) = self.packed_files.windows(2).find(|x| x[0].get_path().join("\\").to_lowercase() == x[1].get_path().join("\\").to_lowercase()) {
+            return Err(ErrorKind::DuplicatedPackedFile(packed_files[1].get_path().to_vec()).into())
+        }
+
+        for packed_file in &mut self.packed_files {
+            if let DecodedPackedFile::Unknown = packed_file.get_ref_decoded() { continue; }
+            packed_file.encode()?;
+        }
+        Ok(())
+    }
+
     /// This function tries to save a `PackFile` to a file in the filesystem.
     ///
     /// If no path is passed, the `PackFile` will be saved in his current path.
@@ -2452,6 +3374,114 @@ impl PackFile {
         if let Some(path) = new_path { self.set_file_path(&path)?; }
         else if !self.get_file_path().is_file() { return Err(ErrorKind::PackFileIsNotAFile.into()) }
 
+        // We may be overwriting the file the data of our PackedFiles is in, so we need to load all of it to memory before creating the file.
+        let should_be_encrypted_pack = self.prepare_for_saving(true)?;
+        let mut file = BufWriter::new(File::create(&self.file_path)?);
+        self.write(&mut file, should_be_encrypted_pack)?;
+        file.flush()?;
+        self.update_source_file()?;
+
+        // Remove again the notes PackedFile, as that one is stored separated from the rest.
+        self.remove_packed_file_by_path(&["notes.rpfm_reserved".to_owned()]);
+
+        // If nothing has failed, return success.
+        Ok(())
+    }
+
+    /// This function tries to save a `PackFile` to the provided writer, without building the entire `PackFile` in memory first.
+    ///
+    /// The header and indexes are written first, then the data of each `PackedFile`. PackedFiles that don't need to change
+    /// (not decoded, and with the right compression and encryption) are copied directly from disk, in chunks.
+    /// Because of that, the writer must not write to the file this `PackFile` was read from.
+    pub fn save_streaming<W: Write>(&mut self, writer: &mut W) -> Result<()> {
+
+        // If any of the problematic masks in the header is set or is one of CA's, return an error.
+        if !self.is_editable(*SETTINGS.read().unwrap().settings_bool.get("allow_editing_of_ca_packfiles").unwrap()) { return Err(ErrorKind::PackFileIsNonEditable.into()) }
+
+        let should_be_encrypted_pack = self.prepare_for_saving(false)?;
+        let result = self.write(writer, should_be_encrypted_pack);
+
+        // Remove again the notes PackedFile, as that one is stored separated from the rest.
+        self.remove_packed_file_by_path(&["notes.rpfm_reserved".to_owned()]);
+        result
+    }
+
+    /// This function saves a copy of this `PackFile` to the provided path, without the risk of breaking the file already there if the save fails.
+    ///
+    /// The `PackFile` is written to a temporary file next to the provided path first, which is synced to disk and then renamed over the
+    /// provided path. If there was a file there, a copy of it is kept with the `.bak` extension appended to its name. If the save fails,
+    /// the temporary file is removed and the file in the provided path is left untouched.
+    ///
+    /// Like `save_streaming`, this leaves the `PackedFiles` prepared for saving, but their data is not loaded to memory, as the file
+    /// we read them from is never written to.
+    pub fn save_safely(&mut self, path: &Path) -> Result<()> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        let result = (|| -> Result<()> {
+            let mut file = BufWriter::new(File::create(&temp_path)?);
+            self.save_streaming(&mut file)?;
+            file.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+
+            if path.is_file() {
+                let mut backup_path = path.as_os_str().to_owned();
+                backup_path.push(".bak");
+                std::fs::copy(path, PathBuf::from(backup_path))?;
+            }
+
+            std::fs::rename(&temp_path, path)?;
+            if path == self.file_path { self.update_source_file()?; }
+            Ok(())
+        })();
+
+        if result.is_err() && temp_path.is_file() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    /// This function saves the metadata of the `PackedFiles` of this `PackFile` to a JSON sidecar file in the provided path.
+    ///
+    /// The sidecar contains an object with the paths of the `PackedFiles` with metadata, joined with `/`, as keys. The
+    /// `PackFile` itself is not touched, so this can be done before or after saving it.
+    pub fn save_metadata_sidecar(&self, path: &Path) -> Result<()> {
+        let metadata = self.packed_files.iter()
+            .filter_map(|x| x.get_metadata().map(|metadata| (x.get_path().join("/"), metadata.clone())))
+            .collect::<BTreeMap<String, serde_json::Value>>();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(serde_json::to_string_pretty(&metadata)?.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// This function loads the metadata of the `PackedFiles` of this `PackFile` from the JSON sidecar file in the provided path.
+    ///
+    /// The metadata of the `PackedFiles` not in the sidecar is left as it is. Entries for paths not in the `PackFile` are ignored.
+    /// It returns the amount of `PackedFiles` which got their metadata loaded.
+    pub fn load_metadata_sidecar(&mut self, path: &Path) -> Result<usize> {
+        let file = BufReader::new(File::open(path)?);
+        let metadata: BTreeMap<String, serde_json::Value> = serde_json::from_reader(file)?;
+
+        let mut loaded = 0;
+        for (packed_file_path, metadata) in metadata {
+            let packed_file_path = packed_file_path.split('/').map(|x| x.to_owned()).collect::<Vec<String>>();
+            if let Some(packed_file) = self.get_ref_mut_packed_file_by_path(&packed_file_path) {
+                packed_file.set_metadata(Some(metadata));
+                loaded += 1;
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// This function prepares the `PackedFiles` of a `PackFile` to be saved, returning if the `PackFile` should be saved encrypted.
+    ///
+    /// This adds the notes PackedFile, sorts the `PackedFiles` and leaves their data compressed/encrypted as they have to be saved.
+    /// If `load_all` is false, `PackedFiles` already in their final form on disk are not loaded to memory.
+    fn prepare_for_saving(&mut self, load_all: bool) -> Result<bool> {
+
         // Before everything else, add the file for the notes if we have them. We'll remove it later, after the file has been saved.
         if let Some(note) = &self.notes {
             let mut data = vec![];
@@ -2465,16 +3495,52 @@ impl PackFile {
         // So, to fix it, we have to sort all the PackedFiles here by path.
         // NOTE: This sorting has to be CASE INSENSITIVE. This means for "ac", "Ab" and "aa" it'll be "aa", "Ab", "ac".
         self.packed_files.sort_unstable_by(|a, b| a.get_path().join("\\").to_lowercase().cmp(&b.get_path().join("\\").to_lowercase()));
+        self.rebuild_packed_files_index();
+
+        // Encryption is set for the entire PackFile, so we only encrypt it if all the PackedFiles in it (except the notes) should be encrypted.
+        // If only some of them should be encrypted, we save them all unencrypted.
+        let should_be_encrypted_pack = self.packed_files.iter()
+            .filter(|x| x.get_path() != ["notes.rpfm_reserved"])
+            .try_fold(false, |_, x| x.get_ref_raw().get_should_be_encrypted().map(|_| true))
+            .unwrap_or(false);
+
+        // We ensure that all the data is in his right form (compressed/encrypted) before attempting to save.
+        // We need to do this here because we need later on their compressed size. This is done in parallel, as compressing is slow.
+        let pfh_version = self.pfh_version;
+        let compression_settings = self.compression_settings;
+        let codec = if self.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) { None } else { Codec::from_pfh_version(pfh_version) };
+        self.packed_files.par_iter_mut().try_for_each(|packed_file| -> Result<()> {
+
+            // Clean PackedFiles that got decompressed in memory get back their compressed data, so we don't compress them again.
+            if let DecodedPackedFile::Unknown = packed_file.get_ref_decoded() {
+                packed_file.get_ref_mut_raw().restore_clean_data();
+            }
 
-        // We ensure that all the data is loaded and in his right form (compressed/encrypted) before attempting to save.
-        // We need to do this here because we need later on their compressed size.
-        for packed_file in &mut self.packed_files {
+            // If we don't need to load everything, PackedFiles that are already on disk in the form they have to be saved are left there.
+            // Tables are never compressed, so take that into account when checking if their compression is going to change.
+            let raw = packed_file.get_ref_raw();
+            let should_be_compressed = raw.get_should_be_compressed() && !matches!(PackedFileType::get_packed_file_type(raw.get_path()), PackedFileType::DB | PackedFileType::Loc);
+            let current_codec = raw.get_compression_codec();
+            if !load_all && raw.is_on_disk() &&
+                matches!(packed_file.get_ref_decoded(), DecodedPackedFile::Unknown) &&
+                raw.get_compression_state() == should_be_compressed &&
+                (!should_be_compressed || codec == Some(current_codec)) &&
+                raw.get_encryption_state() == should_be_encrypted_pack {
+                let raw = packed_file.get_ref_mut_raw();
+                raw.set_should_be_compressed(should_be_compressed);
+                raw.set_should_be_encrypted(if should_be_encrypted_pack { Some(pfh_version) } else { None });
+                return Ok(());
+            }
 
             // If we decoded it, re-encode it. Otherwise, just load it.
             packed_file.encode()?;
 
-            // Remember: first compress (only PFH5), then encrypt.
+            // Remember: first compress (only PFH5), then encrypt. So to change the compression we need the unencrypted data first.
             let (path, data, is_compressed, is_encrypted, should_be_compressed, should_be_encrypted) = packed_file.get_ref_mut_raw().get_data_and_info_from_memory()?;
+            if is_encrypted.is_some() {
+                *data = decrypt_packed_file(data);
+                *is_encrypted = None;
+            }
 
             // If, in any moment, we enabled/disabled the PackFile compression, compress/decompress the PackedFile. EXCEPT FOR TABLES. NEVER COMPRESS TABLES.
             match PackedFileType::get_packed_file_type(path) {
@@ -2482,22 +3548,60 @@ impl PackFile {
                 _ => {}
             }
 
-            if *should_be_compressed && !*is_compressed {
-                *data = compress_data(&data)?;
-                *is_compressed = true;
-            }
-            else if !*should_be_compressed && *is_compressed {
-                *data = decompress_data(&data)?;
+            // Data compressed with a codec other than the one of this PackFile needs to be decompressed and compressed again with the right one.
+            if *is_compressed && (!*should_be_compressed || codec != Some(current_codec)) {
+                *data = current_codec.decompress(&data)?;
                 *is_compressed = false;
             }
 
-            // Encryption is not yet supported. Unencrypt everything.
-            if is_encrypted.is_some() {
-                *data = decrypt_packed_file(&data);
-                *is_encrypted = None;
+            let mut new_codec = current_codec;
+            if *should_be_compressed && !*is_compressed {
+                match codec {
+                    Some(codec) => {
+                        *data = codec.compress(&data, &compression_settings)?;
+                        *is_compressed = true;
+                        new_codec = codec;
+                    }
+                    None => *should_be_compressed = false,
+                }
+            }
+
+            if should_be_encrypted_pack {
+                *data = encrypt_packed_file(data);
+                *is_encrypted = Some(pfh_version);
+                *should_be_encrypted = Some(pfh_version);
+            }
+            else {
                 *should_be_encrypted = None;
             }
-        }
+
+            let raw = packed_file.get_ref_mut_raw();
+            raw.set_compression_codec(new_codec);
+            raw.set_clean();
+            Ok(())
+        })?;
+
+        Ok(should_be_encrypted_pack)
+    }
+
+    /// This function returns if the data of the PackedFiles of this `PackFile` starts in multiples of 8 bytes, depending on if the data is encrypted.
+    ///
+    /// This only happens in PFH5 PackFiles with extended header (only Arena) and encrypted data, due to how the encryption works.
+    fn has_padded_data(&self, is_data_encrypted: bool) -> bool {
+        is_data_encrypted && self.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER) && self.pfh_version == PFHVersion::PFH5
+    }
+
+    /// This function returns the amount of bytes of padding needed after the provided position to reach a multiple of 8.
+    fn get_padding_size(position: u64) -> u64 {
+        (8 - position % 8) % 8
+    }
+
+    /// This function writes the header, the indexes and the data of a `PackFile` already prepared for saving into the provided writer.
+    ///
+    /// The `HAS_ENCRYPTED_DATA` flag of the `PackFile` is updated to reflect if the data has been encrypted or not.
+    fn write<W: Write>(&mut self, writer: &mut W, should_be_encrypted_pack: bool) -> Result<()> {
+        self.bitmask.set(PFHFlags::HAS_ENCRYPTED_DATA, should_be_encrypted_pack);
+        let has_extended_header = self.bitmask.contains(PFHFlags::HAS_EXTENDED_HEADER);
 
         // First we encode the indexes and the data (just in case we compressed it).
         let mut pack_file_index = vec![];
@@ -2508,18 +3612,27 @@ impl PackFile {
             pack_file_index.push(0);
         }
 
-        for packed_file in &self.packed_files {
-            packed_file_index.encode_integer_u32(packed_file.get_ref_raw().get_size());
+        // If the index is encrypted, the sizes, timestamps (only in PFH5 and PFH4) and paths are encrypted, using the amount of PackedFiles after each one.
+        let is_index_encrypted = self.bitmask.contains(PFHFlags::HAS_ENCRYPTED_INDEX);
+        for (position, packed_file) in self.packed_files.iter().enumerate() {
+            let packed_files_after_this_one = (self.packed_files.len() - position - 1) as u32;
+            let size = packed_file.get_ref_raw().get_size();
+            let timestamp = packed_file.get_ref_raw().get_timestamp() as u32;
+            let timestamp = if is_index_encrypted { encrypt_index_item_file_length(timestamp, packed_files_after_this_one) } else { timestamp };
+            packed_file_index.encode_integer_u32(if is_index_encrypted { encrypt_index_item_file_length(size, packed_files_after_this_one) } else { size });
 
             // Depending on the version of the PackFile and his bitmask, the PackedFile index has one format or another.
-            // In PFH5 case, we don't support saving encrypted PackFiles for Arena. So we'll default to Warhammer 2 format.
+            // In PFH5 case, Arena PackFiles (the ones with extended header) use the PFH4 format.
             match self.pfh_version {
+                PFHVersion::PFH5 if has_extended_header => {
+                    if self.bitmask.contains(PFHFlags::HAS_INDEX_WITH_TIMESTAMPS) { packed_file_index.encode_integer_u32(timestamp); }
+                }
                 PFHVersion::PFH5 => {
-                    if self.bitmask.contains(PFHFlags::HAS_INDEX_WITH_TIMESTAMPS) { packed_file_index.encode_integer_u32(packed_file.get_ref_raw().get_timestamp() as u32); }
+                    if self.bitmask.contains(PFHFlags::HAS_INDEX_WITH_TIMESTAMPS) { packed_file_index.encode_integer_u32(timestamp); }
                     if packed_file.get_ref_raw().get_should_be_compressed() { packed_file_index.push(1); } else { packed_file_index.push(0); }
                 }
                 PFHVersion::PFH4 => {
-                    if self.bitmask.contains(PFHFlags::HAS_INDEX_WITH_TIMESTAMPS) { packed_file_index.encode_integer_u32(packed_file.get_ref_raw().get_timestamp() as u32); }
+                    if self.bitmask.contains(PFHFlags::HAS_INDEX_WITH_TIMESTAMPS) { packed_file_index.encode_integer_u32(timestamp); }
                 }
                 PFHVersion::PFH3 | PFHVersion::PFH2 => {
                     if self.bitmask.contains(PFHFlags::HAS_INDEX_WITH_TIMESTAMPS) { packed_file_index.encode_integer_i64(packed_file.get_ref_raw().get_timestamp()); }
@@ -2529,13 +3642,15 @@ impl PackFile {
                 PFHVersion::PFH0 => {}
             }
 
-            packed_file_index.append(&mut packed_file.get_path().join("\\").as_bytes().to_vec());
-            packed_file_index.push(0);
+            if is_index_encrypted {
+                packed_file_index.append(&mut encrypt_index_item_filename(&packed_file.get_path().join("\\"), size as u8));
+            }
+            else {
+                packed_file_index.append(&mut packed_file.get_path().join("\\").as_bytes().to_vec());
+                packed_file_index.push(0);
+            }
         }
 
-        // Create the file to save to, and save the header and the indexes.
-        let mut file = BufWriter::new(File::create(&self.file_path)?);
-
         // Write the entire header.
         let mut header = vec![];
         header.encode_string_u8(&self.pfh_version.get_value());
@@ -2553,19 +3668,36 @@ impl PackFile {
             PFHVersion::PFH0 => {}
         };
 
-        // Write the indexes and the data of the PackedFiles. No need to keep the data, as it has been preloaded before.
-        file.write_all(&header)?;
-        file.write_all(&pack_file_index)?;
-        file.write_all(&packed_file_index)?;
-        for packed_file in &self.packed_files {
-            let data = packed_file.get_ref_raw().get_raw_data()?;
-            file.write_all(&data)?;
+        // PackFiles with extended header have some extra data after the normal header. If we don't have it, we write zeros.
+        if has_extended_header && matches!(self.pfh_version, PFHVersion::PFH5 | PFHVersion::PFH4) {
+            self.extended_header_data.resize(EXTENDED_HEADER_DATA_SIZE, 0);
+            header.extend_from_slice(&self.extended_header_data);
         }
 
-        // Remove again the notes PackedFile, as that one is stored separated from the rest.
-        self.remove_packed_file_by_path(&["notes.rpfm_reserved".to_owned()]);
+        // Write the indexes and the data of the PackedFiles, one by one, so we never have to join all of them in memory.
+        // If the data has to be padded, both the start of the data and the data of each PackedFile have to be padded to multiples of 8.
+        let has_padded_data = self.has_padded_data(should_be_encrypted_pack);
+        writer.write_all(&header)?;
+        writer.write_all(&pack_file_index)?;
+        writer.write_all(&packed_file_index)?;
+        if has_padded_data {
+            let indexes_end = (header.len() + pack_file_index.len() + packed_file_index.len()) as u64;
+            writer.write_all(&vec![0; Self::get_padding_size(indexes_end) as usize])?;
+        }
 
-        // If nothing has failed, return success.
+        for packed_file in &self.packed_files {
+            packed_file.get_ref_raw().write_raw_data(writer)?;
+            if has_padded_data {
+                writer.write_all(&vec![0; Self::get_padding_size(u64::from(packed_file.get_ref_raw().get_size())) as usize])?;
+            }
+        }
+
+        // PFH5 PackFiles with extended header have some extra data at the end too.
+        if has_extended_header && self.pfh_version == PFHVersion::PFH5 {
+            self.extended_footer_data.resize(EXTENDED_FOOTER_DATA_SIZE, 0);
+            writer.write_all(&self.extended_footer_data)?;
+        }
+        writer.flush()?;
         Ok(())
     }
 }
@@ -2581,6 +3713,81 @@ impl Default for PackFile {
     }
 }
 
+/// Implementation of trait `PartialEq` for `PackFile`.
+///
+/// The hash of the file on disk and the path index are not compared, as they say nothing about the contents of the `PackFile`.
+impl PartialEq for PackFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.file_path == other.file_path &&
+            self.pfh_version == other.pfh_version &&
+            self.pfh_file_type == other.pfh_file_type &&
+            self.bitmask == other.bitmask &&
+            self.timestamp == other.timestamp &&
+            self.pack_files == other.pack_files &&
+            self.packed_files == other.packed_files &&
+            self.extended_header_data == other.extended_header_data &&
+            self.extended_footer_data == other.extended_footer_data &&
+            self.notes == other.notes &&
+            self.compression_settings == other.compression_settings
+    }
+}
+
+/// Implementation of `PackedFilesIndex`.
+impl PackedFilesIndex {
+
+    /// This function rebuilds the index from the provided list of PackedFiles, leaving it up to date.
+    ///
+    /// If someone else is looking for a path, this waits until they're done.
+    fn rebuild(&self, packed_files: &[PackedFile]) {
+        let mut positions = self.positions.write().unwrap();
+        *positions = packed_files.iter()
+            .enumerate()
+            .map(|(position, packed_file)| (packed_file.get_path().to_vec(), position))
+            .collect();
+        self.outdated.store(false, AtomicOrdering::SeqCst);
+    }
+}
+
+/// Implementation of `Clone` for `PackedFilesIndex`. The clone gets its own copy of the positions.
+impl Clone for PackedFilesIndex {
+    fn clone(&self) -> Self {
+        Self {
+            positions: RwLock::new(self.positions.read().unwrap().clone()),
+            outdated: AtomicBool::new(self.outdated.load(AtomicOrdering::SeqCst)),
+        }
+    }
+}
+
+/// Implementation of `Clone` for `SourceFile`. The clone gets its own copy of the hash, if we already know it.
+impl Clone for SourceFile {
+    fn clone(&self) -> Self {
+        Self {
+            metadata: self.metadata,
+            hash: RwLock::new(*self.hash.read().unwrap()),
+        }
+    }
+}
+
+/// Implementation of `CompressionSettings`.
+impl CompressionSettings {
+
+    /// This function returns the compression level of these settings, clamped to the range we support.
+    pub fn get_clamped_level(&self) -> u32 {
+        self.level.max(MIN_COMPRESSION_LEVEL).min(MAX_COMPRESSION_LEVEL)
+    }
+}
+
+/// Implementaion of trait `Default` for `CompressionSettings`.
+impl Default for CompressionSettings {
+
+    /// This function creates the default `CompressionSettings`, with the same level vanilla PackedFiles use.
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
 /// Implementation to create a `PackFileInfo` from a `PackFile`.
 impl From<&PackFile> for PackFileInfo {
     fn from(packfile: &PackFile) -> Self {
@@ -2596,6 +3803,42 @@ impl From<&PackFile> for PackFileInfo {
     }
 }
 
+/// Implementation of `CompressionReport`.
+impl CompressionReport {
+
+    /// This function returns the ratio between the stored size and the decompressed size of all the `PackedFiles`.
+    ///
+    /// If there is no data, or none of it is compressed, this is 1.0.
+    pub fn get_ratio(&self) -> f64 {
+        Self::get_ratio_from_sizes(self.compressed_size, self.decompressed_size)
+    }
+
+    /// This function returns the ratio between the provided sizes, or 1.0 if there is no data.
+    fn get_ratio_from_sizes(compressed_size: u64, decompressed_size: u64) -> f64 {
+        if decompressed_size == 0 { 1.0 } else { compressed_size as f64 / decompressed_size as f64 }
+    }
+}
+
+/// Implementation of `CompressionReportEntry`.
+impl CompressionReportEntry {
+
+    /// This function returns the ratio between the stored size and the decompressed size of the `PackedFile`.
+    ///
+    /// If it has no data, or it's not compressed, this is 1.0.
+    pub fn get_ratio(&self) -> f64 {
+        CompressionReport::get_ratio_from_sizes(u64::from(self.compressed_size), u64::from(self.decompressed_size))
+    }
+}
+
+/// Implementation of `PackFileDiff`.
+impl PackFileDiff {
+
+    /// This function returns the paths of all the `PackedFiles` that changed in the provided way.
+    pub fn get_paths_by_state(&self, state: PackedFileDiffState) -> Vec<&[String]> {
+        self.packed_files.iter().filter(|(_, x)| *x == state).map(|(x, _)| &**x).collect()
+    }
+}
+
 /// Implementation of `Manifest`.
 impl Manifest {
 
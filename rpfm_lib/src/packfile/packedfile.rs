@@ -17,20 +17,27 @@ meaning the code that takes care of loading/writing their data from/to disk.
 You'll rarely have to touch anything here.
 !*/
 
+use chrono::NaiveDateTime;
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+use serde_json::Value;
+
 use std::io::prelude::*;
-use std::io::{BufReader, Read, SeekFrom};
+use std::io::{BufReader, Read};
 use std::fs::File;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 
 use rpfm_error::Error;
 
 use crate::packfile::*;
-use crate::packfile::compression::decompress_data;
 use crate::packedfile::{DecodedPackedFile, PackedFileType};
 use crate::packedfile::table::{db::DB, loc::Loc};
 use crate::schema::Schema;
 use crate::SCHEMA;
 
+/// Size of the chunks used to copy the data of a `RawPackedFile` from disk to somewhere else.
+const WRITE_CHUNK_SIZE: usize = 1024 * 1024;
+
 //---------------------------------------------------------------------------//
 //                              Enum & Structs
 //---------------------------------------------------------------------------//
@@ -40,6 +47,9 @@ use crate::SCHEMA;
 pub struct PackedFile {
     raw: RawPackedFile,
     decoded: DecodedPackedFile,
+
+    /// Optional user metadata of the `PackedFile`, like notes or TODO flags. It's never saved in the `PackFile` itself, only in its sidecar file.
+    metadata: Option<Value>,
 }
 
 /// This struct represents a `PackedFile` in memory in his raw form.
@@ -61,10 +71,28 @@ pub struct RawPackedFile {
     /// If the data should be encrypted when saving it to disk. If it should, it contains `Some(PFHVersion)`, being `PFHVersion` the one of the game this `PackedFile` is for.
     should_be_encrypted: Option<PFHVersion>,
 
+    /// The `Codec` the data is compressed with, if it's compressed.
+    compression_codec: Codec,
+
     /// the data of the PackedFile. Use the getter/setter functions to interact with it.
     data: PackedFileData,
+
+    /// The plain (decompressed and decrypted) data of the PackedFile, once someone has asked for it through `get_data_shared`.
+    shared_data: SharedData,
+
+    /// If the data has been changed since it was read from (or prepared to be saved to) a PackFile, so it has to be compressed again when saving.
+    dirty: bool,
+
+    /// The compressed data of a clean `RawPackedFile`, kept when it gets decompressed in memory so we can save it without compressing it again.
+    clean_data: Option<PackedFileData>,
 }
 
+/// This struct holds the plain data of a `RawPackedFile` shared between all the threads reading it.
+///
+/// It's only a cache: it's not cloned with the `RawPackedFile`, and it's ignored when comparing them.
+#[derive(Debug, Default)]
+struct SharedData(RwLock<Option<Arc<[u8]>>>);
+
 /// This enum represents the data of a `PackedFile`, in his current state.
 #[derive(Clone, Debug)]
 pub enum PackedFileData {
@@ -73,8 +101,18 @@ pub enum PackedFileData {
     OnMemory(Vec<u8>, bool, Option<PFHVersion>),
 
     /// The data is not loaded to memory and the variant holds the info needed to get the data loaded to memory on demand
-    /// (reader of the file, position of the start of the data, size of the data, is_compressed, is_encrypted).
-    OnDisk(Arc<Mutex<BufReader<File>>>, u64, u32, bool, Option<PFHVersion>),
+    /// (file the data is in, position of the start of the data, size of the data, is_compressed, is_encrypted).
+    ///
+    /// The file is shared between all the `PackedFiles` of the same `PackFile`, and read using positioned reads,
+    /// so multiple threads can read different `PackedFiles` from it at the same time without blocking each other.
+    OnDisk(Arc<File>, u64, u32, bool, Option<PFHVersion>),
+
+    /// The data is not loaded to memory, but it's in a memory-mapped `PackFile`, and the variant holds the info needed to get it from the map
+    /// (map of the file the data is in, position of the start of the data, size of the data, is_compressed, is_encrypted).
+    ///
+    /// The map is shared between all the `PackedFiles` of the same `PackFile`, and the data is sliced directly from it, without syscalls.
+    #[cfg(feature = "mmap")]
+    Mapped(Arc<Mmap>, u64, u32, bool, Option<PFHVersion>),
 }
 
 /// This struct represents the detailed info about the `PackedFile` we can provide to whoever request it.
@@ -122,9 +160,14 @@ impl PackedFile {
                 timestamp: 0,
                 should_be_compressed: false,
                 should_be_encrypted: None,
+                compression_codec: Codec::default(),
                 data: PackedFileData::OnMemory(vec![], false, None),
+                shared_data: SharedData::default(),
+                dirty: true,
+                clean_data: None,
             },
             decoded: DecodedPackedFile::Unknown,
+            metadata: None,
         }
     }
 
@@ -133,6 +176,7 @@ impl PackedFile {
         Self {
             raw: data.clone(),
             decoded: DecodedPackedFile::Unknown,
+            metadata: None,
         }
     }
 
@@ -145,12 +189,30 @@ impl PackedFile {
                 timestamp: 0,
                 should_be_compressed: false,
                 should_be_encrypted: None,
+                compression_codec: Codec::default(),
                 data: PackedFileData::OnMemory(vec![], false, None),
+                shared_data: SharedData::default(),
+                dirty: true,
+                clean_data: None,
             },
             decoded: data.clone(),
+            metadata: None,
         }
     }
 
+    /// This function creates a new `PackedFile` from the provided `DecodedPackedFile`, path and `PackFile` name, with his data already encoded.
+    ///
+    /// Unlike `new_from_decoded`, the `PackedFile` is ready to be added to a `PackFile`, and his timestamp is set to the current time.
+    /// If the `DecodedPackedFile` cannot be encoded, this returns an error.
+    pub fn new_from_decoded_and_encoded(data: &DecodedPackedFile, path: Vec<String>, packfile_name: String) -> Result<Self> {
+        let encoded_data = data.encode().ok_or_else(|| Error::from(ErrorKind::PackedFileTypeNotEncodable))??;
+        Ok(Self {
+            raw: RawPackedFile::read_from_vec(path, packfile_name, get_current_time(), false, encoded_data),
+            decoded: data.clone(),
+            metadata: None,
+        })
+    }
+
     /// This function creates a new empty `PackedFile` of the provided type and path.
     pub fn new_from_type_and_path(
         packed_file_type: PackedFileType,
@@ -172,7 +234,7 @@ impl PackedFile {
 
             // For dbs, we create them with their last definition, if we found one, and their table name.
             PackedFileType::DB => {
-                let table_name = path.get(1).ok_or_else(|| Error::from(ErrorKind::DBTableIsNotADBTable))?;
+                let table_name = PackedFileType::get_db_table_name(&path)?;
                 let table_definition = match *schema {
                     Some(ref schema) => schema.get_ref_last_definition_db(table_name)?,
                     None => return Err(ErrorKind::SchemaNotFound.into())
@@ -229,6 +291,16 @@ impl PackedFile {
         self.decoded = data.clone();
     }
 
+    /// This function returns a reference to the user metadata of a `PackedFile`, if it has any.
+    pub fn get_metadata(&self) -> Option<&Value> {
+        self.metadata.as_ref()
+    }
+
+    /// This function replaces the user metadata of a `PackedFile` with the provided one. Pass `None` to remove it.
+    pub fn set_metadata(&mut self, metadata: Option<Value>) {
+        self.metadata = metadata;
+    }
+
     /// This function returns a reference of the path of a `PackedFile`.
     pub fn get_path(&self) -> &[String] {
         self.raw.get_path()
@@ -318,6 +390,53 @@ impl PackedFile {
         Ok(())
     }
 
+    /// This function adds the provided data at the end of the data of the `PackedFile`.
+    ///
+    /// If the `PackedFile` is decoded, it's encoded first so the data is added to his current contents, and the decoded data is removed from the cache.
+    pub fn append_data(&mut self, data: &[u8]) -> Result<()> {
+        self.encode_and_clean_cache()?;
+        self.raw.append_data(data)
+    }
+
+    /// This function replaces the data of the `PackedFile` with the provided already compressed data, without recompressing it.
+    ///
+    /// If the data is also encrypted, pass the version it's encrypted for. The decoded data is removed from the cache, as it no longer matches the data.
+    pub fn set_data_compressed(&mut self, data: Vec<u8>, version_if_encrypted: Option<PFHVersion>) {
+        self.decoded = DecodedPackedFile::Unknown;
+        self.raw.set_data_compressed(data, version_if_encrypted);
+    }
+
+    /// This function prepares the `PackedFile` to be added to a PackFile of the provided `PFHVersion`, usually from another game.
+    ///
+    /// The data is loaded to memory and decrypted, as encryption depends on the game. If the `PackedFile` should be encrypted,
+    /// it'll be encrypted for the new version when saving. If the new version doesn't support encryption or compression,
+    /// the `PackedFile` will no longer be encrypted or compressed, and its data is decompressed if needed.
+    /// Data compressed with a `Codec` the new version doesn't use is decompressed too, so it's compressed with the right one when saving.
+    pub fn retarget(&mut self, to: PFHVersion) -> Result<()> {
+        self.raw.load_data()?;
+        let codec = self.raw.compression_codec;
+        if let PackedFileData::OnMemory(ref mut data, ref mut is_compressed, ref mut is_encrypted) = self.raw.data {
+            if is_encrypted.is_some() {
+                *data = decrypt_packed_file(data);
+                *is_encrypted = None;
+            }
+
+            if *is_compressed && Codec::from_pfh_version(to) != Some(codec) {
+                *data = codec.decompress(data)?;
+                *is_compressed = false;
+            }
+        }
+
+        if !to.supports_compression() {
+            self.raw.should_be_compressed = false;
+        }
+
+        if self.raw.should_be_encrypted.is_some() {
+            self.raw.should_be_encrypted = if to.supports_encryption() { Some(to) } else { None };
+        }
+        Ok(())
+    }
+
     /// This function tries to encode a `DecodedPackedFile` into a `RawPackedFile`, storing the results in the `Packedfile`.
     /// Then, it removes the decoded data from the cache.
     ///
@@ -366,6 +485,22 @@ impl PackedFile {
         self.raw.set_data(data);
     }
 
+    /// This function replaces the data of a PackedFile with the contents of the provided file, taking its timestamp from the file's last modified date.
+    ///
+    /// The data is stored in memory, neither compressed nor encrypted, and the decoded data of the PackedFile, if any, is discarded.
+    /// If the file cannot be read, this returns an `IOReadFile` error with its path, and the PackedFile is left untouched.
+    pub fn set_data_from_file(&mut self, source: &Path) -> Result<()> {
+        let error = || Error::from(ErrorKind::IOReadFile(source.to_path_buf()));
+        let mut file = File::open(source).map_err(|_| error())?;
+        let mut data = vec![];
+        file.read_to_end(&mut data).map_err(|_| error())?;
+
+        self.raw.set_data(&data);
+        self.raw.set_timestamp(get_last_modified_time_from_file(&file));
+        self.decoded = DecodedPackedFile::Unknown;
+        Ok(())
+    }
+
     /// This function extracts the provided PackedFile into the provided path.
     pub fn extract_packed_file(&mut self, destination_path: &Path) -> Result<()> {
 
@@ -394,6 +529,13 @@ impl PackedFile {
     pub fn get_packed_file_type_by_path(&self) -> PackedFileType {
         PackedFileType::get_packed_file_type(self.get_path())
     }
+
+    /// This function returns the timestamp of the provided `PackedFile` as a date, or `None` if it has no timestamp.
+    ///
+    /// See `RawPackedFile::get_timestamp_datetime` for details about how the timestamp is stored.
+    pub fn get_timestamp_datetime(&self) -> Option<NaiveDateTime> {
+        self.raw.get_timestamp_datetime()
+    }
 }
 
 /// Implementation of `RawPackedFile`.
@@ -413,7 +555,11 @@ impl RawPackedFile {
             timestamp,
             should_be_compressed,
             should_be_encrypted: None,
+            compression_codec: Codec::default(),
             data: PackedFileData::OnMemory(data, should_be_compressed, None),
+            shared_data: SharedData::default(),
+            dirty: true,
+            clean_data: None,
         }
     }
 
@@ -432,17 +578,25 @@ impl RawPackedFile {
             timestamp,
             should_be_compressed,
             should_be_encrypted,
+            compression_codec: Codec::default(),
             data,
+            shared_data: SharedData::default(),
+            dirty: false,
+            clean_data: None,
         }
     }
 
     /// This function creates a new `RawPackedFile` from a file in the filesystem.
     ///
+    /// The path of the `PackedFile` is normalized (check `normalize_path` for more info), and this fails if it ends up empty.
     /// Keep in mind that you have to set the name of his `PackFile` if you add it to one.
     pub fn read_from_path(
         path_as_file: &Path,
         path_as_packed_file: Vec<String>,
     ) -> Result<Self> {
+        let path_as_packed_file = normalize_path(&path_as_packed_file, false);
+        if path_as_packed_file.is_empty() { return Err(ErrorKind::EmptyInput.into()) }
+
         let mut file = BufReader::new(File::open(&path_as_file)?);
         let mut data = vec![];
         file.read_to_end(&mut data)?;
@@ -451,12 +605,18 @@ impl RawPackedFile {
 
     /// This function loads the data of a `RawPackedFile` to memory, if it isn't loaded already.
     pub fn load_data(&mut self) -> Result<()> {
-        let data_on_memory = if let PackedFileData::OnDisk(ref file, position, size, is_compressed, is_encrypted) = self.data {
-            let mut data = vec![0; size as usize];
-            file.lock().unwrap().seek(SeekFrom::Start(position))?;
-            file.lock().unwrap().read_exact(&mut data)?;
-            PackedFileData::OnMemory(data, is_compressed, is_encrypted)
-        } else { return Ok(()) };
+        let data_on_memory = match self.data {
+            PackedFileData::OnMemory(_, _, _) => return Ok(()),
+            PackedFileData::OnDisk(ref file, position, size, is_compressed, is_encrypted) => {
+                let data = read_onto_vec(file, position, size, &self.path)?;
+                PackedFileData::OnMemory(data, is_compressed, is_encrypted)
+            }
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, is_compressed, is_encrypted) => {
+                let data = get_mapped_slice(map, position, size, &self.path)?.to_vec();
+                PackedFileData::OnMemory(data, is_compressed, is_encrypted)
+            }
+        };
 
         self.data = data_on_memory;
         Ok(())
@@ -470,57 +630,140 @@ impl RawPackedFile {
             PackedFileData::OnMemory(ref data, _, _) => {
                 Ok(data.to_vec())
             },
+            PackedFileData::OnDisk(ref file, position, size, _, _) => read_onto_vec(file, position, size, &self.path),
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, _, _) => Ok(get_mapped_slice(map, position, size, &self.path)?.to_vec()),
+        }
+    }
+
+    /// This function writes the RAW data of the `RawPackedFile` to the provided writer without loading it to memory.
+    ///
+    /// Data on disk is copied in chunks, so this never needs to allocate the entire data at once.
+    pub fn write_raw_data<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self.data {
+            PackedFileData::OnMemory(ref data, _, _) => writer.write_all(data)?,
             PackedFileData::OnDisk(ref file, position, size, _, _) => {
-                let mut data = vec![0; size as usize];
-                file.lock().unwrap().seek(SeekFrom::Start(position))?;
-                file.lock().unwrap().read_exact(&mut data)?;
-                Ok(data)
+                let file_size = file.metadata()?.len();
+                let end = position.saturating_add(u64::from(size));
+                if end > file_size { return Err(ErrorKind::PackedFileDataCouldNotBeRead(self.path.to_vec(), u64::from(size), file_size.saturating_sub(position)).into()) }
+
+                let mut buffer = vec![0; (size as usize).min(WRITE_CHUNK_SIZE)];
+                let mut chunk_position = position;
+                while chunk_position < end {
+                    let chunk_size = ((end - chunk_position) as usize).min(WRITE_CHUNK_SIZE);
+                    if read_exact_at(file, &mut buffer[..chunk_size], chunk_position).is_err() {
+                        return Err(ErrorKind::PackedFileDataCouldNotBeRead(self.path.to_vec(), u64::from(size), file_size.saturating_sub(position)).into())
+                    }
+                    writer.write_all(&buffer[..chunk_size])?;
+                    chunk_position += chunk_size as u64;
+                }
             }
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, _, _) => writer.write_all(get_mapped_slice(map, position, size, &self.path)?)?,
         }
+        Ok(())
+    }
+
+    /// This function returns if the data of the `RawPackedFile` is still on disk (or memory-mapped), or it has been loaded to memory.
+    pub fn is_on_disk(&self) -> bool {
+        !matches!(self.data, PackedFileData::OnMemory(_, _, _))
     }
 
     /// This function returns the data of the `RawPackedFile` without loading it to memory.
     ///
     /// It's for those situations where you just need to check the data once, then forget about it.
     pub fn get_data(&self) -> Result<Vec<u8>> {
+        if let Some(ref data) = *self.shared_data.0.read().unwrap() {
+            return Ok(data.to_vec())
+        }
+
+        self.read_data()
+    }
+
+    /// This function returns a reference to the data of the `RawPackedFile`, if we can get it without copying it.
+    ///
+    /// That's only possible when the data is already in memory (or memory-mapped), and it's neither compressed nor encrypted.
+    /// Otherwise, this returns `None`, and you'll have to use `get_data` instead.
+    pub fn try_data_slice(&self) -> Option<&[u8]> {
+        match self.data {
+            PackedFileData::OnMemory(ref data, false, None) => Some(data),
+            PackedFileData::OnMemory(_, _, _) |
+            PackedFileData::OnDisk(_, _, _, _, _) => None,
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, false, None) => get_mapped_slice(map, position, size, &self.path).ok(),
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(_, _, _, _, _) => None,
+        }
+    }
+
+    /// This function reads the data of the `RawPackedFile`, decrypting and decompressing it if needed, without loading it to memory.
+    fn read_data(&self) -> Result<Vec<u8>> {
         match self.data {
             PackedFileData::OnMemory(ref data, is_compressed, is_encrypted) => {
                 let mut data = data.to_vec();
                 if is_encrypted.is_some() { data = decrypt_packed_file(&data); }
-                if is_compressed { data = decompress_data(&data)?; }
+                if is_compressed { data = self.compression_codec.decompress(&data)?; }
                 Ok(data)
             },
             PackedFileData::OnDisk(ref file, position, size, is_compressed, is_encrypted) => {
-                let mut data = vec![0; size as usize];
-                file.lock().unwrap().seek(SeekFrom::Start(position))?;
-                file.lock().unwrap().read_exact(&mut data)?;
+                let mut data = read_onto_vec(file, position, size, &self.path)?;
                 if is_encrypted.is_some() { data = decrypt_packed_file(&data); }
-                if is_compressed { Ok(decompress_data(&data)?) }
+                if is_compressed { Ok(self.compression_codec.decompress(&data)?) }
                 else { Ok(data) }
             }
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, is_compressed, is_encrypted) => get_mapped_data(map, position, size, is_compressed, is_encrypted, self.compression_codec, &self.path),
         }
     }
 
+    /// This function returns the data of the `RawPackedFile` as a buffer shared between everyone asking for it.
+    ///
+    /// The first call reads (and decrypts/decompresses) the data and keeps it. The next ones, from any thread, just return the same buffer
+    /// without reading or copying the data again, until the data of the `RawPackedFile` is changed.
+    pub fn get_data_shared(&self) -> Result<Arc<[u8]>> {
+        if let Some(ref data) = *self.shared_data.0.read().unwrap() {
+            return Ok(data.clone())
+        }
+
+        let mut shared_data = self.shared_data.0.write().unwrap();
+        match *shared_data {
+
+            // Another thread may have got the data while we waited for the lock.
+            Some(ref data) => Ok(data.clone()),
+            None => {
+                let data: Arc<[u8]> = self.read_data()?.into();
+                *shared_data = Some(data.clone());
+                Ok(data)
+            }
+        }
+    }
+
+    /// This function removes the shared data of the `RawPackedFile`. To be called every time the data may change.
+    fn clear_shared_data(&mut self) {
+        *self.shared_data.0.get_mut().unwrap() = None;
+    }
+
     /// This function returns the data of the provided `RawPackedFile` loading it to memory in the process if it isn't already loaded.
     ///
     /// It's for when you need to keep the data for multiple uses.
     pub fn get_data_and_keep_it(&mut self) -> Result<Vec<u8>> {
+        self.keep_clean_data();
         let data = match self.data {
             PackedFileData::OnMemory(ref mut data, ref mut is_compressed, ref mut is_encrypted) => {
                 if is_encrypted.is_some() { *data = decrypt_packed_file(&data); }
-                if *is_compressed { *data = decompress_data(&data)?; }
+                if *is_compressed { *data = self.compression_codec.decompress(&data)?; }
                 *is_compressed = false;
                 *is_encrypted = None;
                 return Ok(data.to_vec())
             },
             PackedFileData::OnDisk(ref file, position, size, is_compressed, is_encrypted) => {
-                let mut data = vec![0; size as usize];
-                file.lock().unwrap().seek(SeekFrom::Start(position))?;
-                file.lock().unwrap().read_exact(&mut data)?;
+                let mut data = read_onto_vec(file, position, size, &self.path)?;
                 if is_encrypted.is_some() { data = decrypt_packed_file(&data); }
-                if is_compressed { decompress_data(&data)? }
+                if is_compressed { self.compression_codec.decompress(&data)? }
                 else { data }
             }
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, is_compressed, is_encrypted) => get_mapped_data(map, position, size, is_compressed, is_encrypted, self.compression_codec, &self.path)?,
         };
 
         self.data = PackedFileData::OnMemory(data.to_vec(), false, None);
@@ -532,22 +775,24 @@ impl RawPackedFile {
     ///
     /// It's for when you need to modify the data directly. Try to not abuse it.
     pub fn get_ref_mut_data_and_keep_it(&mut self) -> Result<&mut Vec<u8>> {
+        self.clear_shared_data();
+        self.set_dirty();
         let data = match self.data {
             PackedFileData::OnMemory(ref mut data, ref mut is_compressed, ref mut is_encrypted) => {
                 if is_encrypted.is_some() { *data = decrypt_packed_file(&data); }
-                if *is_compressed { *data = decompress_data(&data)?; }
+                if *is_compressed { *data = self.compression_codec.decompress(&data)?; }
                 *is_compressed = false;
                 *is_encrypted = None;
                 return Ok(data)
             },
             PackedFileData::OnDisk(ref file, position, size, is_compressed, is_encrypted) => {
-                let mut data = vec![0; size as usize];
-                file.lock().unwrap().seek(SeekFrom::Start(position))?;
-                file.lock().unwrap().read_exact(&mut data)?;
+                let mut data = read_onto_vec(file, position, size, &self.path)?;
                 if is_encrypted.is_some() { data = decrypt_packed_file(&data); }
-                if is_compressed { decompress_data(&data)? }
+                if is_compressed { self.compression_codec.decompress(&data)? }
                 else { data }
             }
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, is_compressed, is_encrypted) => get_mapped_data(map, position, size, is_compressed, is_encrypted, self.compression_codec, &self.path)?,
         };
 
         self.data = PackedFileData::OnMemory(data, false, None);
@@ -558,34 +803,149 @@ impl RawPackedFile {
     ///
     /// The data returned is `path, data, is_compressed, is_encrypted, should_be_compressed, should_be_encrypted`.
     pub fn get_data_and_info_from_memory(&mut self) -> Result<(&[String], &mut Vec<u8>, &mut bool, &mut Option<PFHVersion>, &mut bool, &mut Option<PFHVersion>)> {
+        self.clear_shared_data();
+        self.set_dirty();
         match self.data {
             PackedFileData::OnMemory(ref mut data, ref mut is_compressed, ref mut is_encrypted) => {
                 Ok((&self.path, data, is_compressed, is_encrypted, &mut self.should_be_compressed, &mut self.should_be_encrypted))
             },
-            PackedFileData::OnDisk(_, _, _, _, _) => {
-                Err(ErrorKind::PackedFileDataIsNotInMemory.into())
-            }
+            _ => Err(ErrorKind::PackedFileDataIsNotInMemory.into()),
+        }
+    }
+
+    /// This function makes the `RawPackedFile` get his data from the provided map of his `PackFile`, if his data is still on disk.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn map_data(&mut self, map: &Arc<Mmap>) {
+        if let PackedFileData::OnDisk(_, position, size, is_compressed, is_encrypted) = self.data {
+            self.data = PackedFileData::Mapped(map.clone(), position, size, is_compressed, is_encrypted);
         }
     }
 
     /// This function replaces the data on the `RawPackedFile` with the provided one.
     pub fn set_data(&mut self, data: &[u8]) {
+        self.clear_shared_data();
+        self.set_dirty();
         self.data = PackedFileData::OnMemory(data.to_vec(), false, None);
     }
 
+    /// This function replaces the data on the `RawPackedFile` with the provided already compressed data, without recompressing it.
+    ///
+    /// The `RawPackedFile` is marked to be compressed, so the data is saved as it is. If the data is also encrypted, pass the version it's encrypted for.
+    pub fn set_data_compressed(&mut self, data: Vec<u8>, version_if_encrypted: Option<PFHVersion>) {
+        self.clear_shared_data();
+        self.set_dirty();
+        self.data = PackedFileData::OnMemory(data, true, version_if_encrypted);
+        self.should_be_compressed = true;
+    }
+
+    /// This function adds the provided data at the end of the data of the `RawPackedFile`.
+    ///
+    /// The data is loaded to memory, decrypted and decompressed first if needed, so after this the data is always in memory and in plain form.
+    pub fn append_data(&mut self, data: &[u8]) -> Result<()> {
+        self.get_ref_mut_data_and_keep_it()?.extend_from_slice(data);
+        Ok(())
+    }
+
     /// This function returns the size of the data of the provided `RawPackedFile`.
     pub fn get_size(&self) -> u32 {
         match self.data {
             PackedFileData::OnMemory(ref data, _, _) => data.len() as u32,
             PackedFileData::OnDisk(_, _, size, _, _) => size,
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(_, _, size, _, _) => size,
         }
     }
 
+    /// This function returns the amount of memory used by the data of the provided `RawPackedFile`, in bytes.
+    ///
+    /// Only data loaded to memory counts, including the shared data, if any. Data still on disk (or memory-mapped) counts as 0.
+    pub fn get_memory_usage(&self) -> usize {
+        let shared_data_size = self.shared_data.0.read().unwrap().as_ref().map_or(0, |data| data.len());
+        shared_data_size + match self.data {
+            PackedFileData::OnMemory(ref data, _, _) => data.capacity(),
+            PackedFileData::OnDisk(..) => 0,
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(..) => 0,
+        }
+    }
+
+    /// This function returns the size the data of the provided `RawPackedFile` will have once decompressed (and decrypted).
+    ///
+    /// For compressed data, only the start of it is read (and decrypted, if needed) to get the size. The data is never decompressed.
+    pub fn get_decompressed_size(&self) -> Result<u32> {
+        let (header, is_encrypted) = match self.data {
+            PackedFileData::OnMemory(ref data, is_compressed, is_encrypted) => {
+                if !is_compressed { return Ok(data.len() as u32) }
+                (data[..data.len().min(9)].to_vec(), is_encrypted)
+            },
+            PackedFileData::OnDisk(ref file, position, size, is_compressed, is_encrypted) => {
+                if !is_compressed { return Ok(size) }
+                (read_onto_vec(file, position, size.min(9), &self.path)?, is_encrypted)
+            }
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(ref map, position, size, is_compressed, is_encrypted) => {
+                if !is_compressed { return Ok(size) }
+                (get_mapped_slice(map, position, size.min(9), &self.path)?.to_vec(), is_encrypted)
+            }
+        };
+
+        let header = if is_encrypted.is_some() { decrypt_packed_file(&header) } else { header };
+        self.compression_codec.get_decompressed_size(&header)
+    }
+
     /// This function returns the current compression state of the provided `RawPackedFile`.
     pub fn get_compression_state(&self) -> bool {
         match self.data {
             PackedFileData::OnMemory(_, state, _) => state,
             PackedFileData::OnDisk(_, _, _, state, _) => state,
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(_, _, _, state, _) => state,
+        }
+    }
+
+    /// This function returns the `Codec` the data of the `RawPackedFile` is compressed with, if it's compressed.
+    pub fn get_compression_codec(&self) -> Codec {
+        self.compression_codec
+    }
+
+    /// This function sets the `Codec` the data of the `RawPackedFile` is compressed with.
+    ///
+    /// This doesn't recompress the data: it's for telling which `Codec` already compressed data uses.
+    pub fn set_compression_codec(&mut self, codec: Codec) {
+        self.clear_shared_data();
+        self.compression_codec = codec;
+    }
+
+    /// This function returns if the data of the `RawPackedFile` has changed since it was read from (or prepared to be saved to) a PackFile.
+    ///
+    /// Clean `RawPackedFiles` that were compressed are saved with their original compressed data, even if they got decompressed in memory.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// This function marks the data of the `RawPackedFile` as changed, forgetting the compressed data it had, if any.
+    fn set_dirty(&mut self) {
+        self.dirty = true;
+        self.clean_data = None;
+    }
+
+    /// This function marks the data of the `RawPackedFile` as being in the form it's going to be saved.
+    pub(crate) fn set_clean(&mut self) {
+        self.dirty = false;
+        self.clean_data = None;
+    }
+
+    /// This function keeps a copy of the compressed data of a clean `RawPackedFile`, before it gets decompressed in memory.
+    fn keep_clean_data(&mut self) {
+        if !self.dirty && self.clean_data.is_none() && self.get_compression_state() {
+            self.clean_data = Some(self.data.clone());
+        }
+    }
+
+    /// This function puts back the compressed data of a clean `RawPackedFile` decompressed in memory, so it doesn't need to be compressed again.
+    pub(crate) fn restore_clean_data(&mut self) {
+        if let Some(data) = self.clean_data.take() {
+            self.data = data;
         }
     }
 
@@ -614,6 +974,8 @@ impl RawPackedFile {
         match self.data {
             PackedFileData::OnMemory(_, _, state) => state.is_some(),
             PackedFileData::OnDisk(_, _, _, _, state) => state.is_some(),
+            #[cfg(feature = "mmap")]
+            PackedFileData::Mapped(_, _, _, _, state) => state.is_some(),
         }
     }
 
@@ -639,6 +1001,15 @@ impl RawPackedFile {
         self.timestamp = timestamp;
     }
 
+    /// This function returns the timestamp of the provided `RawPackedFile` as a date, or `None` if it has no timestamp.
+    ///
+    /// The timestamp is always stored as seconds since the Unix epoch (1970-01-01 00:00:00 UTC). PackFiles that store
+    /// them as Windows FILETIME (100-nanosecond ticks since 1601-01-01) get them converted to Unix time when read.
+    pub fn get_timestamp_datetime(&self) -> Option<NaiveDateTime> {
+        if self.timestamp == 0 { None }
+        else { NaiveDateTime::from_timestamp_opt(self.timestamp, 0) }
+    }
+
     /// This function returns a reference to the path of the provided `RawPackedFile`.
     pub fn get_path(&self) -> &[String] {
         &self.path
@@ -646,16 +1017,86 @@ impl RawPackedFile {
 
     /// This function sets the path of the provided `RawPackedFile`.
     ///
+    /// The path is normalized before storing it, so items with separators get split and empty items get removed. The case is kept as it is.
     /// This can fail if you pass it an empty path, so make sure you check the result.
     ///
     /// ***WARNING***: DON'T USE THIS IF YOUR PACKEDFILE IS INSIDE A PACKFILE. USE THE `move_packedfile` FUNCTION INSTEAD.
     pub fn set_path(&mut self, path: &[String]) -> Result<()> {
+        let path = normalize_path(path, false);
         if path.is_empty() { return Err(ErrorKind::EmptyInput.into()) }
-        self.path = path.to_vec();
+        self.path = path;
         Ok(())
     }
 }
 
+/// This function reads the data of a `PackedFile` stored on disk, from the provided position of the provided file.
+///
+/// It uses positioned reads, so it doesn't move the cursor of the file and it's safe to call it from multiple threads at the same time.
+/// If the file is shorter than expected, the error returned contains the path of the `PackedFile` we tried to read.
+/// This is checked before allocating the buffer, so a file truncated on disk doesn't make us allocate a buffer we cannot fill.
+fn read_onto_vec(file: &File, position: u64, size: u32, path: &[String]) -> Result<Vec<u8>> {
+    let file_size = file.metadata()?.len();
+    let end = position.saturating_add(u64::from(size));
+    if end > file_size { return Err(ErrorKind::PackedFileDataCouldNotBeRead(path.to_vec(), u64::from(size), file_size.saturating_sub(position)).into()) }
+
+    let mut data = vec![0; size as usize];
+    if read_exact_at(file, &mut data, position).is_err() {
+        let available_size = file.metadata().map(|x| x.len().saturating_sub(position)).unwrap_or(0);
+        return Err(ErrorKind::PackedFileDataCouldNotBeRead(path.to_vec(), u64::from(size), available_size).into())
+    }
+    Ok(data)
+}
+
+/// This function returns the slice of the provided map with the data of a `PackedFile`.
+///
+/// If the map is shorter than expected, the error returned contains the path of the `PackedFile` we tried to get.
+#[cfg(feature = "mmap")]
+fn get_mapped_slice<'a>(map: &'a Mmap, position: u64, size: u32, path: &[String]) -> Result<&'a [u8]> {
+    let end = position.saturating_add(u64::from(size));
+    if end > map.len() as u64 { return Err(ErrorKind::PackedFileSizeExceedsFile(path.to_vec(), end, map.len() as u64).into()) }
+    Ok(&map[position as usize..end as usize])
+}
+
+/// This function returns the data of a `PackedFile` from the provided map, decrypted and decompressed if needed.
+#[cfg(feature = "mmap")]
+fn get_mapped_data(map: &Mmap, position: u64, size: u32, is_compressed: bool, is_encrypted: Option<PFHVersion>, codec: Codec, path: &[String]) -> Result<Vec<u8>> {
+    let data = get_mapped_slice(map, position, size, path)?;
+    match (is_compressed, is_encrypted.is_some()) {
+        (true, true) => codec.decompress(&decrypt_packed_file(data)),
+        (true, false) => codec.decompress(data),
+        (false, true) => Ok(decrypt_packed_file(data)),
+        (false, false) => Ok(data.to_vec()),
+    }
+}
+
+/// This function fills the provided buffer with the data of the file starting at the provided position, without using the file's cursor.
+#[cfg(unix)]
+fn read_exact_at(file: &File, buffer: &mut [u8], position: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, position)
+}
+
+/// This function fills the provided buffer with the data of the file starting at the provided position, without using the file's cursor.
+///
+/// Windows has no `read_exact_at`, so we loop over `seek_read` until the buffer is full.
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buffer: &mut [u8], mut position: u64) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+    use std::os::windows::fs::FileExt;
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, position) {
+            Ok(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer")),
+            Ok(read) => {
+                buffer = &mut buffer[read..];
+                position += read as u64;
+            }
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(())
+}
+
 /// Implementation of `PartialEq` for `PackedFileData`.
 impl PartialEq for PackedFileData {
     fn eq(&self, other: &PackedFileData) -> bool {
@@ -671,6 +1112,20 @@ impl PartialEq for PackedFileData {
     }
 }
 
+/// Implementation of `Clone` for `SharedData`. The clone starts empty, so it never shares the data with the original.
+impl Clone for SharedData {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Implementation of `PartialEq` for `SharedData`. As it's just a cache, it's always equal.
+impl PartialEq for SharedData {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
 /// Implementation to create a `PackedFileInfo` from a `PackedFile`.
 impl From<&PackedFile> for PackedFileInfo {
     fn from(packedfile: &PackedFile) -> Self {
@@ -12,9 +12,28 @@
 Module containing test for the `PackFile` module, just to make sure we don't break it... again...
 !*/
 
+use chrono::NaiveDate;
+use xz2::read::XzEncoder;
+use xz2::stream::{LzmaOptions, Stream};
+
+use std::env::temp_dir;
+use std::fs::{File, remove_dir_all, remove_file};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use rpfm_error::ErrorKind;
 
-use super::PackFile;
+use super::{Codec, CompressionSettings, DEFAULT_COMPRESSION_LEVEL, MAX_COMPRESSION_LEVEL, MIN_COMPRESSION_LEVEL, PackedFileDiffState, PackFile, PathType, PFHFlags, PFHVersion};
+use super::compression::{add_ca_header, compress_data, decompress_data, get_lzma_data_from_7z};
+use super::crypto::{decrypt_index_item_filename, decrypt_packed_file, encrypt_index_item_filename, encrypt_packed_file};
+use super::packedfile::{PackedFile, PackedFileData, RawPackedFile};
+use crate::common::{get_last_modified_time_from_file, normalize_path};
+use crate::packedfile::{DecodedPackedFile, PackedFileType};
+use crate::packedfile::table::{DecodedData, loc::Loc};
+use crate::packedfile::text::{Text, TextType};
+use crate::schema::*;
 
 #[test]
 fn test_decode_pfh5() {
@@ -121,3 +140,1739 @@ fn test_encode_pfh0() {
 
 	assert_eq!(pack_file_base, pack_file_new);
 }
+
+#[test]
+fn test_source_packfiles() {
+	let mut pack_file = PackFile::new_with_name("merged.pack", PFHVersion::PFH5);
+	pack_file.packed_files.push(PackedFile::new(vec!["db".to_owned(), "a_tables".to_owned(), "a".to_owned()], "mod_b.pack".to_owned()));
+	pack_file.packed_files.push(PackedFile::new(vec!["db".to_owned(), "a_tables".to_owned(), "b".to_owned()], "mod_a.pack".to_owned()));
+	pack_file.packed_files.push(PackedFile::new(vec!["text".to_owned(), "a.txt".to_owned()], "mod_b.pack".to_owned()));
+
+	assert_eq!(pack_file.list_source_packfiles(), vec!["mod_a.pack".to_owned(), "mod_b.pack".to_owned()]);
+
+	let from_a = pack_file.files_from_source("mod_a.pack");
+	assert_eq!(from_a.len(), 1);
+	assert_eq!(from_a[0].get_path(), &["db".to_owned(), "a_tables".to_owned(), "b".to_owned()]);
+
+	let from_b = pack_file.files_from_source("mod_b.pack");
+	assert_eq!(from_b.len(), 2);
+	assert!(from_b.iter().all(|x| x.get_ref_raw().get_packfile_name() == "mod_b.pack"));
+
+	assert!(pack_file.files_from_source("mod_c.pack").is_empty());
+}
+
+#[test]
+fn test_packed_files_by_glob() {
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	let paths = vec![
+		"db/units_tables/data__",
+		"db/units_tables/mod",
+		"db/land_units_tables/data__",
+		"db/main_units/data__",
+		"text/db/units.loc",
+		"text/db/nested/Buildings.LOC",
+		"script/campaign/mod.lua",
+	];
+	for path in &paths {
+		pack_file.packed_files.push(PackedFile::new(path.split('/').map(|x| x.to_owned()).collect(), "test.pack".to_owned()));
+	}
+
+	let glob = |pattern| {
+		let mut paths = pack_file.find_by_glob(pattern).iter().map(|x| x.get_path().join("/")).collect::<Vec<String>>();
+		paths.sort();
+		paths
+	};
+
+	// Single-segment patterns.
+	assert_eq!(glob("db/*_tables/*"), vec!["db/land_units_tables/data__", "db/units_tables/data__", "db/units_tables/mod"]);
+	assert_eq!(glob("db/*/data__"), vec!["db/land_units_tables/data__", "db/main_units/data__", "db/units_tables/data__"]);
+	assert_eq!(glob("text/db/*.loc"), vec!["text/db/units.loc"]);
+	assert_eq!(glob("DB/UNITS_TABLES/MOD"), vec!["db/units_tables/mod"]);
+
+	// Recursive patterns.
+	assert_eq!(glob("text/**/*.loc"), vec!["text/db/nested/Buildings.LOC", "text/db/units.loc"]);
+	assert_eq!(glob("**/mod*"), vec!["db/units_tables/mod", "script/campaign/mod.lua"]);
+	assert_eq!(glob("**").len(), paths.len());
+
+	// Non-matching patterns.
+	assert!(glob("db/*_tables").is_empty());
+	assert!(glob("db/*_tables/*/*").is_empty());
+	assert!(glob("ui/**").is_empty());
+}
+
+#[test]
+fn test_remove_by_glob() {
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	for path in &["db/units_tables/data__", "ui/previews/a.dds", "ui/previews/nested/b.dds", "ui/skins/c.png", "ui/d.dds"] {
+		pack_file.packed_files.push(PackedFile::new(path.split('/').map(|x| x.to_owned()).collect(), "test.pack".to_owned()));
+	}
+
+	assert_eq!(pack_file.remove_by_glob("ui/previews/**"), 2);
+	assert_eq!(pack_file.get_ref_packed_files_all_paths().iter().map(|x| x.join("/")).collect::<Vec<String>>(), vec!["db/units_tables/data__", "ui/skins/c.png", "ui/d.dds"]);
+
+	// Nothing matches anymore, so nothing is removed.
+	assert_eq!(pack_file.remove_by_glob("ui/previews/**"), 0);
+	assert_eq!(pack_file.get_ref_packed_files_all_paths().len(), 3);
+
+	assert_eq!(pack_file.remove_by_glob("**/*.DDS"), 1);
+	assert_eq!(pack_file.get_ref_packed_files_all_paths().iter().map(|x| x.join("/")).collect::<Vec<String>>(), vec!["db/units_tables/data__", "ui/skins/c.png"]);
+}
+
+#[test]
+fn test_path_index() {
+	let path = |x: &str| x.split('/').map(|x| x.to_owned()).collect::<Vec<String>>();
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	let packed_files = ["db/units_tables/data__", "ui/a.png", "ui/skins/b.png"].iter().map(|x| PackedFile::new(path(x), "test.pack".to_owned())).collect::<Vec<PackedFile>>();
+	pack_file.add_packed_files(&packed_files.iter().collect::<Vec<&PackedFile>>(), false).unwrap();
+
+	// Presence and absence.
+	assert!(pack_file.packedfile_exists(&path("ui/a.png")));
+	assert!(!pack_file.packedfile_exists(&path("ui/A.png")));
+	assert!(!pack_file.packedfile_exists(&path("ui")));
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("ui/skins/b.png")).unwrap().get_path(), &*path("ui/skins/b.png"));
+	assert!(pack_file.get_ref_packed_file_by_path(&path("ui/skins/c.png")).is_none());
+
+	// After moving a file, it should only be found in his new path.
+	assert_eq!(pack_file.move_packedfile(&path("ui/a.png"), &path("ui/c.png"), false).unwrap(), path("ui/c.png"));
+	assert!(!pack_file.packedfile_exists(&path("ui/a.png")));
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("ui/c.png")).unwrap().get_path(), &*path("ui/c.png"));
+
+	// Same after moving an entire folder.
+	pack_file.move_folder(&path("ui/skins"), &path("ui/skins_new"), false).unwrap();
+	assert!(!pack_file.packedfile_exists(&path("ui/skins/b.png")));
+	assert!(pack_file.packedfile_exists(&path("ui/skins_new/b.png")));
+
+	// And after removing files, the remaining ones should still be found in their right place.
+	pack_file.remove_packed_file_by_path(&path("db/units_tables/data__"));
+	assert!(!pack_file.packedfile_exists(&path("db/units_tables/data__")));
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("ui/c.png")).unwrap().get_path(), &*path("ui/c.png"));
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("ui/skins_new/b.png")).unwrap().get_path(), &*path("ui/skins_new/b.png"));
+
+	// Same through the fast lookups.
+	assert!(pack_file.contains_path(&path("ui/c.png")));
+	assert!(!pack_file.contains_path(&path("ui/a.png")));
+	assert_eq!(pack_file.get_by_path(&path("ui/skins_new/b.png")).unwrap().get_path(), &*path("ui/skins_new/b.png"));
+	assert!(pack_file.get_by_path(&path("ui/skins/b.png")).is_none());
+
+	// Paths changed through a mutable reference should be found too, even before the index is updated.
+	pack_file.get_ref_mut_packed_file_by_path(&path("ui/c.png")).unwrap().get_ref_mut_raw().set_path(&path("ui/d.png")).unwrap();
+	assert!(!pack_file.contains_path(&path("ui/c.png")));
+	assert!(pack_file.contains_path(&path("ui/d.png")));
+	assert_eq!(pack_file.get_by_path(&path("ui/d.png")).unwrap().get_path(), &*path("ui/d.png"));
+
+	// And edits after that should use the new path.
+	pack_file.set_data_batch(vec![(path("ui/d.png"), vec![1, 2, 3])]).unwrap();
+	assert!(pack_file.set_data_batch(vec![(path("ui/c.png"), vec![1])]).is_err());
+	assert_eq!(pack_file.get_ref_mut_packed_file_by_path(&path("ui/d.png")).unwrap().get_raw_data().unwrap(), vec![1, 2, 3]);
+	pack_file.add_file(PackedFile::new(path("ui/c.png"), "test.pack".to_owned())).unwrap();
+	assert!(pack_file.add_file(PackedFile::new(path("ui/d.png"), "test.pack".to_owned())).is_err());
+	assert!(pack_file.contains_path(&path("ui/c.png")));
+	assert!(pack_file.contains_path(&path("ui/d.png")));
+}
+
+#[test]
+fn test_new_from_decoded_and_encoded() {
+	let mut definition = Definition::new(1);
+	definition.fields.push(Field { name: "key".to_owned(), field_type: FieldType::StringU16, is_key: true, ..Default::default() });
+	definition.fields.push(Field { name: "text".to_owned(), field_type: FieldType::StringU16, ..Default::default() });
+	definition.fields.push(Field { name: "tooltip".to_owned(), field_type: FieldType::Boolean, ..Default::default() });
+	let mut schema = Schema::default();
+	schema.add_versioned_file(&VersionedFile::Loc(vec![definition.clone()]));
+
+	let mut loc = Loc::new(&definition);
+	loc.set_table_data(&[vec![DecodedData::StringU16("key".to_owned()), DecodedData::StringU16("text".to_owned()), DecodedData::Boolean(true)]]).unwrap();
+	let decoded = DecodedPackedFile::Loc(loc.clone());
+
+	let path = vec!["text".to_owned(), "db".to_owned(), "test.loc".to_owned()];
+	let packed_file = PackedFile::new_from_decoded_and_encoded(&decoded, path.to_vec(), "test.pack".to_owned()).unwrap();
+	assert_eq!(packed_file.get_path(), &*path);
+	assert_eq!(packed_file.get_ref_raw().get_packfile_name(), "test.pack");
+	assert!(packed_file.get_ref_raw().get_timestamp() > 0);
+	assert_eq!(Loc::read(&packed_file.get_raw_data().unwrap(), &schema, false).unwrap(), loc);
+
+	// Types we cannot save must fail.
+	let error = PackedFile::new_from_decoded_and_encoded(&DecodedPackedFile::Unknown, path, "test.pack".to_owned()).unwrap_err();
+	assert_eq!(error.kind(), &ErrorKind::PackedFileTypeNotEncodable);
+}
+
+#[test]
+fn test_rebuild_index() {
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	let mut text = Text::new();
+	text.set_contents("new text");
+	let mut packed_file = PackedFile::new_from_decoded(&DecodedPackedFile::Text(text), vec!["text".to_owned(), "b.txt".to_owned()]);
+	packed_file.get_ref_mut_raw().set_data(b"old");
+	pack_file.packed_files.push(packed_file);
+	pack_file.packed_files.push(PackedFile::new(vec!["Text".to_owned(), "a.txt".to_owned()], "test.pack".to_owned()));
+
+	// The PackedFiles must end up in the index order, with the sizes of their current data.
+	pack_file.rebuild_index().unwrap();
+	assert_eq!(pack_file.get_ref_packed_files_all_paths(), vec![&["Text".to_owned(), "a.txt".to_owned()][..], &["text".to_owned(), "b.txt".to_owned()][..]]);
+	assert_eq!(pack_file.packed_files[1].get_ref_raw().get_size(), 8);
+
+	// Same path, and same path with a different case.
+	pack_file.packed_files.push(PackedFile::new(vec!["text".to_owned(), "b.txt".to_owned()], "test.pack".to_owned()));
+	assert_eq!(pack_file.rebuild_index().unwrap_err().kind(), &ErrorKind::DuplicatedPackedFile(vec!["text".to_owned(), "b.txt".to_owned()]));
+
+	pack_file.packed_files.pop();
+	pack_file.packed_files.push(PackedFile::new(vec!["text".to_owned(), "A.txt".to_owned()], "test.pack".to_owned()));
+	assert_eq!(pack_file.rebuild_index().unwrap_err().kind(), &ErrorKind::DuplicatedPackedFile(vec!["text".to_owned(), "A.txt".to_owned()]));
+}
+
+#[test]
+fn test_save_streaming() {
+	let mut pack_file = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), true).unwrap();
+	let pack_file_base = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), false).unwrap();
+
+	// Edit one of the PackedFiles, so we have to write data from both, memory and disk.
+	let edited_path = pack_file.get_ref_packed_files_all_paths()[0].to_vec();
+	pack_file.get_ref_mut_packed_file_by_path(&edited_path).unwrap().get_ref_mut_raw().set_data(b"edited data");
+
+	let mut cursor = Cursor::new(vec![]);
+	pack_file.save_streaming(&mut cursor).unwrap();
+
+	// The PackedFiles we didn't touch should not have been loaded to memory.
+	assert!(pack_file.get_ref_packed_files_all().iter().filter(|x| x.get_path() != &*edited_path).all(|x| x.get_ref_raw().is_on_disk()));
+
+	let mut path = temp_dir();
+	path.push("rpfm_test_save_streaming.pack");
+	File::create(&path).unwrap().write_all(&cursor.into_inner()).unwrap();
+
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	assert_eq!(pack_file_new.get_ref_packed_files_all_paths(), pack_file_base.get_ref_packed_files_all_paths());
+	for packed_file in pack_file_new.get_ref_packed_files_all() {
+		let data = packed_file.get_ref_raw().get_data().unwrap();
+		if packed_file.get_path() == &*edited_path { assert_eq!(data, b"edited data"); }
+		else { assert_eq!(data, pack_file_base.get_ref_packed_file_by_path(packed_file.get_path()).unwrap().get_ref_raw().get_data().unwrap()); }
+	}
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_encrypt_packed_file() {
+	let data = b"This data has a size that is not a multiple of 8.".to_vec();
+	let encrypted = encrypt_packed_file(&data);
+	assert_eq!(encrypted.len(), data.len());
+	assert_ne!(encrypted, data);
+	assert_eq!(decrypt_packed_file(&encrypted), data);
+
+	assert!(encrypt_packed_file(&[]).is_empty());
+}
+
+#[test]
+fn test_save_encrypted() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_save_encrypted.pack");
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_save_encrypted.pack", PFHVersion::PFH5);
+	for (name, data) in &[("a.txt", &b"first_file"[..]), ("b.txt", &b"second_file_with_more_data"[..])] {
+		let mut packed_file = PackedFile::new(vec!["text".to_owned(), (*name).to_owned()], "rpfm_test_save_encrypted.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(data);
+		packed_file.get_ref_mut_raw().set_should_be_encrypted(Some(PFHVersion::PFH5));
+		pack_file.packed_files.push(packed_file);
+	}
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	assert!(pack_file_new.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_DATA));
+	assert_eq!(pack_file_new.packed_files[0].get_ref_raw().get_data().unwrap(), b"first_file");
+	assert_eq!(pack_file_new.packed_files[1].get_ref_raw().get_data().unwrap(), b"second_file_with_more_data");
+
+	// If only some of the PackedFiles should be encrypted, the PackFile is saved without encryption.
+	pack_file.packed_files[0].get_ref_mut_raw().set_should_be_encrypted(None);
+	pack_file.save(None).unwrap();
+
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	assert!(!pack_file_new.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_DATA));
+	assert_eq!(pack_file_new.packed_files[1].get_ref_raw().get_data().unwrap(), b"second_file_with_more_data");
+
+	// An encrypted PackFile read from disk can be edited, and loses the encryption flag once it's saved unencrypted.
+	pack_file.set_all_encrypted(Some(PFHVersion::PFH5)).unwrap();
+	pack_file.save(None).unwrap();
+	let mut pack_file = PackFile::read(&path, false).unwrap();
+	assert!(pack_file.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_DATA));
+	assert!(pack_file.is_editable(false));
+
+	pack_file.set_all_encrypted(None).unwrap();
+	pack_file.save(None).unwrap();
+	assert!(!pack_file.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_DATA));
+
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	assert!(!pack_file_new.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_DATA));
+	assert_eq!(pack_file_new.packed_files[0].get_ref_raw().get_data().unwrap(), b"first_file");
+	assert_eq!(pack_file_new.packed_files[1].get_ref_raw().get_data().unwrap(), b"second_file_with_more_data");
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_save_extended_header() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_save_extended_header.pack");
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_save_extended_header.pack", PFHVersion::PFH5);
+	pack_file.set_bitmask(PFHFlags::HAS_EXTENDED_HEADER | PFHFlags::HAS_INDEX_WITH_TIMESTAMPS);
+	for (name, data) in &[("a.txt", &b"first_file"[..]), ("b.txt", &b"second_file_with_more_data"[..])] {
+		let mut packed_file = PackedFile::new(vec!["text".to_owned(), (*name).to_owned()], "rpfm_test_save_extended_header.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(data);
+		pack_file.packed_files.push(packed_file);
+	}
+	pack_file.set_all_encrypted(Some(PFHVersion::PFH5)).unwrap();
+	assert!(pack_file.is_editable(false));
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	// Header, indexes (two files with 4 bytes of size and 4 of timestamp), padding to 8, each file padded to 8, and the 256 bytes at the end.
+	let index_size = 2 * 8 + "text\\a.txt\0".len() + "text\\b.txt\0".len();
+	let data_start = 48 + index_size + (8 - (48 + index_size) % 8) % 8;
+	assert_eq!(std::fs::metadata(&path).unwrap().len() as usize, data_start + 16 + 32 + 256);
+
+	// Change the unknown data, to check it's kept when saving.
+	let mut data = std::fs::read(&path).unwrap();
+	data[28..48].copy_from_slice(&[7; 20]);
+	let footer_start = data.len() - 256;
+	data[footer_start..].copy_from_slice(&[9; 256]);
+	File::create(&path).unwrap().write_all(&data).unwrap();
+
+	let mut pack_file = PackFile::read(&path, false).unwrap();
+	assert!(pack_file.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_DATA | PFHFlags::HAS_EXTENDED_HEADER));
+	assert_eq!(pack_file.packed_files[0].get_ref_raw().get_data().unwrap(), b"first_file");
+	assert_eq!(pack_file.packed_files[1].get_ref_raw().get_data().unwrap(), b"second_file_with_more_data");
+
+	pack_file.packed_files[0].get_ref_mut_raw().set_data(b"edited");
+	pack_file.save(None).unwrap();
+	let data = std::fs::read(&path).unwrap();
+	assert_eq!(&data[28..48], &[7; 20]);
+	assert_eq!(&data[data.len() - 256..], &[9; 256][..]);
+
+	let pack_file = PackFile::read(&path, false).unwrap();
+	assert_eq!(pack_file.packed_files[0].get_ref_raw().get_data().unwrap(), b"edited");
+	assert_eq!(pack_file.packed_files[1].get_ref_raw().get_data().unwrap(), b"second_file_with_more_data");
+
+	// The data is not padded when it's not encrypted.
+	let mut pack_file = pack_file;
+	pack_file.set_all_encrypted(None).unwrap();
+	pack_file.save(None).unwrap();
+	assert_eq!(std::fs::metadata(&path).unwrap().len() as usize, 48 + index_size + 6 + 26 + 256);
+	let pack_file = PackFile::read(&path, false).unwrap();
+	assert!(!pack_file.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_DATA));
+	assert_eq!(pack_file.packed_files[0].get_ref_raw().get_data().unwrap(), b"edited");
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_total_decompressed_size() {
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	let new_packed_file = |name: &str, data| PackedFile::new_from_raw(&RawPackedFile::read_from_data(
+		vec!["text".to_owned(), name.to_owned()],
+		"test.pack".to_owned(),
+		0,
+		false,
+		None,
+		data
+	));
+
+	// Only the header of compressed data is used, so we don't need real compressed data here.
+	let mut compressed_data = vec![];
+	compressed_data.extend_from_slice(&[0x00, 0x10, 0x00, 0x00]);
+	compressed_data.extend_from_slice(&[0x5D, 0x00, 0x00, 0x40, 0x00]);
+	compressed_data.extend_from_slice(&[0xAA; 20]);
+
+	pack_file.packed_files.push(new_packed_file("a.txt", PackedFileData::OnMemory(b"uncompressed".to_vec(), false, None)));
+	pack_file.packed_files.push(new_packed_file("b.txt", PackedFileData::OnMemory(compressed_data.to_vec(), true, None)));
+	pack_file.packed_files.push(new_packed_file("c.txt", PackedFileData::OnMemory(encrypt_packed_file(&compressed_data), true, Some(PFHVersion::PFH5))));
+	assert_eq!(pack_file.total_decompressed_size().unwrap(), 12 + 4096 + 4096);
+
+	// Compressed data too short to even have a header is broken.
+	pack_file.packed_files.push(new_packed_file("d.txt", PackedFileData::OnMemory(vec![0x00, 0x10], true, None)));
+	assert!(pack_file.total_decompressed_size().is_err());
+}
+
+#[test]
+fn test_path_normalization() {
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "a.txt".to_owned()], "test.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_path(&["db\\units".to_owned(), "".to_owned(), "file.loc".to_owned()]).unwrap();
+	assert_eq!(packed_file.get_path(), &["db".to_owned(), "units".to_owned(), "file.loc".to_owned()]);
+
+	packed_file.get_ref_mut_raw().set_path(&["/Text//Mixed\\".to_owned(), "Case.TXT".to_owned()]).unwrap();
+	assert_eq!(packed_file.get_path(), &["Text".to_owned(), "Mixed".to_owned(), "Case.TXT".to_owned()]);
+	assert_eq!(normalize_path(packed_file.get_path(), true), vec!["text".to_owned(), "mixed".to_owned(), "case.txt".to_owned()]);
+
+	// Paths that end up empty are rejected, and the old path is kept.
+	assert_eq!(packed_file.get_ref_mut_raw().set_path(&["".to_owned(), "\\".to_owned()]).unwrap_err().kind(), &ErrorKind::EmptyInput);
+	assert_eq!(packed_file.get_path(), &["Text".to_owned(), "Mixed".to_owned(), "Case.TXT".to_owned()]);
+
+	let mut path = temp_dir();
+	path.push("rpfm_test_path_normalization.txt");
+	File::create(&path).unwrap().write_all(b"text").unwrap();
+	let raw_packed_file = RawPackedFile::read_from_path(&path, vec!["text/folder".to_owned(), "".to_owned(), "file.txt".to_owned()]).unwrap();
+	assert_eq!(raw_packed_file.get_path(), &["text".to_owned(), "folder".to_owned(), "file.txt".to_owned()]);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_timestamp_datetime() {
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "a.txt".to_owned()], "test.pack".to_owned());
+	assert_eq!(packed_file.get_timestamp_datetime(), None);
+
+	// 2020-03-01 12:30:45 UTC.
+	packed_file.get_ref_mut_raw().set_timestamp(1_583_065_845);
+	assert_eq!(packed_file.get_timestamp_datetime(), Some(NaiveDate::from_ymd(2020, 3, 1).and_hms(12, 30, 45)));
+}
+
+#[test]
+fn test_rename_folder() {
+	let path = |x: &str| x.split('/').map(|x| x.to_owned()).collect::<Vec<String>>();
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	for packed_file_path in &["text/folder/a.txt", "text/folder/nested/b.txt", "text/folder_2/c.txt", "text/other/a.txt", "text/folder"] {
+		pack_file.add_packed_file(&PackedFile::new(path(packed_file_path), "test.pack".to_owned()), false).unwrap();
+	}
+
+	assert_eq!(pack_file.rename_folder(&path("text/folder"), &path("text/renamed/folder")).unwrap(), 2);
+	let mut paths = pack_file.get_ref_packed_files_all_paths().iter().map(|x| x.join("/")).collect::<Vec<String>>();
+	paths.sort();
+	assert_eq!(paths, vec!["text/folder", "text/folder_2/c.txt", "text/other/a.txt", "text/renamed/folder/a.txt", "text/renamed/folder/nested/b.txt"]);
+	assert!(pack_file.get_ref_packed_file_by_path(&path("text/renamed/folder/nested/b.txt")).is_some());
+
+	// Collisions (ignoring the case) must leave everything untouched.
+	assert_eq!(pack_file.rename_folder(&path("text/renamed/folder"), &path("TEXT/Other")).unwrap_err().kind(), &ErrorKind::DuplicatedPackedFile(path("TEXT/Other/a.txt")));
+	assert!(pack_file.get_ref_packed_file_by_path(&path("text/renamed/folder/a.txt")).is_some());
+	assert!(pack_file.get_ref_packed_file_by_path(&path("text/renamed/folder/nested/b.txt")).is_some());
+
+	assert_eq!(pack_file.rename_folder(&path("missing"), &path("text/missing")).unwrap(), 0);
+	assert_eq!(pack_file.rename_folder(&path("text/other"), &[]).unwrap_err().kind(), &ErrorKind::EmptyInput);
+}
+
+#[test]
+fn test_rename_file() {
+	let path = |x: &str| x.split('/').map(|x| x.to_owned()).collect::<Vec<String>>();
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	for packed_file_path in &["text/a.txt", "text/b.txt", "text/c.txt"] {
+		let mut packed_file = PackedFile::new(path(packed_file_path), "test.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(packed_file_path.as_bytes());
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+	}
+
+	// The index must always point each path to the PackedFile with it.
+	let assert_index_is_consistent = |pack_file: &PackFile| {
+		let positions = pack_file.packed_files_index.positions.read().unwrap();
+		assert_eq!(positions.len(), pack_file.packed_files.len());
+		for (position, packed_file) in pack_file.packed_files.iter().enumerate() {
+			assert_eq!(positions.get(packed_file.get_path()), Some(&position));
+		}
+	};
+
+	pack_file.rename_file(&path("text/b.txt"), &path("text/renamed/b.txt")).unwrap();
+	assert_index_is_consistent(&pack_file);
+	assert!(!pack_file.packedfile_exists(&path("text/b.txt")));
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("text/renamed/b.txt")).unwrap().get_ref_raw().get_data().unwrap(), b"text/b.txt");
+
+	// Collisions and missing sources must leave everything untouched.
+	assert_eq!(pack_file.rename_file(&path("text/a.txt"), &path("text/c.txt")).unwrap_err().kind(), &ErrorKind::DuplicatedPackedFile(path("text/c.txt")));
+	assert_index_is_consistent(&pack_file);
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("text/a.txt")).unwrap().get_ref_raw().get_data().unwrap(), b"text/a.txt");
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("text/c.txt")).unwrap().get_ref_raw().get_data().unwrap(), b"text/c.txt");
+	assert_eq!(pack_file.rename_file(&path("text/a.txt"), &path("TEXT/C.txt")).unwrap_err().kind(), &ErrorKind::DuplicatedPackedFile(path("TEXT/C.txt")));
+	assert_index_is_consistent(&pack_file);
+	assert!(!pack_file.packedfile_exists(&path("TEXT/C.txt")));
+
+	// Changing only the case of the path is not a collision with itself.
+	pack_file.rename_file(&path("text/a.txt"), &path("text/A.txt")).unwrap();
+	assert_index_is_consistent(&pack_file);
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path("text/A.txt")).unwrap().get_ref_raw().get_data().unwrap(), b"text/a.txt");
+	pack_file.rename_file(&path("text/A.txt"), &path("text/a.txt")).unwrap();
+
+	assert_eq!(pack_file.rename_file(&path("text/b.txt"), &path("text/d.txt")).unwrap_err().kind(), &ErrorKind::PackedFileNotFound);
+	assert_index_is_consistent(&pack_file);
+	assert!(!pack_file.packedfile_exists(&path("text/d.txt")));
+
+	assert_eq!(pack_file.rename_file(&path("text/a.txt"), &[]).unwrap_err().kind(), &ErrorKind::EmptyInput);
+	assert_eq!(pack_file.rename_file(&path("text/a.txt"), &path("text/a.txt")).unwrap_err().kind(), &ErrorKind::PathsAreEqual);
+	assert_index_is_consistent(&pack_file);
+
+	// Changing the path through a mutable reference must rebuild the index on the next lookup.
+	pack_file.get_ref_mut_packed_file_by_path(&path("text/c.txt")).unwrap().get_ref_mut_raw().set_path(&path("text/e.txt")).unwrap();
+	assert!(pack_file.packed_files_index.outdated.load(std::sync::atomic::Ordering::SeqCst));
+	assert!(pack_file.contains_path(&path("text/e.txt")));
+	assert!(!pack_file.contains_path(&path("text/c.txt")));
+	assert!(!pack_file.packed_files_index.outdated.load(std::sync::atomic::Ordering::SeqCst));
+	assert_index_is_consistent(&pack_file);
+}
+
+#[test]
+fn test_extract_one() {
+	let pack_file = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), true).unwrap();
+	let pack_file_memory = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), false).unwrap();
+
+	let path = pack_file.get_ref_packed_files_all_paths()[0].to_vec();
+	assert_eq!(pack_file.extract_one(&path).unwrap(), pack_file_memory.get_ref_packed_file_by_path(&path).unwrap().get_ref_raw().get_data().unwrap());
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| x.get_ref_raw().is_on_disk()));
+
+	assert_eq!(pack_file.extract_one(&["missing".to_owned(), "file.txt".to_owned()]).unwrap_err().kind(), &ErrorKind::PackedFileNotFound);
+}
+
+#[test]
+fn test_has_timestamps() {
+	let mut pack_file = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), false).unwrap();
+	let mut path = temp_dir();
+	path.push("rpfm_test_has_timestamps.pack");
+
+	for bitmask in &[PFHFlags::HAS_INDEX_WITH_TIMESTAMPS, PFHFlags::empty()] {
+		pack_file.set_bitmask(*bitmask);
+		let mut cursor = Cursor::new(vec![]);
+		pack_file.save_streaming(&mut cursor).unwrap();
+		File::create(&path).unwrap().write_all(&cursor.into_inner()).unwrap();
+
+		let pack_file_new = PackFile::read(&path, false).unwrap();
+		assert_eq!(pack_file_new.has_timestamps(), !bitmask.is_empty());
+	}
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_on_disk_data() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_on_disk_data.bin");
+	File::create(&path).unwrap().write_all(b"headerfirst_filesecond_file").unwrap();
+	let file = Arc::new(File::open(&path).unwrap());
+
+	let new_packed_file = |position, size| RawPackedFile::read_from_data(
+		vec!["text".to_owned(), "a.txt".to_owned()],
+		"test.pack".to_owned(),
+		0,
+		false,
+		None,
+		PackedFileData::OnDisk(file.clone(), position, size, false, None)
+	);
+
+	// Read the same data through all the entry points, interleaving readers of different files sharing the same file.
+	let mut first = new_packed_file(6, 10);
+	let mut second = new_packed_file(16, 11);
+	assert_eq!(first.get_raw_data().unwrap(), b"first_file");
+	assert_eq!(second.get_data().unwrap(), b"second_file");
+	assert_eq!(first.get_data().unwrap(), b"first_file");
+	assert_eq!(second.get_data_and_keep_it().unwrap(), b"second_file");
+	first.load_data().unwrap();
+	assert_eq!(first.get_data().unwrap(), b"first_file");
+	assert_eq!(second.get_raw_data().unwrap(), b"second_file");
+
+	let mut third = new_packed_file(0, 6);
+	assert_eq!(third.get_ref_mut_data_and_keep_it().unwrap(), b"header");
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_append_data() {
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "a.xml".to_owned()], "test.pack".to_owned());
+	packed_file.append_data(b"<root>").unwrap();
+	packed_file.append_data(b"</root>").unwrap();
+	assert_eq!(packed_file.get_ref_raw().get_data().unwrap(), b"<root></root>");
+
+	// Decoded PackedFiles must get the data appended to their current contents.
+	let mut text = Text::new();
+	text.set_contents("first");
+	let mut packed_file = PackedFile::new_from_decoded(&DecodedPackedFile::Text(text), vec!["text".to_owned(), "b.txt".to_owned()]);
+	packed_file.append_data(b"_second").unwrap();
+	assert_eq!(packed_file.get_ref_decoded(), &DecodedPackedFile::Unknown);
+	assert_eq!(packed_file.get_ref_raw().get_data().unwrap(), b"first_second");
+
+	// Data on disk must be loaded to memory and left in plain form.
+	let mut path = temp_dir();
+	path.push("rpfm_test_append_data.bin");
+	File::create(&path).unwrap().write_all(&encrypt_packed_file(b"headerdisk_data")).unwrap();
+	let raw_packed_file = RawPackedFile::read_from_data(
+		vec!["text".to_owned(), "c.txt".to_owned()],
+		"test.pack".to_owned(),
+		0,
+		false,
+		None,
+		PackedFileData::OnDisk(Arc::new(File::open(&path).unwrap()), 0, 15, false, Some(PFHVersion::PFH5))
+	);
+	let mut packed_file = PackedFile::new_from_raw(&raw_packed_file);
+	packed_file.append_data(b"_appended").unwrap();
+	assert!(!packed_file.get_ref_raw().get_encryption_state());
+	assert!(!packed_file.get_ref_raw().get_compression_state());
+	assert_eq!(packed_file.get_ref_raw().get_raw_data().unwrap(), b"headerdisk_data_appended");
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_on_disk_data_truncated() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_on_disk_data_truncated.bin");
+	File::create(&path).unwrap().write_all(b"header_short").unwrap();
+	let file = Arc::new(File::open(&path).unwrap());
+
+	let packed_file_path = vec!["text".to_owned(), "a.txt".to_owned()];
+	let mut packed_file = RawPackedFile::read_from_data(
+		packed_file_path.to_vec(),
+		"test.pack".to_owned(),
+		0,
+		false,
+		None,
+		PackedFileData::OnDisk(file, 7, 20, false, None)
+	);
+
+	let error = ErrorKind::PackedFileDataCouldNotBeRead(packed_file_path, 20, 5);
+	assert_eq!(packed_file.get_raw_data().unwrap_err().kind(), &error);
+	assert_eq!(packed_file.get_data().unwrap_err().kind(), &error);
+	assert_eq!(packed_file.load_data().unwrap_err().kind(), &error);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_oversized_index_entry() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_open_oversized_index_entry.pack");
+	let packed_file_path = vec!["text".to_owned(), "a.txt".to_owned()];
+	let mut pack_file = PackFile::new_with_name("rpfm_test_open_oversized_index_entry.pack", PFHVersion::PFH5);
+	let mut packed_file = PackedFile::new(packed_file_path.to_vec(), String::new());
+	packed_file.get_ref_mut_raw().set_data(b"data");
+	pack_file.add_file(packed_file).unwrap();
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	// The size of the only PackedFile is the first thing in the index, right after the 28 bytes of the header.
+	let mut data = std::fs::read(&path).unwrap();
+	data[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+	std::fs::write(&path, &data).unwrap();
+
+	// The entry must be rejected when opening the PackFile, before anyone tries to allocate its data.
+	let data_end = data.len() as u64 - 4 + u64::from(u32::MAX);
+	let error = ErrorKind::PackedFileSizeExceedsFile(packed_file_path, data_end, data.len() as u64);
+	assert_eq!(PackFile::read(&path, true).unwrap_err().kind(), &error);
+	assert_eq!(PackFile::open_index_only(&path).unwrap_err().kind(), &error);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_on_disk_data_concurrent() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_on_disk_data_concurrent.bin");
+	let files = (0..16u8).map(|x| vec![x; 1024 + x as usize]).collect::<Vec<Vec<u8>>>();
+	File::create(&path).unwrap().write_all(&files.concat()).unwrap();
+	let file = Arc::new(File::open(&path).unwrap());
+
+	// Read all the files from the same backing file at the same time, multiple times each, to catch readers stepping on each other.
+	let mut position = 0;
+	let mut handles = vec![];
+	for data in &files {
+		let packed_file = RawPackedFile::read_from_data(
+			vec!["text".to_owned(), format!("{}.txt", data[0])],
+			"test.pack".to_owned(),
+			0,
+			false,
+			None,
+			PackedFileData::OnDisk(file.clone(), position, data.len() as u32, false, None)
+		);
+		position += data.len() as u64;
+
+		let expected = data.to_vec();
+		handles.push(thread::spawn(move || {
+			for _ in 0..64 {
+				assert_eq!(packed_file.get_data().unwrap(), expected);
+			}
+		}));
+	}
+
+	for handle in handles {
+		handle.join().unwrap();
+	}
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_bulk_operations_progress() {
+	let mut pack_file = PackFile::new_with_name("rpfm_test_progress.pack", PFHVersion::PFH5);
+	let paths = (0..5).map(|x| vec!["text".to_owned(), format!("{}.txt", x)]).collect::<Vec<Vec<String>>>();
+	for path in &paths {
+		let mut packed_file = PackedFile::new(path.to_vec(), "rpfm_test_progress.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(b"text");
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+	}
+
+	let mut calls = vec![];
+	let errors = pack_file.decode_packed_files_by_types_with_progress(&[PackedFileType::Text(TextType::Plain)], &Schema::default(), |done, total| calls.push((done, total)));
+	assert!(errors.is_empty());
+	assert_eq!(calls, (1..=5).map(|x| (x, 5)).collect::<Vec<(usize, usize)>>());
+
+	let mut path = temp_dir();
+	path.push("rpfm_test_progress");
+	let mut calls = vec![];
+	let extracted = pack_file.extract_packed_files_by_type_with_progress(&[PathType::PackFile], &path, |done, total| calls.push((done, total))).unwrap();
+	assert_eq!(extracted, 5);
+	assert_eq!(calls, (1..=5).map(|x| (x, 5)).collect::<Vec<(usize, usize)>>());
+
+	// Failed files count as done too.
+	let mut calls = vec![];
+	let item_types = vec![PathType::File(paths[0].to_vec()), PathType::File(vec!["text".to_owned(), "missing.txt".to_owned()])];
+	assert!(pack_file.extract_packed_files_by_type_with_progress(&item_types, &path, |done, total| calls.push((done, total))).is_err());
+	assert_eq!(calls, vec![(1, 2), (2, 2)]);
+	remove_dir_all(&path).unwrap();
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_read_mapped() {
+	for version in &["PFH5", "PFH4", "PFH3", "PFH2", "PFH0"] {
+		let path = PathBuf::from(format!("../test_files/{}_test.pack", version));
+		let pack_file = PackFile::read(&path, true).unwrap();
+		let pack_file_mapped = PackFile::read_mapped(&path).unwrap();
+		assert_eq!(pack_file_mapped.get_ref_packed_files_all_paths(), pack_file.get_ref_packed_files_all_paths());
+
+		for packed_file in pack_file_mapped.get_ref_packed_files_all() {
+			let packed_file_on_disk = pack_file.get_ref_packed_file_by_path(packed_file.get_path()).unwrap();
+			assert!(packed_file.get_ref_raw().is_on_disk());
+			assert_eq!(packed_file.get_ref_raw().get_data().unwrap(), packed_file_on_disk.get_ref_raw().get_data().unwrap());
+			assert_eq!(packed_file.get_ref_raw().get_raw_data().unwrap(), packed_file_on_disk.get_ref_raw().get_raw_data().unwrap());
+			assert_eq!(packed_file.get_ref_raw().get_size(), packed_file_on_disk.get_ref_raw().get_size());
+		}
+	}
+}
+
+#[test]
+fn test_diff() {
+	let path = |x: &str| x.split('/').map(|x| x.to_owned()).collect::<Vec<String>>();
+	let get_test_pack_file = |files: &[(&str, &[u8])]| {
+		let mut pack_file = PackFile::new_with_name("rpfm_test_diff.pack", PFHVersion::PFH5);
+		for (packed_file_path, data) in files {
+			let mut packed_file = PackedFile::new(path(packed_file_path), "rpfm_test_diff.pack".to_owned());
+			packed_file.get_ref_mut_raw().set_data(data);
+			pack_file.add_packed_file(&packed_file, false).unwrap();
+		}
+		pack_file
+	};
+
+	let old = get_test_pack_file(&[("text/same.txt", b"same"), ("text/modified.txt", b"old data"), ("text/removed.txt", b"removed")]);
+	let new = get_test_pack_file(&[("text/same.txt", b"same"), ("text/modified.txt", b"new data"), ("text/added.txt", b"added")]);
+
+	let diff = old.diff(&new).unwrap();
+	assert_eq!(diff.packed_files, vec![
+		(path("text/added.txt"), PackedFileDiffState::Added),
+		(path("text/modified.txt"), PackedFileDiffState::Modified),
+		(path("text/removed.txt"), PackedFileDiffState::Removed),
+		(path("text/same.txt"), PackedFileDiffState::Unchanged),
+	]);
+	assert_eq!(diff.get_paths_by_state(PackedFileDiffState::Modified), vec![&*path("text/modified.txt")]);
+
+	// Compare a PackFile with itself, after reading it from disk.
+	let pack_file = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), true).unwrap();
+	let pack_file_memory = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), false).unwrap();
+	let diff = pack_file.diff(&pack_file_memory).unwrap();
+	assert!(diff.packed_files.iter().all(|(_, state)| *state == PackedFileDiffState::Unchanged));
+	assert_eq!(diff.packed_files.len(), pack_file.get_ref_packed_files_all().len());
+}
+
+#[test]
+fn test_validate_all() {
+	let mut pack_file = PackFile::new_with_name("rpfm_test_validate_all.pack", PFHVersion::PFH5);
+	for (path, data) in &[("text/good.txt", &b"Good text."[..]), ("text/broken.loc", &b"Not a Loc"[..]), ("ui/image.png", &[0x89, 0x50, 0x4E, 0x47][..])] {
+		let mut packed_file = PackedFile::new(path.split('/').map(|x| x.to_owned()).collect(), "rpfm_test_validate_all.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(data);
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+	}
+
+	let issues = pack_file.validate_all(&Schema::default());
+	assert_eq!(issues.len(), 1);
+	assert_eq!(issues[0].path, vec!["text".to_owned(), "broken.loc".to_owned()]);
+	assert!(issues[0].description.starts_with("This PackedFile cannot be decoded"));
+}
+
+#[test]
+fn test_optimize_compression() {
+	let mut pack_file = PackFile::new_with_name("rpfm_test_optimize_compression.pack", PFHVersion::PFH5);
+	for size in &[0, 10, 100, 101, 1000] {
+		let mut packed_file = PackedFile::new(vec!["text".to_owned(), format!("{}.txt", size)], "rpfm_test_optimize_compression.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(&vec![b'a'; *size]);
+		pack_file.packed_files.push(packed_file);
+	}
+
+	// Only the header of compressed data is read, so we don't need real compressed data here.
+	for (name, decompressed_size) in &[("shrinks.txt", 4096u32), ("grows.txt", 20)] {
+		let mut compressed_data = decompressed_size.to_le_bytes().to_vec();
+		compressed_data.extend_from_slice(&[0x5D, 0, 0, 0x40, 0]);
+		compressed_data.extend_from_slice(&[0; 200]);
+		pack_file.packed_files.push(PackedFile::new_from_raw(&RawPackedFile::read_from_data(
+			vec!["text".to_owned(), (*name).to_owned()],
+			"rpfm_test_optimize_compression.pack".to_owned(),
+			0,
+			true,
+			None,
+			PackedFileData::OnMemory(compressed_data, true, None)
+		)));
+	}
+
+	pack_file.toggle_compression(true);
+	pack_file.optimize_compression(100);
+	let flags = pack_file.get_ref_packed_files_all().iter().map(|x| x.get_ref_raw().get_should_be_compressed()).collect::<Vec<bool>>();
+	assert_eq!(flags, vec![false, false, false, true, true, true, false]);
+
+	pack_file.optimize_compression(0);
+	let flags = pack_file.get_ref_packed_files_all().iter().map(|x| x.get_ref_raw().get_should_be_compressed()).collect::<Vec<bool>>();
+	assert_eq!(flags, vec![false, true, true, true, true, true, false]);
+}
+
+#[test]
+fn test_compression_report() {
+	let data = vec![b'a'; 4096];
+
+	// Only the header of compressed data is read, so we don't need real compressed data here.
+	let mut compressed_data = 4096u32.to_le_bytes().to_vec();
+	compressed_data.extend_from_slice(&[0x5D, 0, 0, 0x40, 0]);
+	compressed_data.extend_from_slice(&[0; 40]);
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_compression_report.pack", PFHVersion::PFH5);
+	pack_file.packed_files.push(PackedFile::new_from_raw(&RawPackedFile::read_from_data(
+		vec!["text".to_owned(), "compressed.txt".to_owned()],
+		"rpfm_test_compression_report.pack".to_owned(),
+		0,
+		true,
+		None,
+		PackedFileData::OnMemory(compressed_data.to_vec(), true, None)
+	)));
+
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "plain.txt".to_owned()], "rpfm_test_compression_report.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(&data[..1000]);
+	pack_file.packed_files.push(packed_file);
+
+	let report = pack_file.compression_report().unwrap();
+	assert_eq!(report.packed_files[0].compressed_size as usize, compressed_data.len());
+	assert_eq!(report.packed_files[0].decompressed_size, 4096);
+	assert!(report.packed_files[0].get_ratio() < 1.0);
+	assert_eq!(report.packed_files[1].compressed_size, 1000);
+	assert_eq!(report.packed_files[1].decompressed_size, 1000);
+	assert_eq!(report.packed_files[1].get_ratio(), 1.0);
+
+	assert_eq!(report.compressed_size, compressed_data.len() as u64 + 1000);
+	assert_eq!(report.decompressed_size, 5096);
+	assert_eq!(report.get_ratio(), (compressed_data.len() as f64 + 1000.0) / 5096.0);
+
+	assert_eq!(PackFile::new().compression_report().unwrap().get_ratio(), 1.0);
+}
+
+#[test]
+fn test_set_data_compressed() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_set_data_compressed.pack");
+
+	// Only the header of compressed data is read, so we don't need real compressed data here.
+	let mut compressed_data = 4096u32.to_le_bytes().to_vec();
+	compressed_data.extend_from_slice(&[0x5D, 0, 0, 0x40, 0]);
+	compressed_data.extend_from_slice(&[1; 40]);
+
+	let mut source = PackedFile::new(vec!["text".to_owned(), "compressed.txt".to_owned()], "source.pack".to_owned());
+	source.set_data_compressed(compressed_data.to_vec(), None);
+	assert!(source.get_ref_raw().get_compression_state());
+
+	// Move it to another PackFile, carrying the compressed data over as it is.
+	let mut packed_file = PackedFile::new(source.get_path().to_vec(), "rpfm_test_set_data_compressed.pack".to_owned());
+	packed_file.set_data_compressed(source.get_ref_raw().get_raw_data().unwrap(), None);
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_set_data_compressed.pack", PFHVersion::PFH5);
+	pack_file.add_packed_file(&packed_file, false).unwrap();
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	let raw = pack_file_new.get_ref_packed_file_by_path(source.get_path()).unwrap().get_ref_raw();
+	assert!(raw.get_compression_state());
+	assert_eq!(raw.get_raw_data().unwrap(), compressed_data);
+	assert_eq!(raw.get_decompressed_size().unwrap(), 4096);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_with_verification() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_open_with_verification.pack");
+
+	// This is not valid LZMA data, so it cannot be decompressed.
+	let mut compressed_data = 4096u32.to_le_bytes().to_vec();
+	compressed_data.extend_from_slice(&[0x5D, 0, 0, 0x40, 0]);
+	compressed_data.extend_from_slice(&[1; 40]);
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_open_with_verification.pack", PFHVersion::PFH5);
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "corrupted.txt".to_owned()], "rpfm_test_open_with_verification.pack".to_owned());
+	packed_file.set_data_compressed(compressed_data, None);
+	pack_file.add_packed_file(&packed_file, false).unwrap();
+
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "good.txt".to_owned()], "rpfm_test_open_with_verification.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(b"Good text.");
+	pack_file.add_packed_file(&packed_file, false).unwrap();
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	// Without verification, the corrupted PackedFile is only noticed when reading it.
+	let pack_file_new = PackFile::open_with_verification(&path, false).unwrap();
+	assert_eq!(pack_file_new.get_packed_files_all_paths().len(), 2);
+
+	match PackFile::open_with_verification(&path, true).unwrap_err().kind() {
+		ErrorKind::PackFileCorruptedCompressedData(errors) => {
+			assert_eq!(errors.len(), 1);
+			assert!(errors[0].starts_with("text/corrupted.txt: "));
+		}
+		error => panic!("Unexpected error: {:?}", error),
+	}
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_compression_settings() {
+	let pack_file = PackFile::new();
+	assert_eq!(pack_file.get_compression_settings(), CompressionSettings::default());
+	assert_eq!(CompressionSettings::default().level, DEFAULT_COMPRESSION_LEVEL);
+
+	// Levels out of range are clamped.
+	assert_eq!(CompressionSettings { level: 0 }.get_clamped_level(), MIN_COMPRESSION_LEVEL);
+	assert_eq!(CompressionSettings { level: MIN_COMPRESSION_LEVEL }.get_clamped_level(), MIN_COMPRESSION_LEVEL);
+	assert_eq!(CompressionSettings::default().get_clamped_level(), DEFAULT_COMPRESSION_LEVEL);
+	assert_eq!(CompressionSettings { level: MAX_COMPRESSION_LEVEL }.get_clamped_level(), MAX_COMPRESSION_LEVEL);
+	assert_eq!(CompressionSettings { level: 100 }.get_clamped_level(), MAX_COMPRESSION_LEVEL);
+}
+
+#[test]
+fn test_compression_header() {
+	let data = (0..2_000).map(|x| format!("header line {}\n", x % 11)).collect::<String>().into_bytes();
+
+	// Get LZMA data like the one 7z generates (with an end marker), and put it in a fake 7z file.
+	let stream = Stream::new_lzma_encoder(&LzmaOptions::new_preset(DEFAULT_COMPRESSION_LEVEL).unwrap()).unwrap();
+	let mut lzma_alone = vec![];
+	XzEncoder::new_stream(&*data, stream).read_to_end(&mut lzma_alone).unwrap();
+	let lzma_data = lzma_alone[13..].to_vec();
+
+	let mut seven_zip = vec![0; 32];
+	seven_zip[12..16].copy_from_slice(&(lzma_data.len() as u32).to_le_bytes());
+	seven_zip.extend_from_slice(&lzma_data);
+	seven_zip.extend_from_slice(&[1, 2, 3, 4]);
+	assert_eq!(get_lzma_data_from_7z(&mut Cursor::new(seven_zip)).unwrap(), lzma_data);
+	assert!(get_lzma_data_from_7z(&mut Cursor::new(vec![0; 12])).is_err());
+
+	// The header is the decompressed size, followed by the LZMA properties, and it should be decompressable.
+	let compressed_data = add_ca_header(data.len(), lzma_data.to_vec());
+	assert_eq!(&compressed_data[..4], &(data.len() as u32).to_le_bytes());
+	assert_eq!(&compressed_data[4..9], &[0x5D, 0x00, 0x00, 0x40, 0x00]);
+	assert_eq!(&compressed_data[9..], &*lzma_data);
+	assert_eq!(Codec::Lzma1.get_decompressed_size(&compressed_data).unwrap(), data.len() as u32);
+	assert_eq!(decompress_data(&compressed_data).unwrap(), data);
+}
+
+#[test]
+#[ignore = "needs 7z in the PATH"]
+fn test_compression_settings_7z() {
+	let data = (0..20_000).map(|x| format!("line {} of {}\n", x % 37, x % 101)).collect::<String>().into_bytes();
+	let mut sizes = vec![];
+	for level in &[0, MIN_COMPRESSION_LEVEL, DEFAULT_COMPRESSION_LEVEL, MAX_COMPRESSION_LEVEL, 100] {
+		let compressed_data = compress_data(&data, &CompressionSettings { level: *level }).unwrap();
+		assert_eq!(decompress_data(&compressed_data).unwrap(), data);
+		sizes.push(compressed_data.len());
+	}
+	assert!(sizes[3] <= sizes[1]);
+
+	// The settings of the PackFile are the ones used when saving it.
+	let mut path = temp_dir();
+	path.push("rpfm_test_compression_settings.pack");
+	let mut pack_file = PackFile::new_with_name("rpfm_test_compression_settings.pack", PFHVersion::PFH5);
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "lines.txt".to_owned()], "rpfm_test_compression_settings.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(&data);
+	pack_file.add_packed_file(&packed_file, false).unwrap();
+	pack_file.toggle_compression(true);
+	pack_file.set_compression_settings(CompressionSettings { level: MAX_COMPRESSION_LEVEL });
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	let raw = pack_file_new.get_ref_packed_file_by_path(packed_file.get_path()).unwrap().get_ref_raw();
+	assert!(raw.get_compression_state());
+	assert_eq!(raw.get_raw_data().unwrap().len(), sizes[3]);
+	assert_eq!(raw.get_data().unwrap(), data);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_compression_codecs() {
+	assert_eq!(Codec::from_pfh_version(PFHVersion::PFH5), Some(Codec::Lzma1));
+	for pfh_version in &[PFHVersion::PFH4, PFHVersion::PFH3, PFHVersion::PFH2, PFHVersion::PFH0] {
+		assert_eq!(Codec::from_pfh_version(*pfh_version), None);
+		assert!(!pfh_version.supports_compression());
+	}
+}
+
+#[test]
+#[ignore = "needs 7z in the PATH"]
+fn test_compression_codecs_7z() {
+	let path = vec!["text".to_owned(), "codec.txt".to_owned()];
+	let data = (0..2_000).map(|x| format!("codec line {}\n", x % 13)).collect::<String>().into_bytes();
+	let pfh_versions = [PFHVersion::PFH5, PFHVersion::PFH4, PFHVersion::PFH3, PFHVersion::PFH2, PFHVersion::PFH0];
+	for (pfh_version, codec) in pfh_versions.iter().filter_map(|x| Some((x, Codec::from_pfh_version(*x)?))) {
+		let compressed_data = codec.compress(&data, &CompressionSettings::default()).unwrap();
+		assert_eq!(codec.decompress(&compressed_data).unwrap(), data);
+		assert_eq!(codec.get_decompressed_size(&compressed_data).unwrap(), data.len() as u32);
+
+		// A file tagged with the codec gets decompressed with it.
+		let mut raw = RawPackedFile::read_from_data(path.to_vec(), "test.pack".to_owned(), 0, true, None, PackedFileData::OnMemory(compressed_data, true, None));
+		raw.set_compression_codec(codec);
+		assert_eq!(raw.get_compression_codec(), codec);
+		assert_eq!(raw.get_decompressed_size().unwrap(), data.len() as u32);
+		assert_eq!(raw.get_data().unwrap(), data);
+
+		// And a PackFile of the version tags its compressed files with it when reading them.
+		let mut pack_path = temp_dir();
+		pack_path.push(format!("rpfm_test_compression_codecs_{}.pack", pfh_version));
+		let mut pack_file = PackFile::new_with_name("rpfm_test_compression_codecs.pack", *pfh_version);
+		let mut packed_file = PackedFile::new(path.to_vec(), "rpfm_test_compression_codecs.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(&data);
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+		pack_file.toggle_compression(true);
+		pack_file.save(Some(pack_path.to_path_buf())).unwrap();
+
+		let pack_file_new = PackFile::read(&pack_path, false).unwrap();
+		let raw = pack_file_new.get_ref_packed_file_by_path(&path).unwrap().get_ref_raw();
+		assert!(raw.get_compression_state());
+		assert_eq!(raw.get_compression_codec(), codec);
+		assert_eq!(raw.get_data().unwrap(), data);
+		remove_file(&pack_path).unwrap();
+	}
+}
+
+#[test]
+fn test_set_data_batch() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_set_data_batch.pack");
+	let file_path = |x: usize| vec!["text".to_owned(), format!("{}.txt", x)];
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_set_data_batch.pack", PFHVersion::PFH5);
+	for index in 0..5 {
+		let mut packed_file = PackedFile::new(file_path(index), "rpfm_test_set_data_batch.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(format!("Old text {}.", index).as_bytes());
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+	}
+
+	// If any of the paths is wrong, nothing changes.
+	let entries = vec![(file_path(0), b"New text 0.".to_vec()), (file_path(9), b"New text 9.".to_vec())];
+	assert_eq!(pack_file.set_data_batch(entries).unwrap_err().kind(), &ErrorKind::PackedFileNotFound);
+	assert_eq!(pack_file.extract_one(&file_path(0)).unwrap(), b"Old text 0.");
+
+	// The decoded data is dropped, so it doesn't overwrite the new data when saving.
+	pack_file.get_ref_mut_packed_file_by_path(&file_path(1)).unwrap().decode_no_locks(&Schema::default()).unwrap();
+	let entries = [0, 1, 3].iter().map(|x| (file_path(*x), format!("New text {}.", x).into_bytes())).collect();
+	pack_file.set_data_batch(entries).unwrap();
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&file_path(1)).unwrap().get_ref_decoded(), &DecodedPackedFile::Unknown);
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	for (index, text) in ["New text 0.", "New text 1.", "Old text 2.", "New text 3.", "Old text 4."].iter().enumerate() {
+		assert_eq!(pack_file_new.extract_one(&file_path(index)).unwrap(), text.as_bytes());
+	}
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_memory_usage() {
+	let mut pack_file = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), true).unwrap();
+	assert_eq!(pack_file.memory_usage(), 0);
+
+	let paths = pack_file.get_packed_files_all_paths();
+	let mut last_usage = 0;
+	for path in &paths {
+		let packed_file = pack_file.get_ref_mut_packed_file_by_path(path).unwrap();
+		let size = packed_file.get_ref_raw().get_size() as usize;
+		packed_file.get_ref_mut_raw().load_data().unwrap();
+
+		let usage = pack_file.memory_usage();
+		assert!(usage >= last_usage + size);
+		last_usage = usage;
+	}
+	assert!(last_usage > 0);
+
+	let mut packed_file = PackedFile::new(vec!["text".to_owned(), "new.txt".to_owned()], "PFH5_test.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(&[0; 1000]);
+	pack_file.add_packed_file(&packed_file, false).unwrap();
+	assert!(pack_file.memory_usage() >= last_usage + 1000);
+}
+
+#[test]
+fn test_add_file() {
+	let path = vec!["text".to_owned(), "file.txt".to_owned()];
+	let mut pack_file = PackFile::new_with_name("rpfm_test_add_file.pack", PFHVersion::PFH5);
+
+	let mut packed_file = PackedFile::new(path.to_vec(), "other.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(b"Old text.");
+	pack_file.add_file(packed_file).unwrap();
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&path).unwrap().get_ref_raw().get_packfile_name(), "rpfm_test_add_file.pack");
+	assert_eq!(pack_file.extract_one(&path).unwrap(), b"Old text.");
+
+	// Duplicates are rejected, and the original PackedFile is kept.
+	let mut packed_file = PackedFile::new(path.to_vec(), "other.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(b"New text.");
+	assert_eq!(pack_file.add_file(packed_file.clone()).unwrap_err().kind(), &ErrorKind::DuplicatedPackedFile(path.to_vec()));
+	assert_eq!(pack_file.extract_one(&path).unwrap(), b"Old text.");
+
+	// Unless we want to overwrite it.
+	pack_file.add_file_overwrite(packed_file).unwrap();
+	assert_eq!(pack_file.get_packed_files_all_paths().len(), 1);
+	assert_eq!(pack_file.extract_one(&path).unwrap(), b"New text.");
+
+	let other_path = vec!["text".to_owned(), "other.txt".to_owned()];
+	pack_file.add_file_overwrite(PackedFile::new(other_path.to_vec(), "other.pack".to_owned())).unwrap();
+	assert!(pack_file.get_ref_packed_file_by_path(&other_path).is_some());
+	assert_eq!(pack_file.get_packed_files_all_paths().len(), 2);
+
+	// Empty and reserved paths are rejected.
+	assert_eq!(pack_file.add_file(PackedFile::new(vec![], "other.pack".to_owned())).unwrap_err().kind(), &ErrorKind::EmptyInput);
+	assert_eq!(pack_file.add_file(PackedFile::new(vec!["text".to_owned(), String::new()], "other.pack".to_owned())).unwrap_err().kind(), &ErrorKind::EmptyInput);
+	let reserved_path = PackFile::get_reserved_packed_file_names()[0].to_vec();
+	assert_eq!(pack_file.add_file_overwrite(PackedFile::new(reserved_path, "other.pack".to_owned())).unwrap_err().kind(), &ErrorKind::ReservedFiles);
+	assert_eq!(pack_file.get_packed_files_all_paths().len(), 2);
+}
+
+#[test]
+fn test_metadata_sidecar() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_metadata_sidecar.pack");
+	let mut sidecar_path = temp_dir();
+	sidecar_path.push("rpfm_test_metadata_sidecar.json");
+	let file_path = vec!["text".to_owned(), "file.txt".to_owned()];
+	let other_path = vec!["text".to_owned(), "other.txt".to_owned()];
+	let note = serde_json::json!({ "comment": "Needs translation.", "todo": true });
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_metadata_sidecar.pack", PFHVersion::PFH5);
+	let mut packed_file = PackedFile::new(file_path.to_vec(), "rpfm_test_metadata_sidecar.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(b"Text.");
+	pack_file.add_file(packed_file.clone()).unwrap();
+	packed_file.get_ref_mut_raw().set_path(&other_path).unwrap();
+	pack_file.add_file(packed_file).unwrap();
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+	let data_without_metadata = std::fs::read(&path).unwrap();
+
+	// The metadata must not change the PackFile.
+	pack_file.get_ref_mut_packed_file_by_path(&file_path).unwrap().set_metadata(Some(note.clone()));
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+	assert_eq!(std::fs::read(&path).unwrap(), data_without_metadata);
+	pack_file.save_metadata_sidecar(&sidecar_path).unwrap();
+
+	let mut pack_file_new = PackFile::read(&path, false).unwrap();
+	assert!(pack_file_new.get_ref_packed_file_by_path(&file_path).unwrap().get_metadata().is_none());
+	assert_eq!(pack_file_new.load_metadata_sidecar(&sidecar_path).unwrap(), 1);
+	assert_eq!(pack_file_new.get_ref_packed_file_by_path(&file_path).unwrap().get_metadata(), Some(&note));
+	assert!(pack_file_new.get_ref_packed_file_by_path(&other_path).unwrap().get_metadata().is_none());
+
+	remove_file(&path).unwrap();
+	remove_file(&sidecar_path).unwrap();
+}
+
+#[test]
+fn test_retarget() {
+	let path = vec!["text".to_owned(), "file.txt".to_owned()];
+	let data = b"This data was encrypted for another game.".to_vec();
+	let encrypted_data = PackedFileData::OnMemory(encrypt_packed_file(&data), false, Some(PFHVersion::PFH5));
+	let mut packed_file = PackedFile::new_from_raw(&RawPackedFile::read_from_data(path.to_vec(), "other.pack".to_owned(), 0, true, Some(PFHVersion::PFH5), encrypted_data.clone()));
+
+	// Both versions support encryption, but only the original one supports compression.
+	packed_file.retarget(PFHVersion::PFH4).unwrap();
+	assert!(!packed_file.get_ref_raw().get_encryption_state());
+	assert_eq!(packed_file.get_ref_raw().get_should_be_encrypted(), &Some(PFHVersion::PFH4));
+	assert!(!packed_file.get_ref_raw().get_should_be_compressed());
+	assert_eq!(packed_file.get_ref_raw().get_data().unwrap(), data);
+
+	// Neither encryption nor compression are supported.
+	let mut packed_file = PackedFile::new_from_raw(&RawPackedFile::read_from_data(path.to_vec(), "other.pack".to_owned(), 0, true, Some(PFHVersion::PFH5), encrypted_data));
+	packed_file.retarget(PFHVersion::PFH0).unwrap();
+	assert!(!packed_file.get_ref_raw().get_encryption_state());
+	assert_eq!(packed_file.get_ref_raw().get_should_be_encrypted(), &None);
+	assert!(!packed_file.get_ref_raw().get_should_be_compressed());
+	assert_eq!(packed_file.get_ref_raw().get_data().unwrap(), data);
+
+	// Files that shouldn't be encrypted stay that way, and compressed data is kept compressed if the new version supports it.
+	let mut compressed_data = vec![];
+	compressed_data.extend_from_slice(&(data.len() as u32).to_le_bytes());
+	compressed_data.extend_from_slice(&[0x5D, 0, 0, 0x40, 0]);
+	compressed_data.extend_from_slice(&[0; 16]);
+	let mut packed_file = PackedFile::new(path.to_vec(), "other.pack".to_owned());
+	packed_file.set_data_compressed(compressed_data.to_vec(), None);
+	packed_file.retarget(PFHVersion::PFH5).unwrap();
+	assert!(packed_file.get_ref_raw().get_compression_state());
+	assert!(packed_file.get_ref_raw().get_should_be_compressed());
+	assert_eq!(packed_file.get_ref_raw().get_should_be_encrypted(), &None);
+
+	// If the new version doesn't support compression, the data has to be decompressed.
+	assert_eq!(packed_file.retarget(PFHVersion::PFH3).unwrap_err().kind(), &ErrorKind::PackedFileDataCouldNotBeDecompressed);
+}
+
+#[test]
+fn test_zero_length_packed_file() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_zero_length_packed_file.pack");
+	let file_path = vec!["text".to_owned(), "empty.txt".to_owned()];
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_zero_length_packed_file.pack", PFHVersion::PFH5);
+	pack_file.add_file(PackedFile::new(file_path.to_vec(), "rpfm_test_zero_length_packed_file.pack".to_owned())).unwrap();
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	let mut pack_file = PackFile::read(&path, true).unwrap();
+	let packed_file = pack_file.get_ref_mut_packed_file_by_path(&file_path).unwrap();
+	assert!(packed_file.get_ref_raw().is_on_disk());
+	assert_eq!(packed_file.get_ref_raw().get_size(), 0);
+	assert!(packed_file.get_ref_raw().get_data().unwrap().is_empty());
+	assert!(packed_file.get_ref_mut_raw().get_data_and_keep_it().unwrap().is_empty());
+	match packed_file.decode_return_ref_no_locks(&Schema::default()).unwrap() {
+		DecodedPackedFile::Text(text) => assert!(text.get_ref_contents().is_empty()),
+		_ => panic!("Empty text file not decoded as text."),
+	}
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file_hash() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_file_hash.pack");
+	let file_path = vec!["text".to_owned(), "file.txt".to_owned()];
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_file_hash.pack", PFHVersion::PFH5);
+	assert!(pack_file.source_hash().is_none());
+	let mut packed_file = PackedFile::new(file_path.to_vec(), "rpfm_test_file_hash.pack".to_owned());
+	packed_file.get_ref_mut_raw().set_data(b"Text.");
+	pack_file.add_file(packed_file).unwrap();
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+	assert_eq!(pack_file.source_hash(), Some(PackFile::file_hash(&path).unwrap()));
+
+	let pack_file = PackFile::read(&path, true).unwrap();
+	let hash = PackFile::file_hash(&path).unwrap();
+	assert_eq!(pack_file.source_hash(), Some(hash));
+	assert_eq!(PackFile::file_hash(&path).unwrap(), hash);
+	assert!(!pack_file.has_source_changed());
+
+	// Neither the hash nor the path index are compared, as they're just caches.
+	let pack_file_memory = PackFile::read(&path, false).unwrap();
+	let mut pack_file_other = PackFile::read(&path, false).unwrap();
+	pack_file_other.get_ref_mut_packed_file_by_path(&file_path).unwrap();
+	pack_file_other.packed_files_index.positions.write().unwrap().clear();
+	assert!(pack_file_other.source_hash().is_some());
+	assert_eq!(pack_file_other, pack_file_memory);
+
+	// Writing the same data again changes the modification time, but not the hash.
+	let mut data = std::fs::read(&path).unwrap();
+	thread::sleep(std::time::Duration::from_millis(20));
+	std::fs::write(&path, &data).unwrap();
+	assert!(!pack_file.has_source_changed());
+
+	// Change one byte of the data of the PackedFile, keeping the size of the file.
+	*data.last_mut().unwrap() = b'!';
+	thread::sleep(std::time::Duration::from_millis(20));
+	std::fs::write(&path, &data).unwrap();
+	assert_ne!(PackFile::file_hash(&path).unwrap(), hash);
+	assert_eq!(pack_file.source_hash(), Some(hash));
+	assert!(pack_file.has_source_changed());
+
+	// If the file changed before we asked for its hash, we cannot know its old hash, but we know it changed.
+	let pack_file = PackFile::read(&path, true).unwrap();
+	data.push(0);
+	std::fs::write(&path, &data).unwrap();
+	assert!(pack_file.has_source_changed());
+	assert!(pack_file.source_hash().is_none());
+
+	assert!(PackFile::file_hash(&temp_dir().join("rpfm_test_file_hash_missing.pack")).is_err());
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_get_data_shared() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_get_data_shared.bin");
+	let expected = (0..4096u32).map(|x| x as u8).collect::<Vec<u8>>();
+	File::create(&path).unwrap().write_all(&expected).unwrap();
+
+	let packed_file = Arc::new(RawPackedFile::read_from_data(
+		vec!["text".to_owned(), "shared.txt".to_owned()],
+		"test.pack".to_owned(),
+		0,
+		false,
+		None,
+		PackedFileData::OnDisk(Arc::new(File::open(&path).unwrap()), 0, expected.len() as u32, false, None)
+	));
+	let shared_data = packed_file.get_data_shared().unwrap();
+	assert_eq!(&*shared_data, &expected[..]);
+
+	// Once shared, the data no longer comes from disk, so emptying the file changes nothing.
+	File::create(&path).unwrap();
+	let mut handles = vec![];
+	for _ in 0..16 {
+		let packed_file = packed_file.clone();
+		handles.push(thread::spawn(move || {
+			(0..64).map(|_| packed_file.get_data_shared().unwrap()).collect::<Vec<Arc<[u8]>>>()
+		}));
+	}
+
+	for handle in handles {
+		for data in handle.join().unwrap() {
+			assert!(Arc::ptr_eq(&data, &shared_data));
+		}
+	}
+	assert_eq!(packed_file.get_data().unwrap(), expected);
+
+	// Changing the data must stop sharing the old one.
+	let mut packed_file = Arc::try_unwrap(packed_file).unwrap();
+	packed_file.set_data(b"Text.");
+	assert_eq!(&*packed_file.get_data_shared().unwrap(), b"Text.");
+	assert_eq!(&*shared_data, &expected[..]);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_pfh_version() {
+	for (file, version) in &[("PFH5_test.pack", PFHVersion::PFH5), ("PFH4_test.pack", PFHVersion::PFH4), ("PFH0_test.pack", PFHVersion::PFH0)] {
+		let path = PathBuf::from("../test_files").join(file);
+		assert_eq!(PackFile::read(&path, false).unwrap().get_pfh_version(), *version);
+		assert_eq!(PackFile::read_pfh_version(&path).unwrap(), *version);
+	}
+
+	let mut path = temp_dir();
+	path.push("rpfm_test_pfh_version.pack");
+	File::create(&path).unwrap().write_all(b"PF").unwrap();
+	assert_eq!(PackFile::read_pfh_version(&path).unwrap_err().kind(), &ErrorKind::PackFileHeaderNotComplete);
+
+	File::create(&path).unwrap().write_all(b"PFH9").unwrap();
+	assert_eq!(PackFile::read_pfh_version(&path).unwrap_err().kind(), &ErrorKind::PackFileIsNotAPackFile);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_set_all_compressed_encrypted() {
+	let mut pack_file = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), true).unwrap();
+	assert!(pack_file.get_packed_files_all_paths().len() > 1);
+
+	pack_file.set_all_compressed(true).unwrap();
+	pack_file.set_all_encrypted(Some(PFHVersion::PFH5)).unwrap();
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| x.get_ref_raw().get_should_be_compressed()));
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| x.get_ref_raw().get_should_be_encrypted() == &Some(PFHVersion::PFH5)));
+
+	pack_file.set_all_compressed(false).unwrap();
+	pack_file.set_all_encrypted(None).unwrap();
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| !x.get_ref_raw().get_should_be_compressed()));
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| x.get_ref_raw().get_should_be_encrypted().is_none()));
+
+	// Unsupported combinations must fail without changing anything.
+	assert_eq!(pack_file.set_all_encrypted(Some(PFHVersion::PFH3)).unwrap_err().kind(), &ErrorKind::PackFileVersionDoesNotSupportEncryption("PFH3".to_owned()));
+
+	let mut pack_file = PackFile::read(&PathBuf::from("../test_files/PFH4_test.pack"), true).unwrap();
+	assert_eq!(pack_file.set_all_compressed(true).unwrap_err().kind(), &ErrorKind::PackFileVersionDoesNotSupportCompression("PFH4".to_owned()));
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| !x.get_ref_raw().get_should_be_compressed()));
+	pack_file.set_all_encrypted(Some(PFHVersion::PFH4)).unwrap();
+
+	let mut pack_file = PackFile::read(&PathBuf::from("../test_files/PFH0_test.pack"), true).unwrap();
+	assert_eq!(pack_file.set_all_encrypted(Some(PFHVersion::PFH5)).unwrap_err().kind(), &ErrorKind::PackFileVersionDoesNotSupportEncryption("PFH0".to_owned()));
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| x.get_ref_raw().get_should_be_encrypted().is_none()));
+}
+
+#[test]
+fn test_files_of_type() {
+	let mut pack_file = PackFile::new_with_name("rpfm_test_files_of_type.pack", PFHVersion::PFH5);
+	for path in &["db/land_units_tables/mod_units", "db/units_tables/mod_units", "text/mod_units.loc", "text/readme.txt", "script/mod.lua", "db/loose_file"] {
+		let mut packed_file = PackedFile::new(path.split('/').map(|x| x.to_owned()).collect(), "rpfm_test_files_of_type.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(b"Data.");
+		pack_file.add_file(packed_file).unwrap();
+	}
+
+	let get_paths = |packed_file_type| pack_file.files_of_type(packed_file_type).iter().map(|x| x.get_path().join("/")).collect::<Vec<String>>();
+	assert_eq!(get_paths(PackedFileType::DB), vec!["db/land_units_tables/mod_units".to_owned(), "db/units_tables/mod_units".to_owned()]);
+	assert_eq!(get_paths(PackedFileType::Loc), vec!["text/mod_units.loc".to_owned()]);
+	assert_eq!(get_paths(PackedFileType::Text(TextType::Plain)), vec!["text/readme.txt".to_owned()]);
+	assert_eq!(get_paths(PackedFileType::Text(TextType::Lua)), vec!["script/mod.lua".to_owned()]);
+	assert!(get_paths(PackedFileType::Image).is_empty());
+}
+
+#[test]
+fn test_move_file_to() {
+	let path = vec!["text".to_owned(), "file.txt".to_owned()];
+	let other_path = vec!["text".to_owned(), "other.txt".to_owned()];
+	let mut source = PackFile::new_with_name("rpfm_test_move_source.pack", PFHVersion::PFH5);
+	let mut dest = PackFile::new_with_name("rpfm_test_move_dest.pack", PFHVersion::PFH5);
+	let add = |pack_file: &mut PackFile, path: &[String], data: &[u8]| {
+		let mut packed_file = PackedFile::new(path.to_vec(), String::new());
+		packed_file.get_ref_mut_raw().set_data(data);
+		pack_file.add_file(packed_file).unwrap();
+	};
+	add(&mut source, &path, b"Moved.");
+	add(&mut source, &other_path, b"Stays.");
+	add(&mut dest, &other_path, b"Other.");
+
+	source.move_file_to(&path, &mut dest).unwrap();
+	assert!(!source.packedfile_exists(&path));
+	assert!(source.get_ref_packed_file_by_path(&path).is_none());
+	assert_eq!(source.extract_one(&other_path).unwrap(), b"Stays.");
+
+	let packed_file = dest.get_ref_packed_file_by_path(&path).unwrap();
+	assert_eq!(packed_file.get_ref_raw().get_packfile_name(), "rpfm_test_move_dest.pack");
+	assert_eq!(dest.extract_one(&path).unwrap(), b"Moved.");
+	assert_eq!(dest.get_packed_files_all_paths().len(), 2);
+
+	// Missing and duplicated paths are rejected, without touching anything.
+	assert_eq!(source.move_file_to(&path, &mut dest).unwrap_err().kind(), &ErrorKind::PackedFileNotFound);
+	assert_eq!(source.move_file_to(&other_path, &mut dest).unwrap_err().kind(), &ErrorKind::DuplicatedPackedFile(other_path.to_vec()));
+	assert_eq!(source.extract_one(&other_path).unwrap(), b"Stays.");
+	assert_eq!(dest.extract_one(&other_path).unwrap(), b"Other.");
+}
+
+#[test]
+fn test_open_index_only() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_open_index_only.pack");
+	let file_path = vec!["text".to_owned(), "file.txt".to_owned()];
+	let mut pack_file = PackFile::new_with_name("rpfm_test_open_index_only.pack", PFHVersion::PFH5);
+	let mut packed_file = PackedFile::new(file_path.to_vec(), String::new());
+	packed_file.get_ref_mut_raw().set_data(b"Original.");
+	pack_file.add_file(packed_file).unwrap();
+	pack_file.set_notes(&Some("Some notes.".to_owned()));
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	let pack_file = PackFile::open_index_only(&path).unwrap();
+	assert_eq!(pack_file.get_packed_files_all_paths(), vec![file_path.to_vec()]);
+	assert_eq!(pack_file.get_notes(), &Some("Some notes.".to_owned()));
+	assert_eq!(pack_file.source_hash(), PackFile::file_hash(&path).ok());
+	let raw = pack_file.get_ref_packed_file_by_path(&file_path).unwrap().get_ref_raw();
+	assert!(raw.is_on_disk());
+	assert_eq!(raw.get_size(), 9);
+	assert_eq!(raw.get_data().unwrap(), b"Original.");
+
+	// Only the header, the indexes and the notes are read when opening it. The data of the file, at the end, is never touched.
+	let file = Arc::new(File::open(&path).unwrap());
+	let mut reader = CountingReader { reader: &*file, bytes_read: 0 };
+	let pack_file = PackFile::read_from_reader(&mut reader, file.clone(), &path, true).unwrap();
+	assert_eq!(reader.bytes_read, file.metadata().unwrap().len() - 9);
+	assert_eq!(pack_file.get_notes(), &Some("Some notes.".to_owned()));
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&file_path).unwrap().get_ref_raw().get_data().unwrap(), b"Original.");
+	remove_file(&path).unwrap();
+}
+
+/// Reader that counts how many bytes have been read through it.
+struct CountingReader<R> {
+	reader: R,
+	bytes_read: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let bytes_read = self.reader.read(buf)?;
+		self.bytes_read += bytes_read as u64;
+		Ok(bytes_read)
+	}
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+	fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+		self.reader.seek(position)
+	}
+}
+
+#[test]
+fn test_save_safely() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_save_safely.pack");
+	let mut backup_path = temp_dir();
+	backup_path.push("rpfm_test_save_safely.pack.bak");
+	let original = std::fs::read("../test_files/PFH5_test.pack").unwrap();
+	File::create(&path).unwrap().write_all(&original).unwrap();
+
+	let mut pack_file = PackFile::read(&path, true).unwrap();
+	let edited_path = pack_file.get_ref_packed_files_all_paths()[0].to_vec();
+	pack_file.get_ref_mut_packed_file_by_path(&edited_path).unwrap().get_ref_mut_raw().set_data(b"edited data");
+	pack_file.save_safely(&path).unwrap();
+
+	// The previous file is kept as backup, and the new one has our changes.
+	assert_eq!(std::fs::read(&backup_path).unwrap(), original);
+	let pack_file_new = PackFile::read(&path, false).unwrap();
+	assert_eq!(pack_file_new.get_ref_packed_file_by_path(&edited_path).unwrap().get_ref_raw().get_data().unwrap(), b"edited data");
+	let saved = std::fs::read(&path).unwrap();
+
+	// The PackFile we saved is still usable, and knows the file is the one it saved.
+	assert!(!pack_file.has_source_changed());
+	for packed_file in pack_file.get_ref_packed_files_all() {
+		assert_eq!(packed_file.get_ref_raw().get_data().unwrap(), pack_file_new.get_ref_packed_file_by_path(packed_file.get_path()).unwrap().get_ref_raw().get_data().unwrap());
+	}
+
+	// A PackedFile which data cannot be read makes the save fail midway, after the header has been written.
+	let mut broken_path = temp_dir();
+	broken_path.push("rpfm_test_save_safely_broken.bin");
+	File::create(&broken_path).unwrap().write_all(b"short").unwrap();
+	let broken = RawPackedFile::read_from_data(
+		vec!["text".to_owned(), "broken.txt".to_owned()],
+		"test.pack".to_owned(),
+		0,
+		false,
+		None,
+		PackedFileData::OnDisk(Arc::new(File::open(&broken_path).unwrap()), 0, 20, false, None)
+	);
+	pack_file.add_packed_file(&PackedFile::new_from_raw(&broken), true).unwrap();
+	assert!(pack_file.save_safely(&path).is_err());
+	assert_eq!(std::fs::read(&path).unwrap(), saved);
+	assert!(!PathBuf::from(format!("{}.tmp", path.display())).exists());
+
+	remove_file(&path).unwrap();
+	remove_file(&backup_path).unwrap();
+	remove_file(&broken_path).unwrap();
+}
+
+#[test]
+fn test_try_data_slice() {
+	let path = vec!["text".to_owned(), "a.txt".to_owned()];
+	let packed_file = RawPackedFile::read_from_vec(path.to_vec(), "test.pack".to_owned(), 0, false, b"Plain text.".to_vec());
+	assert_eq!(packed_file.try_data_slice(), Some(&b"Plain text."[..]));
+
+	// Data that needs processing, or is not in memory, must be got with `get_data`.
+	let packed_file = RawPackedFile::read_from_data(path.to_vec(), "test.pack".to_owned(), 0, false, None, PackedFileData::OnMemory(b"Compressed.".to_vec(), true, None));
+	assert_eq!(packed_file.try_data_slice(), None);
+
+	let packed_file = RawPackedFile::read_from_data(path.to_vec(), "test.pack".to_owned(), 0, false, None, PackedFileData::OnMemory(b"Encrypted.".to_vec(), false, Some(PFHVersion::PFH5)));
+	assert_eq!(packed_file.try_data_slice(), None);
+
+	let pack_file = PackFile::read(&PathBuf::from("../test_files/PFH5_test.pack"), true).unwrap();
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| x.get_ref_raw().try_data_slice().is_none()));
+}
+
+#[test]
+fn test_extract_by_glob() {
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	for path in &["text/db/units.loc", "text/db/nested/units.loc", "text/mod/Units.loc", "text/db/buildings.loc", "script/mod.lua", "text/names"] {
+		let path = path.split('/').map(|x| x.to_owned()).collect::<Vec<String>>();
+		let data = path.join("/").into_bytes();
+		pack_file.packed_files.push(PackedFile::new_from_raw(&RawPackedFile::read_from_vec(path, "test.pack".to_owned(), 0, false, data)));
+	}
+
+	let mut base = temp_dir();
+	base.push("rpfm_test_extract_by_glob");
+	let _ = remove_dir_all(&base);
+
+	// Keeping the folders.
+	let extracted = pack_file.extract_by_glob("text/**/*.loc", &base, false).unwrap();
+	assert_eq!(extracted, vec![
+		base.join("text/db/units.loc"),
+		base.join("text/db/nested/units.loc"),
+		base.join("text/mod/Units.loc"),
+		base.join("text/db/buildings.loc"),
+	]);
+	assert_eq!(std::fs::read(base.join("text/db/nested/units.loc")).unwrap(), b"text/db/nested/units.loc");
+	remove_dir_all(&base).unwrap();
+
+	// Flattening, with files with the same name getting a suffix. Files without extension too.
+	let extracted = pack_file.extract_by_glob("text/**", &base, true).unwrap();
+	assert_eq!(extracted, vec![
+		base.join("units.loc"),
+		base.join("units_1.loc"),
+		base.join("Units_2.loc"),
+		base.join("buildings.loc"),
+		base.join("names"),
+	]);
+	assert_eq!(std::fs::read(base.join("units_1.loc")).unwrap(), b"text/db/nested/units.loc");
+	assert_eq!(std::fs::read(base.join("Units_2.loc")).unwrap(), b"text/mod/Units.loc");
+	assert_eq!(std::fs::read_dir(&base).unwrap().count(), 5);
+
+	assert!(pack_file.extract_by_glob("ui/**", &base, true).unwrap().is_empty());
+	remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn test_set_data_from_file() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_set_data_from_file.txt");
+	File::create(&path).unwrap().write_all(b"New data.").unwrap();
+	let expected_timestamp = get_last_modified_time_from_file(&File::open(&path).unwrap());
+
+	let mut packed_file = PackedFile::new_from_decoded(&DecodedPackedFile::Text(Text::new()), vec!["text".to_owned(), "a.txt".to_owned()]);
+	packed_file.get_ref_mut_raw().set_data_compressed(b"Old data.".to_vec(), Some(PFHVersion::PFH5));
+	packed_file.get_ref_mut_raw().set_timestamp(0);
+	packed_file.set_data_from_file(&path).unwrap();
+
+	assert_eq!(packed_file.get_ref_raw().try_data_slice(), Some(&b"New data."[..]));
+	assert_eq!(packed_file.get_ref_raw().get_timestamp(), expected_timestamp);
+	assert_ne!(expected_timestamp, 0);
+	assert_eq!(packed_file.get_ref_decoded(), &DecodedPackedFile::Unknown);
+
+	// A file we cannot read leaves the PackedFile as it was.
+	remove_file(&path).unwrap();
+	assert_eq!(packed_file.set_data_from_file(&path).unwrap_err().kind(), &ErrorKind::IOReadFile(path.to_path_buf()));
+	assert_eq!(packed_file.get_raw_data().unwrap(), b"New data.");
+}
+
+#[test]
+fn test_files_sorted_by_timestamp() {
+	let mut pack_file = PackFile::new_with_name("test.pack", PFHVersion::PFH5);
+	for (path, timestamp) in &[("b.txt", 200), ("unset_b.txt", 0), ("c.txt", 100), ("A.txt", 200), ("d.txt", 300), ("unset_a.txt", 0)] {
+		let raw = RawPackedFile::read_from_vec(vec![(*path).to_owned()], "test.pack".to_owned(), *timestamp, false, vec![]);
+		pack_file.packed_files.push(PackedFile::new_from_raw(&raw));
+	}
+
+	let sorted = |newest_first| pack_file.files_sorted_by_timestamp(newest_first).iter().map(|x| x.get_path().join("/")).collect::<Vec<String>>();
+	assert_eq!(sorted(true), vec!["d.txt", "A.txt", "b.txt", "c.txt", "unset_a.txt", "unset_b.txt"]);
+	assert_eq!(sorted(false), vec!["c.txt", "A.txt", "b.txt", "d.txt", "unset_a.txt", "unset_b.txt"]);
+}
+
+#[test]
+fn test_read_encrypted_index() {
+	let paths = vec![
+		vec!["db".to_owned(), "units_tables".to_owned(), "encrypted".to_owned()],
+		vec!["script".to_owned(), "s.lua".to_owned()],
+		vec!["text".to_owned(), "a_long_path_to_go_through_more_than_one_round_of_the_key_of_the_index".to_owned(), "file.txt".to_owned()],
+	];
+
+	for pfh_version in &[PFHVersion::PFH5, PFHVersion::PFH4] {
+		let mut path = temp_dir();
+		path.push(format!("rpfm_test_read_encrypted_index_{}.pack", pfh_version));
+
+		let mut pack_file = PackFile::new_with_name("rpfm_test_read_encrypted_index.pack", *pfh_version);
+		pack_file.set_bitmask(PFHFlags::HAS_ENCRYPTED_INDEX | PFHFlags::HAS_INDEX_WITH_TIMESTAMPS);
+		for (index, packed_file_path) in paths.iter().enumerate() {
+			let mut packed_file = PackedFile::new(packed_file_path.to_vec(), "rpfm_test_read_encrypted_index.pack".to_owned());
+			packed_file.get_ref_mut_raw().set_data(format!("Data of the file number {}.", index).as_bytes());
+			packed_file.get_ref_mut_raw().set_timestamp(1_600_000_000 + index as i64);
+			pack_file.add_packed_file(&packed_file, false).unwrap();
+		}
+
+		// PackFiles with encrypted indexes are not editable, so we write it directly.
+		let mut data = vec![];
+		pack_file.prepare_for_saving(false).unwrap();
+		pack_file.write(&mut data, false).unwrap();
+		assert!(!data.windows(5).any(|x| x == b"s.lua"));
+		File::create(&path).unwrap().write_all(&data).unwrap();
+
+		let pack_file_new = PackFile::read(&path, false).unwrap();
+		assert!(pack_file_new.get_bitmask().contains(PFHFlags::HAS_ENCRYPTED_INDEX));
+		assert_eq!(pack_file_new.get_ref_packed_files_all_paths(), paths.iter().map(|x| &**x).collect::<Vec<&[String]>>());
+		for (index, packed_file_path) in paths.iter().enumerate() {
+			let raw = pack_file_new.get_ref_packed_file_by_path(packed_file_path).unwrap().get_ref_raw();
+			assert_eq!(raw.get_data().unwrap(), format!("Data of the file number {}.", index).as_bytes());
+			assert_eq!(raw.get_timestamp(), 1_600_000_000 + index as i64);
+		}
+		remove_file(&path).unwrap();
+	}
+
+	// Paths cut before their end must fail instead of reading past the index.
+	let encrypted_path = encrypt_index_item_filename("script\\s.lua", 26);
+	let mut offset = 0;
+	assert_eq!(decrypt_index_item_filename(&encrypted_path, 26, &mut offset).unwrap(), "script\\s.lua");
+	assert_eq!(offset, encrypted_path.len());
+	assert_eq!(decrypt_index_item_filename(&encrypted_path[..5], 26, &mut offset).unwrap_err().kind(), &ErrorKind::PackFileIndexesNotComplete);
+
+	// Paths with non-ASCII characters must come back unchanged.
+	let encrypted_path = encrypt_index_item_filename("script\\campaña\\ñandú_ü.lua", 26);
+	let mut offset = 0;
+	assert_eq!(decrypt_index_item_filename(&encrypted_path, 26, &mut offset).unwrap(), "script\\campaña\\ñandú_ü.lua");
+	assert_eq!(offset, encrypted_path.len());
+}
+
+#[test]
+fn test_read_file_count_mismatch() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_read_file_count_mismatch.pack");
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_read_file_count_mismatch.pack", PFHVersion::PFH5);
+	for packed_file_path in &[vec!["script".to_owned(), "a.lua".to_owned()], vec!["script".to_owned(), "b.lua".to_owned()]] {
+		let mut packed_file = PackedFile::new(packed_file_path.to_vec(), "rpfm_test_read_file_count_mismatch.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(b"Data.");
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+	}
+
+	let mut data = vec![];
+	pack_file.prepare_for_saving(false).unwrap();
+	pack_file.write(&mut data, false).unwrap();
+	File::create(&path).unwrap().write_all(&data).unwrap();
+	assert_eq!(PackFile::read(&path, false).unwrap().get_packed_files_all_paths().len(), 2);
+
+	// Header claiming more files than the index contains.
+	data[16..20].copy_from_slice(&3u32.to_le_bytes());
+	File::create(&path).unwrap().write_all(&data).unwrap();
+	assert_eq!(PackFile::read(&path, false).unwrap_err().kind(), &ErrorKind::PackFileFileCountMismatch { declared: 3, found: 2 });
+
+	// Header claiming less files than the index contains.
+	data[16..20].copy_from_slice(&1u32.to_le_bytes());
+	File::create(&path).unwrap().write_all(&data).unwrap();
+	assert_eq!(PackFile::read(&path, false).unwrap_err().kind(), &ErrorKind::PackFileFileCountMismatch { declared: 1, found: 2 });
+	remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_save_clean() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_save_clean.pack");
+	let clean_path = vec!["text".to_owned(), "clean.txt".to_owned()];
+	let dirty_path = vec!["text".to_owned(), "dirty.txt".to_owned()];
+	let data = b"Clean data.\n".repeat(10);
+
+	// New files are always dirty, until they're saved.
+	let mut pack_file = PackFile::new_with_name("rpfm_test_save_clean.pack", PFHVersion::PFH5);
+	for packed_file_path in &[&clean_path, &dirty_path] {
+		let mut packed_file = PackedFile::new(packed_file_path.to_vec(), "rpfm_test_save_clean.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(&data);
+		assert!(packed_file.get_ref_raw().is_dirty());
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+	}
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| !x.get_ref_raw().is_dirty()));
+
+	// Reading their data doesn't make them dirty. Changing it does.
+	let mut pack_file = PackFile::read(&path, false).unwrap();
+	for packed_file_path in &[&clean_path, &dirty_path] {
+		let raw = pack_file.get_ref_mut_packed_file_by_path(packed_file_path).unwrap().get_ref_mut_raw();
+		assert!(!raw.is_dirty());
+		assert_eq!(raw.get_data_and_keep_it().unwrap(), data);
+		assert!(!raw.is_dirty());
+	}
+
+	let new_data = b"New data.\n".repeat(10);
+	let raw = pack_file.get_ref_mut_packed_file_by_path(&dirty_path).unwrap().get_ref_mut_raw();
+	raw.set_data(&new_data);
+	assert!(raw.is_dirty());
+	assert!(!pack_file.get_ref_packed_file_by_path(&clean_path).unwrap().get_ref_raw().is_dirty());
+
+	pack_file.save(None).unwrap();
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| !x.get_ref_raw().is_dirty()));
+
+	let pack_file = PackFile::read(&path, false).unwrap();
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&clean_path).unwrap().get_ref_raw().get_data().unwrap(), data);
+	assert_eq!(pack_file.get_ref_packed_file_by_path(&dirty_path).unwrap().get_ref_raw().get_data().unwrap(), new_data);
+	remove_file(&path).unwrap();
+}
+
+#[test]
+#[ignore = "needs 7z in the PATH"]
+fn test_save_clean_compressed() {
+	let mut path = temp_dir();
+	path.push("rpfm_test_save_clean_compressed.pack");
+	let clean_path = vec!["text".to_owned(), "clean.txt".to_owned()];
+	let dirty_path = vec!["text".to_owned(), "dirty.txt".to_owned()];
+	let data = (0..2_000).map(|x| format!("clean line {}\n", x % 17)).collect::<String>().into_bytes();
+
+	let mut pack_file = PackFile::new_with_name("rpfm_test_save_clean_compressed.pack", PFHVersion::PFH5);
+	for packed_file_path in &[&clean_path, &dirty_path] {
+		let mut packed_file = PackedFile::new(packed_file_path.to_vec(), "rpfm_test_save_clean_compressed.pack".to_owned());
+		packed_file.get_ref_mut_raw().set_data(&data);
+		pack_file.add_packed_file(&packed_file, false).unwrap();
+	}
+	pack_file.toggle_compression(true);
+	pack_file.save(Some(path.to_path_buf())).unwrap();
+
+	// Read it back, change the compression level, and decompress both files in memory. Only the edited one is dirty.
+	let mut pack_file = PackFile::read(&path, false).unwrap();
+	let compressed_data = pack_file.get_ref_packed_file_by_path(&clean_path).unwrap().get_ref_raw().get_raw_data().unwrap();
+	let settings = CompressionSettings { level: MAX_COMPRESSION_LEVEL };
+	pack_file.set_compression_settings(settings);
+	for packed_file_path in &[&clean_path, &dirty_path] {
+		let raw = pack_file.get_ref_mut_packed_file_by_path(packed_file_path).unwrap().get_ref_mut_raw();
+		assert!(!raw.is_dirty());
+		assert_eq!(raw.get_data_and_keep_it().unwrap(), data);
+		assert!(!raw.get_compression_state());
+		assert!(!raw.is_dirty());
+	}
+
+	let new_data = b"New data for the dirty file.\n".repeat(100);
+	let raw = pack_file.get_ref_mut_packed_file_by_path(&dirty_path).unwrap().get_ref_mut_raw();
+	raw.set_data(&new_data);
+	assert!(raw.is_dirty());
+	pack_file.save(None).unwrap();
+	assert!(pack_file.get_ref_packed_files_all().iter().all(|x| !x.get_ref_raw().is_dirty()));
+
+	// The clean one keeps the exact same compressed data, while the dirty one gets compressed again with the new level.
+	let pack_file = PackFile::read(&path, false).unwrap();
+	let raw = pack_file.get_ref_packed_file_by_path(&clean_path).unwrap().get_ref_raw();
+	assert!(raw.get_compression_state());
+	assert_eq!(raw.get_raw_data().unwrap(), compressed_data);
+	assert_eq!(raw.get_data().unwrap(), data);
+
+	let raw = pack_file.get_ref_packed_file_by_path(&dirty_path).unwrap().get_ref_raw();
+	assert!(raw.get_compression_state());
+	assert_eq!(raw.get_raw_data().unwrap(), Codec::Lzma1.compress(&new_data, &settings).unwrap());
+	assert_eq!(raw.get_data().unwrap(), new_data);
+	remove_file(&path).unwrap();
+}
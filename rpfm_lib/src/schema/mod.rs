@@ -65,7 +65,7 @@ use ron::de::{from_str, from_reader};
 use ron::ser::{to_string_pretty, PrettyConfig};
 use serde_derive::{Serialize, Deserialize};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::cmp::Ordering;
 use std::fs::{DirBuilder, File};
 use std::{fmt, fmt::Display};
@@ -78,9 +78,13 @@ use crate::assembly_kit::table_definition::{RawDefinition, RawField};
 use crate::DEPENDENCY_DATABASE;
 use crate::SUPPORTED_GAMES;
 use crate::config::get_config_path;
+use crate::packedfile::PackedFileType;
 use crate::packedfile::table::db::DB;
 use self::versions::VersionsFile;
 
+#[cfg(test)]
+mod schema_test;
+
 // Legacy Schemas, to keep backwards compatibility during updates.
 pub(crate) mod v1;
 pub(crate) mod v0;
@@ -108,14 +112,20 @@ const CURRENT_STRUCTURAL_VERSION: u16 = 2;
 //---------------------------------------------------------------------------//
 
 /// This struct represents a Schema File in memory, ready to be used to decode versioned PackedFiles.
-#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Schema {
 
     /// It stores the structural version of the Schema.
     version: u16,
 
     /// It stores the versioned files inside the Schema.
-    versioned_files: Vec<VersionedFile>
+    versioned_files: Vec<VersionedFile>,
+
+    /// It stores the position of each DB `VersionedFile` in `versioned_files`, by table name, to speed up lookups.
+    ///
+    /// This is just a cache. It's not saved and it's rebuilt every time the list of versioned files changes.
+    #[serde(skip)]
+    db_index: HashMap<String, usize>,
 }
 
 /// This enum defines all types of versioned files that the schema system supports.
@@ -201,6 +211,41 @@ pub enum FieldType {
     Sequence(Definition)
 }
 
+/// This struct represents a problem found in the layout of a `Definition` while validating a `Schema`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SchemaWarning {
+
+    /// Name of the table the `Definition` is for. For Loc and DepManager definitions, this is `Loc` and `DepManager`.
+    /// For definitions inside `Sequence` fields, this is the name of the table followed by the name of the field, separated by `/`.
+    pub table_name: String,
+
+    /// Version of the `Definition` with the problem.
+    pub version: i32,
+
+    /// Name of the field with the problem, if the problem is with an specific field.
+    pub field_name: Option<String>,
+
+    /// The problem found.
+    pub warning_type: SchemaWarningType,
+}
+
+/// This enum defines all the types of problems we can find in the layout of a `Definition`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SchemaWarningType {
+
+    /// There is more than one `Definition` with the same version for the same table.
+    DuplicatedVersion,
+
+    /// There is more than one field with the same name in the `Definition`.
+    DuplicatedField,
+
+    /// The field claims the same position in CA's table (`ca_order`) as a previous field. Contains the position.
+    OverlappingPosition(i16),
+
+    /// No field claims this position in CA's table (`ca_order`), but fields after it do. Contains the position.
+    PositionGap(i16),
+}
+
 //---------------------------------------------------------------------------//
 //                       Enum & Structs Implementations
 //---------------------------------------------------------------------------//
@@ -215,6 +260,65 @@ impl Schema {
             Some(position) => { self.versioned_files.splice(position..=position, [versioned_file.clone()].iter().cloned()); },
             None => self.versioned_files.push(versioned_file.clone()),
         }
+        self.rebuild_db_index();
+    }
+
+    /// This function rebuilds the cache used to speed up the lookup of DB definitions.
+    fn rebuild_db_index(&mut self) {
+        self.db_index = self.versioned_files.iter()
+            .enumerate()
+            .filter_map(|(position, x)| if let VersionedFile::DB(name, _) = x { Some((name.to_owned(), position)) } else { None })
+            .collect();
+    }
+
+    /// This function returns a reference to the definition of a specific version of a DB Table, if it finds it.
+    ///
+    /// Unlike `get_ref_versioned_file_db`, this doesn't scan the schema, so use it when you need to get a lot of definitions.
+    pub fn definition_for(&self, table_name: &str, version: i32) -> Option<&Definition> {
+        self.versioned_file_db_for(table_name)?.get_version(version).ok()
+    }
+
+    /// This function returns a reference to the `VersionedFile` of a DB Table, if it finds it.
+    ///
+    /// Like `definition_for`, this doesn't scan the schema unless the cache is not valid for the table.
+    pub fn versioned_file_db_for(&self, table_name: &str) -> Option<&VersionedFile> {
+        // If the cache is not valid for this table, fallback to the slow search.
+        self.db_index.get(table_name)
+            .and_then(|x| self.versioned_files.get(*x))
+            .filter(|x| if let VersionedFile::DB(name, _) = x { name == table_name } else { false })
+            .or_else(|| self.get_ref_versioned_file_db(table_name).ok())
+    }
+
+    /// This function returns the name and version of all the DB Table definitions that have a field with the provided name.
+    ///
+    /// They're returned in the order they're in the schema. Only the names of the fields are checked, not their types.
+    pub fn tables_with_column(&self, column: &str) -> Vec<(String, i32)> {
+        self.versioned_files.iter()
+            .filter_map(|x| if let VersionedFile::DB(table_name, definitions) = x { Some((table_name, definitions)) } else { None })
+            .flat_map(|(table_name, definitions)| definitions.iter()
+                .filter(|x| x.fields.iter().any(|x| x.name == column))
+                .map(move |x| (table_name.to_owned(), x.version)))
+            .collect()
+    }
+
+    /// This function merges the provided `Schema` into this one.
+    ///
+    /// `VersionedFiles` and versions only present in one of the schemas are always kept. If both schemas have the same
+    /// version of the same `VersionedFile`, the one from the other schema is used only if `prefer_other` is true.
+    pub fn merge(&mut self, other: Schema, prefer_other: bool) {
+        for versioned_file_other in other.versioned_files {
+            match self.versioned_files.iter_mut().find(|x| x.conflict(&versioned_file_other)) {
+                Some(versioned_file) => {
+                    for definition in versioned_file_other.get_version_list() {
+                        if prefer_other || versioned_file.get_version(definition.version).is_err() {
+                            versioned_file.add_version(definition);
+                        }
+                    }
+                }
+                None => self.versioned_files.push(versioned_file_other),
+            }
+        }
+        self.rebuild_db_index();
     }
 
     /// This function returns the structural version of the provided Schema.
@@ -325,8 +429,7 @@ impl Schema {
         // Version is... complicated. We don't really want the last one, but the last one compatible with our game.
         // So we have to try to get it first from the Dependency Database first. If that fails, we fall back to the schema.
         if let Some(vanilla_table) = DEPENDENCY_DATABASE.lock().unwrap().iter_mut()
-            .filter(|x| x.get_path().len() == 3)
-            .find(|x| x.get_path()[0] == "db" && x.get_path()[1] == *table_name) {
+            .find(|x| PackedFileType::get_db_table_name(x.get_path()).map_or(false, |x| x == table_name)) {
             match DB::read_header(&vanilla_table.get_ref_mut_raw().get_data_and_keep_it().unwrap()) {
                 Ok(data) => self.get_ref_versioned_file_db(table_name)?.get_version(data.0),
                 Err(error) => Err(error),
@@ -363,7 +466,9 @@ impl Schema {
         file_path.push(schema_file);
 
         let file = BufReader::new(File::open(&file_path)?);
-        from_reader(file).map_err(From::from)
+        let mut schema: Self = from_reader(file)?;
+        schema.rebuild_db_index();
+        Ok(schema)
     }
 
     /// This function saves a `Schema` from memory to a file in the `schemas/` folder.
@@ -407,6 +512,37 @@ impl Schema {
                 }
             }
         });
+        self.rebuild_db_index();
+    }
+
+    /// This function checks the layout of all the definitions of the `Schema`, returning all the problems found.
+    ///
+    /// This is a diagnostic tool for schema maintainers. It checks for duplicated versions and fields, and for fields
+    /// overlapping or leaving gaps in their position on CA's table. Positions are only checked if all the fields of a definition have one.
+    pub fn validate(&self) -> Vec<SchemaWarning> {
+        let mut warnings = vec![];
+        for versioned_file in &self.versioned_files {
+            let (table_name, definitions) = match versioned_file {
+                VersionedFile::DB(table_name, definitions) => (table_name.to_owned(), definitions),
+                VersionedFile::DepManager(definitions) => ("DepManager".to_owned(), definitions),
+                VersionedFile::Loc(definitions) => ("Loc".to_owned(), definitions),
+            };
+
+            for (index, definition) in definitions.iter().enumerate() {
+                if definitions[..index].iter().any(|x| x.version == definition.version) {
+                    warnings.push(SchemaWarning {
+                        table_name: table_name.to_owned(),
+                        version: definition.version,
+                        field_name: None,
+                        warning_type: SchemaWarningType::DuplicatedVersion,
+                    });
+                }
+
+                definition.validate_layout(&table_name, &mut warnings);
+            }
+        }
+
+        warnings
     }
 
     /// This function exports all the schema files from the `schemas/` folder to `.json`.
@@ -710,6 +846,45 @@ impl Definition {
         }
     }
 
+    /// This function checks the layout of the fields of this definition (and the ones in his sequences), adding the problems found to the provided list.
+    fn validate_layout(&self, table_name: &str, warnings: &mut Vec<SchemaWarning>) {
+        let new_warning = |field: &Field, warning_type| SchemaWarning {
+            table_name: table_name.to_owned(),
+            version: self.version,
+            field_name: Some(field.name.to_owned()),
+            warning_type,
+        };
+
+        for (index, field) in self.fields.iter().enumerate() {
+            if self.fields[..index].iter().any(|x| x.name == field.name) {
+                warnings.push(new_warning(field, SchemaWarningType::DuplicatedField));
+            }
+
+            if field.ca_order >= 0 && self.fields[..index].iter().any(|x| x.ca_order == field.ca_order) {
+                warnings.push(new_warning(field, SchemaWarningType::OverlappingPosition(field.ca_order)));
+            }
+
+            if let FieldType::Sequence(ref definition) = field.field_type {
+                definition.validate_layout(&format!("{}/{}", table_name, field.name), warnings);
+            }
+        }
+
+        // Gaps only make sense if we know the position of all the fields.
+        if !self.fields.is_empty() && self.fields.iter().all(|x| x.ca_order >= 0) {
+            let max_position = self.fields.iter().map(|x| x.ca_order).max().unwrap_or(0);
+            for position in 0..max_position {
+                if !self.fields.iter().any(|x| x.ca_order == position) {
+                    warnings.push(SchemaWarning {
+                        table_name: table_name.to_owned(),
+                        version: self.version,
+                        field_name: None,
+                        warning_type: SchemaWarningType::PositionGap(position),
+                    });
+                }
+            }
+        }
+    }
+
     /// This function returns the reference and lookup data of a definition.
     pub fn get_reference_data(&self) -> BTreeMap<i32, (String, String, Option<Vec<String>>)> {
         self.fields.iter()
@@ -1130,11 +1305,22 @@ impl Default for Schema {
     fn default() -> Self {
         Self {
             version: CURRENT_STRUCTURAL_VERSION,
-            versioned_files: vec![]
+            versioned_files: vec![],
+            db_index: HashMap::new(),
         }
     }
 }
 
+/// PartialEq implementation of `Schema`. We need this implementation to ignore the lookup cache.
+impl PartialEq for Schema {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version && self.versioned_files == other.versioned_files
+    }
+}
+
+/// Eq implementation of `Schema`.
+impl Eq for Schema {}
+
 /// Default implementation of `FieldType`.
 impl Default for Field {
     fn default() -> Self {
@@ -0,0 +1,140 @@
+//---------------------------------------------------------------------------//
+// Copyright (c) 2017-2020 Ismael Gutiérrez González. All rights reserved.
+//
+// This file is part of the Rusted PackFile Manager (RPFM) project,
+// which can be found here: https://github.com/Frodo45127/rpfm.
+//
+// This file is licensed under the MIT license, which can be found here:
+// https://github.com/Frodo45127/rpfm/blob/master/LICENSE.
+//---------------------------------------------------------------------------//
+
+/*!
+Module containing test for the `Schema` module, just to make sure we don't break it... again...
+!*/
+
+use super::*;
+
+/// This function returns a Schema with a few versioned files, for testing purpouses.
+fn get_test_schema() -> Schema {
+    let mut schema = Schema::default();
+    for table_name in &["c_tables", "a_tables", "b_tables"] {
+        let mut definition_1 = Definition::new(1);
+        definition_1.fields.push(Field { name: format!("{}_key", table_name), ..Default::default() });
+
+        let mut definition_2 = definition_1.clone();
+        definition_2.version = 2;
+        definition_2.fields.push(Field { name: "value".to_owned(), field_type: FieldType::Integer, ..Default::default() });
+
+        schema.add_versioned_file(&VersionedFile::DB(table_name.to_string(), vec![definition_1, definition_2]));
+    }
+    schema.add_versioned_file(&VersionedFile::Loc(vec![Definition::new(1)]));
+    schema
+}
+
+/// Test to make sure the indexed lookup of definitions returns the same as the full scan.
+#[test]
+fn test_definition_for() {
+    let mut schema = get_test_schema();
+    for _ in 0..2 {
+        for table_name in &["a_tables", "b_tables", "c_tables"] {
+            assert_eq!(schema.versioned_file_db_for(table_name), schema.get_ref_versioned_file_db(table_name).ok());
+            for version in 0..=3 {
+                let scanned = schema.get_ref_versioned_file_db(table_name).unwrap().get_version(version).ok();
+                assert_eq!(schema.definition_for(table_name, version), scanned);
+            }
+        }
+
+        assert_eq!(schema.definition_for("missing_tables", 1), None);
+        assert_eq!(schema.versioned_file_db_for("missing_tables"), None);
+
+        // Sorting moves the versioned files around, so the lookup must keep working after it.
+        schema.sort();
+    }
+
+    // A deserialized schema doesn't have the cache built, but it must still find the definitions.
+    let schema_no_cache: Schema = from_str(&to_string_pretty(&schema, PrettyConfig::default()).unwrap()).unwrap();
+    assert!(schema_no_cache.db_index.is_empty());
+    assert_eq!(schema_no_cache, schema);
+    assert_eq!(schema_no_cache.definition_for("b_tables", 2), schema.definition_for("b_tables", 2));
+}
+
+/// Test to make sure we find all the definitions with a column, and only those.
+#[test]
+fn test_tables_with_column() {
+    let mut schema = get_test_schema();
+    let mut tables = schema.tables_with_column("value");
+    tables.sort();
+    assert_eq!(tables, vec![("a_tables".to_owned(), 2), ("b_tables".to_owned(), 2), ("c_tables".to_owned(), 2)]);
+    assert_eq!(schema.tables_with_column("b_tables_key"), vec![("b_tables".to_owned(), 1), ("b_tables".to_owned(), 2)]);
+    assert!(schema.tables_with_column("missing").is_empty());
+
+    // Names must match exactly, and Loc definitions are not tables.
+    assert!(schema.tables_with_column("Value").is_empty());
+    schema.add_versioned_file(&VersionedFile::Loc(vec![Definition::new(1)]));
+    schema.get_ref_mut_versioned_file_loc().unwrap().get_ref_mut_version(1).unwrap().fields.push(Field { name: "value".to_owned(), ..Default::default() });
+    assert_eq!(schema.tables_with_column("value").len(), 3);
+}
+
+/// Test to make sure merging schemas keeps everything, and resolves conflicts according to `prefer_other`.
+#[test]
+fn test_merge() {
+    let mut definition_other = Definition::new(2);
+    definition_other.fields.push(Field { name: "other_field".to_owned(), ..Default::default() });
+    let mut definition_new_version = Definition::new(3);
+    definition_new_version.fields.push(Field { name: "new_field".to_owned(), ..Default::default() });
+
+    let mut other = Schema::default();
+    other.add_versioned_file(&VersionedFile::DB("a_tables".to_owned(), vec![definition_other.clone(), definition_new_version.clone()]));
+    other.add_versioned_file(&VersionedFile::DB("d_tables".to_owned(), vec![Definition::new(1)]));
+
+    // Without preference, the overlapping version is kept from our schema.
+    let mut schema = get_test_schema();
+    let original_definition = schema.definition_for("a_tables", 2).cloned().unwrap();
+    schema.merge(other.clone(), false);
+    assert_eq!(schema.definition_for("a_tables", 1), get_test_schema().definition_for("a_tables", 1));
+    assert_eq!(schema.definition_for("a_tables", 2), Some(&original_definition));
+    assert_eq!(schema.definition_for("a_tables", 3), Some(&definition_new_version));
+    assert_eq!(schema.definition_for("b_tables", 2), get_test_schema().definition_for("b_tables", 2));
+    assert_eq!(schema.definition_for("d_tables", 1), Some(&Definition::new(1)));
+    assert!(schema.get_ref_versioned_file_loc().is_ok());
+
+    // With preference, the overlapping version is replaced by the other one.
+    let mut schema = get_test_schema();
+    schema.merge(other, true);
+    assert_eq!(schema.definition_for("a_tables", 1), get_test_schema().definition_for("a_tables", 1));
+    assert_eq!(schema.definition_for("a_tables", 2), Some(&definition_other));
+    assert_eq!(schema.definition_for("a_tables", 3), Some(&definition_new_version));
+    assert_eq!(schema.definition_for("d_tables", 1), Some(&Definition::new(1)));
+    assert_eq!(schema.get_ref_versioned_file_db_all().len(), 4);
+}
+
+/// Test to make sure the validation of a schema finds the problems in the layout of his definitions.
+#[test]
+fn test_validate() {
+    let mut schema = get_test_schema();
+    assert!(schema.validate().is_empty());
+
+    // A definition with overlapping positions, a gap between them, and a duplicated field. And a duplicated version.
+    let mut definition = Definition::new(1);
+    definition.fields.push(Field { name: "key".to_owned(), ca_order: 0, ..Default::default() });
+    definition.fields.push(Field { name: "value".to_owned(), ca_order: 0, ..Default::default() });
+    definition.fields.push(Field { name: "value".to_owned(), ca_order: 3, ..Default::default() });
+    schema.add_versioned_file(&VersionedFile::DB("d_tables".to_owned(), vec![definition.clone(), definition]));
+
+    let new_warning = |field_name: Option<&str>, warning_type| SchemaWarning {
+        table_name: "d_tables".to_owned(),
+        version: 1,
+        field_name: field_name.map(|x| x.to_owned()),
+        warning_type,
+    };
+
+    let warnings = schema.validate();
+    assert_eq!(warnings.len(), 9);
+    assert_eq!(&warnings[..4], &[
+        new_warning(Some("value"), SchemaWarningType::OverlappingPosition(0)),
+        new_warning(Some("value"), SchemaWarningType::DuplicatedField),
+        new_warning(None, SchemaWarningType::PositionGap(1)),
+        new_warning(None, SchemaWarningType::PositionGap(2)),
+    ]);
+    assert_eq!(warnings[4], new_warning(None, SchemaWarningType::DuplicatedVersion));
+}
@@ -298,6 +298,7 @@ impl From<&SchemaV1> for Schema {
     fn from(legacy_schema: &SchemaV1) -> Self {
         let mut schema = Self::default();
         legacy_schema.0.iter().map(From::from).for_each(|x| schema.versioned_files.push(x));
+        schema.rebuild_db_index();
         schema
     }
 }
@@ -625,7 +625,7 @@ pub fn background_loop() {
             Command::ExportTSV((internal_path, external_path)) => {
                 match pack_file_decoded.get_ref_mut_packed_file_by_path(&internal_path) {
                     Some(packed_file) => match packed_file.get_decoded() {
-                        DecodedPackedFile::DB(data) => match data.export_tsv(&external_path, &internal_path[1]) {
+                        DecodedPackedFile::DB(data) => match PackedFileType::get_db_table_name(&internal_path).and_then(|table_name| data.export_tsv(&external_path, table_name)) {
                             Ok(_) => CENTRAL_COMMAND.send_message_rust(Response::Success),
                             Err(error) =>  CENTRAL_COMMAND.send_message_rust(Response::Error(error)),
                         },
@@ -648,7 +648,7 @@ pub fn background_loop() {
             Command::ImportTSV((internal_path, external_path)) => {
                 match pack_file_decoded.get_ref_mut_packed_file_by_path(&internal_path) {
                     Some(packed_file) => match packed_file.get_decoded() {
-                        DecodedPackedFile::DB(data) => match DB::import_tsv(&data.get_definition(), &external_path, &internal_path[1]) {
+                        DecodedPackedFile::DB(data) => match PackedFileType::get_db_table_name(&internal_path).and_then(|table_name| DB::import_tsv(&data.get_definition(), &external_path, table_name)) {
                             Ok(data) => CENTRAL_COMMAND.send_message_rust(Response::TableType(TableType::DB(data))),
                             Err(error) =>  CENTRAL_COMMAND.send_message_rust(Response::Error(error)),
                         },
@@ -702,7 +702,7 @@ pub fn background_loop() {
                                     Ok(data) => {
                                         if let DecodedPackedFile::DB(data) = data {
                                             temporal_file_path.set_extension("tsv");
-                                            match data.export_tsv(&temporal_file_path, &path[1]) {
+                                            match PackedFileType::get_db_table_name(&path).and_then(|table_name| data.export_tsv(&temporal_file_path, table_name)) {
                                                 Ok(_) => {
                                                     that_in_background(&temporal_file_path);
                                                     CENTRAL_COMMAND.send_message_rust(Response::PathBuf(temporal_file_path));
@@ -770,7 +770,7 @@ pub fn background_loop() {
                                 match packed_file.decode_return_ref_mut() {
                                     Ok(data) => {
                                         if let DecodedPackedFile::DB(ref mut data) = data {
-                                            match DB::import_tsv(&data.get_definition(), &external_path, &path[1]) {
+                                            match PackedFileType::get_db_table_name(&path).and_then(|table_name| DB::import_tsv(&data.get_definition(), &external_path, table_name)) {
                                                 Ok(new_data) => {
                                                     *data = new_data;
                                                     match packed_file.encode_and_clean_cache() {
@@ -117,6 +117,8 @@ impl IconType {
                         TextType::Xml => &TREEVIEW_ICONS.text_xml,
                         TextType::Lua => &TREEVIEW_ICONS.text_generic,
                         TextType::Cpp => &TREEVIEW_ICONS.text_generic,
+                        TextType::Csv => &TREEVIEW_ICONS.text_csv,
+                        TextType::Tsv => &TREEVIEW_ICONS.text_csv,
                         TextType::Plain => &TREEVIEW_ICONS.text_txt,
                         TextType::Markdown => &TREEVIEW_ICONS.text_txt,
                     }
@@ -171,6 +173,8 @@ impl IconType {
                         TextType::Xml => &TREEVIEW_ICONS.text_xml,
                         TextType::Lua => &TREEVIEW_ICONS.text_generic,
                         TextType::Cpp => &TREEVIEW_ICONS.text_generic,
+                        TextType::Csv => &TREEVIEW_ICONS.text_csv,
+                        TextType::Tsv => &TREEVIEW_ICONS.text_csv,
                         TextType::Plain => &TREEVIEW_ICONS.text_txt,
                         TextType::Markdown => &TREEVIEW_ICONS.text_txt,
                     }
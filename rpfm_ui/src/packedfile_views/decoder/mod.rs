@@ -349,7 +349,7 @@ impl PackedFileDecoderView {
         let packed_file_info_entry_count_label = QLabel::from_q_string(&QString::from_std_str("PackedFile entry count:"));
 
         let packed_file_info_type_decoded_label = QLabel::from_q_string(&QString::from_std_str(match packed_file_type {
-            PackedFileType::DB => format!("DB/{}", packed_file_view.get_path()[1]),
+            PackedFileType::DB => format!("DB/{}", PackedFileType::get_db_table_name(&packed_file_view.get_path()).unwrap_or_default()),
             _ => format!("{}", packed_file_type),
         }));
         let mut packed_file_info_version_decoded_label = QLabel::new();
@@ -1187,7 +1187,7 @@ impl PackedFileDecoderViewRaw {
 
             // Depending on the type, get one version list or another.
             let versioned_file = match self.packed_file_type {
-                PackedFileType::DB => schema.get_ref_versioned_file_db(&self.packed_file_path[1]),
+                PackedFileType::DB => PackedFileType::get_db_table_name(&self.packed_file_path).and_then(|table_name| schema.get_ref_versioned_file_db(table_name)),
                 PackedFileType::Loc => schema.get_ref_versioned_file_loc(),
                 _ => unimplemented!(),
             };
@@ -1303,7 +1303,7 @@ impl PackedFileDecoderViewRaw {
         };
 
         let versioned_file = match self.packed_file_type {
-            PackedFileType::DB => schema.get_ref_mut_versioned_file_db(&self.packed_file_path[1]),
+            PackedFileType::DB => PackedFileType::get_db_table_name(&self.packed_file_path).and_then(|table_name| schema.get_ref_mut_versioned_file_db(table_name)),
             PackedFileType::Loc => schema.get_ref_mut_versioned_file_loc(),
             _ => unimplemented!(),
         };
@@ -1325,7 +1325,10 @@ impl PackedFileDecoderViewRaw {
 
                 let definitions = vec![definition];
                 let versioned_file = match self.packed_file_type {
-                    PackedFileType::DB => VersionedFile::DB(self.packed_file_path[1].to_owned(), definitions),
+                    PackedFileType::DB => match PackedFileType::get_db_table_name(&self.packed_file_path) {
+                        Ok(table_name) => VersionedFile::DB(table_name.to_owned(), definitions),
+                        Err(_) => return schema,
+                    },
                     PackedFileType::Loc => VersionedFile::Loc(definitions),
                     PackedFileType::DependencyPackFilesList => VersionedFile::DepManager(definitions),
                     _ => unimplemented!()
@@ -1362,7 +1365,7 @@ fn get_definition(
 
         // Depending on the type, get one version list or another.
         let versioned_file = match packed_file_type {
-            PackedFileType::DB => schema.get_ref_versioned_file_db(&packed_file_path[1]),
+            PackedFileType::DB => PackedFileType::get_db_table_name(packed_file_path).and_then(|table_name| schema.get_ref_versioned_file_db(table_name)),
             PackedFileType::Loc => schema.get_ref_versioned_file_loc(),
             _ => unimplemented!(),
         };
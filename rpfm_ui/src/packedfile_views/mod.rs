@@ -48,7 +48,7 @@ use self::ca_vp8::{PackedFileCaVp8View, slots::PackedFileCaVp8ViewSlots};
 use self::decoder::{PackedFileDecoderView, slots::PackedFileDecoderViewSlots};
 use self::external::{PackedFileExternalView, slots::PackedFileExternalViewSlots};
 use self::image::{PackedFileImageView, slots::PackedFileImageViewSlots};
-use self::table::{PackedFileTableView, slots::PackedFileTableViewSlots, TableType};
+use self::table::{PackedFileTableView, slots::PackedFileTableViewSlots, TableType, ITEM_IS_ABSENT};
 use self::text::{PackedFileTextView, slots::PackedFileTextViewSlots};
 use self::packfile::{PackFileExtraView, slots::PackFileExtraViewSlots};
 use self::rigidmodel::{PackedFileRigidModelView, slots::PackedFileRigidModelViewSlots};
@@ -217,8 +217,16 @@ impl PackedFileView {
                                     // All these are just normal Strings.
                                     FieldType::StringU8 => DecodedData::StringU8(QString::to_std_string(&model.item_2a(row as i32, column as i32).text())),
                                     FieldType::StringU16 => DecodedData::StringU16(QString::to_std_string(&model.item_2a(row as i32, column as i32).text())),
-                                    FieldType::OptionalStringU8 => DecodedData::OptionalStringU8(QString::to_std_string(&model.item_2a(row as i32, column as i32).text())),
-                                    FieldType::OptionalStringU16 => DecodedData::OptionalStringU16(QString::to_std_string(&model.item_2a(row as i32, column as i32).text())),
+
+                                    // Optional strings are only saved as absent values if they're empty and marked as absent,
+                                    // because they were absent when loaded or they have been cleared with the smart delete.
+                                    FieldType::OptionalStringU8 |
+                                    FieldType::OptionalStringU16 => {
+                                        let item = model.item_2a(row as i32, column as i32);
+                                        let text = QString::to_std_string(&item.text());
+                                        if text.is_empty() && item.data_1a(ITEM_IS_ABSENT).to_bool() { DecodedData::default(&field.field_type) }
+                                        else { DecodedData::from_str_typed(&text, &field.field_type)? }
+                                    }
 
                                     // Sequences in the UI are not yet supported.
                                     FieldType::Sequence(_) => return Err(ErrorKind::PackedFileSaveError(self.get_path()).into()),
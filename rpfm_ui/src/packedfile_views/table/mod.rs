@@ -86,6 +86,9 @@ static COLUMN_SIZE_STRING: i32 = 350;
 static ITEM_HAS_SOURCE_VALUE: i32 = 30;
 static ITEM_SOURCE_VALUE: i32 = 31;
 
+/// Role of the items of optional strings that are absent, instead of empty.
+pub static ITEM_IS_ABSENT: i32 = 32;
+
 //-------------------------------------------------------------------------------//
 //                              Enums & Structs
 //-------------------------------------------------------------------------------//
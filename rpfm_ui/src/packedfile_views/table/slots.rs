@@ -502,6 +502,7 @@ impl PackedFileTableViewSlots {
                 // - Checkboxes: unchecked.
                 // - Numbers: 0.
                 // - Strings: empty.
+                // - Optional strings: absent.
                 let mut editions = 0;
                 for (row, columns) in &individual_cells {
                     for column in columns {
@@ -537,6 +538,15 @@ impl PackedFileTableViewSlots {
                                 }
                             }
 
+                            FieldType::OptionalStringU8 |
+                            FieldType::OptionalStringU16 => {
+                                if !current_value.is_empty() || !item.data_1a(ITEM_IS_ABSENT).to_bool() {
+                                    item.set_text(&QString::from_std_str(""));
+                                    item.set_data_2a(&QVariant::from_bool(true), ITEM_IS_ABSENT);
+                                    editions += 1;
+                                }
+                            }
+
                             _ => {
                                 if !current_value.is_empty() {
                                     item.set_text(&QString::from_std_str(""));
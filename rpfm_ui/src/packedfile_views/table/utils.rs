@@ -249,13 +249,23 @@ unsafe fn get_default_item_from_field(field: &Field) -> CppBox<QStandardItem> {
             item
         },
         FieldType::StringU8 |
-        FieldType::StringU16 |
+        FieldType::StringU16 => {
+            if let Some(default_value) = &field.default_value {
+                QStandardItem::from_q_string(&QString::from_std_str(default_value))
+            } else {
+                QStandardItem::from_q_string(&QString::new())
+            }
+        },
+
+        // Optional strings without default value are absent by default.
         FieldType::OptionalStringU8 |
         FieldType::OptionalStringU16 => {
             if let Some(default_value) = &field.default_value {
                 QStandardItem::from_q_string(&QString::from_std_str(default_value))
             } else {
-                QStandardItem::from_q_string(&QString::new())
+                let mut item = QStandardItem::from_q_string(&QString::new());
+                item.set_data_2a(&QVariant::from_bool(true), ITEM_IS_ABSENT);
+                item
             }
         },
         FieldType::Sequence(_) => QStandardItem::from_q_string(&qtr("packedfile_noneditable_sequence")),
@@ -450,13 +460,23 @@ unsafe fn get_item_from_decoded_data(data: &DecodedData) -> CppBox<QStandardItem
         },
         // All these are Strings, so it can be together,
         DecodedData::StringU8(ref data) |
-        DecodedData::StringU16(ref data) |
+        DecodedData::StringU16(ref data) => {
+            let mut item = QStandardItem::from_q_string(&QString::from_std_str(data));
+            item.set_tool_tip(&QString::from_std_str(&tre("original_data", &[&data])));
+            item.set_data_2a(&QVariant::from_bool(true), ITEM_HAS_SOURCE_VALUE);
+            item.set_data_2a(&QVariant::from_q_string(&QString::from_std_str(data)), ITEM_SOURCE_VALUE);
+            item
+        },
+        // Absent optional strings are shown as empty strings, but marked as absent so they're saved back as they were.
         DecodedData::OptionalStringU8(ref data) |
         DecodedData::OptionalStringU16(ref data) => {
+            let is_absent = data.is_none();
+            let data = data.as_deref().unwrap_or_default();
             let mut item = QStandardItem::from_q_string(&QString::from_std_str(data));
             item.set_tool_tip(&QString::from_std_str(&tre("original_data", &[&data])));
             item.set_data_2a(&QVariant::from_bool(true), ITEM_HAS_SOURCE_VALUE);
             item.set_data_2a(&QVariant::from_q_string(&QString::from_std_str(data)), ITEM_SOURCE_VALUE);
+            item.set_data_2a(&QVariant::from_bool(is_absent), ITEM_IS_ABSENT);
             item
         },
         DecodedData::Sequence(_) => {
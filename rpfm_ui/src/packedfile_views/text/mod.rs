@@ -98,7 +98,7 @@ impl PackedFileTextView {
             TextType::Html => QString::from_std_str(HTML),
             TextType::Lua => QString::from_std_str(LUA),
             TextType::Xml => QString::from_std_str(XML),
-            TextType::Plain => QString::from_std_str(PLAIN),
+            TextType::Csv | TextType::Tsv | TextType::Plain => QString::from_std_str(PLAIN),
             TextType::Markdown => QString::from_std_str(MARKDOWN),
         };
 
@@ -133,7 +133,7 @@ impl PackedFileTextView {
             TextType::Html => QString::from_std_str(HTML),
             TextType::Lua => QString::from_std_str(LUA),
             TextType::Xml => QString::from_std_str(XML),
-            TextType::Plain => QString::from_std_str(PLAIN),
+            TextType::Csv | TextType::Tsv | TextType::Plain => QString::from_std_str(PLAIN),
             TextType::Markdown => QString::from_std_str(MARKDOWN),
         };
 